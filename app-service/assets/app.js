@@ -2,6 +2,19 @@ const loginLink = document.getElementById("login-link");
 const logoutLink = document.getElementById("logout-link");
 const protectImg = document.getElementById("protected-img");
 
+// The auth service sets the CSRF token of the session in a cookie next to the auth cookie.
+// Requests that change state with the auth cookie must send it back in the X-CSRF-Token header.
+function csrfToken() {
+    const names = ["__Host-csrf_token", "csrf_token"];
+    for (const cookie of document.cookie.split("; ")) {
+        const [name, ...value] = cookie.split("=");
+        if (names.includes(name)) {
+            return value.join("=");
+        }
+    }
+    return "";
+}
+
 logoutLink.addEventListener("click", (e) => {
     e.preventDefault();
 
@@ -10,6 +23,9 @@ logoutLink.addEventListener("click", (e) => {
     fetch(url, {
         method: 'POST',
         credentials: 'include', // This will include cookies in the request
+        headers: {
+            'X-CSRF-Token': csrfToken(),
+        },
     }).then(response => {
         if (response.ok) {
             loginLink.style.display = "block";
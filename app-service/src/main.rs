@@ -1,27 +1,308 @@
 use std::env;
 
 use askama::Template;
+use auth_token::{AuthToken, TokenPrecedence};
 use axum::{
-    http::StatusCode,
-    response::{Html, IntoResponse},
+    async_trait,
+    extract::{FromRequestParts, Request},
+    http::{
+        header,
+        request::Parts,
+        uri::{Authority, PathAndQuery},
+        HeaderMap, HeaderValue, StatusCode, Uri,
+    },
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Redirect, Response},
     routing::get,
-    Json, Router,
+    Extension, Json, Router,
 };
-use axum_extra::extract::CookieJar;
-use serde::Serialize;
+use axum_server::tls_rustls::RustlsConfig;
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_http::HeaderExtractor;
+use opentelemetry_otlp::{Protocol, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use security_headers::{CspNonce, SecurityHeaderSettings, SecurityHeadersLayer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::net::SocketAddr;
+use std::time::Duration;
 use tower_http::services::ServeDir;
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tracing::{Instrument, Level, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Identifies a request in the logs of both services; auth-service reads the same header.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+const APP_ADDRESS: &str = "0.0.0.0:8000";
+/// How often the certificate files are checked for a renewed certificate.
+const CERTIFICATE_RELOAD_INTERVAL: Duration = Duration::from_secs(10);
 
 #[tokio::main]
 async fn main() {
+    let provider = init_tracing();
+
+    let tls = non_empty_var("TLS_CERT_PATH").zip(non_empty_var("TLS_KEY_PATH"));
+    // Configured with the same variables as in auth-service.
+    let security_headers = SecurityHeaderSettings::from_env(tls.is_some());
+
     let app = Router::new()
         .nest_service("/assets", ServeDir::new("assets"))
         .route("/", get(root))
-        .route("/protected", get(protected));
+        .route("/protected", get(protected))
+        .route("/admin", get(admin))
+        .layer(Extension(TokenLookup::from_env()))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(make_span)
+                .on_response(DefaultOnResponse::new().level(Level::INFO)),
+        )
+        .layer(middleware::from_fn(assign_request_id))
+        .layer(SecurityHeadersLayer::new(&security_headers).expect("Invalid security header"));
+
+    match tls {
+        Some((cert_path, key_path)) => serve_https(app, cert_path, key_path).await,
+        None => {
+            let listener = tokio::net::TcpListener::bind(APP_ADDRESS).await.unwrap();
+            tracing::info!(address = %listener.local_addr().unwrap(), "Listening");
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown_signal())
+                .await
+                .unwrap();
+        }
+    }
+
+    if let Some(provider) = provider {
+        if let Err(err) = provider.shutdown() {
+            eprintln!("Failed to export the remaining spans: {}", err);
+        }
+    }
+}
+
+fn non_empty_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// Serves HTTPS with the PEM files at `TLS_CERT_PATH` and `TLS_KEY_PATH`, picking up a renewed
+/// certificate without a restart. Plain HTTP requests to `TLS_REDIRECT_ADDRESS`, if set, are
+/// redirected to it.
+async fn serve_https(app: Router, cert_path: String, key_path: String) {
+    // ring is the only provider compiled in; this fails only if it is installed already.
+    let _ = rustls::crypto::ring::default_provider().install_default();
+    let config = RustlsConfig::from_pem_file(&cert_path, &key_path)
+        .await
+        .expect("Failed to load the TLS certificate");
+    tokio::spawn(reload_certificate(config.clone(), cert_path, key_path));
+
+    let address: SocketAddr = APP_ADDRESS.parse().unwrap();
+    let handle = axum_server::Handle::new();
+    let (stop_redirect, redirect_stopped) = tokio::sync::oneshot::channel::<()>();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            shutdown_signal().await;
+            handle.graceful_shutdown(None);
+            let _ = stop_redirect.send(());
+        }
+    });
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:8000").await.unwrap();
+    if let Some(redirect_address) = non_empty_var("TLS_REDIRECT_ADDRESS") {
+        let listener = tokio::net::TcpListener::bind(redirect_address)
+            .await
+            .unwrap();
+        tracing::info!(address = %listener.local_addr().unwrap(), "Redirecting to HTTPS");
+        let https_port = address.port();
+        let redirect = Router::new().fallback(move |headers: HeaderMap, uri: Uri| async move {
+            redirect_to_https(&headers, &uri, https_port)
+        });
+        tokio::spawn(async move {
+            axum::serve(listener, redirect)
+                .with_graceful_shutdown(async {
+                    let _ = redirect_stopped.await;
+                })
+                .await
+        });
+    }
+
+    tracing::info!(%address, "Listening");
+    axum_server::bind_rustls(address, config)
+        .handle(handle)
+        .serve(app.into_make_service())
+        .await
+        .unwrap();
+}
 
-    println!("listening on {}", listener.local_addr().unwrap());
-    axum::serve(listener, app).await.unwrap();
+/// Loads the certificate again whenever its files change. One that fails to load is logged
+/// and the previous one kept, so that the key can be replaced after the certificate.
+async fn reload_certificate(config: RustlsConfig, cert_path: String, key_path: String) {
+    // Compared by content, as modification times can be too coarse to tell two writes apart.
+    let read = || {
+        Some((
+            std::fs::read(&cert_path).ok()?,
+            std::fs::read(&key_path).ok()?,
+        ))
+    };
+    let mut loaded = read();
+    let mut interval = tokio::time::interval(CERTIFICATE_RELOAD_INTERVAL);
+    loop {
+        interval.tick().await;
+        let current = read();
+        if current == loaded {
+            continue;
+        }
+        let Some((cert, key)) = current.clone() else {
+            continue;
+        };
+        match config.reload_from_pem(cert, key).await {
+            Ok(()) => {
+                loaded = current;
+                tracing::info!("Reloaded the TLS certificate");
+            }
+            Err(err) => tracing::error!(error = %err, "Failed to reload the TLS certificate"),
+        }
+    }
+}
+
+/// A permanent redirect to the same URL over HTTPS on `https_port`.
+fn redirect_to_https(headers: &HeaderMap, uri: &Uri, https_port: u16) -> Response {
+    let Some(host) = headers
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<Authority>().ok())
+    else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let authority = match https_port {
+        443 => host.host().to_owned(),
+        port => format!("{}:{}", host.host(), port),
+    };
+    let path = uri.path_and_query().map_or("/", PathAndQuery::as_str);
+    Redirect::permanent(&format!("https://{}{}", authority, path)).into_response()
+}
+
+/// `https` once auth-service is set up with TLS, `http` otherwise.
+fn auth_service_scheme() -> String {
+    non_empty_var("AUTH_SERVICE_SCHEME").unwrap_or("http".to_owned())
+}
+
+/// Resolves on SIGINT (Ctrl+C) or SIGTERM, after which the requests in flight are finished
+/// before the server stops.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for SIGINT");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+    tracing::info!("Shutting down");
+}
+
+/// JSON lines on stdout, filtered by `RUST_LOG`, like in auth-service. Spans are exported
+/// over OTLP/HTTP when `OTEL_EXPORTER_OTLP_ENDPOINT` is set, and not at all otherwise.
+fn init_tracing() -> Option<SdkTracerProvider> {
+    let provider = env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+        .ok()
+        .filter(|endpoint| !endpoint.is_empty())
+        .and_then(|endpoint| {
+            SpanExporter::builder()
+                .with_http()
+                .with_protocol(Protocol::HttpJson)
+                .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+                .build()
+                .map_err(|err| eprintln!("Failed to set up the OTLP exporter: {}", err))
+                .ok()
+        })
+        .map(|exporter| {
+            SdkTracerProvider::builder()
+                .with_batch_exporter(exporter)
+                .with_resource(
+                    Resource::builder_empty()
+                        .with_service_name("app-service")
+                        .build(),
+                )
+                .build()
+        });
+    let traces = provider
+        .as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer("app-service")));
+    tracing_subscriber::registry()
+        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
+        .with(
+            tracing_subscriber::fmt::layer()
+                .json()
+                .flatten_event(true)
+                .with_current_span(false)
+                .with_span_list(true),
+        )
+        .with(traces)
+        .init();
+    provider
+}
+
+/// Keeps the `X-Request-Id` the caller sent if it is usable and generates one otherwise, so
+/// that the request can be followed into auth-service.
+async fn assign_request_id(mut request: Request, next: Next) -> Response {
+    let value = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .filter(|value| {
+            value.to_str().is_ok_and(|id| {
+                !id.is_empty()
+                    && id.len() <= MAX_REQUEST_ID_LENGTH
+                    && id.chars().all(|c| c.is_ascii_graphic())
+            })
+        })
+        .cloned()
+        .unwrap_or_else(|| {
+            HeaderValue::from_str(&Uuid::new_v4().to_string()).expect("Invalid request id")
+        });
+    request
+        .headers_mut()
+        .insert(REQUEST_ID_HEADER, value.clone());
+    let mut response = next.run(request).await;
+    response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    response
+}
+
+fn make_span(request: &Request) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let span = tracing::info_span!(
+        "request",
+        otel.name = format!("{} {}", request.method(), request.uri().path()),
+        otel.kind = "server",
+        method = %request.method(),
+        path = request.uri().path(),
+        request_id,
+    );
+    let parent = TraceContextPropagator::new().extract(&HeaderExtractor(request.headers()));
+    // Fails only when nothing exports spans.
+    let _ = span.set_parent(parent);
+    span
 }
 
 #[derive(Template)]
@@ -29,60 +310,187 @@ async fn main() {
 struct IndexTemplate {
     login_link: String,
     logout_link: String,
+    // Allows the page's scripts to run; empty if the policy does not use a nonce.
+    nonce: String,
 }
 
-async fn root() -> impl IntoResponse {
+async fn root(nonce: Option<Extension<CspNonce>>) -> impl IntoResponse {
     let mut address = env::var("AUTH_SERVICE_IP").unwrap_or("localhost".to_owned());
     if address.is_empty() {
         address = "localhost".to_owned();
     }
-    let login_link = format!("http://{}:3000", address);
-    let logout_link = format!("http://{}:3000/logout", address);
+    let scheme = auth_service_scheme();
+    let login_link = format!("{}://{}:3000", scheme, address);
+    let logout_link = format!("{}://{}:3000/logout", scheme, address);
 
     let template = IndexTemplate {
         login_link,
         logout_link,
+        nonce: nonce
+            .map(|Extension(nonce)| nonce.to_string())
+            .unwrap_or_default(),
     };
     Html(template.render().unwrap())
 }
 
-async fn protected(jar: CookieJar) -> impl IntoResponse {
-    let jwt_cookie = match jar.get("jwt") {
-        Some(cookie) => cookie,
-        None => {
-            return StatusCode::UNAUTHORIZED.into_response();
+/// How the token of a caller is looked for, like in auth-service and configured with the same
+/// variables. Read once at startup and handed to the extractors as an extension.
+#[derive(Debug, Clone, Copy)]
+struct TokenLookup {
+    precedence: TokenPrecedence,
+    cookie_name: &'static str,
+}
+
+impl TokenLookup {
+    fn from_env() -> Self {
+        let precedence = non_empty_var(auth_token::env::AUTH_TOKEN_PRECEDENCE_ENV_VAR)
+            .and_then(|value| TokenPrecedence::parse(&value))
+            .unwrap_or_default();
+        let host_prefix =
+            non_empty_var(auth_token::env::COOKIE_HOST_PREFIX_ENV_VAR).is_some_and(|value| {
+                matches!(
+                    value.trim().to_ascii_lowercase().as_str(),
+                    "true" | "1" | "yes"
+                )
+            });
+        Self {
+            precedence,
+            cookie_name: auth_token::cookie_name(host_prefix),
         }
-    };
+    }
+}
 
-    let api_client = reqwest::Client::builder().build().unwrap();
+/// The claims of a token that auth-service accepted, as returned by `/verify-token`.
+#[derive(Deserialize)]
+struct VerifiedUser {
+    sub: String,
+    #[serde(default)]
+    permissions: Vec<String>,
+}
 
-    let verify_token_body = serde_json::json!({
-        "token": &jwt_cookie.value(),
-    });
+#[async_trait]
+impl<S> FromRequestParts<S> for VerifiedUser
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
 
-    let auth_hostname = env::var("AUTH_SERVICE_HOST_NAME").unwrap_or("0.0.0.0".to_owned());
-    let url = format!("http://{}:3000/verify-token", auth_hostname);
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let lookup = parts
+            .extensions
+            .get::<TokenLookup>()
+            .copied()
+            .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+        let AuthToken { token, .. } =
+            AuthToken::from_headers(&parts.headers, lookup.precedence, lookup.cookie_name)
+                .ok_or(StatusCode::UNAUTHORIZED)?;
 
-    let response = match api_client.post(&url).json(&verify_token_body).send().await {
-        Ok(response) => response,
-        Err(_) => {
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        let mut api_client = reqwest::Client::builder();
+        // E.g. the CA of a self-signed certificate of auth-service.
+        if let Some(path) = non_empty_var("AUTH_SERVICE_CA_PATH") {
+            let pem = std::fs::read(path).expect("Failed to read AUTH_SERVICE_CA_PATH");
+            let certificate = reqwest::Certificate::from_pem(&pem)
+                .expect("Invalid certificate in AUTH_SERVICE_CA_PATH");
+            api_client = api_client.add_root_certificate(certificate);
         }
-    };
+        let api_client = api_client.build().unwrap();
+
+        let verify_token_body = serde_json::json!({
+            "token": &token,
+        });
 
-    match response.status() {
-        reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::BAD_REQUEST => {
-            StatusCode::UNAUTHORIZED.into_response()
+        let auth_hostname = env::var("AUTH_SERVICE_HOST_NAME").unwrap_or("0.0.0.0".to_owned());
+        let url = format!(
+            "{}://{}:3000/verify-token",
+            auth_service_scheme(),
+            auth_hostname
+        );
+
+        let mut request = api_client.post(&url).json(&verify_token_body);
+        // Set by `assign_request_id`, so both services log the call under the same id.
+        if let Some(request_id) = parts.headers.get(REQUEST_ID_HEADER) {
+            request = request.header(REQUEST_ID_HEADER, request_id.as_bytes());
+        }
+        // The `traceparent` header makes the spans of auth-service part of this trace.
+        let span = tracing::info_span!(
+            "verify_token",
+            otel.name = "POST /verify-token",
+            otel.kind = "client",
+        );
+        let mut trace_headers = HashMap::new();
+        TraceContextPropagator::new().inject_context(&span.context(), &mut trace_headers);
+        for (name, value) in trace_headers {
+            request = request.header(name, value);
+        }
+        let response = request.send().instrument(span).await.map_err(|err| {
+            tracing::error!(error = %err, "Failed to call auth-service");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+        match response.status() {
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::BAD_REQUEST => {
+                tracing::info!(status = %response.status(), "Token rejected by auth-service");
+                Err(StatusCode::UNAUTHORIZED)
+            }
+            reqwest::StatusCode::OK => response.json::<VerifiedUser>().await.map_err(|err| {
+                tracing::error!(error = %err, "Invalid response from auth-service");
+                StatusCode::INTERNAL_SERVER_ERROR
+            }),
+            status => {
+                tracing::error!(%status, "Unexpected response from auth-service");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
         }
-        reqwest::StatusCode::OK => Json(ProtectedRouteResponse {
-            img_url: "https://i.ibb.co/YP90j68/Light-Live-Bootcamp-Certificate.png".to_owned(),
-        })
-        .into_response(),
-        _ => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
     }
 }
 
+/// A permission granted through the roles in auth-service, see `auth_service::domain::Role`.
+trait Permission {
+    const NAME: &'static str;
+}
+
+struct UsersRead;
+
+impl Permission for UsersRead {
+    const NAME: &'static str = "users:read";
+}
+
+/// A [`VerifiedUser`] whose token grants the permission `P`; everyone else gets a 403.
+struct RequirePermission<P>(VerifiedUser, PhantomData<P>);
+
+#[async_trait]
+impl<S, P> FromRequestParts<S> for RequirePermission<P>
+where
+    S: Send + Sync,
+    P: Permission,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let user = VerifiedUser::from_request_parts(parts, state).await?;
+        if !user.permissions.iter().any(|granted| granted == P::NAME) {
+            return Err(StatusCode::FORBIDDEN);
+        }
+        Ok(RequirePermission(user, PhantomData))
+    }
+}
+
+async fn protected(_user: VerifiedUser) -> impl IntoResponse {
+    Json(ProtectedRouteResponse {
+        img_url: "https://i.ibb.co/YP90j68/Light-Live-Bootcamp-Certificate.png".to_owned(),
+    })
+}
+
+async fn admin(RequirePermission(user, _): RequirePermission<UsersRead>) -> impl IntoResponse {
+    Json(AdminRouteResponse { email: user.sub })
+}
+
 #[derive(Serialize)]
 pub struct ProtectedRouteResponse {
     pub img_url: String,
 }
+
+#[derive(Serialize)]
+pub struct AdminRouteResponse {
+    pub email: String,
+}
@@ -6,6 +6,14 @@ const signupLink = document.getElementById("signup-link");
 const twoFALoginLink = document.getElementById("2fa-login-link");
 const signupLoginLink = document.getElementById("signup-login-link");
 
+// Error responses are problem details, or {"error": "..."} if the legacy format is configured.
+function errorMessage(data) {
+    if (data.errors !== undefined && data.errors.length > 0) {
+        return data.errors.map(error => error.detail).join(". ");
+    }
+    return data.detail ?? data.error;
+}
+
 signupLink.addEventListener("click", (e) => {
     e.preventDefault();
 
@@ -66,10 +74,16 @@ loginButton.addEventListener("click", (e) => {
             loginForm.email.value = "";
             loginForm.password.value = "";
             loginErrAlter.style.display = "none";
+            // Resume an OpenID Connect authorization request that sent the user here to log in.
+            const returnTo = new URLSearchParams(window.location.search).get("return_to");
+            if (returnTo !== null && returnTo.startsWith("/authorize")) {
+                window.location.assign(returnTo);
+                return;
+            }
             alert("You have successfully logged in.");
         } else {
             response.json().then(data => {
-                let error_msg = data.error;
+                let error_msg = errorMessage(data);
                 if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
                     loginErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
                     loginErrAlter.style.display = "block";
@@ -110,7 +124,7 @@ signupButton.addEventListener("click", (e) => {
             signupSection.style.display = "none";
         } else {
             response.json().then(data => {
-                let error_msg = data.error;
+                let error_msg = errorMessage(data);
                 if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
                     signupErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
                     signupErrAlter.style.display = "block";
@@ -151,7 +165,7 @@ TwoFAButton.addEventListener("click", (e) => {
             signupSection.style.display = "none";
         } else {
             response.json().then(data => {
-                let error_msg = data.error;
+                let error_msg = errorMessage(data);
                 if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
                     TwoFAErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
                     TwoFAErrAlter.style.display = "block";
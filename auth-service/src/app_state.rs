@@ -1,3 +1,5 @@
+mod config;
 mod state;
 
+pub use config::*;
 pub use state::*;
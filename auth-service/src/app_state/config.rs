@@ -0,0 +1,529 @@
+use crate::domain::UserId;
+use crate::utils::extractors::TokenPrecedence;
+use crate::utils::{env, CSRF_COOKIE_NAME};
+use axum_extra::extract::cookie::SameSite;
+use dotenvy::dotenv;
+use security_headers::SecurityHeaderSettings;
+use std::env as std_env;
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Where an email has to be unique, see [`UserId`](crate::domain::UserId).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmailUniqueness {
+    /// One account per email; the account can be a member of several organizations.
+    #[default]
+    Global,
+    /// One account per email and organization, chosen with `org_id` at signup and login.
+    PerTenant,
+}
+
+impl EmailUniqueness {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "global" => Some(Self::Global),
+            "tenant" => Some(Self::PerTenant),
+            _ => None,
+        }
+    }
+}
+
+/// The body of error responses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// `application/problem+json` (RFC 9457) with a stable `code` per error.
+    #[default]
+    Problem,
+    /// `{"error": "..."}` with an English message, for clients that have not moved on yet.
+    Legacy,
+}
+
+impl ErrorFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "problem" => Some(Self::Problem),
+            "legacy" => Some(Self::Legacy),
+            _ => None,
+        }
+    }
+}
+
+/// How the outbox of domain events is worked off, see
+/// [`EventOutbox`](crate::domain::EventOutbox).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct OutboxSettings {
+    /// How often the outbox is checked for events that are due.
+    pub poll_interval: Duration,
+    /// The wait before the first retry of an event, doubled for each one after it.
+    pub backoff: Duration,
+    /// Attempts after which an event is parked instead of retried.
+    pub max_attempts: u32,
+}
+
+impl Default for OutboxSettings {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_millis(250),
+            backoff: Duration::from_secs(5),
+            max_attempts: 12,
+        }
+    }
+}
+
+impl OutboxSettings {
+    /// Creates a new `OutboxSettings` instance.
+    #[must_use]
+    pub fn new(poll_interval: Duration, backoff: Duration, max_attempts: u32) -> Self {
+        Self {
+            poll_interval,
+            backoff,
+            max_attempts,
+        }
+    }
+}
+
+/// How the outbox of webhook deliveries is worked off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct WebhookSettings {
+    /// How often the outbox is checked for deliveries that are due.
+    pub poll_interval: Duration,
+    /// The wait before the first retry, doubled for each one after it.
+    pub backoff: Duration,
+    /// Attempts after which a delivery is given up on.
+    pub max_attempts: u32,
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            backoff: Duration::from_secs(30),
+            max_attempts: 8,
+        }
+    }
+}
+
+impl WebhookSettings {
+    /// Creates a new `WebhookSettings` instance.
+    #[must_use]
+    pub fn new(poll_interval: Duration, backoff: Duration, max_attempts: u32) -> Self {
+        Self {
+            poll_interval,
+            backoff,
+            max_attempts,
+        }
+    }
+}
+
+/// How the server stops once asked to, see [`Application::run`](crate::Application::run).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ShutdownSettings {
+    /// How long in-flight requests and the final pass of the background tasks may take
+    /// before whatever is left is dropped.
+    pub drain_timeout: Duration,
+}
+
+impl Default for ShutdownSettings {
+    fn default() -> Self {
+        Self {
+            drain_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ShutdownSettings {
+    /// Creates a new `ShutdownSettings` instance.
+    #[must_use]
+    pub fn new(drain_timeout: Duration) -> Self {
+        Self { drain_timeout }
+    }
+}
+
+/// Serving HTTPS instead of plain HTTP, see [`tls`](crate::utils::tls).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TlsSettings {
+    /// PEM file with the certificate chain, leaf certificate first.
+    pub cert_path: PathBuf,
+    /// PEM file with the private key of the leaf certificate.
+    pub key_path: PathBuf,
+    /// How often the files are checked for a renewed certificate.
+    pub reload_interval: Duration,
+    /// Where to listen for plain HTTP requests that are redirected to HTTPS, if anywhere.
+    pub redirect_address: Option<String>,
+}
+
+impl TlsSettings {
+    /// Creates a new `TlsSettings` instance.
+    #[must_use]
+    pub fn new(cert_path: impl Into<PathBuf>, key_path: impl Into<PathBuf>) -> Self {
+        Self {
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+            reload_interval: Duration::from_secs(10),
+            redirect_address: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_reload_interval(mut self, reload_interval: Duration) -> Self {
+        self.reload_interval = reload_interval;
+        self
+    }
+
+    #[must_use]
+    pub fn with_redirect_address(mut self, address: impl Into<String>) -> Self {
+        self.redirect_address = Some(address.into());
+        self
+    }
+}
+
+/// The attributes of the `jwt` cookie that carries the token of a browser session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CookieSettings {
+    /// Keeps the token away from scripts.
+    pub http_only: bool,
+    /// Only sends the cookie over HTTPS. When not set, it is sent that way if the service
+    /// serves TLS itself, see [`AppConfig::secure_cookies`].
+    pub secure: Option<bool>,
+    pub same_site: SameSite,
+    pub path: String,
+    /// Shares the cookie with the subdomains of this domain; host-only when not set.
+    pub domain: Option<String>,
+    /// Names the cookie `__Host-jwt`, which browsers only accept if it is `Secure`, has
+    /// `Path=/` and no `Domain`. These are enforced over the settings above.
+    pub host_prefix: bool,
+}
+
+impl Default for CookieSettings {
+    fn default() -> Self {
+        Self {
+            http_only: true,
+            secure: None,
+            same_site: SameSite::Lax,
+            path: "/".to_owned(),
+            domain: None,
+            host_prefix: false,
+        }
+    }
+}
+
+impl CookieSettings {
+    /// The name of the cookie, with the `__Host-` prefix if it is enabled.
+    pub fn name(&self) -> &'static str {
+        auth_token::cookie_name(self.host_prefix)
+    }
+
+    /// The name of the cookie carrying the CSRF token, prefixed like the auth cookie.
+    pub fn csrf_name(&self) -> &'static str {
+        if self.host_prefix {
+            HOST_PREFIXED_CSRF_COOKIE_NAME
+        } else {
+            CSRF_COOKIE_NAME
+        }
+    }
+
+    #[must_use]
+    pub fn with_secure(mut self, secure: bool) -> Self {
+        self.secure = Some(secure);
+        self
+    }
+
+    #[must_use]
+    pub fn with_same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    #[must_use]
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    #[must_use]
+    pub fn with_domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_host_prefix(mut self) -> Self {
+        self.host_prefix = true;
+        self
+    }
+}
+
+const HOST_PREFIXED_CSRF_COOKIE_NAME: &str = "__Host-csrf_token";
+
+fn parse_same_site(value: &str) -> Option<SameSite> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "strict" => Some(SameSite::Strict),
+        "lax" => Some(SameSite::Lax),
+        "none" => Some(SameSite::None),
+        _ => None,
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" => Some(true),
+        "false" | "0" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Runtime settings that tests need to vary per `Application` instance.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct AppConfig {
+    /// Initial access token required to register OAuth clients. Registration is
+    /// disabled when it is not set.
+    pub client_registration_token: Option<String>,
+    /// Bearer token required to scrape `/metrics`, which is not served when it is not set.
+    pub metrics_token: Option<String>,
+    /// The public URL of the service, which ID tokens are issued by and discovery points to.
+    /// Required, as it cannot be trusted from the requests.
+    pub oidc_issuer: Option<String>,
+    /// Whether the `jwt` cookie or the `Authorization: Bearer` header wins when both are sent.
+    pub token_precedence: TokenPrecedence,
+    /// Emails that are given the admin role once their owner verified them, so that a fresh
+    /// deployment has someone who can manage the other users.
+    pub admin_emails: Vec<String>,
+    pub email_uniqueness: EmailUniqueness,
+    pub outbox: OutboxSettings,
+    pub webhooks: WebhookSettings,
+    pub shutdown: ShutdownSettings,
+    /// Plain HTTP is served when not set.
+    pub tls: Option<TlsSettings>,
+    pub cookies: CookieSettings,
+    pub security_headers: SecurityHeaderSettings,
+    pub error_format: ErrorFormat,
+}
+
+impl AppConfig {
+    /// Reads the settings from the environment (and `.env`). Fails if a cookie setting is
+    /// set to a value that does not parse.
+    pub fn from_env() -> Result<Self, Box<dyn Error>> {
+        dotenv().ok();
+        let var = |name| std_env::var(name).ok().filter(|value| !value.is_empty());
+        let tls = var(env::TLS_CERT_PATH_ENV_VAR)
+            .zip(var(env::TLS_KEY_PATH_ENV_VAR))
+            .map(|(cert_path, key_path)| {
+                let settings = TlsSettings::new(cert_path, key_path);
+                match var(env::TLS_REDIRECT_ADDRESS_ENV_VAR) {
+                    Some(address) => settings.with_redirect_address(address),
+                    None => settings,
+                }
+            });
+        Ok(Self {
+            client_registration_token: std_env::var(env::CLIENT_REGISTRATION_TOKEN_ENV_VAR)
+                .ok()
+                .filter(|token| !token.is_empty()),
+            metrics_token: var(env::METRICS_TOKEN_ENV_VAR),
+            oidc_issuer: var(env::OIDC_ISSUER_ENV_VAR)
+                .map(|issuer| issuer.trim_end_matches('/').to_owned()),
+            token_precedence: std_env::var(env::AUTH_TOKEN_PRECEDENCE_ENV_VAR)
+                .ok()
+                .and_then(|value| TokenPrecedence::parse(&value))
+                .unwrap_or_default(),
+            admin_emails: std_env::var(env::ADMIN_EMAILS_ENV_VAR)
+                .map(|emails| {
+                    emails
+                        .split(',')
+                        .map(|email| email.trim().to_owned())
+                        .filter(|email| !email.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            email_uniqueness: std_env::var(env::EMAIL_UNIQUENESS_ENV_VAR)
+                .ok()
+                .and_then(|value| EmailUniqueness::parse(&value))
+                .unwrap_or_default(),
+            outbox: OutboxSettings::default(),
+            webhooks: WebhookSettings::default(),
+            shutdown: std_env::var(env::SHUTDOWN_TIMEOUT_SECS_ENV_VAR)
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+                .map(|secs| ShutdownSettings::new(Duration::from_secs(secs)))
+                .unwrap_or_default(),
+            // `Strict-Transport-Security` is only sent by default when serving HTTPS.
+            security_headers: SecurityHeaderSettings::from_env(tls.is_some()),
+            tls,
+            cookies: cookie_settings_from_env()?,
+            error_format: var(env::ERROR_FORMAT_ENV_VAR)
+                .and_then(|value| ErrorFormat::parse(&value))
+                .unwrap_or_default(),
+        })
+    }
+
+    #[must_use]
+    pub fn with_client_registration_token(mut self, token: impl Into<String>) -> Self {
+        self.client_registration_token = Some(token.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_metrics_token(mut self, token: impl Into<String>) -> Self {
+        self.metrics_token = Some(token.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_oidc_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.oidc_issuer = Some(issuer.into().trim_end_matches('/').to_owned());
+        self
+    }
+
+    #[must_use]
+    pub fn with_admin_email(mut self, email: impl Into<String>) -> Self {
+        self.admin_emails.push(email.into());
+        self
+    }
+
+    /// Whether the user is one of the configured admins. Only accounts outside of any tenant
+    /// qualify, so that an organization's namespace cannot claim an admin email.
+    pub fn is_admin_email(&self, id: &UserId) -> bool {
+        id.tenant.is_none()
+            && self
+                .admin_emails
+                .iter()
+                .any(|admin| admin.eq_ignore_ascii_case(id.email.as_ref()))
+    }
+
+    #[must_use]
+    pub fn with_email_uniqueness(mut self, email_uniqueness: EmailUniqueness) -> Self {
+        self.email_uniqueness = email_uniqueness;
+        self
+    }
+
+    /// The namespace the email of a user signing up or logging in for `org_id` is unique in.
+    pub fn tenant_for(&self, org_id: Option<&str>) -> Option<String> {
+        match self.email_uniqueness {
+            EmailUniqueness::Global => None,
+            EmailUniqueness::PerTenant => org_id.map(str::to_owned),
+        }
+    }
+
+    #[must_use]
+    pub fn with_outbox_settings(mut self, outbox: OutboxSettings) -> Self {
+        self.outbox = outbox;
+        self
+    }
+
+    #[must_use]
+    pub fn with_webhook_settings(mut self, webhooks: WebhookSettings) -> Self {
+        self.webhooks = webhooks;
+        self
+    }
+
+    #[must_use]
+    pub fn with_shutdown_settings(mut self, shutdown: ShutdownSettings) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    #[must_use]
+    pub fn with_tls(mut self, tls: TlsSettings) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    #[must_use]
+    pub fn with_cookie_settings(mut self, cookies: CookieSettings) -> Self {
+        self.cookies = cookies;
+        self
+    }
+
+    #[must_use]
+    pub fn with_security_headers(mut self, security_headers: SecurityHeaderSettings) -> Self {
+        self.security_headers = security_headers;
+        self
+    }
+
+    #[must_use]
+    pub fn with_error_format(mut self, error_format: ErrorFormat) -> Self {
+        self.error_format = error_format;
+        self
+    }
+
+    /// Whether the `jwt` cookie is `Secure`. It has to be with the `__Host-` prefix or
+    /// `SameSite=None`, as browsers drop it otherwise.
+    pub fn secure_cookies(&self) -> bool {
+        self.cookies.host_prefix
+            || self.cookies.same_site == SameSite::None
+            || self.cookies.secure.unwrap_or(self.tls.is_some())
+    }
+
+    #[must_use]
+    pub fn with_token_precedence(mut self, token_precedence: TokenPrecedence) -> Self {
+        self.token_precedence = token_precedence;
+        self
+    }
+}
+
+fn cookie_settings_from_env() -> Result<CookieSettings, Box<dyn Error>> {
+    let var = |name| std_env::var(name).ok().filter(|value| !value.is_empty());
+    let defaults = CookieSettings::default();
+    Ok(CookieSettings {
+        http_only: parse_var(env::COOKIE_HTTP_ONLY_ENV_VAR, parse_bool)?
+            .unwrap_or(defaults.http_only),
+        secure: parse_var(env::COOKIE_SECURE_ENV_VAR, parse_bool)?,
+        same_site: parse_var(env::COOKIE_SAME_SITE_ENV_VAR, parse_same_site)?
+            .unwrap_or(defaults.same_site),
+        path: var(env::COOKIE_PATH_ENV_VAR).unwrap_or(defaults.path),
+        domain: var(env::COOKIE_DOMAIN_ENV_VAR),
+        host_prefix: parse_var(env::COOKIE_HOST_PREFIX_ENV_VAR, parse_bool)?
+            .unwrap_or(defaults.host_prefix),
+    })
+}
+
+/// Reads a setting that weakens the cookies when misread, failing on values that do not
+/// parse rather than falling back to the default.
+fn parse_var<T>(name: &str, parse: fn(&str) -> Option<T>) -> Result<Option<T>, Box<dyn Error>> {
+    let value = std_env::var(name).ok().filter(|value| !value.is_empty());
+    parse_setting(name, value.as_deref(), parse)
+}
+
+fn parse_setting<T>(
+    name: &str,
+    value: Option<&str>,
+    parse: fn(&str) -> Option<T>,
+) -> Result<Option<T>, Box<dyn Error>> {
+    match value {
+        Some(value) => match parse(value) {
+            Some(parsed) => Ok(Some(parsed)),
+            None => Err(format!("{} has an invalid value: {:?}", name, value).into()),
+        },
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cookie_settings_do_not_fall_back_on_invalid_values() {
+        let name = env::COOKIE_SAME_SITE_ENV_VAR;
+        assert!(matches!(
+            parse_setting(name, None, parse_same_site),
+            Ok(None)
+        ));
+        assert!(matches!(
+            parse_setting(name, Some("Strict"), parse_same_site),
+            Ok(Some(SameSite::Strict))
+        ));
+        let err = parse_setting(name, Some("stirct"), parse_same_site).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "COOKIE_SAME_SITE has an invalid value: \"stirct\""
+        );
+        assert!(parse_setting(env::COOKIE_SECURE_ENV_VAR, Some("ture"), parse_bool).is_err());
+    }
+}
@@ -1,22 +1,82 @@
-use crate::domain::{BannedTokenStore, UserStore};
+use crate::app_state::AppConfig;
+use crate::domain::{
+    ApiKeyStore, AuditSink, AuthorizationCodeStore, BannedTokenStore, ClientStore, EmailClient,
+    InvitationStore, OrganizationStore, PendingLoginStore, RefreshTokenStore, SessionStore,
+    UserStore, WebhookStore,
+};
+use crate::utils::metrics::Metrics;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 // Using a type alias to improve readability!
 pub type UserStoreType = Arc<RwLock<Box<dyn UserStore>>>;
 pub type BannedTokenStoreType = Arc<RwLock<Box<dyn BannedTokenStore>>>;
+pub type ClientStoreType = Arc<RwLock<Box<dyn ClientStore>>>;
+pub type AuthorizationCodeStoreType = Arc<RwLock<Box<dyn AuthorizationCodeStore>>>;
+pub type RefreshTokenStoreType = Arc<RwLock<Box<dyn RefreshTokenStore>>>;
+pub type SessionStoreType = Arc<RwLock<Box<dyn SessionStore>>>;
+pub type PendingLoginStoreType = Arc<RwLock<Box<dyn PendingLoginStore>>>;
+pub type OrganizationStoreType = Arc<RwLock<Box<dyn OrganizationStore>>>;
+pub type InvitationStoreType = Arc<RwLock<Box<dyn InvitationStore>>>;
+pub type ApiKeyStoreType = Arc<RwLock<Box<dyn ApiKeyStore>>>;
+pub type AuditSinkType = Arc<RwLock<Box<dyn AuditSink>>>;
+pub type WebhookStoreType = Arc<RwLock<Box<dyn WebhookStore>>>;
+pub type EmailClientType = Arc<dyn EmailClient>;
 
 #[derive(Clone)]
 pub struct AppState {
     pub user_store: UserStoreType,
     pub banned_tokens: BannedTokenStoreType,
+    pub oauth_clients: ClientStoreType,
+    pub authorization_codes: AuthorizationCodeStoreType,
+    pub refresh_tokens: RefreshTokenStoreType,
+    pub sessions: SessionStoreType,
+    pub pending_logins: PendingLoginStoreType,
+    pub organizations: OrganizationStoreType,
+    pub invitations: InvitationStoreType,
+    pub api_keys: ApiKeyStoreType,
+    pub audit_sink: AuditSinkType,
+    pub webhooks: WebhookStoreType,
+    pub email_client: EmailClientType,
+    pub metrics: Arc<Metrics>,
+    pub config: Arc<AppConfig>,
 }
 
 impl AppState {
-    pub fn new(user_store: UserStoreType, banned_token_store: BannedTokenStoreType) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        user_store: UserStoreType,
+        banned_token_store: BannedTokenStoreType,
+        client_store: ClientStoreType,
+        authorization_code_store: AuthorizationCodeStoreType,
+        refresh_token_store: RefreshTokenStoreType,
+        session_store: SessionStoreType,
+        pending_login_store: PendingLoginStoreType,
+        organization_store: OrganizationStoreType,
+        invitation_store: InvitationStoreType,
+        api_key_store: ApiKeyStoreType,
+        audit_sink: AuditSinkType,
+        webhook_store: WebhookStoreType,
+        email_client: EmailClientType,
+        metrics: Arc<Metrics>,
+        config: AppConfig,
+    ) -> Self {
         Self {
             user_store,
             banned_tokens: banned_token_store,
+            oauth_clients: client_store,
+            authorization_codes: authorization_code_store,
+            refresh_tokens: refresh_token_store,
+            sessions: session_store,
+            pending_logins: pending_login_store,
+            organizations: organization_store,
+            invitations: invitation_store,
+            api_keys: api_key_store,
+            audit_sink,
+            webhooks: webhook_store,
+            email_client,
+            metrics,
+            config: Arc::new(config),
         }
     }
 }
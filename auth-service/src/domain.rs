@@ -1,11 +1,33 @@
+mod api_key;
+mod audit;
 pub(crate) mod data_stores;
 mod email;
+mod email_client;
 mod errors;
+mod events;
+mod invitation;
+mod oauth;
+mod organization;
 mod password;
+mod role;
+mod session;
+mod two_fa;
 pub(crate) mod user;
+mod webhook;
 
+pub use crate::domain::api_key::*;
+pub use crate::domain::audit::*;
 pub use crate::domain::data_stores::*;
 pub use crate::domain::email::*;
+pub use crate::domain::email_client::*;
 pub use crate::domain::errors::*;
+pub use crate::domain::events::*;
+pub use crate::domain::invitation::*;
+pub use crate::domain::oauth::*;
+pub use crate::domain::organization::*;
 pub use crate::domain::password::*;
+pub use crate::domain::role::*;
+pub use crate::domain::session::*;
+pub use crate::domain::two_fa::*;
 pub use crate::domain::user::*;
+pub use crate::domain::webhook::*;
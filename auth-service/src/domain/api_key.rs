@@ -0,0 +1,119 @@
+use super::oauth::hash_secret;
+use crate::domain::{Scope, UserId};
+use chrono::{DateTime, Utc};
+use subtle::ConstantTimeEq;
+
+/// What every API key starts with, so that they can be told apart from JWTs.
+pub const API_KEY_PREFIX: &str = "ak_";
+
+/// A personal API key for scripts that cannot go through a cookie login.
+///
+/// Keys look like `ak_<id>_<secret>`. The id is used to look the key up; of the secret
+/// only the SHA-256 digest is stored, so the key is shown once when it is created.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ApiKey {
+    pub id: String,
+    pub user: UserId,
+    pub name: String,
+    pub secret_hash: String,
+    /// Limits the key to these of the user's permissions; all of them if `None`.
+    pub scope: Option<Scope>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    /// Creates a new `ApiKey` instance.
+    #[must_use]
+    pub fn new(
+        id: String,
+        user: UserId,
+        name: String,
+        secret: &str,
+        scope: Option<Scope>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            id,
+            user,
+            name,
+            secret_hash: hash_secret(secret),
+            scope,
+            created_at: Utc::now(),
+            last_used_at: None,
+            expires_at,
+        }
+    }
+
+    /// The full key as handed to the user.
+    pub fn format(id: &str, secret: &str) -> String {
+        format!("{}{}_{}", API_KEY_PREFIX, id, secret)
+    }
+
+    /// Splits a key into its id and secret, `None` if it is not shaped like an API key.
+    pub fn parse(key: &str) -> Option<(&str, &str)> {
+        let (id, secret) = key.strip_prefix(API_KEY_PREFIX)?.split_once('_')?;
+        if id.is_empty() || secret.is_empty() {
+            return None;
+        }
+        Some((id, secret))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+
+    pub fn verify_secret(&self, secret: &str) -> bool {
+        self.secret_hash
+            .as_bytes()
+            .ct_eq(hash_secret(secret).as_bytes())
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Email;
+    use chrono::Duration;
+    use std::str::FromStr;
+
+    fn api_key(expires_at: Option<DateTime<Utc>>) -> ApiKey {
+        ApiKey::new(
+            "0123abcd".to_owned(),
+            Email::from_str("test@test.com").unwrap().into(),
+            "ci".to_owned(),
+            "s3cr3t_with-chars",
+            None,
+            expires_at,
+        )
+    }
+
+    #[test]
+    fn test_format_and_parse() {
+        let key = ApiKey::format("0123abcd", "s3cr3t_with-chars");
+        assert_eq!(key, "ak_0123abcd_s3cr3t_with-chars");
+        assert_eq!(ApiKey::parse(&key), Some(("0123abcd", "s3cr3t_with-chars")));
+        assert_eq!(ApiKey::parse("ak_0123abcd"), None);
+        assert_eq!(ApiKey::parse("ak__secret"), None);
+        assert_eq!(ApiKey::parse("eyJhbGciOiJIUzI1NiJ9.e30.sig"), None);
+    }
+
+    #[test]
+    fn test_only_the_secret_digest_is_stored() {
+        let key = api_key(None);
+        assert_ne!(key.secret_hash, "s3cr3t_with-chars");
+        assert!(key.verify_secret("s3cr3t_with-chars"));
+        assert!(!key.verify_secret("wrong"));
+    }
+
+    #[test]
+    fn test_expiry_is_optional() {
+        assert!(!api_key(None).is_expired());
+        assert!(!api_key(Some(Utc::now() + Duration::days(1))).is_expired());
+        assert!(api_key(Some(Utc::now() - Duration::seconds(1))).is_expired());
+    }
+}
@@ -0,0 +1,250 @@
+use crate::domain::{Role, UserId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+/// A security relevant thing that happened, with the details that are specific to it.
+///
+/// Failures carry the code of the error returned to the caller, e.g.
+/// `incorrect_credentials` or `invalid_grant`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuditEvent {
+    SignupSucceeded,
+    SignupFailed {
+        reason: String,
+    },
+    LoginSucceeded,
+    LoginFailed {
+        reason: String,
+    },
+    PasswordChanged,
+    EmailVerified,
+    TwoFactorRequested,
+    TwoFactorVerified,
+    TwoFactorFailed {
+        reason: String,
+    },
+    Logout,
+    TokenRejected {
+        reason: String,
+    },
+    SessionRevoked {
+        session_id: String,
+    },
+    AllSessionsRevoked {
+        count: usize,
+    },
+    ApiKeyCreated {
+        key_id: String,
+    },
+    ApiKeyRevoked {
+        key_id: String,
+    },
+    OrganizationCreated {
+        org_id: String,
+    },
+    OrganizationSwitched {
+        org_id: String,
+    },
+    MemberAdded {
+        org_id: String,
+        member: String,
+        roles: Vec<Role>,
+    },
+    InvitationCreated {
+        invitation_id: String,
+        org_id: String,
+        email: String,
+    },
+    InvitationRevoked {
+        invitation_id: String,
+        org_id: String,
+    },
+    InvitationAccepted {
+        invitation_id: String,
+        org_id: String,
+    },
+    UsersListed,
+    UserViewed {
+        target: String,
+    },
+    UserUpdated {
+        target: String,
+    },
+    UserDisabled {
+        target: String,
+    },
+    UserEnabled {
+        target: String,
+    },
+    UserDeleted {
+        target: String,
+    },
+    PasswordReset {
+        target: String,
+    },
+    UserLoggedOut {
+        target: String,
+    },
+    AuditLogRead,
+    WebhookCreated {
+        webhook_id: String,
+    },
+    WebhookRemoved {
+        webhook_id: String,
+    },
+    ClientRegistered {
+        client_id: String,
+    },
+    ClientRegistrationFailed {
+        reason: String,
+    },
+    AuthorizationCodeIssued {
+        client_id: String,
+    },
+    AuthorizationFailed {
+        client_id: String,
+        reason: String,
+    },
+    TokenIssued {
+        client_id: String,
+        grant_type: String,
+    },
+    TokenRequestFailed {
+        client_id: Option<String>,
+        grant_type: String,
+        reason: String,
+    },
+    TokenRevoked {
+        client_id: String,
+    },
+    TokenIntrospected {
+        client_id: String,
+        active: bool,
+    },
+    UserInfoRead {
+        client_id: Option<String>,
+    },
+}
+
+/// An [`AuditEvent`] with who caused it and where the request came from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub id: String,
+    pub at: DateTime<Utc>,
+    /// The email of the user who acted, or tried to, if known.
+    pub user: Option<String>,
+    /// The namespace of `user`, see [`UserId`].
+    #[serde(default)]
+    pub tenant: Option<String>,
+    pub request_id: String,
+    pub ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    pub event: AuditEvent,
+}
+
+impl AuditRecord {
+    /// Creates a new `AuditRecord` instance.
+    #[must_use]
+    pub fn new(
+        id: String,
+        user: Option<&UserId>,
+        request_id: String,
+        ip: Option<IpAddr>,
+        user_agent: Option<String>,
+        event: AuditEvent,
+    ) -> Self {
+        Self {
+            id,
+            at: Utc::now(),
+            user: user.map(|user| user.email.as_ref().to_owned()),
+            tenant: user.and_then(|user| user.tenant.clone()),
+            request_id,
+            ip,
+            user_agent,
+            event,
+        }
+    }
+
+    pub fn matches(&self, query: &AuditQuery) -> bool {
+        self.tenant == query.tenant
+            && query
+                .user
+                .as_ref()
+                .is_none_or(|user| self.user.as_ref() == Some(user))
+            && query.from.is_none_or(|from| self.at >= from)
+            && query.to.is_none_or(|to| self.at <= to)
+    }
+}
+
+/// Which records to return from [`AuditSink::query`](crate::domain::AuditSink::query),
+/// most recent first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditQuery {
+    /// Only return records of users in this namespace, see [`UserId`].
+    pub tenant: Option<String>,
+    /// Only return records of the user with this email.
+    pub user: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Email;
+    use chrono::Duration;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_serializes_event_with_its_type() {
+        let event = AuditEvent::LoginFailed {
+            reason: "incorrect_credentials".to_owned(),
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "type": "login_failed", "reason": "incorrect_credentials" })
+        );
+        assert_eq!(serde_json::from_value::<AuditEvent>(json).unwrap(), event);
+    }
+
+    #[test]
+    fn test_matches_user_tenant_and_time_range() {
+        let user = UserId::new(None, Email::from_str("a@example.com").unwrap());
+        let record = AuditRecord::new(
+            "1".to_owned(),
+            Some(&user),
+            "request".to_owned(),
+            None,
+            None,
+            AuditEvent::LoginSucceeded,
+        );
+        let query = AuditQuery {
+            user: Some("a@example.com".to_owned()),
+            from: Some(record.at - Duration::minutes(1)),
+            to: Some(record.at),
+            ..AuditQuery::default()
+        };
+        assert!(record.matches(&query));
+
+        for query in [
+            AuditQuery {
+                user: Some("b@example.com".to_owned()),
+                ..query.clone()
+            },
+            AuditQuery {
+                tenant: Some("acme".to_owned()),
+                ..query.clone()
+            },
+            AuditQuery {
+                from: Some(record.at + Duration::seconds(1)),
+                ..query.clone()
+            },
+        ] {
+            assert!(!record.matches(&query), "{query:?}");
+        }
+    }
+}
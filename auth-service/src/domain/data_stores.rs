@@ -1,5 +1,10 @@
-use crate::domain::{Email, Password, User};
+use crate::domain::{
+    ApiKey, AuditQuery, AuditRecord, AuthorizationCode, DeliveryAttempt, Email, Invitation,
+    OAuthClient, Organization, OutboxEntry, Password, PendingLogin, RefreshToken, Role, Session,
+    User, UserId, Webhook, WebhookDelivery,
+};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 
 #[derive(Debug, PartialEq)]
 pub enum UserStoreError {
@@ -9,12 +14,72 @@ pub enum UserStoreError {
     UnexpectedError,
 }
 
+/// Which page of users to return from [`UserStore::list_users`], ordered by email.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserQuery {
+    /// Only return users in this namespace, see [`UserId`].
+    pub tenant: Option<String>,
+    /// Only return users whose email contains this, ignoring case.
+    pub email_contains: Option<String>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserPage {
+    pub users: Vec<User>,
+    /// The number of users matching the query across all pages.
+    pub total: usize,
+}
+
+/// The domain events a store recorded along with its changes, see
+/// [`DomainEvent`](crate::domain::DomainEvent).
+#[async_trait]
+pub trait EventOutbox: Send + Sync {
+    /// Returns the entries that are due, oldest first, leaving out parked ones.
+    async fn pending_events(
+        &self,
+        now: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<OutboxEntry>, UserStoreError>;
+    /// Removes an entry once every handler has handled it.
+    async fn complete_event(&mut self, id: &str) -> Result<(), UserStoreError>;
+    /// Counts a failed attempt and puts the entry off until `next_attempt_at`.
+    async fn retry_event(
+        &mut self,
+        id: &str,
+        next_attempt_at: DateTime<Utc>,
+    ) -> Result<(), UserStoreError>;
+    /// Counts the last failed attempt and parks the entry, see
+    /// [`OutboxEntry::parked_at`](crate::domain::OutboxEntry::parked_at).
+    async fn park_event(&mut self, id: &str, at: DateTime<Utc>) -> Result<(), UserStoreError>;
+    /// Returns the parked entries, oldest first.
+    async fn parked_events(&self, limit: usize) -> Result<Vec<OutboxEntry>, UserStoreError>;
+}
+
+/// Mutations record the events they cause in the outbox of the store in the same step, so
+/// that a change is never committed without them: `add_user` records `UserCreated`,
+/// `update_user` `UserVerified` when the email becomes verified, `change_email`
+/// `UserEmailChanged` and `delete_user` `UserDeleted`.
 #[async_trait]
-pub trait UserStore: Send + Sync {
+pub trait UserStore: EventOutbox + Send + Sync {
     async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
-    async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
-    async fn validate_user(&self, email: &Email, password: &Password)
-        -> Result<(), UserStoreError>;
+    async fn get_user(&self, id: &UserId) -> Result<User, UserStoreError>;
+    async fn validate_user(&self, id: &UserId, password: &Password) -> Result<(), UserStoreError>;
+    async fn set_roles(&mut self, id: &UserId, roles: Vec<Role>) -> Result<(), UserStoreError>;
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError>;
+    /// Replaces the stored user that has the same id.
+    async fn update_user(&mut self, user: User) -> Result<(), UserStoreError>;
+    /// Moves the user to a new email, which is part of their id, and returns the user.
+    async fn change_email(&mut self, id: &UserId, email: Email) -> Result<User, UserStoreError>;
+    async fn delete_user(&mut self, id: &UserId) -> Result<User, UserStoreError>;
+    /// Returns the users that are members of the organization.
+    async fn get_members(&self, org_id: &str) -> Result<Vec<User>, UserStoreError>;
+    /// Checks that the backend of the store can serve requests, for `/health/ready`. Stores
+    /// that keep everything in memory are always healthy.
+    async fn health_check(&self) -> Result<(), UserStoreError> {
+        Ok(())
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -27,4 +92,254 @@ pub enum TokenStoreError {
 pub trait BannedTokenStore: Send + Sync {
     async fn ban_token(&mut self, token: &str) -> Result<(), TokenStoreError>;
     async fn is_token_banned(&self, token: &str) -> Result<bool, TokenStoreError>;
+    /// Returns how many tokens are banned.
+    async fn count(&self) -> Result<usize, TokenStoreError>;
+    /// See [`UserStore::health_check`].
+    async fn health_check(&self) -> Result<(), TokenStoreError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ClientStoreError {
+    ClientAlreadyExists,
+    ClientNotFound,
+    UnexpectedError,
+}
+
+#[async_trait]
+pub trait ClientStore: Send + Sync {
+    async fn add_client(&mut self, client: OAuthClient) -> Result<(), ClientStoreError>;
+    async fn get_client(&self, client_id: &str) -> Result<OAuthClient, ClientStoreError>;
+    /// See [`UserStore::health_check`].
+    async fn health_check(&self) -> Result<(), ClientStoreError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AuthorizationCodeStoreError {
+    CodeAlreadyExists,
+    CodeNotFound,
+    UnexpectedError,
+}
+
+#[async_trait]
+pub trait AuthorizationCodeStore: Send + Sync {
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+    ) -> Result<(), AuthorizationCodeStoreError>;
+    /// Removes and returns the code, so that each code can only be redeemed once.
+    async fn take_code(
+        &mut self,
+        code: &str,
+    ) -> Result<AuthorizationCode, AuthorizationCodeStoreError>;
+    /// See [`UserStore::health_check`].
+    async fn health_check(&self) -> Result<(), AuthorizationCodeStoreError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RefreshTokenStoreError {
+    TokenAlreadyExists,
+    TokenNotFound,
+    UnexpectedError,
+}
+
+#[async_trait]
+pub trait RefreshTokenStore: Send + Sync {
+    async fn add_refresh_token(
+        &mut self,
+        token: RefreshToken,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn get_refresh_token(&self, token: &str) -> Result<RefreshToken, RefreshTokenStoreError>;
+    async fn revoke_refresh_token(&mut self, token: &str) -> Result<(), RefreshTokenStoreError>;
+    /// Revokes every refresh token of the user and returns how many there were.
+    async fn revoke_all_for_user(&mut self, user: &UserId)
+        -> Result<usize, RefreshTokenStoreError>;
+    /// See [`UserStore::health_check`].
+    async fn health_check(&self) -> Result<(), RefreshTokenStoreError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SessionStoreError {
+    SessionAlreadyExists,
+    SessionNotFound,
+    UnexpectedError,
+}
+
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError>;
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError>;
+    /// Returns the sessions of a user that have not expired yet, most recent first.
+    async fn get_sessions(&self, user: &UserId) -> Result<Vec<Session>, SessionStoreError>;
+    async fn touch_session(&mut self, id: &str, at: DateTime<Utc>)
+        -> Result<(), SessionStoreError>;
+    async fn remove_session(&mut self, id: &str) -> Result<Session, SessionStoreError>;
+    /// See [`UserStore::health_check`].
+    async fn health_check(&self) -> Result<(), SessionStoreError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PendingLoginStoreError {
+    LoginAlreadyExists,
+    LoginNotFound,
+    UnexpectedError,
+}
+
+#[async_trait]
+pub trait PendingLoginStore: Send + Sync {
+    async fn add_pending_login(
+        &mut self,
+        login: PendingLogin,
+    ) -> Result<(), PendingLoginStoreError>;
+    /// Returns the login unless it has expired.
+    async fn get_pending_login(&self, id: &str) -> Result<PendingLogin, PendingLoginStoreError>;
+    /// Counts a wrong code entered for the login and returns how many there have been.
+    async fn record_failed_attempt(&mut self, id: &str) -> Result<u32, PendingLoginStoreError>;
+    async fn remove_pending_login(
+        &mut self,
+        id: &str,
+    ) -> Result<PendingLogin, PendingLoginStoreError>;
+    /// See [`UserStore::health_check`].
+    async fn health_check(&self) -> Result<(), PendingLoginStoreError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum OrganizationStoreError {
+    OrganizationAlreadyExists,
+    OrganizationNotFound,
+    UnexpectedError,
+}
+
+#[async_trait]
+pub trait OrganizationStore: Send + Sync {
+    async fn add_organization(
+        &mut self,
+        organization: Organization,
+    ) -> Result<(), OrganizationStoreError>;
+    async fn get_organization(&self, id: &str) -> Result<Organization, OrganizationStoreError>;
+    /// See [`UserStore::health_check`].
+    async fn health_check(&self) -> Result<(), OrganizationStoreError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum InvitationStoreError {
+    InvitationAlreadyExists,
+    InvitationNotFound,
+    UnexpectedError,
+}
+
+#[async_trait]
+pub trait InvitationStore: Send + Sync {
+    async fn add_invitation(&mut self, invitation: Invitation) -> Result<(), InvitationStoreError>;
+    async fn get_invitation(&self, id: &str) -> Result<Invitation, InvitationStoreError>;
+    /// Returns the invitations of an organization that have not expired yet, most recent first.
+    async fn get_invitations(&self, org_id: &str) -> Result<Vec<Invitation>, InvitationStoreError>;
+    async fn remove_invitation(&mut self, id: &str) -> Result<Invitation, InvitationStoreError>;
+    /// Removes and returns the invitation unless it has expired, so that each invitation can
+    /// only be accepted once.
+    async fn take_invitation(&mut self, id: &str) -> Result<Invitation, InvitationStoreError>;
+    /// See [`UserStore::health_check`].
+    async fn health_check(&self) -> Result<(), InvitationStoreError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ApiKeyStoreError {
+    ApiKeyAlreadyExists,
+    ApiKeyNotFound,
+    UnexpectedError,
+}
+
+#[async_trait]
+pub trait ApiKeyStore: Send + Sync {
+    async fn add_api_key(&mut self, api_key: ApiKey) -> Result<(), ApiKeyStoreError>;
+    async fn get_api_key(&self, id: &str) -> Result<ApiKey, ApiKeyStoreError>;
+    /// Returns the keys of a user that have not expired yet, most recent first.
+    async fn get_api_keys(&self, user: &UserId) -> Result<Vec<ApiKey>, ApiKeyStoreError>;
+    async fn touch_api_key(&mut self, id: &str, at: DateTime<Utc>) -> Result<(), ApiKeyStoreError>;
+    async fn remove_api_key(&mut self, id: &str) -> Result<ApiKey, ApiKeyStoreError>;
+    /// Removes every key of the user and returns how many there were.
+    async fn revoke_all_for_user(&mut self, user: &UserId) -> Result<usize, ApiKeyStoreError>;
+    /// See [`UserStore::health_check`].
+    async fn health_check(&self) -> Result<(), ApiKeyStoreError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AuditSinkError {
+    UnexpectedError,
+}
+
+/// Where audit records are kept. Sinks are append-only: records cannot be changed or removed
+/// through this trait.
+#[async_trait]
+pub trait AuditSink: Send + Sync {
+    async fn record(&mut self, record: AuditRecord) -> Result<(), AuditSinkError>;
+    /// Returns the records matching the query, most recent first.
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, AuditSinkError>;
+    /// Checks that records can be written, see [`UserStore::health_check`].
+    async fn health_check(&self) -> Result<(), AuditSinkError> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WebhookStoreError {
+    WebhookAlreadyExists,
+    WebhookNotFound,
+    UnexpectedError,
+}
+
+/// Webhooks together with the outbox of their deliveries and the log of delivery attempts.
+#[async_trait]
+pub trait WebhookStore: Send + Sync {
+    async fn add_webhook(&mut self, webhook: Webhook) -> Result<(), WebhookStoreError>;
+    async fn get_webhook(&self, id: &str) -> Result<Webhook, WebhookStoreError>;
+    /// Returns the webhooks registered in the namespace, most recent first.
+    async fn get_webhooks(&self, tenant: Option<&str>) -> Result<Vec<Webhook>, WebhookStoreError>;
+    /// Removes the webhook and drops its pending deliveries.
+    async fn remove_webhook(&mut self, id: &str) -> Result<Webhook, WebhookStoreError>;
+    /// Adds deliveries to the outbox, all or none of them. Deliveries with the id of one that
+    /// is already there are skipped.
+    async fn enqueue_deliveries(
+        &mut self,
+        deliveries: Vec<WebhookDelivery>,
+    ) -> Result<(), WebhookStoreError>;
+    /// Returns up to `limit` pending deliveries due at `now`, oldest first.
+    async fn due_deliveries(
+        &self,
+        now: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<WebhookDelivery>, WebhookStoreError>;
+    /// Records an attempt at a delivery along with the new state of the delivery.
+    async fn record_attempt(
+        &mut self,
+        delivery: WebhookDelivery,
+        attempt: DeliveryAttempt,
+    ) -> Result<(), WebhookStoreError>;
+    /// Returns the latest attempts at deliveries to the webhook, most recent first.
+    async fn get_attempts(
+        &self,
+        webhook_id: &str,
+        limit: usize,
+    ) -> Result<Vec<DeliveryAttempt>, WebhookStoreError>;
+    /// See [`UserStore::health_check`].
+    async fn health_check(&self) -> Result<(), WebhookStoreError> {
+        Ok(())
+    }
 }
@@ -17,7 +17,8 @@ impl FromStr for Email {
         };
         match email.validate() {
             Ok(_) => Ok(email),
-            Err(e) => Err(format!("Invalid email format: {}", e)),
+            // The validation errors repeat the value, which is not worth sending back.
+            Err(_) => Err("Invalid email format".to_owned()),
         }
     }
 }
@@ -0,0 +1,18 @@
+use crate::domain::Email;
+use async_trait::async_trait;
+
+#[derive(Debug, PartialEq)]
+pub enum EmailClientError {
+    UnexpectedError,
+}
+
+/// Sends emails to users, e.g. their 2FA codes.
+#[async_trait]
+pub trait EmailClient: Send + Sync {
+    async fn send_email(
+        &self,
+        recipient: &Email,
+        subject: &str,
+        content: &str,
+    ) -> Result<(), EmailClientError>;
+}
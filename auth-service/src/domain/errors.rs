@@ -1,8 +1,131 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug)]
 pub enum AuthAPIError {
     UserAlreadyExists,
     InvalidCredentials,
+    /// Fields of the request that did not parse, e.g. an email that is not one.
+    ValidationFailed(Vec<FieldError>),
     UnexpectedError,
     IncorrectCredentials,
     MissingToken,
     InvalidToken,
+    SessionNotFound,
+    Forbidden,
+    UserNotFound,
+    AccountSuspended,
+    AccountNotVerified,
+    PasswordChangeRequired,
+    OrganizationNotFound,
+    OrganizationAlreadyExists,
+    InvitationNotFound,
+    ApiKeyNotFound,
+    WebhookNotFound,
+    InvalidCsrfToken,
+}
+
+impl AuthAPIError {
+    /// An `UnexpectedError` caused by `err`, which is logged here as it is not passed on to
+    /// the caller.
+    pub fn unexpected(err: impl std::fmt::Debug) -> Self {
+        tracing::error!(cause = ?err, "Unexpected error");
+        AuthAPIError::UnexpectedError
+    }
+
+    /// A `ValidationFailed` for `field`, e.g. as `Email::from_str(..).map_err(..)`.
+    pub fn invalid_field(field: &str, detail: impl Into<String>) -> Self {
+        AuthAPIError::ValidationFailed(vec![FieldError::new(field, detail)])
+    }
+
+    /// A `ValidationFailed` listing every field that has an error, so that callers can fix
+    /// them all at once.
+    pub fn invalid_fields<'a>(fields: impl IntoIterator<Item = (&'a str, Option<String>)>) -> Self {
+        AuthAPIError::ValidationFailed(
+            fields
+                .into_iter()
+                .filter_map(|(field, detail)| detail.map(|detail| FieldError::new(field, detail)))
+                .collect(),
+        )
+    }
+
+    /// A stable name for the error, e.g. for audit records.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AuthAPIError::UserAlreadyExists => "user_already_exists",
+            AuthAPIError::InvalidCredentials => "invalid_credentials",
+            AuthAPIError::ValidationFailed(_) => "validation_failed",
+            AuthAPIError::UnexpectedError => "unexpected_error",
+            AuthAPIError::IncorrectCredentials => "incorrect_credentials",
+            AuthAPIError::MissingToken => "missing_token",
+            AuthAPIError::InvalidToken => "invalid_token",
+            AuthAPIError::SessionNotFound => "session_not_found",
+            AuthAPIError::Forbidden => "forbidden",
+            AuthAPIError::UserNotFound => "user_not_found",
+            AuthAPIError::AccountSuspended => "account_suspended",
+            AuthAPIError::AccountNotVerified => "account_not_verified",
+            AuthAPIError::PasswordChangeRequired => "password_change_required",
+            AuthAPIError::OrganizationNotFound => "organization_not_found",
+            AuthAPIError::OrganizationAlreadyExists => "organization_already_exists",
+            AuthAPIError::InvitationNotFound => "invitation_not_found",
+            AuthAPIError::ApiKeyNotFound => "api_key_not_found",
+            AuthAPIError::WebhookNotFound => "webhook_not_found",
+            AuthAPIError::InvalidCsrfToken => "invalid_csrf_token",
+        }
+    }
+}
+
+/// Why a field of a request was rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub detail: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Error codes defined by RFC 6749 and OpenID Connect for the OAuth endpoints.
+#[derive(Debug, PartialEq)]
+pub enum OAuthError {
+    InvalidRequest,
+    InvalidClient,
+    InvalidGrant,
+    UnauthorizedClient,
+    UnsupportedGrantType,
+    UnsupportedResponseType,
+    InvalidScope,
+    LoginRequired,
+    InvalidToken,
+    InvalidRedirectUri,
+    InvalidClientMetadata,
+    ServerError,
+    AccountInactive,
+}
+
+impl OAuthError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            OAuthError::InvalidRequest => "invalid_request",
+            OAuthError::InvalidClient => "invalid_client",
+            OAuthError::InvalidGrant => "invalid_grant",
+            OAuthError::UnauthorizedClient => "unauthorized_client",
+            OAuthError::UnsupportedGrantType => "unsupported_grant_type",
+            OAuthError::UnsupportedResponseType => "unsupported_response_type",
+            OAuthError::InvalidScope => "invalid_scope",
+            OAuthError::LoginRequired => "login_required",
+            OAuthError::InvalidToken => "invalid_token",
+            OAuthError::InvalidRedirectUri => "invalid_redirect_uri",
+            OAuthError::InvalidClientMetadata => "invalid_client_metadata",
+            OAuthError::ServerError => "server_error",
+            // Not defined by RFC 6749: the grant is fine, but the user it was issued for is
+            // suspended or not verified, which clients may want to tell apart.
+            OAuthError::AccountInactive => "account_inactive",
+        }
+    }
 }
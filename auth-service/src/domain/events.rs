@@ -0,0 +1,73 @@
+use crate::domain::{Email, UserId};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+/// Something that happened to the data of the service, which other parts of it or other
+/// systems react to, e.g. by sending webhooks.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DomainEvent {
+    UserCreated {
+        user: UserId,
+    },
+    /// The email of the user was marked as verified.
+    UserVerified {
+        user: UserId,
+    },
+    /// `user` has the new email.
+    UserEmailChanged {
+        user: UserId,
+        previous_email: Email,
+    },
+    UserDeleted {
+        user: UserId,
+    },
+}
+
+/// A [`DomainEvent`] waiting in the outbox of the store that recorded it, until every
+/// [`EventHandler`] has handled it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxEntry {
+    /// Stays the same across retries, so handlers can use it to drop duplicates.
+    pub id: String,
+    pub event: DomainEvent,
+    pub created_at: DateTime<Utc>,
+    pub attempts: u32,
+    pub next_attempt_at: DateTime<Utc>,
+    /// When the entry ran out of attempts. Parked entries are kept for operators to look
+    /// into but are not handed to the handlers again.
+    pub parked_at: Option<DateTime<Utc>>,
+}
+
+impl OutboxEntry {
+    /// Creates a new `OutboxEntry` instance, due right away.
+    #[must_use]
+    pub fn new(id: String, event: DomainEvent) -> Self {
+        let now = Utc::now();
+        Self {
+            id,
+            event,
+            created_at: now,
+            attempts: 0,
+            next_attempt_at: now,
+            parked_at: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EventHandlerError {
+    UnexpectedError,
+}
+
+/// Reacts to domain events once they are committed.
+///
+/// Events are delivered at least once: an entry is retried until every handler succeeded
+/// for it, including the handlers that already did, so handlers have to be idempotent.
+/// An entry that keeps failing is parked after
+/// [`OutboxSettings::max_attempts`](crate::OutboxSettings::max_attempts).
+#[async_trait]
+pub trait EventHandler: Send + Sync {
+    /// Identifies the handler in logs.
+    fn name(&self) -> &'static str;
+    async fn handle(&self, entry: &OutboxEntry) -> Result<(), EventHandlerError>;
+}
@@ -0,0 +1,45 @@
+use crate::domain::{Email, Role};
+use chrono::{DateTime, Utc};
+
+/// An invite for an email to join an organization with a preassigned role.
+///
+/// Only the id travels in the invite link, signed, see
+/// [`sign_invitation`](crate::utils::auth::sign_invitation).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invitation {
+    pub id: String,
+    pub org_id: String,
+    pub email: Email,
+    pub role: Role,
+    /// Email of the member who sent the invite.
+    pub invited_by: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Invitation {
+    /// Creates a new `Invitation` instance.
+    #[must_use]
+    pub fn new(
+        id: String,
+        org_id: String,
+        email: Email,
+        role: Role,
+        invited_by: String,
+        expires_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id,
+            org_id,
+            email,
+            role,
+            invited_by,
+            created_at: Utc::now(),
+            expires_at,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
@@ -0,0 +1,320 @@
+use crate::domain::UserId;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use ring::digest::{digest, SHA256};
+use std::fmt;
+use subtle::ConstantTimeEq;
+
+/// An OAuth 2.0 / OpenID Connect client registered with the auth service.
+///
+/// Public clients (such as single page apps) have no secret. Confidential clients
+/// authenticate with a secret, of which only the SHA-256 digest is stored, and may
+/// obtain tokens for themselves via the client credentials grant.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct OAuthClient {
+    pub client_id: String,
+    pub redirect_uris: Vec<String>,
+    pub secret_hash: Option<String>,
+    pub scope: Scope,
+}
+
+impl OAuthClient {
+    /// Creates a new public `OAuthClient` instance.
+    #[must_use]
+    pub fn new(client_id: String, redirect_uris: Vec<String>) -> Self {
+        Self {
+            client_id,
+            redirect_uris,
+            secret_hash: None,
+            scope: Scope::default(),
+        }
+    }
+
+    /// Creates a new confidential `OAuthClient` instance, allowed to request `scope`.
+    #[must_use]
+    pub fn confidential(
+        client_id: String,
+        client_secret: &str,
+        redirect_uris: Vec<String>,
+        scope: Scope,
+    ) -> Self {
+        Self {
+            client_id,
+            redirect_uris,
+            secret_hash: Some(hash_secret(client_secret)),
+            scope,
+        }
+    }
+
+    pub fn is_confidential(&self) -> bool {
+        self.secret_hash.is_some()
+    }
+
+    pub fn verify_secret(&self, client_secret: &str) -> bool {
+        match &self.secret_hash {
+            Some(secret_hash) => secret_hash
+                .as_bytes()
+                .ct_eq(hash_secret(client_secret).as_bytes())
+                .into(),
+            None => false,
+        }
+    }
+
+    /// Redirect URIs are compared with simple string matching, as required by OpenID Connect.
+    pub fn allows_redirect_uri(&self, redirect_uri: &str) -> bool {
+        self.redirect_uris.iter().any(|uri| uri == redirect_uri)
+    }
+}
+
+// Client secrets and API keys are long random values, so a fast digest is enough to protect
+// them at rest.
+pub(crate) fn hash_secret(secret: &str) -> String {
+    digest(&SHA256, secret.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// A space-delimited list of OAuth scopes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scope(Vec<String>);
+
+impl Scope {
+    pub const OPENID: &'static str = "openid";
+    pub const EMAIL: &'static str = "email";
+
+    pub fn parse(value: &str) -> Self {
+        let mut scopes: Vec<String> = Vec::new();
+        for scope in value.split_whitespace() {
+            if !scopes.iter().any(|s| s == scope) {
+                scopes.push(scope.to_owned());
+            }
+        }
+        Self(scopes)
+    }
+
+    pub fn contains(&self, scope: &str) -> bool {
+        self.0.iter().any(|s| s == scope)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn is_subset_of(&self, other: &Scope) -> bool {
+        self.0.iter().all(|scope| other.contains(scope))
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.join(" "))
+    }
+}
+
+/// A single-use code handed to a relying party by `/authorize` and redeemed at `/token`.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct AuthorizationCode {
+    pub code: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub user: UserId,
+    pub scope: Scope,
+    pub nonce: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    /// The PKCE (RFC 7636) `S256` challenge the code was requested with, if any.
+    pub code_challenge: Option<String>,
+}
+
+impl AuthorizationCode {
+    /// Creates a new `AuthorizationCode` instance.
+    #[must_use]
+    pub fn new(
+        code: String,
+        client_id: String,
+        redirect_uri: String,
+        user: UserId,
+        scope: Scope,
+        nonce: Option<String>,
+        expires_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            code,
+            client_id,
+            redirect_uri,
+            user,
+            scope,
+            nonce,
+            expires_at,
+            code_challenge: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_code_challenge(mut self, code_challenge: Option<String>) -> Self {
+        self.code_challenge = code_challenge;
+        self
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+
+    /// Whether `code_verifier` proves that the code is redeemed by whoever requested it. Codes
+    /// requested without a challenge need no verifier.
+    pub fn verifies(&self, code_verifier: Option<&str>) -> bool {
+        match (&self.code_challenge, code_verifier) {
+            (None, _) => true,
+            (Some(challenge), Some(verifier)) => {
+                is_code_verifier(verifier)
+                    && s256_code_challenge(verifier)
+                        .as_bytes()
+                        .ct_eq(challenge.as_bytes())
+                        .into()
+            }
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// The `S256` PKCE challenge for `code_verifier`.
+pub fn s256_code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(digest(&SHA256, code_verifier.as_bytes()))
+}
+
+/// Whether `value` is shaped like an `S256` challenge, i.e. an unpadded base64url SHA-256 digest.
+pub fn is_s256_code_challenge(value: &str) -> bool {
+    value.len() == 43
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+}
+
+// RFC 7636 section 4.1: 43 to 128 unreserved characters.
+fn is_code_verifier(value: &str) -> bool {
+    (43..=128).contains(&value.len())
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"-._~".contains(&byte))
+}
+
+/// A long-lived token a client can exchange for new access tokens at `/token`.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct RefreshToken {
+    pub token: String,
+    pub client_id: String,
+    pub user: UserId,
+    pub scope: Scope,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl RefreshToken {
+    /// Creates a new `RefreshToken` instance.
+    #[must_use]
+    pub fn new(
+        token: String,
+        client_id: String,
+        user: UserId,
+        scope: Scope,
+        expires_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            token,
+            client_id,
+            user,
+            scope,
+            expires_at,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_parse_deduplicates() {
+        let scope = Scope::parse("openid  email openid");
+        assert!(scope.contains(Scope::OPENID));
+        assert!(scope.contains(Scope::EMAIL));
+        assert_eq!(scope.to_string(), "openid email");
+    }
+
+    #[test]
+    fn test_scope_empty() {
+        assert!(Scope::parse("   ").is_empty());
+    }
+
+    #[test]
+    fn test_scope_subset() {
+        let allowed = Scope::parse("users:read users:write");
+        assert!(Scope::parse("users:read").is_subset_of(&allowed));
+        assert!(Scope::default().is_subset_of(&allowed));
+        assert!(!Scope::parse("users:read admin").is_subset_of(&allowed));
+    }
+
+    #[test]
+    fn test_confidential_client_secret() {
+        let client = OAuthClient::confidential(
+            "service".to_owned(),
+            "s3cr3t",
+            vec![],
+            Scope::parse("users:read"),
+        );
+        assert!(client.is_confidential());
+        assert_ne!(client.secret_hash.as_deref(), Some("s3cr3t"));
+        assert!(client.verify_secret("s3cr3t"));
+        assert!(!client.verify_secret("wrong"));
+    }
+
+    #[test]
+    fn test_public_client_has_no_secret() {
+        let client = OAuthClient::new("spa".to_owned(), vec![]);
+        assert!(!client.is_confidential());
+        assert!(!client.verify_secret(""));
+    }
+
+    #[test]
+    fn test_client_redirect_uri_exact_match() {
+        let client = OAuthClient::new(
+            "client".to_owned(),
+            vec!["https://rp.example.com/callback".to_owned()],
+        );
+        assert!(client.allows_redirect_uri("https://rp.example.com/callback"));
+        assert!(!client.allows_redirect_uri("https://rp.example.com/callback/"));
+        assert!(!client.allows_redirect_uri("https://evil.example.com/callback"));
+    }
+
+    #[test]
+    fn test_code_verifier_matches_s256_challenge() {
+        // The example of RFC 7636 appendix B.
+        let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        let challenge = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+        assert_eq!(s256_code_challenge(verifier), challenge);
+        assert!(is_s256_code_challenge(challenge));
+
+        let code = AuthorizationCode::new(
+            "code".to_owned(),
+            "client".to_owned(),
+            "https://rp.example.com/callback".to_owned(),
+            UserId::new(None, "user@example.com".parse().unwrap()),
+            Scope::parse("openid"),
+            None,
+            Utc::now(),
+        );
+        assert!(code.verifies(None));
+        let code = code.with_code_challenge(Some(challenge.to_owned()));
+        assert!(code.verifies(Some(verifier)));
+        assert!(!code.verifies(None));
+        assert!(!code.verifies(Some(&verifier.replace('d', "e"))));
+    }
+}
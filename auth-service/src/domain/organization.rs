@@ -0,0 +1,58 @@
+use crate::domain::Role;
+use serde::{Deserialize, Serialize};
+
+/// A customer company. Users join organizations through a [`Membership`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Organization {
+    pub id: String,
+    pub name: String,
+}
+
+impl Organization {
+    /// Creates a new `Organization` instance.
+    #[must_use]
+    pub fn new(id: String, name: String) -> Self {
+        Self { id, name }
+    }
+
+    /// Organization ids end up in URLs and tokens, so they are kept to lowercase slugs.
+    pub fn is_valid_id(id: &str) -> bool {
+        (1..=63).contains(&id.len())
+            && id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            && !id.starts_with('-')
+    }
+}
+
+/// The roles a user has within one organization, on top of their own roles.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Membership {
+    pub org_id: String,
+    pub roles: Vec<Role>,
+}
+
+impl Membership {
+    /// Creates a new `Membership` instance.
+    #[must_use]
+    pub fn new(org_id: String, roles: Vec<Role>) -> Self {
+        Self { org_id, roles }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_ids() {
+        assert!(Organization::is_valid_id("acme"));
+        assert!(Organization::is_valid_id("acme-42"));
+        assert!(!Organization::is_valid_id(""));
+        assert!(!Organization::is_valid_id("-acme"));
+        assert!(!Organization::is_valid_id("Acme"));
+        assert!(!Organization::is_valid_id("acme/evil"));
+        assert!(!Organization::is_valid_id(&"a".repeat(64)));
+    }
+}
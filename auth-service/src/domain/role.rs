@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A permission that handlers can require with
+/// [`RequirePermission`](crate::utils::extractors::RequirePermission).
+///
+/// Permissions are types rather than strings so that a typo fails to compile.
+pub trait Permission {
+    const NAME: &'static str;
+}
+
+macro_rules! permissions {
+    ($($(#[$meta:meta])* $name:ident => $value:literal),* $(,)?) => {
+        $(
+            $(#[$meta])*
+            #[derive(Debug, Clone, Copy)]
+            pub struct $name;
+
+            impl Permission for $name {
+                const NAME: &'static str = $value;
+            }
+        )*
+    };
+}
+
+permissions! {
+    /// Look up other users' accounts.
+    UsersRead => "users:read",
+    /// Change other users' accounts.
+    UsersWrite => "users:write",
+    /// See who belongs to the current organization.
+    MembersRead => "members:read",
+    /// Add people to the current organization or change their roles in it.
+    MembersWrite => "members:write",
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    User,
+    Admin,
+    /// Manages the members of an organization; only meaningful in a [`Membership`].
+    ///
+    /// [`Membership`]: crate::domain::Membership
+    OrgAdmin,
+}
+
+impl Role {
+    /// The permissions granted by the role, on top of acting on one's own account.
+    pub fn permissions(&self) -> &'static [&'static str] {
+        match self {
+            Role::User => &[],
+            Role::Admin => &[UsersRead::NAME, UsersWrite::NAME],
+            Role::OrgAdmin => &[MembersRead::NAME, MembersWrite::NAME],
+        }
+    }
+
+    /// Whether the role can be granted through an organization; global roles cannot.
+    pub fn is_org_role(&self) -> bool {
+        matches!(self, Role::User | Role::OrgAdmin)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Admin => "admin",
+            Role::OrgAdmin => "org_admin",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(Role::User),
+            "admin" => Ok(Role::Admin),
+            "org_admin" => Ok(Role::OrgAdmin),
+            _ => Err(format!("Unknown role: {}", s)),
+        }
+    }
+}
+
+/// The permissions granted by any of the roles, sorted and without duplicates.
+pub fn permissions_of(roles: &[Role]) -> Vec<String> {
+    let mut permissions: Vec<String> = roles
+        .iter()
+        .flat_map(Role::permissions)
+        .map(|permission| (*permission).to_owned())
+        .collect();
+    permissions.sort();
+    permissions.dedup();
+    permissions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admin_can_read_and_write_users() {
+        assert_eq!(
+            permissions_of(&[Role::User, Role::Admin]),
+            vec!["users:read", "users:write"]
+        );
+        assert!(permissions_of(&[Role::User]).is_empty());
+    }
+
+    #[test]
+    fn test_org_admin_cannot_manage_users() {
+        assert_eq!(
+            permissions_of(&[Role::User, Role::OrgAdmin]),
+            vec!["members:read", "members:write"]
+        );
+    }
+
+    #[test]
+    fn test_role_round_trips_through_strings() {
+        for role in [Role::User, Role::Admin, Role::OrgAdmin] {
+            assert_eq!(Role::from_str(role.as_str()), Ok(role));
+        }
+        assert!(Role::from_str("root").is_err());
+    }
+}
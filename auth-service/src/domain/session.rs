@@ -0,0 +1,47 @@
+use crate::domain::UserId;
+use chrono::{DateTime, Utc};
+use std::net::IpAddr;
+
+/// A browser or API login, identified by the `jti` of the token issued for it.
+///
+/// The token itself is kept so that revoking the session can ban it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub id: String,
+    pub user: UserId,
+    pub token: String,
+    pub created_at: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub user_agent: Option<String>,
+    pub ip: Option<IpAddr>,
+}
+
+impl Session {
+    /// Creates a new `Session` instance.
+    #[must_use]
+    pub fn new(
+        id: String,
+        user: UserId,
+        token: String,
+        expires_at: DateTime<Utc>,
+        user_agent: Option<String>,
+        ip: Option<IpAddr>,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            id,
+            user,
+            token,
+            created_at: now,
+            last_seen: now,
+            expires_at,
+            user_agent,
+            ip,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
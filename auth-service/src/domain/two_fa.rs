@@ -0,0 +1,49 @@
+use crate::domain::UserId;
+use chrono::{DateTime, Utc};
+use subtle::ConstantTimeEq;
+
+/// A login whose password was correct and that waits for the 2FA code sent to the user,
+/// see `/verify-2fa`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingLogin {
+    /// The `loginAttemptId` returned by `/login`.
+    pub id: String,
+    pub user: UserId,
+    /// The organization the login is scoped to, if any.
+    pub org_id: Option<String>,
+    pub code: String,
+    pub expires_at: DateTime<Utc>,
+    pub failed_attempts: u32,
+}
+
+impl PendingLogin {
+    /// Wrong codes accepted before the login is dropped and has to be started over.
+    pub const MAX_ATTEMPTS: u32 = 5;
+
+    /// Creates a new `PendingLogin` instance.
+    #[must_use]
+    pub fn new(
+        id: String,
+        user: UserId,
+        org_id: Option<String>,
+        code: String,
+        expires_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id,
+            user,
+            org_id,
+            code,
+            expires_at,
+            failed_attempts: 0,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+
+    pub fn verifies(&self, code: &str) -> bool {
+        bool::from(self.code.as_bytes().ct_eq(code.as_bytes()))
+    }
+}
@@ -1,13 +1,77 @@
-use crate::domain::{Email, Password};
-use serde::Deserialize;
+use crate::domain::{AuthAPIError, Email, Membership, Password, Role};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Whether an account may be used. Only active users can log in or refresh tokens;
+/// the data of the others is kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserStatus {
+    #[default]
+    Active,
+    Suspended,
+    PendingVerification,
+}
+
+/// Identifies a user account.
+///
+/// Emails are unique across the deployment by default and `tenant` is `None`. When they are
+/// unique per tenant instead, the same email can sign up once in every organization and the
+/// organization it signed up in becomes part of the identity.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UserId {
+    pub tenant: Option<String>,
+    pub email: Email,
+}
+
+impl UserId {
+    /// Creates a new `UserId` instance.
+    #[must_use]
+    pub fn new(tenant: Option<String>, email: Email) -> Self {
+        Self { tenant, email }
+    }
+}
+
+impl From<Email> for UserId {
+    fn from(email: Email) -> Self {
+        Self::new(None, email)
+    }
+}
 
 #[derive(Debug, Clone, Deserialize, PartialEq)]
 #[non_exhaustive]
 pub struct User {
+    /// Opaque identifier of the account that does not change with its email, used as the
+    /// `sub` of ID tokens and userinfo responses.
+    #[serde(default = "new_subject")]
+    pub subject: String,
     pub email: Email,
+    /// The organization whose namespace the email is unique in, see [`UserId`].
+    #[serde(default)]
+    pub tenant: Option<String>,
     pub password: Password,
     #[serde(rename = "requires2FA")]
     pub requires_2fa: bool,
+    #[serde(rename = "emailVerified", default)]
+    pub email_verified: bool,
+    #[serde(default = "default_roles")]
+    pub roles: Vec<Role>,
+    #[serde(default)]
+    pub status: UserStatus,
+    #[serde(default)]
+    pub memberships: Vec<Membership>,
+    /// Set when an operator resets the password, which the user then has to replace before
+    /// they can log in.
+    #[serde(rename = "mustChangePassword", default)]
+    pub must_change_password: bool,
+}
+
+fn new_subject() -> String {
+    Uuid::new_v4().to_string()
+}
+
+fn default_roles() -> Vec<Role> {
+    vec![Role::User]
 }
 
 impl User {
@@ -15,9 +79,122 @@ impl User {
     #[must_use]
     pub fn new(email: Email, password: Password, requires_2fa: bool) -> Self {
         Self {
+            subject: new_subject(),
             email,
+            tenant: None,
             password,
             requires_2fa,
+            email_verified: false,
+            roles: default_roles(),
+            status: UserStatus::Active,
+            memberships: Vec::new(),
+            must_change_password: false,
+        }
+    }
+
+    pub fn id(&self) -> UserId {
+        UserId::new(self.tenant.clone(), self.email.clone())
+    }
+
+    #[must_use]
+    pub fn with_tenant(mut self, tenant: Option<String>) -> Self {
+        self.tenant = tenant;
+        self
+    }
+
+    pub fn membership(&self, org_id: &str) -> Option<&Membership> {
+        self.memberships
+            .iter()
+            .find(|membership| membership.org_id == org_id)
+    }
+
+    /// Adds the membership, or replaces the roles if the user is a member already.
+    pub fn join(&mut self, membership: Membership) {
+        match self
+            .memberships
+            .iter_mut()
+            .find(|existing| existing.org_id == membership.org_id)
+        {
+            Some(existing) => existing.roles = membership.roles,
+            None => self.memberships.push(membership),
+        }
+    }
+
+    /// The user's own roles plus those they have in the organization, if any.
+    pub fn roles_in(&self, org_id: Option<&str>) -> Vec<Role> {
+        let mut roles = self.roles.clone();
+        if let Some(membership) = org_id.and_then(|org_id| self.membership(org_id)) {
+            for role in &membership.roles {
+                if !roles.contains(role) {
+                    roles.push(*role);
+                }
+            }
         }
+        roles
+    }
+
+    #[must_use]
+    pub fn with_roles(mut self, roles: Vec<Role>) -> Self {
+        self.roles = roles;
+        self
+    }
+
+    pub fn has_role(&self, role: Role) -> bool {
+        self.roles.contains(&role)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.status == UserStatus::Active
+    }
+
+    /// Fails with the error that tells the user why their account cannot be used.
+    pub fn ensure_active(&self) -> Result<(), AuthAPIError> {
+        match self.status {
+            UserStatus::Active => Ok(()),
+            UserStatus::Suspended => Err(AuthAPIError::AccountSuspended),
+            UserStatus::PendingVerification => Err(AuthAPIError::AccountNotVerified),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn user() -> User {
+        User::new(
+            Email::from_str("test@test.com").unwrap(),
+            Password::from_str("password").unwrap(),
+            false,
+        )
+    }
+
+    #[test]
+    fn test_roles_in_organization() {
+        let mut user = user();
+        user.join(Membership::new("acme".to_owned(), vec![Role::Admin]));
+        user.join(Membership::new("globex".to_owned(), vec![Role::User]));
+
+        assert_eq!(user.roles_in(None), vec![Role::User]);
+        assert_eq!(user.roles_in(Some("acme")), vec![Role::User, Role::Admin]);
+        assert_eq!(user.roles_in(Some("globex")), vec![Role::User]);
+        assert_eq!(user.roles_in(Some("initech")), vec![Role::User]);
+    }
+
+    #[test]
+    fn test_join_replaces_roles() {
+        let mut user = user();
+        user.join(Membership::new("acme".to_owned(), vec![Role::Admin]));
+        user.join(Membership::new("acme".to_owned(), vec![Role::User]));
+        assert_eq!(user.memberships.len(), 1);
+        assert_eq!(user.membership("acme").unwrap().roles, vec![Role::User]);
+    }
+
+    #[test]
+    fn test_id_includes_tenant() {
+        let user = user().with_tenant(Some("acme".to_owned()));
+        assert_eq!(user.id().tenant.as_deref(), Some("acme"));
+        assert_ne!(user.id(), UserId::from(user.email.clone()));
     }
 }
@@ -0,0 +1,148 @@
+use crate::domain::UserId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The user lifecycle events a webhook can subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum WebhookEventType {
+    #[serde(rename = "user.created")]
+    UserCreated,
+    /// The email of the user was marked as verified.
+    #[serde(rename = "user.verified")]
+    UserVerified,
+    /// `data.email` is the new email and `data.previous_email` the one it replaced.
+    #[serde(rename = "user.email_changed")]
+    UserEmailChanged,
+    #[serde(rename = "user.deleted")]
+    UserDeleted,
+}
+
+/// An endpoint of a downstream system that is notified of user lifecycle events.
+///
+/// Webhooks belong to the namespace of the operator who registered them, see [`UserId`], and
+/// only hear about users in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Webhook {
+    pub id: String,
+    pub tenant: Option<String>,
+    pub url: String,
+    /// Key for the signatures of the deliveries, which receivers use to check them.
+    pub secret: String,
+    pub events: Vec<WebhookEventType>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Webhook {
+    /// Creates a new `Webhook` instance.
+    #[must_use]
+    pub fn new(
+        id: String,
+        tenant: Option<String>,
+        url: String,
+        secret: String,
+        events: Vec<WebhookEventType>,
+    ) -> Self {
+        Self {
+            id,
+            tenant,
+            url,
+            secret,
+            events,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn subscribes_to(&self, event_type: WebhookEventType) -> bool {
+        self.events.contains(&event_type)
+    }
+}
+
+/// The body of a delivery. `id` is the same for every delivery of the event, so receivers
+/// can drop the duplicates that retries may cause.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub event_type: WebhookEventType,
+    pub created_at: DateTime<Utc>,
+    pub data: WebhookUserData,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookUserData {
+    pub email: String,
+    pub tenant: Option<String>,
+    /// Only set for `user.email_changed`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_email: Option<String>,
+}
+
+impl WebhookEvent {
+    /// Creates a new `WebhookEvent` instance.
+    #[must_use]
+    pub fn new(id: String, event_type: WebhookEventType, user: &UserId) -> Self {
+        Self {
+            id,
+            event_type,
+            created_at: Utc::now(),
+            data: WebhookUserData {
+                email: user.email.as_ref().to_owned(),
+                tenant: user.tenant.clone(),
+                previous_email: None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    /// Given up on after the last retry.
+    Failed,
+}
+
+/// An event waiting in the outbox to be delivered to one webhook, or that was.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub webhook_id: String,
+    pub event_id: String,
+    /// The serialized [`WebhookEvent`], sent as is on every attempt so the signature of a
+    /// retry covers the same bytes.
+    pub payload: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub next_attempt_at: DateTime<Utc>,
+}
+
+impl WebhookDelivery {
+    /// Creates a new `WebhookDelivery` instance, due right away.
+    #[must_use]
+    pub fn new(id: String, webhook_id: String, event_id: String, payload: String) -> Self {
+        Self {
+            id,
+            webhook_id,
+            event_id,
+            payload,
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            next_attempt_at: Utc::now(),
+        }
+    }
+}
+
+/// One try at a delivery, kept for operators to debug their receivers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryAttempt {
+    pub delivery_id: String,
+    pub webhook_id: String,
+    pub event_id: String,
+    pub at: DateTime<Utc>,
+    /// The status of the receiver's response, if there was one.
+    pub status_code: Option<u16>,
+    /// Why the request failed before there was a response, e.g. a timeout.
+    pub error: Option<String>,
+    pub succeeded: bool,
+}
@@ -1,75 +1,249 @@
 use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
 
-pub use crate::app_state::AppState;
-use crate::domain::AuthAPIError;
-use crate::routes::{login, logout, signup, verify_2fa, verify_token};
-use axum::http::{Method, StatusCode};
+pub use crate::app_state::{
+    AppConfig, AppState, CookieSettings, EmailUniqueness, ErrorFormat, OutboxSettings,
+    ShutdownSettings, TlsSettings, WebhookSettings,
+};
+use crate::domain::{AuthAPIError, EventHandler, OAuthError};
+use crate::routes::{
+    admin_routes, authorize, change_password, create_api_key, health_routes, introspect,
+    invitation_routes, jwks, list_api_keys, list_sessions, login, logout, metrics,
+    openid_configuration, organization_routes, register_client, revoke, revoke_all_sessions,
+    revoke_api_key, revoke_session, root, signup, token, userinfo, verify_2fa, verify_email,
+    verify_token,
+};
+use crate::utils::constants::CSRF_HEADER;
+use crate::utils::metrics::MetricsLayer;
+use crate::utils::problem::Problem;
+use crate::utils::shutdown::ShutdownHandle;
+use crate::utils::tls::{
+    load_certified_key, redirect_router, spawn_reloader, CertificateResolver, TlsListener,
+};
+use crate::utils::webhooks::WebhookEventHandler;
+use axum::http::{HeaderName, Method, StatusCode};
 use axum::response::{IntoResponse, Response};
-use axum::{serve::Serve, Json, Router};
+use axum::serve::ListenerExt;
+use axum::{Json, Router};
+use security_headers::SecurityHeadersLayer;
 use serde::{Deserialize, Serialize};
-use tokio::net::TcpListener;
+use std::future::{Future, IntoFuture};
+use std::pin::Pin;
+use tokio::task::JoinHandle;
 use tower_http::cors::CorsLayer;
 use tower_http::services::ServeDir;
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tracing::Level;
 
 #[cfg(test)]
 extern crate quickcheck;
 #[cfg(test)]
 extern crate quickcheck_macros;
-mod domain;
+pub mod domain;
 pub mod routes;
 pub mod utils;
 
 mod app_state;
 pub mod services;
 
-#[derive(Serialize, Deserialize)]
+/// The body of errors in the legacy format, and of the errors of the OAuth endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub error: String,
 }
 
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
-            AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
-            AuthAPIError::UnexpectedError => {
-                (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
+        tracing::info!(error = ?self, code = self.code(), "Request failed");
+        let (status, title, detail) = match &self {
+            AuthAPIError::UserAlreadyExists => (
+                StatusCode::CONFLICT,
+                "User already exists",
+                "An account with this email already exists.",
+            ),
+            AuthAPIError::InvalidCredentials => (
+                StatusCode::BAD_REQUEST,
+                "Invalid credentials",
+                "The request has a value that is not allowed.",
+            ),
+            AuthAPIError::ValidationFailed(_) => (
+                StatusCode::BAD_REQUEST,
+                "Invalid fields",
+                "Some fields of the request are invalid, see `errors`.",
+            ),
+            AuthAPIError::UnexpectedError => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Unexpected error",
+                "The request could not be completed. Quote the instance when reporting this.",
+            ),
+            AuthAPIError::IncorrectCredentials => (
+                StatusCode::UNAUTHORIZED,
+                "Incorrect credentials",
+                "The email or the password is wrong.",
+            ),
+            AuthAPIError::InvalidToken => (
+                StatusCode::UNAUTHORIZED,
+                "Invalid token",
+                "The token is malformed, expired or revoked.",
+            ),
+            AuthAPIError::MissingToken => (
+                StatusCode::BAD_REQUEST,
+                "Missing token",
+                "The request has neither an auth cookie nor a bearer token.",
+            ),
+            AuthAPIError::SessionNotFound => (
+                StatusCode::NOT_FOUND,
+                "Session not found",
+                "The user has no session with this id.",
+            ),
+            AuthAPIError::Forbidden => (
+                StatusCode::FORBIDDEN,
+                "Forbidden",
+                "The caller is not allowed to do this.",
+            ),
+            AuthAPIError::UserNotFound => (
+                StatusCode::NOT_FOUND,
+                "User not found",
+                "There is no user with this email.",
+            ),
+            AuthAPIError::AccountSuspended => (
+                StatusCode::FORBIDDEN,
+                "Account suspended",
+                "The account has been suspended.",
+            ),
+            AuthAPIError::AccountNotVerified => (
+                StatusCode::FORBIDDEN,
+                "Account pending verification",
+                "The account has not been verified yet.",
+            ),
+            AuthAPIError::PasswordChangeRequired => (
+                StatusCode::FORBIDDEN,
+                "Password change required",
+                "The password was reset and has to be changed through /change-password first.",
+            ),
+            AuthAPIError::OrganizationNotFound => (
+                StatusCode::NOT_FOUND,
+                "Organization not found",
+                "There is no organization with this id.",
+            ),
+            AuthAPIError::OrganizationAlreadyExists => (
+                StatusCode::CONFLICT,
+                "Organization already exists",
+                "An organization with this id already exists.",
+            ),
+            AuthAPIError::InvitationNotFound => (
+                StatusCode::NOT_FOUND,
+                "Invitation not found",
+                "The invitation does not exist or can no longer be accepted.",
+            ),
+            AuthAPIError::ApiKeyNotFound => (
+                StatusCode::NOT_FOUND,
+                "API key not found",
+                "The user has no API key with this id.",
+            ),
+            AuthAPIError::WebhookNotFound => (
+                StatusCode::NOT_FOUND,
+                "Webhook not found",
+                "There is no webhook with this id.",
+            ),
+            AuthAPIError::InvalidCsrfToken => (
+                StatusCode::FORBIDDEN,
+                "Invalid CSRF token",
+                "Requests authenticated with the cookie have to send the CSRF token in the X-CSRF-Token header.",
+            ),
+        };
+        // The messages clients of the legacy format know, where they differ from the title.
+        let legacy = ErrorResponse {
+            error: match &self {
+                AuthAPIError::ValidationFailed(_) => "Invalid credentials",
+                AuthAPIError::IncorrectCredentials => "Unauthorized",
+                _ => title,
             }
-            AuthAPIError::IncorrectCredentials => (StatusCode::UNAUTHORIZED, "Unauthorized"),
-            AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token"),
-            AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing token"),
+            .to_owned(),
+        };
+        let code = self.code();
+        let errors = match self {
+            AuthAPIError::ValidationFailed(errors) => errors,
+            _ => Vec::new(),
+        };
+        let mut response = Problem::new(code, status, title, detail)
+            .with_errors(errors)
+            .into_response();
+        response.extensions_mut().insert(legacy);
+        response
+    }
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        tracing::info!(error = ?self, code = self.code(), "OAuth request failed");
+        let status = match self {
+            OAuthError::InvalidClient | OAuthError::InvalidToken => StatusCode::UNAUTHORIZED,
+            OAuthError::ServerError => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
         };
         let body = Json(ErrorResponse {
-            error: error_message.to_string(),
+            error: self.code().to_string(),
         });
         (status, body).into_response()
     }
 }
 
+// Already set up to stop gracefully once the shutdown handle is triggered.
+type Server = Pin<Box<dyn Future<Output = Result<(), std::io::Error>> + Send>>;
+
 // This struct encapsulates our application-related logic.
 pub struct Application {
-    server: Serve<TcpListener, Router, Router>,
+    server: Server,
     // address is exposed as a public field
     // so we have access to it in tests.
     pub address: String,
+    /// Where plain HTTP requests are redirected to HTTPS, if TLS is configured with a
+    /// redirect address.
+    pub redirect_address: Option<String>,
+    shutdown: ShutdownHandle,
+    settings: ShutdownSettings,
+    // Stopped one after the other once the server is, in this order.
+    background_tasks: Vec<(ShutdownHandle, JoinHandle<()>)>,
 }
 
 impl Application {
-    pub fn new(server: Serve<TcpListener, Router, Router>, address: String) -> Self {
-        Self { server, address }
+    pub fn new(server: Server, address: String, shutdown: ShutdownHandle) -> Self {
+        Self {
+            server,
+            address,
+            redirect_address: None,
+            shutdown,
+            settings: ShutdownSettings::default(),
+            background_tasks: Vec::new(),
+        }
+    }
+
+    /// Stops the server when triggered, see [`Application::run`].
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
     }
 
     pub async fn build(app_state: AppState, address: &str) -> Result<Self, Box<dyn Error>> {
+        if app_state.config.oidc_issuer.is_none() {
+            return Err(
+                "OIDC_ISSUER must be set, as the issuer cannot be taken from requests".into(),
+            );
+        }
         // Allow the app service(running on our local machine and in production) to call the auth service
         let allowed_origins = [
             "http://localhost:8000".parse()?,
+            "https://localhost:8000".parse()?,
             // TODO: Replace [YOUR_DROPLET_IP] with your Droplet IP address
             "http://http://137.184.153.39:8000".parse()?,
         ];
 
         let cors = CorsLayer::new()
-            // Allow GET and POST requests
-            .allow_methods([Method::GET, Method::POST])
+            // Allow GET, POST, PATCH and DELETE requests
+            .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
+            // Allow the app service to send the CSRF token of the session
+            .allow_headers([HeaderName::from_static(CSRF_HEADER)])
             // Allow cookies to be included in requests
             .allow_credentials(true)
             .allow_origin(allowed_origins);
@@ -79,23 +253,174 @@ impl Application {
         // We don't need it at this point!
         let router = Router::new()
             .fallback_service(ServeDir::new("assets"))
+            .route("/", axum::routing::get(root))
             .route("/signup", axum::routing::post(signup))
             .route("/login", axum::routing::post(login))
+            .route("/change-password", axum::routing::post(change_password))
             .route("/logout", axum::routing::post(logout))
             .route("/verify-2fa", axum::routing::post(verify_2fa))
+            .route("/verify-email", axum::routing::post(verify_email))
             .route("/verify-token", axum::routing::post(verify_token))
-            .with_state(app_state)
+            .route(
+                "/.well-known/openid-configuration",
+                axum::routing::get(openid_configuration),
+            )
+            .route("/.well-known/jwks.json", axum::routing::get(jwks))
+            .route("/authorize", axum::routing::get(authorize))
+            .route("/token", axum::routing::post(token))
+            .route("/clients", axum::routing::post(register_client))
+            .route("/introspect", axum::routing::post(introspect))
+            .route("/revoke", axum::routing::post(revoke))
+            .route("/userinfo", axum::routing::get(userinfo).post(userinfo))
+            .route(
+                "/sessions",
+                axum::routing::get(list_sessions).delete(revoke_all_sessions),
+            )
+            .route("/sessions/{id}", axum::routing::delete(revoke_session))
+            .route(
+                "/api-keys",
+                axum::routing::get(list_api_keys).post(create_api_key),
+            )
+            .route("/api-keys/{id}", axum::routing::delete(revoke_api_key))
+            .route("/metrics", axum::routing::get(metrics))
+            .nest("/health", health_routes())
+            .nest("/admin", admin_routes())
+            .nest("/orgs", organization_routes())
+            .nest("/invitations", invitation_routes())
+            .method_not_allowed_fallback(utils::problem::method_not_allowed)
+            .with_state(app_state.clone())
+            .layer(axum::middleware::from_fn_with_state(
+                app_state.clone(),
+                utils::csrf::verify_csrf,
+            ))
+            .layer(axum::middleware::from_fn_with_state(
+                app_state.clone(),
+                utils::problem::render_problems,
+            ))
+            // Added per route, so that requests are labelled with the route they matched.
+            .layer(MetricsLayer::new(app_state.metrics.clone()))
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(utils::telemetry::make_span)
+                    .on_response(DefaultOnResponse::new().level(Level::INFO)),
+            )
+            .layer(axum::middleware::from_fn(
+                utils::telemetry::assign_request_id,
+            ))
+            .layer(SecurityHeadersLayer::new(
+                &app_state.config.security_headers,
+            )?)
             .layer(cors);
 
         let listener = tokio::net::TcpListener::bind(address).await?;
-        let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        // The outbox goes first, as its last pass queues webhook deliveries.
+        let outbox_shutdown = ShutdownHandle::new();
+        let outbox = utils::outbox::spawn_dispatcher(
+            app_state.user_store.clone(),
+            event_handlers(&app_state),
+            app_state.config.outbox,
+            outbox_shutdown.clone(),
+        );
+        let webhooks_shutdown = ShutdownHandle::new();
+        let webhooks = utils::webhooks::spawn_dispatcher(&app_state, webhooks_shutdown.clone());
+        let mut background_tasks = vec![(outbox_shutdown, outbox), (webhooks_shutdown, webhooks)];
+
+        let shutdown = ShutdownHandle::new();
+        let local_addr = listener.local_addr()?;
+        // Connect info gives handlers the peer address, e.g. to record where a session came from.
+        let service = router.into_make_service_with_connect_info::<SocketAddr>();
+        let mut redirect_address = None;
+        let server: Server = match &app_state.config.tls {
+            None => Box::pin(
+                axum::serve(listener, service)
+                    .with_graceful_shutdown(stopped(&shutdown))
+                    .into_future(),
+            ),
+            Some(tls) => {
+                let key = load_certified_key(&tls.cert_path, &tls.key_path)?;
+                let resolver = Arc::new(CertificateResolver::new(key));
+                let reloader_shutdown = ShutdownHandle::new();
+                let reloader =
+                    spawn_reloader(resolver.clone(), tls.clone(), reloader_shutdown.clone());
+                background_tasks.push((reloader_shutdown, reloader));
 
-        Ok(Self::new(server, address))
+                // Tapping the IO is a no-op that makes `ConnectInfo<SocketAddr>` available,
+                // as axum only provides it for TCP and tapped listeners.
+                let listener = TlsListener::new(listener, resolver)?.tap_io(|_| {});
+                let https = axum::serve(listener, service)
+                    .with_graceful_shutdown(stopped(&shutdown))
+                    .into_future();
+                match &tls.redirect_address {
+                    None => Box::pin(https),
+                    Some(address) => {
+                        let listener = tokio::net::TcpListener::bind(address).await?;
+                        redirect_address = Some(listener.local_addr()?.to_string());
+                        let redirect = axum::serve(listener, redirect_router(local_addr.port()))
+                            .with_graceful_shutdown(stopped(&shutdown))
+                            .into_future();
+                        Box::pin(async move { tokio::try_join!(https, redirect).map(|_| ()) })
+                    }
+                }
+            }
+        };
+
+        Ok(Self {
+            redirect_address,
+            settings: app_state.config.shutdown,
+            background_tasks,
+            ..Self::new(server, local_addr.to_string(), shutdown)
+        })
     }
 
+    /// Serves requests until the shutdown handle is triggered. The server then stops accepting
+    /// connections, lets the requests in flight finish and has the background tasks work off
+    /// what is due, e.g. the events in the outbox. Whatever is left when the drain timeout runs
+    /// out is dropped.
     pub async fn run(self) -> Result<(), std::io::Error> {
-        println!("listening on {}", &self.address);
-        self.server.await
+        tracing::info!(
+            address = %self.address,
+            redirect_address = self.redirect_address,
+            "Listening"
+        );
+        let mut server = self.server;
+        tokio::select! {
+            // Only finishes on its own if it failed.
+            result = &mut server => return result,
+            () = self.shutdown.wait() => {}
+        }
+
+        tracing::info!("Shutting down");
+        let background_tasks = self.background_tasks;
+        let drain = async move {
+            let result = server.await;
+            for (shutdown, task) in background_tasks {
+                shutdown.shutdown();
+                if let Err(err) = task.await {
+                    tracing::error!(error = ?err, "Background task failed");
+                }
+            }
+            result
+        };
+        match tokio::time::timeout(self.settings.drain_timeout, drain).await {
+            Ok(result) => result?,
+            Err(_) => tracing::warn!(
+                timeout = ?self.settings.drain_timeout,
+                "Drain timeout elapsed, dropping the remaining work"
+            ),
+        }
+        tracing::info!("Stopped");
+        Ok(())
     }
 }
+
+fn stopped(shutdown: &ShutdownHandle) -> impl Future<Output = ()> + Send + 'static {
+    let shutdown = shutdown.clone();
+    async move { shutdown.wait().await }
+}
+
+/// The handlers that react to the domain events of the user store.
+fn event_handlers(app_state: &AppState) -> Vec<Arc<dyn EventHandler>> {
+    vec![Arc::new(WebhookEventHandler::new(
+        app_state.webhooks.clone(),
+    ))]
+}
@@ -1,21 +1,81 @@
+use auth_service::domain::AuditSink;
 use auth_service::services::hashmap_user_store::HashmapUserStore;
-use auth_service::services::HashSetBannedTokenStore;
-use auth_service::utils::prod;
-use auth_service::Application;
+use auth_service::services::{
+    HashSetBannedTokenStore, HashmapApiKeyStore, HashmapAuthorizationCodeStore, HashmapClientStore,
+    HashmapInvitationStore, HashmapOrganizationStore, HashmapPendingLoginStore,
+    HashmapRefreshTokenStore, HashmapSessionStore, InstrumentedBannedTokenStore,
+    InstrumentedUserStore, JsonLinesAuditSink, MockEmailClient, SqliteAuditSink,
+    SqliteWebhookStore,
+};
+use auth_service::utils::metrics::Metrics;
+use auth_service::utils::shutdown::os_signal;
+use auth_service::utils::telemetry::init_tracing;
+use auth_service::utils::{env, prod};
+use auth_service::{AppConfig, Application};
+use std::env as std_env;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 #[tokio::main]
 async fn main() {
-    let user_store = HashmapUserStore::new();
+    let _telemetry = init_tracing();
+    let config = AppConfig::from_env().expect("Invalid configuration");
+    let metrics = Arc::new(Metrics::new());
+    let user_store = InstrumentedUserStore::new(Box::new(HashmapUserStore::new()), metrics.clone());
+    let banned_tokens = InstrumentedBannedTokenStore::new(
+        Box::new(HashSetBannedTokenStore::new()),
+        metrics.clone(),
+    );
     let app_state = auth_service::AppState {
         user_store: Arc::new(RwLock::new(Box::new(user_store))),
-        banned_tokens: Arc::new(RwLock::new(Box::new(HashSetBannedTokenStore::new()))),
+        banned_tokens: Arc::new(RwLock::new(Box::new(banned_tokens))),
+        oauth_clients: Arc::new(RwLock::new(Box::new(HashmapClientStore::new()))),
+        authorization_codes: Arc::new(RwLock::new(Box::new(HashmapAuthorizationCodeStore::new()))),
+        refresh_tokens: Arc::new(RwLock::new(Box::new(HashmapRefreshTokenStore::new()))),
+        sessions: Arc::new(RwLock::new(Box::new(HashmapSessionStore::new()))),
+        pending_logins: Arc::new(RwLock::new(Box::new(HashmapPendingLoginStore::new()))),
+        organizations: Arc::new(RwLock::new(Box::new(HashmapOrganizationStore::new()))),
+        invitations: Arc::new(RwLock::new(Box::new(HashmapInvitationStore::new()))),
+        api_keys: Arc::new(RwLock::new(Box::new(HashmapApiKeyStore::new()))),
+        audit_sink: Arc::new(RwLock::new(audit_sink())),
+        webhooks: Arc::new(RwLock::new(Box::new(
+            SqliteWebhookStore::open(
+                std_env::var(env::WEBHOOK_DATABASE_PATH_ENV_VAR)
+                    .ok()
+                    .filter(|path| !path.is_empty())
+                    .unwrap_or_else(|| "webhooks.db".to_owned()),
+            )
+            .expect("Failed to open webhook database"),
+        ))),
+        email_client: Arc::new(MockEmailClient::new()),
+        metrics,
+        config: Arc::new(config),
     };
 
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await
         .expect("Failed to build app");
 
+    let shutdown = app.shutdown_handle();
+    tokio::spawn(async move {
+        os_signal().await;
+        shutdown.shutdown();
+    });
     app.run().await.expect("Failed to run app");
 }
+
+/// Keeps the audit log in a SQLite database if `AUDIT_DATABASE_PATH` is set, and in a
+/// JSON lines file otherwise.
+fn audit_sink() -> Box<dyn AuditSink> {
+    let var = |name| {
+        std_env::var(name)
+            .ok()
+            .filter(|value: &String| !value.is_empty())
+    };
+    match var(env::AUDIT_DATABASE_PATH_ENV_VAR) {
+        Some(path) => Box::new(SqliteAuditSink::open(path).expect("Failed to open audit database")),
+        None => Box::new(JsonLinesAuditSink::new(
+            var(env::AUDIT_LOG_PATH_ENV_VAR).unwrap_or_else(|| "audit.jsonl".to_owned()),
+        )),
+    }
+}
@@ -1,12 +1,48 @@
+mod admin;
+mod api_keys;
+mod authorize;
+mod change_password;
+mod health;
+mod introspect;
+mod invitations;
 mod login;
 mod logout;
+mod metrics;
+mod organizations;
+mod register_client;
+mod revoke;
+mod root;
+mod sessions;
 mod signup;
+mod token;
+mod userinfo;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
+mod webhooks;
+mod well_known;
 
 // re-export items from sub-modules
+pub use admin::*;
+pub use api_keys::*;
+pub use authorize::*;
+pub use change_password::*;
+pub use health::*;
+pub use introspect::*;
+pub use invitations::*;
 pub use login::*;
 pub use logout::*;
+pub use metrics::*;
+pub use organizations::*;
+pub use register_client::*;
+pub use revoke::*;
+pub use root::*;
+pub use sessions::*;
 pub use signup::*;
+pub use token::*;
+pub use userinfo::*;
 pub use verify_2fa::*;
+pub use verify_email::*;
 pub use verify_token::*;
+pub use webhooks::*;
+pub use well_known::*;
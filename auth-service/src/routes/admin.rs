@@ -0,0 +1,433 @@
+use super::sessions::{revoke_credentials_of, revoke_sessions_of};
+use super::verify_email::mark_email_verified;
+use super::webhooks::webhook_routes;
+use crate::domain::{
+    AuditEvent, AuditQuery, AuditRecord, AuthAPIError, Email, Password, Role, User, UserId,
+    UserQuery, UserStatus, UserStoreError, UsersRead, UsersWrite,
+};
+use crate::utils::audit::audit;
+use crate::utils::auth::generate_opaque_token;
+use crate::utils::extractors::{
+    AuthenticatedUser, Json, Path, Query, RequestContext, RequirePermission,
+};
+use crate::AppState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::Router;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+const DEFAULT_PER_PAGE: usize = 20;
+const MAX_PER_PAGE: usize = 100;
+const DEFAULT_AUDIT_LIMIT: usize = 100;
+const MAX_AUDIT_LIMIT: usize = 1000;
+
+/// Routes for operators, mounted under `/admin`. Reading users, the audit log and webhooks
+/// requires `users:read`, changing them `users:write`; both are granted by the admin role.
+pub fn admin_routes() -> Router<AppState> {
+    Router::new()
+        .route("/audit", get(list_audit_records))
+        .route("/users", get(list_users))
+        .route(
+            "/users/{email}",
+            get(get_user).patch(update_user).delete(delete_user),
+        )
+        .route("/users/{email}/disable", post(disable_user))
+        .route("/users/{email}/enable", post(enable_user))
+        .route("/users/{email}/password-reset", post(reset_password))
+        .route("/users/{email}/logout", post(logout_user))
+        .nest("/webhooks", webhook_routes())
+}
+
+/// A user as shown to operators, i.e. without the password.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct AdminUser {
+    pub email: String,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    #[serde(rename = "emailVerified")]
+    pub email_verified: bool,
+    pub roles: Vec<Role>,
+    pub status: UserStatus,
+    #[serde(rename = "mustChangePassword")]
+    pub must_change_password: bool,
+}
+
+impl From<User> for AdminUser {
+    fn from(user: User) -> Self {
+        Self {
+            email: user.email.as_ref().to_owned(),
+            requires_2fa: user.requires_2fa,
+            email_verified: user.email_verified,
+            roles: user.roles,
+            status: user.status,
+            must_change_password: user.must_change_password,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListUsersParams {
+    /// Only list users whose email contains this, ignoring case.
+    pub search: Option<String>,
+    /// 1-based page number.
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct UserListResponse {
+    pub users: Vec<AdminUser>,
+    pub page: usize,
+    pub per_page: usize,
+    pub total: usize,
+}
+
+/// Fields left out are not changed.
+#[derive(Debug, Deserialize)]
+pub struct UpdateUserRequest {
+    /// Moves the user to this email, which logs them out everywhere.
+    pub email: Option<String>,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: Option<bool>,
+    #[serde(rename = "emailVerified")]
+    pub email_verified: Option<bool>,
+    pub roles: Option<Vec<Role>>,
+    pub status: Option<UserStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditLogParams {
+    /// Only list records of the user with this email.
+    pub user: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct PasswordResetResponse {
+    /// Shown only once; hand it to the user through a trusted channel. It only works for
+    /// `/change-password`, which the user has to call before they can log in again.
+    pub temporary_password: String,
+}
+
+pub async fn list_users(
+    State(state): State<AppState>,
+    context: RequestContext,
+    RequirePermission { user: admin, .. }: RequirePermission<UsersRead>,
+    Query(params): Query<ListUsersParams>,
+) -> Result<Json<UserListResponse>, AuthAPIError> {
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+    let query = UserQuery {
+        tenant: admin.claims.tenant.clone(),
+        email_contains: params
+            .search
+            .map(|search| search.trim().to_owned())
+            .filter(|search| !search.is_empty()),
+        offset: (page - 1).saturating_mul(per_page),
+        limit: per_page,
+    };
+
+    let result = state
+        .user_store
+        .read()
+        .await
+        .list_users(&query)
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+
+    audit_admin(&state, &context, &admin, AuditEvent::UsersListed).await;
+
+    Ok(Json(UserListResponse {
+        users: result.users.into_iter().map(AdminUser::from).collect(),
+        page,
+        per_page,
+        total: result.total,
+    }))
+}
+
+pub async fn get_user(
+    State(state): State<AppState>,
+    context: RequestContext,
+    RequirePermission { user: admin, .. }: RequirePermission<UsersRead>,
+    Path(email): Path<String>,
+) -> Result<Json<AdminUser>, AuthAPIError> {
+    let user = find_user(&state, &user_id(&admin, &email)?).await?;
+    audit_admin(
+        &state,
+        &context,
+        &admin,
+        AuditEvent::UserViewed { target: email },
+    )
+    .await;
+    Ok(Json(user.into()))
+}
+
+pub async fn update_user(
+    State(state): State<AppState>,
+    context: RequestContext,
+    RequirePermission { user: admin, .. }: RequirePermission<UsersWrite>,
+    Path(email): Path<String>,
+    Json(request): Json<UpdateUserRequest>,
+) -> Result<Json<AdminUser>, AuthAPIError> {
+    let mut id = user_id(&admin, &email)?;
+    let new_email = request
+        .email
+        .as_deref()
+        .map(Email::from_str)
+        .transpose()
+        .map_err(|err| AuthAPIError::invalid_field("email", err))?;
+    // Sessions, API keys and refresh tokens are tied to the old email, which someone else
+    // may sign up with next.
+    if let Some(new_email) = new_email.filter(|new_email| *new_email != id.email) {
+        let moved = state
+            .user_store
+            .write()
+            .await
+            .change_email(&id, new_email)
+            .await
+            .map_err(|err| match err {
+                UserStoreError::UserAlreadyExists => AuthAPIError::UserAlreadyExists,
+                err => map_user_store_error(err),
+            })?;
+        revoke_credentials_of(&state, &id).await?;
+        id = moved.id();
+    }
+
+    // Tokens carry the roles they were issued with, so the user has to log in again
+    // for a role change to take effect, just like after being suspended.
+    let mut force_logout = false;
+    let user = modify_user(&state, &id, |user| {
+        if let Some(requires_2fa) = request.requires_2fa {
+            user.requires_2fa = requires_2fa;
+        }
+        match request.email_verified {
+            Some(true) => mark_email_verified(&state.config, user),
+            Some(false) => user.email_verified = false,
+            None => {}
+        }
+        if let Some(roles) = request.roles {
+            force_logout |= roles != user.roles;
+            user.roles = roles;
+        }
+        if let Some(status) = request.status {
+            force_logout |= user.is_active() && status != UserStatus::Active;
+            user.status = status;
+        }
+    })
+    .await?;
+    if force_logout {
+        revoke_credentials_of(&state, &id).await?;
+    }
+    audit_admin(
+        &state,
+        &context,
+        &admin,
+        AuditEvent::UserUpdated { target: email },
+    )
+    .await;
+    Ok(Json(user.into()))
+}
+
+/// Suspends the user and logs them out everywhere.
+pub async fn disable_user(
+    State(state): State<AppState>,
+    context: RequestContext,
+    RequirePermission { user: admin, .. }: RequirePermission<UsersWrite>,
+    Path(email): Path<String>,
+) -> Result<Json<AdminUser>, AuthAPIError> {
+    let id = user_id(&admin, &email)?;
+    let user = modify_user(&state, &id, |user| user.status = UserStatus::Suspended).await?;
+    revoke_sessions_of(&state, &id).await?;
+    audit_admin(
+        &state,
+        &context,
+        &admin,
+        AuditEvent::UserDisabled { target: email },
+    )
+    .await;
+    Ok(Json(user.into()))
+}
+
+pub async fn enable_user(
+    State(state): State<AppState>,
+    context: RequestContext,
+    RequirePermission { user: admin, .. }: RequirePermission<UsersWrite>,
+    Path(email): Path<String>,
+) -> Result<Json<AdminUser>, AuthAPIError> {
+    let id = user_id(&admin, &email)?;
+    let user = modify_user(&state, &id, |user| user.status = UserStatus::Active).await?;
+    audit_admin(
+        &state,
+        &context,
+        &admin,
+        AuditEvent::UserEnabled { target: email },
+    )
+    .await;
+    Ok(Json(user.into()))
+}
+
+pub async fn delete_user(
+    State(state): State<AppState>,
+    context: RequestContext,
+    RequirePermission { user: admin, .. }: RequirePermission<UsersWrite>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let id = user_id(&admin, &email)?;
+    state
+        .user_store
+        .write()
+        .await
+        .delete_user(&id)
+        .await
+        .map_err(map_user_store_error)?;
+    revoke_credentials_of(&state, &id).await?;
+    audit_admin(
+        &state,
+        &context,
+        &admin,
+        AuditEvent::UserDeleted { target: email },
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Replaces the password with a random one and logs the user out everywhere. The user cannot
+/// log in until they have replaced it with one only they know.
+pub async fn reset_password(
+    State(state): State<AppState>,
+    context: RequestContext,
+    RequirePermission { user: admin, .. }: RequirePermission<UsersWrite>,
+    Path(email): Path<String>,
+) -> Result<Json<PasswordResetResponse>, AuthAPIError> {
+    let id = user_id(&admin, &email)?;
+    let temporary_password = generate_opaque_token().map_err(AuthAPIError::unexpected)?;
+    let password = Password::from_str(&temporary_password).map_err(AuthAPIError::unexpected)?;
+    modify_user(&state, &id, |user| {
+        user.password = password;
+        user.must_change_password = true;
+    })
+    .await?;
+    revoke_credentials_of(&state, &id).await?;
+    audit_admin(
+        &state,
+        &context,
+        &admin,
+        AuditEvent::PasswordReset { target: email },
+    )
+    .await;
+
+    Ok(Json(PasswordResetResponse { temporary_password }))
+}
+
+pub async fn logout_user(
+    State(state): State<AppState>,
+    context: RequestContext,
+    RequirePermission { user: admin, .. }: RequirePermission<UsersWrite>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let id = user_id(&admin, &email)?;
+    find_user(&state, &id).await?;
+    revoke_credentials_of(&state, &id).await?;
+    audit_admin(
+        &state,
+        &context,
+        &admin,
+        AuditEvent::UserLoggedOut { target: email },
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Lists the audit records of the users in the operator's namespace, most recent first.
+pub async fn list_audit_records(
+    State(state): State<AppState>,
+    context: RequestContext,
+    RequirePermission { user: admin, .. }: RequirePermission<UsersRead>,
+    Query(params): Query<AuditLogParams>,
+) -> Result<Json<Vec<AuditRecord>>, AuthAPIError> {
+    let query = AuditQuery {
+        tenant: admin.claims.tenant.clone(),
+        user: params
+            .user
+            .map(|user| user.trim().to_owned())
+            .filter(|user| !user.is_empty()),
+        from: params.from,
+        to: params.to,
+        limit: params
+            .limit
+            .unwrap_or(DEFAULT_AUDIT_LIMIT)
+            .clamp(1, MAX_AUDIT_LIMIT),
+    };
+    let records = state
+        .audit_sink
+        .read()
+        .await
+        .query(&query)
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+
+    // Recorded after the query, so that reading the log does not show up in the result.
+    audit_admin(&state, &context, &admin, AuditEvent::AuditLogRead).await;
+    Ok(Json(records))
+}
+
+async fn audit_admin(
+    state: &AppState,
+    context: &RequestContext,
+    admin: &AuthenticatedUser,
+    event: AuditEvent,
+) {
+    audit(state, context, admin.claims.user_id().as_ref(), event).await;
+}
+
+// Operators manage the users in their own namespace, see `UserId`.
+fn user_id(admin: &AuthenticatedUser, email: &str) -> Result<UserId, AuthAPIError> {
+    let email = Email::from_str(email).map_err(|err| AuthAPIError::invalid_field("email", err))?;
+    Ok(UserId::new(admin.claims.tenant.clone(), email))
+}
+
+async fn find_user(state: &AppState, id: &UserId) -> Result<User, AuthAPIError> {
+    state
+        .user_store
+        .read()
+        .await
+        .get_user(id)
+        .await
+        .map_err(map_user_store_error)
+}
+
+/// Applies `change` to the stored user and returns the result. The user is read and written
+/// back under one write lock, so that concurrent changes of the same user are not lost.
+pub(crate) async fn modify_user(
+    state: &AppState,
+    id: &UserId,
+    change: impl FnOnce(&mut User) + Send,
+) -> Result<User, AuthAPIError> {
+    let mut user_store = state.user_store.write().await;
+    let mut user = user_store
+        .get_user(id)
+        .await
+        .map_err(map_user_store_error)?;
+    change(&mut user);
+    user_store
+        .update_user(user.clone())
+        .await
+        .map_err(map_user_store_error)?;
+    Ok(user)
+}
+
+fn map_user_store_error(err: UserStoreError) -> AuthAPIError {
+    match err {
+        UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+        err => AuthAPIError::unexpected(err),
+    }
+}
@@ -0,0 +1,171 @@
+use crate::domain::{ApiKey, ApiKeyStoreError, AuditEvent, AuthAPIError, Scope};
+use crate::utils::api_key::{generate_api_key, is_api_key};
+use crate::utils::audit::audit;
+use crate::utils::extractors::{AuthenticatedUser, Json, Path, RequestContext};
+use crate::AppState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+const MAX_NAME_LENGTH: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    /// Permissions of the user to limit the key to; the key gets all of them if left out.
+    pub scopes: Option<Vec<String>>,
+    /// The key never expires if left out.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeyResponse {
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<String>>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// The key itself, only returned when it is created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(api_key: ApiKey) -> Self {
+        Self {
+            id: api_key.id,
+            name: api_key.name,
+            scopes: api_key.scope.map(|scope| {
+                scope
+                    .to_string()
+                    .split_whitespace()
+                    .map(str::to_owned)
+                    .collect()
+            }),
+            created_at: api_key.created_at,
+            last_used_at: api_key.last_used_at,
+            expires_at: api_key.expires_at,
+            key: None,
+        }
+    }
+}
+
+/// Creates an API key for the current user. Keys can only be created from a login of the
+/// user, not with another key or an OAuth access token, and can only be scoped to
+/// permissions the user has.
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    context: RequestContext,
+    AuthenticatedUser { token, claims }: AuthenticatedUser,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    // Keys without scopes get every permission of the user, which neither another key nor
+    // a token issued to an OAuth client may hand out.
+    if is_api_key(&token.token) || claims.is_delegated() {
+        return Err(AuthAPIError::Forbidden);
+    }
+    let user = claims.user_id().ok_or(AuthAPIError::InvalidToken)?;
+
+    let name = request.name.trim().to_owned();
+    let name_error = if name.is_empty() {
+        Some("Name must not be empty".to_owned())
+    } else if name.chars().count() > MAX_NAME_LENGTH {
+        Some(format!(
+            "Name must be at most {} characters long",
+            MAX_NAME_LENGTH
+        ))
+    } else {
+        None
+    };
+    let expires_at_error = request
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now())
+        .then(|| "Must be in the future".to_owned());
+    if name_error.is_some() || expires_at_error.is_some() {
+        return Err(AuthAPIError::invalid_fields([
+            ("name", name_error),
+            ("expires_at", expires_at_error),
+        ]));
+    }
+    let scope = request.scopes.map(|scopes| Scope::parse(&scopes.join(" ")));
+    if let Some(scope) = &scope {
+        let granted = Scope::parse(&claims.permissions.join(" "));
+        if !scope.is_subset_of(&granted) {
+            return Err(AuthAPIError::Forbidden);
+        }
+    }
+
+    let (id, secret) = generate_api_key().map_err(AuthAPIError::unexpected)?;
+    let api_key = ApiKey::new(id, user, name, &secret, scope, request.expires_at);
+    state
+        .api_keys
+        .write()
+        .await
+        .add_api_key(api_key.clone())
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+    let event = AuditEvent::ApiKeyCreated {
+        key_id: api_key.id.clone(),
+    };
+    audit(&state, &context, Some(&api_key.user), event).await;
+
+    let response = Json(ApiKeyResponse {
+        key: Some(ApiKey::format(&api_key.id, &secret)),
+        ..api_key.into()
+    });
+    Ok((StatusCode::CREATED, response))
+}
+
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    AuthenticatedUser { claims, .. }: AuthenticatedUser,
+) -> Result<Json<Vec<ApiKeyResponse>>, AuthAPIError> {
+    let user = claims.user_id().ok_or(AuthAPIError::InvalidToken)?;
+    let api_keys = state
+        .api_keys
+        .read()
+        .await
+        .get_api_keys(&user)
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+
+    Ok(Json(
+        api_keys.into_iter().map(ApiKeyResponse::from).collect(),
+    ))
+}
+
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    context: RequestContext,
+    AuthenticatedUser { claims, .. }: AuthenticatedUser,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    // Keys of other users are reported as missing, like sessions.
+    let api_key = state
+        .api_keys
+        .read()
+        .await
+        .get_api_key(&id)
+        .await
+        .ok()
+        .filter(|api_key| Some(&api_key.user) == claims.user_id().as_ref())
+        .ok_or(AuthAPIError::ApiKeyNotFound)?;
+
+    match state
+        .api_keys
+        .write()
+        .await
+        .remove_api_key(&api_key.id)
+        .await
+    {
+        Ok(_) | Err(ApiKeyStoreError::ApiKeyNotFound) => {}
+        Err(err) => return Err(AuthAPIError::unexpected(err)),
+    }
+    let event = AuditEvent::ApiKeyRevoked { key_id: api_key.id };
+    audit(&state, &context, Some(&api_key.user), event).await;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,168 @@
+use crate::domain::{is_s256_code_challenge, AuditEvent, AuthorizationCode, OAuthError, Scope};
+use crate::utils::audit::audit;
+use crate::utils::auth::{generate_opaque_token, validate_token};
+use crate::utils::extractors::RequestContext;
+use crate::utils::AUTHORIZATION_CODE_TTL_SECONDS;
+use crate::AppState;
+use axum::extract::{Query, State};
+use axum::http::Uri;
+use axum::response::Redirect;
+use axum_extra::extract::CookieJar;
+use chrono::{Duration, Utc};
+use url::Url;
+
+#[derive(serde::Deserialize)]
+pub struct AuthorizeRequest {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+pub async fn authorize(
+    State(state): State<AppState>,
+    context: RequestContext,
+    jar: CookieJar,
+    uri: Uri,
+    Query(request): Query<AuthorizeRequest>,
+) -> Result<Redirect, OAuthError> {
+    let client_id = request.client_id.clone();
+    let result = issue_code(&state, &context, jar, uri, request).await;
+    if let Err(err) = &result {
+        let event = AuditEvent::AuthorizationFailed {
+            client_id,
+            reason: err.code().to_owned(),
+        };
+        audit(&state, &context, None, event).await;
+    }
+    result
+}
+
+async fn issue_code(
+    state: &AppState,
+    context: &RequestContext,
+    jar: CookieJar,
+    uri: Uri,
+    request: AuthorizeRequest,
+) -> Result<Redirect, OAuthError> {
+    // Errors about the client or its redirect URI must not be sent to that redirect URI.
+    let client = state
+        .oauth_clients
+        .read()
+        .await
+        .get_client(&request.client_id)
+        .await
+        .map_err(|_| OAuthError::InvalidClient)?;
+    if !client.allows_redirect_uri(&request.redirect_uri) {
+        return Err(OAuthError::InvalidRequest);
+    }
+
+    let redirect_error = |error: OAuthError| {
+        redirect_to_client(
+            &request.redirect_uri,
+            &[("error", error.code())],
+            request.state.as_deref(),
+        )
+    };
+
+    if request.response_type != "code" {
+        return redirect_error(OAuthError::UnsupportedResponseType);
+    }
+    let scope = Scope::parse(request.scope.as_deref().unwrap_or_default());
+    if scope.is_empty() {
+        return redirect_error(OAuthError::InvalidScope);
+    }
+    // Public clients have no secret to redeem the code with, so PKCE has to bind the code to
+    // them. Confidential clients may use it too. Only S256 is supported, not `plain`.
+    match (
+        &request.code_challenge,
+        request.code_challenge_method.as_deref(),
+    ) {
+        (Some(challenge), Some("S256")) if is_s256_code_challenge(challenge) => {}
+        (None, None) if client.is_confidential() => {}
+        _ => return redirect_error(OAuthError::InvalidRequest),
+    }
+
+    // Users without a valid session are sent to the login UI, which brings them back here.
+    let claims = match jar.get(state.config.cookies.name()) {
+        Some(cookie) => {
+            let token = cookie.value();
+            let is_banned = state
+                .banned_tokens
+                .read()
+                .await
+                .is_token_banned(token)
+                .await
+                .map_err(|_| OAuthError::ServerError)?;
+            match validate_token(token).await {
+                Ok(claims) if !is_banned && claims.is_user_token() => Some(claims),
+                _ => None,
+            }
+        }
+        None => None,
+    };
+    let Some(claims) = claims else {
+        return Ok(redirect_to_login(&uri));
+    };
+    let user = claims.user_id().ok_or(OAuthError::ServerError)?;
+
+    let code = generate_opaque_token().map_err(|_| OAuthError::ServerError)?;
+    let authorization_code = AuthorizationCode::new(
+        code.clone(),
+        client.client_id.clone(),
+        request.redirect_uri.clone(),
+        user.clone(),
+        scope,
+        request.nonce.clone(),
+        Utc::now() + Duration::seconds(AUTHORIZATION_CODE_TTL_SECONDS),
+    )
+    .with_code_challenge(request.code_challenge.clone());
+    state
+        .authorization_codes
+        .write()
+        .await
+        .add_code(authorization_code)
+        .await
+        .map_err(|_| OAuthError::ServerError)?;
+    let event = AuditEvent::AuthorizationCodeIssued {
+        client_id: client.client_id,
+    };
+    audit(state, context, Some(&user), event).await;
+
+    redirect_to_client(
+        &request.redirect_uri,
+        &[("code", code.as_str())],
+        request.state.as_deref(),
+    )
+}
+
+fn redirect_to_client(
+    redirect_uri: &str,
+    params: &[(&str, &str)],
+    state: Option<&str>,
+) -> Result<Redirect, OAuthError> {
+    let mut url = Url::parse(redirect_uri).map_err(|_| OAuthError::InvalidRequest)?;
+    {
+        let mut query = url.query_pairs_mut();
+        query.extend_pairs(params);
+        if let Some(state) = state {
+            query.append_pair("state", state);
+        }
+    }
+    Ok(Redirect::to(url.as_str()))
+}
+
+fn redirect_to_login(uri: &Uri) -> Redirect {
+    let return_to = uri
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/authorize");
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("return_to", return_to)
+        .finish();
+    Redirect::to(&format!("/?{}", query))
+}
@@ -0,0 +1,88 @@
+use super::sessions::revoke_sessions_of;
+use crate::domain::{AuditEvent, AuthAPIError, Email, Password, UserId, UserStoreError};
+use crate::utils::audit::audit;
+use crate::utils::extractors::{Json, RequestContext};
+use crate::AppState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use std::str::FromStr;
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    pub email: String,
+    pub password: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+    /// The organization the user signed up in, see `LoginRequest`.
+    #[serde(default, rename = "orgId")]
+    pub org_id: Option<String>,
+}
+
+/// Replaces the password of the user, who proves they know the current one. This is the only
+/// way back in after an operator reset the password, so it works without a session. Every
+/// session of the user is revoked.
+pub async fn change_password(
+    State(state): State<AppState>,
+    context: RequestContext,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (email, password, new_password) = match (
+        Email::from_str(&request.email),
+        Password::from_str(&request.password),
+        Password::from_str(&request.new_password),
+    ) {
+        (Ok(email), Ok(password), Ok(new_password)) => (email, password, new_password),
+        (email, password, new_password) => {
+            return Err(AuthAPIError::invalid_fields([
+                ("email", email.err()),
+                ("password", password.err()),
+                ("newPassword", new_password.err()),
+            ]))
+        }
+    };
+    if new_password == password {
+        return Err(AuthAPIError::invalid_field(
+            "newPassword",
+            "The new password must differ from the current one",
+        ));
+    }
+    let id = UserId::new(state.config.tenant_for(request.org_id.as_deref()), email);
+
+    replace_password(&state, &id, &password, new_password).await?;
+    revoke_sessions_of(&state, &id).await?;
+    audit(&state, &context, Some(&id), AuditEvent::PasswordChanged).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn replace_password(
+    state: &AppState,
+    id: &UserId,
+    password: &Password,
+    new_password: Password,
+) -> Result<(), AuthAPIError> {
+    let mut user_store = state.user_store.write().await;
+    user_store
+        .validate_user(id, password)
+        .await
+        .map_err(|err| match err {
+            UserStoreError::InvalidCredentials | UserStoreError::UserNotFound => {
+                AuthAPIError::IncorrectCredentials
+            }
+            err => AuthAPIError::unexpected(err),
+        })?;
+    let mut user = user_store
+        .get_user(id)
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+    user.ensure_active()?;
+
+    user.password = new_password;
+    user.must_change_password = false;
+    user_store
+        .update_user(user)
+        .await
+        .map_err(AuthAPIError::unexpected)
+}
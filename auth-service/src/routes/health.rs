@@ -0,0 +1,136 @@
+use crate::AppState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::future::Future;
+use std::time::Duration;
+
+/// How long a backend may take to answer before it counts as unavailable, so that a hung
+/// backend fails the probe instead of hanging it.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    Unavailable,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComponentHealth {
+    pub status: HealthStatus,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct HealthResponse {
+    pub status: HealthStatus,
+    /// The health of each store, by name; only part of the readiness check.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub components: BTreeMap<String, ComponentHealth>,
+}
+
+pub fn health_routes() -> Router<AppState> {
+    Router::new()
+        .route("/live", get(live))
+        .route("/ready", get(ready))
+}
+
+/// The process is up and serving requests.
+async fn live() -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: HealthStatus::Ok,
+        components: BTreeMap::new(),
+    })
+}
+
+/// Every store can serve requests. Answers 503 otherwise, so that traffic is held back until
+/// the backends are there.
+async fn ready(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    let components = tokio::join!(
+        check("users", async {
+            state.user_store.read().await.health_check().await
+        }),
+        check("banned_tokens", async {
+            state.banned_tokens.read().await.health_check().await
+        }),
+        check("oauth_clients", async {
+            state.oauth_clients.read().await.health_check().await
+        }),
+        check("authorization_codes", async {
+            state.authorization_codes.read().await.health_check().await
+        }),
+        check("refresh_tokens", async {
+            state.refresh_tokens.read().await.health_check().await
+        }),
+        check("sessions", async {
+            state.sessions.read().await.health_check().await
+        }),
+        check("pending_logins", async {
+            state.pending_logins.read().await.health_check().await
+        }),
+        check("organizations", async {
+            state.organizations.read().await.health_check().await
+        }),
+        check("invitations", async {
+            state.invitations.read().await.health_check().await
+        }),
+        check("api_keys", async {
+            state.api_keys.read().await.health_check().await
+        }),
+        check("audit", async {
+            state.audit_sink.read().await.health_check().await
+        }),
+        check("webhooks", async {
+            state.webhooks.read().await.health_check().await
+        }),
+    );
+    let components: BTreeMap<String, ComponentHealth> = [
+        components.0,
+        components.1,
+        components.2,
+        components.3,
+        components.4,
+        components.5,
+        components.6,
+        components.7,
+        components.8,
+        components.9,
+        components.10,
+        components.11,
+    ]
+    .into_iter()
+    .map(|(name, status)| (name.to_owned(), ComponentHealth { status }))
+    .collect();
+
+    let healthy = components
+        .values()
+        .all(|component| component.status == HealthStatus::Ok);
+    let (code, status) = if healthy {
+        (StatusCode::OK, HealthStatus::Ok)
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, HealthStatus::Unavailable)
+    };
+    (code, Json(HealthResponse { status, components }))
+}
+
+async fn check<E: Debug>(
+    component: &'static str,
+    health_check: impl Future<Output = Result<(), E>>,
+) -> (&'static str, HealthStatus) {
+    let status = match tokio::time::timeout(CHECK_TIMEOUT, health_check).await {
+        Ok(Ok(())) => HealthStatus::Ok,
+        Ok(Err(err)) => {
+            tracing::warn!(component, error = ?err, "Health check failed");
+            HealthStatus::Unavailable
+        }
+        Err(_) => {
+            tracing::warn!(component, "Health check timed out");
+            HealthStatus::Unavailable
+        }
+    };
+    (component, status)
+}
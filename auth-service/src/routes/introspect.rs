@@ -0,0 +1,101 @@
+use crate::domain::{AuditEvent, OAuthError};
+use crate::utils::audit::audit;
+use crate::utils::auth::validate_token;
+use crate::utils::client_auth::authenticate_client;
+use crate::utils::extractors::RequestContext;
+use crate::AppState;
+use axum::extract::State;
+use axum::http::{header, HeaderMap};
+use axum::response::IntoResponse;
+use axum::{Form, Json};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct IntrospectionRequest {
+    pub token: String,
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+/// Token metadata as defined by RFC 7662. Only `active` (and `revoked`, for tokens that
+/// were valid until they were revoked) is returned for tokens that are not active.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+}
+
+pub async fn introspect(
+    State(state): State<AppState>,
+    context: RequestContext,
+    headers: HeaderMap,
+    Form(request): Form<IntrospectionRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    // Only resource servers holding client credentials may learn about other parties' tokens.
+    let client = authenticate_client(
+        &state,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+    if !client.is_confidential() {
+        return Err(OAuthError::UnauthorizedClient);
+    }
+
+    let response = match validate_token(&request.token).await {
+        Ok(claims) => {
+            let revoked = state
+                .banned_tokens
+                .read()
+                .await
+                .is_token_banned(&request.token)
+                .await
+                .map_err(|_| OAuthError::ServerError)?;
+            if revoked {
+                IntrospectionResponse {
+                    active: false,
+                    revoked: Some(true),
+                    ..IntrospectionResponse::default()
+                }
+            } else {
+                IntrospectionResponse {
+                    active: true,
+                    revoked: Some(false),
+                    sub: Some(claims.sub),
+                    exp: Some(claims.exp),
+                    iat: Some(claims.iat),
+                    scope: claims.scope,
+                    jti: Some(claims.jti),
+                    client_id: claims.client_id,
+                    token_type: Some("Bearer".to_owned()),
+                }
+            }
+        }
+        // Expired, malformed and foreign tokens are simply not active.
+        Err(_) => IntrospectionResponse::default(),
+    };
+    let event = AuditEvent::TokenIntrospected {
+        client_id: client.client_id,
+        active: response.active,
+    };
+    audit(&state, &context, None, event).await;
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], Json(response)))
+}
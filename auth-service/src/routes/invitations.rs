@@ -0,0 +1,331 @@
+use super::organizations::ensure_scoped_to;
+use super::signup::new_user;
+use crate::domain::{
+    AuditEvent, AuthAPIError, Email, Invitation, InvitationStoreError, MembersRead, MembersWrite,
+    Membership, Role, UserId, UserStoreError,
+};
+use crate::utils::audit::audit;
+use crate::utils::auth::{sign_invitation, verify_invitation_token};
+use crate::utils::extractors::{Json, Path, RequestContext, RequirePermission};
+use crate::utils::INVITATION_TTL_SECONDS;
+use crate::AppState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{delete, get, post};
+use axum::Router;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Routes for invitations, mounted under `/invitations`. Invitations are managed with a
+/// token scoped to the organization, see `/orgs/switch`; accepting one needs no token.
+pub fn invitation_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_invitations).post(create_invitation))
+        .route("/{id}", delete(revoke_invitation))
+        .route("/accept", post(accept_invitation))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInvitationRequest {
+    pub email: String,
+    #[serde(default = "default_role")]
+    pub role: Role,
+}
+
+fn default_role() -> Role {
+    Role::User
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct InvitationResponse {
+    pub id: String,
+    pub org_id: String,
+    pub email: String,
+    pub role: Role,
+    pub invited_by: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// The token for the invite link, only returned when the invitation is created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl From<Invitation> for InvitationResponse {
+    fn from(invitation: Invitation) -> Self {
+        Self {
+            id: invitation.id,
+            org_id: invitation.org_id,
+            email: invitation.email.as_ref().to_owned(),
+            role: invitation.role,
+            invited_by: invitation.invited_by,
+            created_at: invitation.created_at,
+            expires_at: invitation.expires_at,
+            token: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcceptInvitationRequest {
+    pub token: String,
+    /// The password of the new account, or of the existing one if the email has one already.
+    pub password: String,
+    #[serde(default, rename = "requires2FA")]
+    pub requires_2fa: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct AcceptInvitationResponse {
+    pub message: String,
+    #[serde(rename = "orgId")]
+    pub org_id: String,
+}
+
+/// Invites an email to the organization the caller is acting in. The token for the invite
+/// link is emailed to the invitee, and returned in the response as well.
+pub async fn create_invitation(
+    State(state): State<AppState>,
+    context: RequestContext,
+    RequirePermission { user: caller, .. }: RequirePermission<MembersWrite>,
+    Json(request): Json<CreateInvitationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let org_id = caller
+        .claims
+        .org_id
+        .clone()
+        .ok_or(AuthAPIError::Forbidden)?;
+    if !request.role.is_org_role() {
+        return Err(AuthAPIError::Forbidden);
+    }
+    let email = Email::from_str(request.email.trim())
+        .map_err(|err| AuthAPIError::invalid_field("email", err))?;
+
+    let id = UserId::new(state.config.tenant_for(Some(&org_id)), email.clone());
+    match state.user_store.read().await.get_user(&id).await {
+        Ok(user) if user.membership(&org_id).is_some() => {
+            return Err(AuthAPIError::UserAlreadyExists)
+        }
+        Ok(_) | Err(UserStoreError::UserNotFound) => {}
+        Err(err) => return Err(AuthAPIError::unexpected(err)),
+    }
+
+    let invitation = Invitation::new(
+        Uuid::new_v4().to_string(),
+        org_id,
+        email,
+        request.role,
+        caller.claims.sub.clone(),
+        Utc::now() + Duration::seconds(INVITATION_TTL_SECONDS),
+    );
+    state
+        .invitations
+        .write()
+        .await
+        .add_invitation(invitation.clone())
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+    let event = AuditEvent::InvitationCreated {
+        invitation_id: invitation.id.clone(),
+        org_id: invitation.org_id.clone(),
+        email: invitation.email.as_ref().to_owned(),
+    };
+    audit(&state, &context, caller.claims.user_id().as_ref(), event).await;
+
+    let token = sign_invitation(&invitation.id);
+    // The invitation stands either way; the caller can pass the token on themselves.
+    if let Err(err) = send_invitation_email(&state, &invitation, &token).await {
+        tracing::warn!(error = ?err, "Failed to send the invitation");
+    }
+    let response = Json(InvitationResponse {
+        token: Some(token),
+        ..invitation.into()
+    });
+    Ok((StatusCode::CREATED, response))
+}
+
+/// Lists the pending invitations of the organization the caller is acting in.
+pub async fn list_invitations(
+    State(state): State<AppState>,
+    RequirePermission { user: caller, .. }: RequirePermission<MembersRead>,
+) -> Result<Json<Vec<InvitationResponse>>, AuthAPIError> {
+    let org_id = caller
+        .claims
+        .org_id
+        .as_deref()
+        .ok_or(AuthAPIError::Forbidden)?;
+    let invitations = state
+        .invitations
+        .read()
+        .await
+        .get_invitations(org_id)
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+
+    Ok(Json(
+        invitations
+            .into_iter()
+            .map(InvitationResponse::from)
+            .collect(),
+    ))
+}
+
+pub async fn revoke_invitation(
+    State(state): State<AppState>,
+    context: RequestContext,
+    RequirePermission { user: caller, .. }: RequirePermission<MembersWrite>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    // Invitations of other organizations are reported as missing, like sessions.
+    let invitation = find_invitation(&state, &id).await?;
+    ensure_scoped_to(&caller, &invitation.org_id).map_err(|_| AuthAPIError::InvitationNotFound)?;
+
+    remove_invitation(&state, &id).await?;
+    let event = AuditEvent::InvitationRevoked {
+        invitation_id: invitation.id,
+        org_id: invitation.org_id,
+    };
+    audit(&state, &context, caller.claims.user_id().as_ref(), event).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Signs up the invited email, or adds the existing account to the organization if the
+/// email has one, and consumes the invitation.
+pub async fn accept_invitation(
+    State(state): State<AppState>,
+    context: RequestContext,
+    Json(request): Json<AcceptInvitationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let id = verify_invitation_token(&request.token).ok_or(AuthAPIError::InvitationNotFound)?;
+    // Taking the invitation out of the store first means concurrent requests cannot both
+    // accept it. It is put back if it could not be applied, so the invitee can retry.
+    let invitation = state
+        .invitations
+        .write()
+        .await
+        .take_invitation(id)
+        .await
+        .map_err(map_invitation_store_error)?;
+    let (user_id, status) = match join_invited(&state, &invitation, &request).await {
+        Ok(accepted) => accepted,
+        Err(err) => {
+            if let Err(restore_err) = state
+                .invitations
+                .write()
+                .await
+                .add_invitation(invitation)
+                .await
+            {
+                tracing::warn!(error = ?restore_err, "Failed to restore the invitation");
+            }
+            return Err(err);
+        }
+    };
+
+    let event = AuditEvent::InvitationAccepted {
+        invitation_id: invitation.id,
+        org_id: invitation.org_id.clone(),
+    };
+    audit(&state, &context, Some(&user_id), event).await;
+
+    let response = Json(AcceptInvitationResponse {
+        message: "Invitation accepted!".to_owned(),
+        org_id: invitation.org_id,
+    });
+    Ok((status, response))
+}
+
+/// Adds the membership of the invitation to the account of the invited email, signing it
+/// up if there is none yet.
+async fn join_invited(
+    state: &AppState,
+    invitation: &Invitation,
+    request: &AcceptInvitationRequest,
+) -> Result<(UserId, StatusCode), AuthAPIError> {
+    let mut user = new_user(
+        invitation.email.as_ref(),
+        &request.password,
+        request.requires_2fa,
+    )?
+    .with_tenant(state.config.tenant_for(Some(&invitation.org_id)));
+    let membership = Membership::new(invitation.org_id.clone(), vec![invitation.role]);
+    let user_id = user.id();
+
+    let mut user_store = state.user_store.write().await;
+    let status = match user_store.get_user(&user_id).await {
+        Ok(mut existing) => {
+            user_store
+                .validate_user(&user_id, &user.password)
+                .await
+                .map_err(|err| match err {
+                    UserStoreError::InvalidCredentials => AuthAPIError::IncorrectCredentials,
+                    err => AuthAPIError::unexpected(err),
+                })?;
+            existing.ensure_active()?;
+            existing.join(membership);
+            user_store
+                .update_user(existing)
+                .await
+                .map_err(AuthAPIError::unexpected)?;
+            StatusCode::OK
+        }
+        Err(UserStoreError::UserNotFound) => {
+            user.join(membership);
+            user_store
+                .add_user(user)
+                .await
+                .map_err(|_| AuthAPIError::UserAlreadyExists)?;
+            StatusCode::CREATED
+        }
+        Err(err) => return Err(AuthAPIError::unexpected(err)),
+    };
+    Ok((user_id, status))
+}
+
+/// Sends the invitee the token that accepts the invitation.
+async fn send_invitation_email(
+    state: &AppState,
+    invitation: &Invitation,
+    token: &str,
+) -> Result<(), AuthAPIError> {
+    let content = format!(
+        "You have been invited to join {}. Accept the invitation with this token: {}. It expires in {} days.",
+        invitation.org_id,
+        token,
+        INVITATION_TTL_SECONDS / 86_400
+    );
+    state
+        .email_client
+        .send_email(&invitation.email, "You have been invited", &content)
+        .await
+        .map_err(AuthAPIError::unexpected)
+}
+
+async fn find_invitation(state: &AppState, id: &str) -> Result<Invitation, AuthAPIError> {
+    state
+        .invitations
+        .read()
+        .await
+        .get_invitation(id)
+        .await
+        .map_err(map_invitation_store_error)
+}
+
+async fn remove_invitation(state: &AppState, id: &str) -> Result<Invitation, AuthAPIError> {
+    state
+        .invitations
+        .write()
+        .await
+        .remove_invitation(id)
+        .await
+        .map_err(map_invitation_store_error)
+}
+
+fn map_invitation_store_error(err: InvitationStoreError) -> AuthAPIError {
+    match err {
+        InvitationStoreError::InvitationNotFound => AuthAPIError::InvitationNotFound,
+        err => AuthAPIError::unexpected(err),
+    }
+}
@@ -1,10 +1,14 @@
-use crate::domain::{AuthAPIError, Email, Password, UserStoreError};
-use crate::utils::auth::generate_auth_cookie;
+use super::sessions::start_session;
+use super::verify_2fa::{start_2fa, TwoFactorAuthResponse};
+use crate::domain::{AuditEvent, AuthAPIError, Email, Password, UserId, UserStoreError};
+use crate::utils::audit::audit;
+use crate::utils::csrf::csrf_cookie;
+use crate::utils::extractors::{ClientInfo, Json, RequestContext};
 use crate::AppState;
 use axum::extract::State;
 use axum::http::StatusCode;
-use axum::response::IntoResponse;
-use axum::Json;
+use axum::response::{IntoResponse, Response};
+use axum_extra::extract::cookie::Cookie;
 use axum_extra::extract::CookieJar;
 use std::str::FromStr;
 
@@ -12,11 +16,18 @@ use std::str::FromStr;
 pub struct LoginRequest {
     email: String,
     password: String,
+    /// Logs into this organization, which the user must be a member of.
+    #[serde(default, rename = "orgId")]
+    org_id: Option<String>,
 }
 
 impl LoginRequest {
     pub fn new(email: String, password: String) -> LoginRequest {
-        LoginRequest { email, password }
+        LoginRequest {
+            email,
+            password,
+            org_id: None,
+        }
     }
 
     pub fn email(&self) -> &str {
@@ -26,31 +37,115 @@ impl LoginRequest {
         &self.password
     }
 }
+/// What a login with the right password leads to.
+enum LoginOutcome {
+    Session(Cookie<'static>),
+    /// The user has 2FA enabled, so the session only starts at `/verify-2fa`. Holds the id of
+    /// the login attempt.
+    TwoFactorRequired(String),
+}
+
 pub async fn login(
     State(state): State<AppState>,
+    context: RequestContext,
     jar: CookieJar,
     Json(credentials): Json<LoginRequest>,
-) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
-    let email =
-        Email::from_str(&credentials.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
-    let password =
-        Password::from_str(&credentials.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
-    let user_store = &state.user_store.read().await;
-    user_store
-        .validate_user(&email, &password)
-        .await
-        .map_err(|err| match err {
-            UserStoreError::InvalidCredentials => AuthAPIError::IncorrectCredentials,
-            _ => AuthAPIError::UnexpectedError,
-        })?;
-
-    // Call the generate_auth_cookie function defined in the auth module.
-    // If the function call fails return AuthAPIError::UnexpectedError.
-    let auth_cookie = generate_auth_cookie("test")
-        .await
-        .map_err(|_| AuthAPIError::UnexpectedError)?;
+) -> Result<(CookieJar, Response), AuthAPIError> {
+    let result = login_user(&state, context.client.clone(), &credentials).await;
+
+    // Failures are recorded for the email tried, so that guessing shows up for the account.
+    let id = Email::from_str(&credentials.email).ok().map(|email| {
+        UserId::new(
+            state.config.tenant_for(credentials.org_id.as_deref()),
+            email,
+        )
+    });
+    let event = match &result {
+        Ok(LoginOutcome::Session(_)) => {
+            state.metrics.login_succeeded();
+            AuditEvent::LoginSucceeded
+        }
+        Ok(LoginOutcome::TwoFactorRequired(_)) => AuditEvent::TwoFactorRequested,
+        Err(err) => {
+            state.metrics.login_failed(err);
+            AuditEvent::LoginFailed {
+                reason: err.code().to_owned(),
+            }
+        }
+    };
+    audit(&state, &context, id.as_ref(), event).await;
 
-    let updated_jar = jar.add(auth_cookie);
+    let auth_cookie = match result? {
+        LoginOutcome::Session(auth_cookie) => auth_cookie,
+        LoginOutcome::TwoFactorRequired(login_attempt_id) => {
+            let response = Json(TwoFactorAuthResponse {
+                message: "2FA required".to_owned(),
+                login_attempt_id,
+            });
+            return Ok((jar, (StatusCode::PARTIAL_CONTENT, response).into_response()));
+        }
+    };
+    // The CSRF cookie is readable by scripts, so that they can send its token back.
+    let updated_jar = jar
+        .add(csrf_cookie(&state.config, auth_cookie.value()))
+        .add(auth_cookie);
 
     Ok((updated_jar, StatusCode::OK.into_response()))
 }
+
+async fn login_user(
+    state: &AppState,
+    client: ClientInfo,
+    credentials: &LoginRequest,
+) -> Result<LoginOutcome, AuthAPIError> {
+    let (email, password) = match (
+        Email::from_str(&credentials.email),
+        Password::from_str(&credentials.password),
+    ) {
+        (Ok(email), Ok(password)) => (email, password),
+        (email, password) => {
+            return Err(AuthAPIError::invalid_fields([
+                ("email", email.err()),
+                ("password", password.err()),
+            ]))
+        }
+    };
+    let org_id = credentials.org_id.as_deref();
+    let id = UserId::new(state.config.tenant_for(org_id), email);
+    let user = {
+        let user_store = &state.user_store.read().await;
+        user_store
+            .validate_user(&id, &password)
+            .await
+            .map_err(|err| match err {
+                // Unknown emails fail like wrong passwords, so that logins do not reveal who
+                // has an account.
+                UserStoreError::InvalidCredentials | UserStoreError::UserNotFound => {
+                    AuthAPIError::IncorrectCredentials
+                }
+                err => AuthAPIError::unexpected(err),
+            })?;
+        user_store
+            .get_user(&id)
+            .await
+            .map_err(AuthAPIError::unexpected)?
+    };
+    user.ensure_active()?;
+    if user.must_change_password {
+        return Err(AuthAPIError::PasswordChangeRequired);
+    }
+    if let Some(org_id) = org_id {
+        if user.membership(org_id).is_none() {
+            return Err(AuthAPIError::Forbidden);
+        }
+    }
+
+    if user.requires_2fa {
+        return start_2fa(state, &user, org_id)
+            .await
+            .map(LoginOutcome::TwoFactorRequired);
+    }
+    start_session(state, &user, org_id, client)
+        .await
+        .map(LoginOutcome::Session)
+}
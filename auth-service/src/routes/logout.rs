@@ -1,5 +1,8 @@
-use crate::domain::AuthAPIError;
-use crate::utils::JWT_COOKIE_NAME;
+use crate::domain::{AuditEvent, AuthAPIError};
+use crate::utils::audit::audit;
+use crate::utils::auth::removal_cookie;
+use crate::utils::csrf::csrf_removal_cookie;
+use crate::utils::extractors::{AuthToken, RequestContext, TokenSource};
 use crate::AppState;
 use axum::extract::State;
 use axum::http::StatusCode;
@@ -8,32 +11,44 @@ use axum_extra::extract::CookieJar;
 
 pub async fn logout(
     State(state): State<AppState>,
+    context: RequestContext,
     jar: CookieJar,
+    auth_token: AuthToken,
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
-    // Retrieve JWT cookie from the `CookieJar`
-    // Return AuthAPIError::MissingToken is the cookie is not found
-    let cookie = jar.get(JWT_COOKIE_NAME);
-    let cookie = match cookie {
-        Some(c) => c,
-        None => return Err(AuthAPIError::MissingToken),
-    };
-
-    let token = cookie.value().to_owned();
+    // The token comes from the `jwt` cookie or an `Authorization: Bearer` header.
+    // AuthAPIError::MissingToken is returned by the extractor if neither is present.
+    let token = auth_token.token;
 
     let result = crate::utils::auth::validate_token(&token).await;
 
     match result {
-        Ok(valid) => {
-            if !valid {
-                Err(AuthAPIError::InvalidToken)
-            } else {
-                let jar = jar.clone().remove(cookie.clone());
-                let result = state.banned_tokens.write().await.ban_token(&token).await;
-                if result.is_err() {
-                    return Err(AuthAPIError::UnexpectedError);
-                }
-                Ok((jar, StatusCode::OK.into_response()))
+        Ok(claims) => {
+            // Only browser sessions have a cookie to clear.
+            let jar = match auth_token.source {
+                TokenSource::Cookie => jar
+                    .remove(removal_cookie(&state.config))
+                    .remove(csrf_removal_cookie(&state.config)),
+                TokenSource::Bearer => jar,
+            };
+            let result = state.banned_tokens.write().await.ban_token(&token).await;
+            if let Err(err) = result {
+                return Err(AuthAPIError::unexpected(err));
             }
+            // Not every token has a session (e.g. OAuth access tokens), so a miss is fine.
+            let _ = state
+                .sessions
+                .write()
+                .await
+                .remove_session(&claims.jti)
+                .await;
+            audit(
+                &state,
+                &context,
+                claims.user_id().as_ref(),
+                AuditEvent::Logout,
+            )
+            .await;
+            Ok((jar, StatusCode::OK.into_response()))
         }
         Err(_) => Err(AuthAPIError::InvalidToken),
     }
@@ -0,0 +1,32 @@
+use crate::domain::AuthAPIError;
+use crate::AppState;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use subtle::ConstantTimeEq;
+
+/// The content type of the Prometheus text format.
+const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Requires the token configured in `METRICS_TOKEN` as a bearer token, since login failures
+/// and token counts are not for everyone to see. Without one there is nothing to scrape.
+pub async fn metrics(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, AuthAPIError> {
+    let Some(expected_token) = state.config.metrics_token.as_deref() else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(AuthAPIError::MissingToken)?;
+    if !bool::from(token.as_bytes().ct_eq(expected_token.as_bytes())) {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    // The banned token gauge is kept current by the store, so scrapes take no store locks.
+    let body = state.metrics.encode().map_err(AuthAPIError::unexpected)?;
+    Ok(([(header::CONTENT_TYPE, METRICS_CONTENT_TYPE)], body).into_response())
+}
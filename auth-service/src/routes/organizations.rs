@@ -0,0 +1,283 @@
+use super::admin::modify_user;
+use super::sessions::{end_session, start_session};
+use crate::domain::{
+    AuditEvent, AuthAPIError, Email, MembersRead, MembersWrite, Membership, Organization,
+    OrganizationStoreError, Role, User, UserId, UserStoreError,
+};
+use crate::utils::api_key::is_api_key;
+use crate::utils::audit::audit;
+use crate::utils::csrf::csrf_cookie;
+use crate::utils::extractors::{
+    AuthenticatedUser, Json, Path, RequestContext, RequirePermission, TokenSource,
+};
+use crate::AppState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::Router;
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+const INVALID_ID: &str =
+    "Must be 1 to 63 lowercase letters, digits or dashes and not start with a dash";
+
+/// Routes for organizations, mounted under `/orgs`. Managing members requires a token
+/// scoped to the organization, see `/orgs/switch`.
+pub fn organization_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_organizations).post(create_organization))
+        .route("/switch", post(switch_organization))
+        .route("/{id}/members", get(list_members).post(add_member))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrganizationRequest {
+    /// Derived from the name if left out.
+    pub id: Option<String>,
+    pub name: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct OrganizationResponse {
+    pub id: String,
+    pub name: String,
+    /// The caller's roles in the organization.
+    pub roles: Vec<Role>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SwitchOrganizationRequest {
+    #[serde(rename = "orgId")]
+    pub org_id: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SwitchOrganizationResponse {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddMemberRequest {
+    pub email: String,
+    pub roles: Vec<Role>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct MemberResponse {
+    pub email: String,
+    pub roles: Vec<Role>,
+}
+
+/// Creates an organization with the caller as its first admin.
+pub async fn create_organization(
+    State(state): State<AppState>,
+    context: RequestContext,
+    AuthenticatedUser { claims, .. }: AuthenticatedUser,
+    Json(request): Json<CreateOrganizationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let name = request.name.trim().to_owned();
+    let name_error = name.is_empty().then(|| "Name must not be empty".to_owned());
+    // An id derived from an empty name is only worth reporting as such.
+    let id_error = match &request.id {
+        Some(id) if !Organization::is_valid_id(id) => Some(INVALID_ID),
+        None if name_error.is_none() && !Organization::is_valid_id(&slug(&name)) => {
+            Some("Could not derive an id from the name, choose one")
+        }
+        _ => None,
+    };
+    if name_error.is_some() || id_error.is_some() {
+        return Err(AuthAPIError::invalid_fields([
+            ("name", name_error),
+            ("id", id_error.map(str::to_owned)),
+        ]));
+    }
+    let id = request.id.unwrap_or_else(|| slug(&name));
+    let user_id = claims.user_id().ok_or(AuthAPIError::InvalidToken)?;
+
+    let organization = Organization::new(id, name);
+    state
+        .organizations
+        .write()
+        .await
+        .add_organization(organization.clone())
+        .await
+        .map_err(|err| match err {
+            OrganizationStoreError::OrganizationAlreadyExists => {
+                AuthAPIError::OrganizationAlreadyExists
+            }
+            err => AuthAPIError::unexpected(err),
+        })?;
+
+    let roles = vec![Role::User, Role::OrgAdmin];
+    let membership = Membership::new(organization.id.clone(), roles.clone());
+    modify_user(&state, &user_id, |user| user.join(membership)).await?;
+    let event = AuditEvent::OrganizationCreated {
+        org_id: organization.id.clone(),
+    };
+    audit(&state, &context, Some(&user_id), event).await;
+
+    let response = Json(OrganizationResponse {
+        id: organization.id,
+        name: organization.name,
+        roles,
+    });
+    Ok((StatusCode::CREATED, response))
+}
+
+/// Lists the organizations the caller is a member of.
+pub async fn list_organizations(
+    State(state): State<AppState>,
+    AuthenticatedUser { claims, .. }: AuthenticatedUser,
+) -> Result<Json<Vec<OrganizationResponse>>, AuthAPIError> {
+    let user = find_user(&state, &claims.user_id().ok_or(AuthAPIError::InvalidToken)?).await?;
+
+    let organizations = state.organizations.read().await;
+    let mut response = Vec::with_capacity(user.memberships.len());
+    for membership in user.memberships {
+        let organization = organizations
+            .get_organization(&membership.org_id)
+            .await
+            .map_err(AuthAPIError::unexpected)?;
+        response.push(OrganizationResponse {
+            id: organization.id,
+            name: organization.name,
+            roles: membership.roles,
+        });
+    }
+    Ok(Json(response))
+}
+
+/// Replaces the caller's token with one scoped to another of their organizations.
+pub async fn switch_organization(
+    State(state): State<AppState>,
+    context: RequestContext,
+    jar: CookieJar,
+    AuthenticatedUser { token, claims }: AuthenticatedUser,
+    Json(request): Json<SwitchOrganizationRequest>,
+) -> Result<(CookieJar, Json<SwitchOrganizationResponse>), AuthAPIError> {
+    // A scoped API key or OAuth access token must not be traded for a token with all of the
+    // user's permissions.
+    if is_api_key(&token.token) || claims.is_delegated() {
+        return Err(AuthAPIError::Forbidden);
+    }
+    let user = find_user(&state, &claims.user_id().ok_or(AuthAPIError::InvalidToken)?).await?;
+    user.ensure_active()?;
+    if user.membership(&request.org_id).is_none() {
+        return Err(AuthAPIError::Forbidden);
+    }
+
+    let auth_cookie =
+        start_session(&state, &user, Some(&request.org_id), context.client.clone()).await?;
+    end_session(&state, &token.token, &claims.jti).await?;
+    let event = AuditEvent::OrganizationSwitched {
+        org_id: request.org_id,
+    };
+    audit(&state, &context, Some(&user.id()), event).await;
+
+    let response = Json(SwitchOrganizationResponse {
+        token: auth_cookie.value().to_owned(),
+    });
+    // Only browser sessions have a cookie to replace.
+    let jar = match token.source {
+        TokenSource::Cookie => jar
+            .add(csrf_cookie(&state.config, auth_cookie.value()))
+            .add(auth_cookie),
+        TokenSource::Bearer => jar,
+    };
+    Ok((jar, response))
+}
+
+pub async fn list_members(
+    State(state): State<AppState>,
+    RequirePermission { user: caller, .. }: RequirePermission<MembersRead>,
+    Path(org_id): Path<String>,
+) -> Result<Json<Vec<MemberResponse>>, AuthAPIError> {
+    ensure_scoped_to(&caller, &org_id)?;
+    let members = state
+        .user_store
+        .read()
+        .await
+        .get_members(&org_id)
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+
+    Ok(Json(
+        members
+            .into_iter()
+            .filter_map(|member| {
+                let roles = member.membership(&org_id)?.roles.clone();
+                Some(MemberResponse {
+                    email: member.email.as_ref().to_owned(),
+                    roles,
+                })
+            })
+            .collect(),
+    ))
+}
+
+/// Adds an existing user to the organization, or changes their roles in it. The new roles
+/// take effect the next time the member switches to the organization.
+pub async fn add_member(
+    State(state): State<AppState>,
+    context: RequestContext,
+    RequirePermission { user: caller, .. }: RequirePermission<MembersWrite>,
+    Path(org_id): Path<String>,
+    Json(request): Json<AddMemberRequest>,
+) -> Result<Json<MemberResponse>, AuthAPIError> {
+    ensure_scoped_to(&caller, &org_id)?;
+    // Global roles such as admin cannot be handed out through an organization.
+    if !request.roles.iter().all(Role::is_org_role) {
+        return Err(AuthAPIError::Forbidden);
+    }
+
+    let email =
+        Email::from_str(&request.email).map_err(|err| AuthAPIError::invalid_field("email", err))?;
+    let id = UserId::new(state.config.tenant_for(Some(&org_id)), email);
+    let membership = Membership::new(org_id.clone(), request.roles.clone());
+    let user = modify_user(&state, &id, |user| user.join(membership)).await?;
+    let event = AuditEvent::MemberAdded {
+        org_id,
+        member: user.email.as_ref().to_owned(),
+        roles: request.roles.clone(),
+    };
+    audit(&state, &context, caller.claims.user_id().as_ref(), event).await;
+
+    Ok(Json(MemberResponse {
+        email: user.email.as_ref().to_owned(),
+        roles: request.roles,
+    }))
+}
+
+pub(crate) fn ensure_scoped_to(
+    caller: &AuthenticatedUser,
+    org_id: &str,
+) -> Result<(), AuthAPIError> {
+    if caller.claims.org_id.as_deref() == Some(org_id) {
+        Ok(())
+    } else {
+        Err(AuthAPIError::Forbidden)
+    }
+}
+
+fn slug(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+async fn find_user(state: &AppState, id: &UserId) -> Result<User, AuthAPIError> {
+    state
+        .user_store
+        .read()
+        .await
+        .get_user(id)
+        .await
+        .map_err(|err| match err {
+            UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+            err => AuthAPIError::unexpected(err),
+        })
+}
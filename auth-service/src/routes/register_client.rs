@@ -0,0 +1,135 @@
+use crate::domain::{AuditEvent, OAuthClient, OAuthError, Scope};
+use crate::utils::audit::audit;
+use crate::utils::auth::generate_opaque_token;
+use crate::utils::extractors::RequestContext;
+use crate::AppState;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::Json;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use url::Url;
+use uuid::Uuid;
+
+const CLIENT_SECRET_BASIC: &str = "client_secret_basic";
+const CLIENT_SECRET_POST: &str = "client_secret_post";
+const NONE: &str = "none";
+
+/// Client metadata as defined by RFC 7591.
+#[derive(Deserialize)]
+pub struct RegisterClientRequest {
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    pub scope: Option<String>,
+    pub token_endpoint_auth_method: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct RegisterClientResponse {
+    pub client_id: String,
+    /// Only returned once, at registration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    pub client_id_issued_at: i64,
+    pub redirect_uris: Vec<String>,
+    pub scope: String,
+    pub token_endpoint_auth_method: String,
+}
+
+pub async fn register_client(
+    State(state): State<AppState>,
+    context: RequestContext,
+    headers: HeaderMap,
+    Json(request): Json<RegisterClientRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let result = register(&state, &headers, request).await;
+    let event = match &result {
+        Ok(response) => AuditEvent::ClientRegistered {
+            client_id: response.client_id.clone(),
+        },
+        Err(err) => AuditEvent::ClientRegistrationFailed {
+            reason: err.code().to_owned(),
+        },
+    };
+    audit(&state, &context, None, event).await;
+
+    Ok((
+        StatusCode::CREATED,
+        [(header::CACHE_CONTROL, "no-store")],
+        Json(result?),
+    ))
+}
+
+async fn register(
+    state: &AppState,
+    headers: &HeaderMap,
+    request: RegisterClientRequest,
+) -> Result<RegisterClientResponse, OAuthError> {
+    // Registration is protected by an initial access token (RFC 7591, section 3).
+    let expected_token = state
+        .config
+        .client_registration_token
+        .as_deref()
+        .ok_or(OAuthError::InvalidToken)?;
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(OAuthError::InvalidToken)?;
+    if !bool::from(token.as_bytes().ct_eq(expected_token.as_bytes())) {
+        return Err(OAuthError::InvalidToken);
+    }
+
+    if request
+        .redirect_uris
+        .iter()
+        .any(|redirect_uri| Url::parse(redirect_uri).is_err())
+    {
+        return Err(OAuthError::InvalidRedirectUri);
+    }
+
+    let auth_method = request
+        .token_endpoint_auth_method
+        .unwrap_or_else(|| CLIENT_SECRET_BASIC.to_owned());
+    let scope = Scope::parse(request.scope.as_deref().unwrap_or_default());
+    let client_id = Uuid::new_v4().simple().to_string();
+
+    let (client, client_secret) = match auth_method.as_str() {
+        CLIENT_SECRET_BASIC | CLIENT_SECRET_POST => {
+            let client_secret = generate_opaque_token().map_err(|_| OAuthError::ServerError)?;
+            let client = OAuthClient::confidential(
+                client_id.clone(),
+                &client_secret,
+                request.redirect_uris.clone(),
+                scope.clone(),
+            );
+            (client, Some(client_secret))
+        }
+        // Public clients can only use the authorization code flow, which needs a redirect URI.
+        NONE if !request.redirect_uris.is_empty() => (
+            OAuthClient::new(client_id.clone(), request.redirect_uris.clone()),
+            None,
+        ),
+        NONE => return Err(OAuthError::InvalidRedirectUri),
+        _ => return Err(OAuthError::InvalidClientMetadata),
+    };
+
+    state
+        .oauth_clients
+        .write()
+        .await
+        .add_client(client)
+        .await
+        .map_err(|_| OAuthError::ServerError)?;
+
+    Ok(RegisterClientResponse {
+        client_id,
+        client_secret,
+        client_id_issued_at: Utc::now().timestamp(),
+        redirect_uris: request.redirect_uris,
+        scope: scope.to_string(),
+        token_endpoint_auth_method: auth_method,
+    })
+}
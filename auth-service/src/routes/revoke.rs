@@ -0,0 +1,91 @@
+use crate::domain::{AuditEvent, OAuthClient, OAuthError, TokenStoreError};
+use crate::utils::audit::audit;
+use crate::utils::auth::validate_token;
+use crate::utils::client_auth::authenticate_client;
+use crate::utils::extractors::RequestContext;
+use crate::AppState;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::Form;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct RevocationRequest {
+    pub token: String,
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+/// Revokes an access or refresh token (RFC 7009).
+///
+/// Per the RFC the response is `200 OK` whether or not the token was known, so callers can't
+/// use this endpoint to probe for valid tokens. Clients can only revoke tokens issued to them;
+/// browser session tokens, which belong to no client, can be revoked by any client holding them.
+pub async fn revoke(
+    State(state): State<AppState>,
+    context: RequestContext,
+    headers: HeaderMap,
+    Form(request): Form<RevocationRequest>,
+) -> Result<StatusCode, OAuthError> {
+    let client = authenticate_client(
+        &state,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+
+    // The hint only decides which kind of token is looked up first.
+    if request.token_type_hint.as_deref() == Some("refresh_token") {
+        if !revoke_refresh_token(&state, &client, &request.token).await? {
+            revoke_access_token(&state, &client, &request.token).await?;
+        }
+    } else if !revoke_access_token(&state, &client, &request.token).await? {
+        revoke_refresh_token(&state, &client, &request.token).await?;
+    }
+    let event = AuditEvent::TokenRevoked {
+        client_id: client.client_id,
+    };
+    audit(&state, &context, None, event).await;
+
+    Ok(StatusCode::OK)
+}
+
+async fn revoke_access_token(
+    state: &AppState,
+    client: &OAuthClient,
+    token: &str,
+) -> Result<bool, OAuthError> {
+    let Ok(claims) = validate_token(token).await else {
+        return Ok(false);
+    };
+    if claims
+        .client_id
+        .as_deref()
+        .is_some_and(|client_id| client_id != client.client_id)
+    {
+        return Ok(false);
+    }
+
+    match state.banned_tokens.write().await.ban_token(token).await {
+        Ok(()) | Err(TokenStoreError::TokenAlreadyBanned) => Ok(true),
+        Err(_) => Err(OAuthError::ServerError),
+    }
+}
+
+async fn revoke_refresh_token(
+    state: &AppState,
+    client: &OAuthClient,
+    token: &str,
+) -> Result<bool, OAuthError> {
+    let mut refresh_tokens = state.refresh_tokens.write().await;
+    match refresh_tokens.get_refresh_token(token).await {
+        Ok(refresh_token) if refresh_token.client_id == client.client_id => refresh_tokens
+            .revoke_refresh_token(token)
+            .await
+            .map(|_| true)
+            .map_err(|_| OAuthError::ServerError),
+        _ => Ok(false),
+    }
+}
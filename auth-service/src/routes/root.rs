@@ -0,0 +1,25 @@
+use crate::domain::AuthAPIError;
+use askama::Template;
+use axum::response::Html;
+use axum::Extension;
+use security_headers::CspNonce;
+
+#[derive(Template)]
+#[template(path = "index.html")]
+struct IndexTemplate {
+    // Allows the page's scripts to run; empty if the policy does not use a nonce.
+    nonce: String,
+}
+
+// The login/signup UI
+pub async fn root(nonce: Option<Extension<CspNonce>>) -> Result<Html<String>, AuthAPIError> {
+    let template = IndexTemplate {
+        nonce: nonce
+            .map(|Extension(nonce)| nonce.to_string())
+            .unwrap_or_default(),
+    };
+    template
+        .render()
+        .map(Html)
+        .map_err(AuthAPIError::unexpected)
+}
@@ -0,0 +1,259 @@
+use crate::domain::{AuditEvent, AuthAPIError, Session, SessionStoreError, User, UserId};
+use crate::utils::api_key::is_api_key;
+use crate::utils::audit::audit;
+use crate::utils::auth::{generate_auth_cookie, removal_cookie, validate_token};
+use crate::utils::csrf::csrf_removal_cookie;
+use crate::utils::extractors::{
+    AuthenticatedUser, ClientInfo, Json, Path, RequestContext, TokenSource,
+};
+use crate::AppState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum_extra::extract::cookie::Cookie;
+use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    /// Whether this is the session the request was made with.
+    pub current: bool,
+}
+
+impl SessionResponse {
+    fn new(session: Session, current_id: &str) -> Self {
+        Self {
+            current: session.id == current_id,
+            id: session.id,
+            created_at: session.created_at,
+            last_seen: session.last_seen,
+            expires_at: session.expires_at,
+            user_agent: session.user_agent,
+            ip: session.ip.map(|ip| ip.to_string()),
+        }
+    }
+}
+
+/// Lists the sessions of the current user. Sessions are managed from a login of the user,
+/// not with an API key or an OAuth access token.
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    AuthenticatedUser { token, claims }: AuthenticatedUser,
+) -> Result<Json<Vec<SessionResponse>>, AuthAPIError> {
+    if is_api_key(&token.token) || claims.is_delegated() {
+        return Err(AuthAPIError::Forbidden);
+    }
+    let user_id = claims.user_id().ok_or(AuthAPIError::InvalidToken)?;
+    let sessions = state
+        .sessions
+        .read()
+        .await
+        .get_sessions(&user_id)
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+
+    Ok(Json(
+        sessions
+            .into_iter()
+            .map(|session| SessionResponse::new(session, &claims.jti))
+            .collect(),
+    ))
+}
+
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    context: RequestContext,
+    jar: CookieJar,
+    AuthenticatedUser { token, claims }: AuthenticatedUser,
+    Path(id): Path<String>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    if is_api_key(&token.token) || claims.is_delegated() {
+        return Err(AuthAPIError::Forbidden);
+    }
+    // Sessions of other users are reported as missing rather than forbidden,
+    // so that session ids cannot be probed.
+    let session = state
+        .sessions
+        .read()
+        .await
+        .get_session(&id)
+        .await
+        .ok()
+        .filter(|session| Some(&session.user) == claims.user_id().as_ref())
+        .ok_or(AuthAPIError::SessionNotFound)?;
+
+    revoke(&state, &session).await?;
+    let event = AuditEvent::SessionRevoked {
+        session_id: session.id.clone(),
+    };
+    audit(&state, &context, Some(&session.user), event).await;
+
+    let jar = if session.id == claims.jti && token.source == TokenSource::Cookie {
+        jar.remove(removal_cookie(&state.config))
+            .remove(csrf_removal_cookie(&state.config))
+    } else {
+        jar
+    };
+    Ok((jar, StatusCode::NO_CONTENT))
+}
+
+/// Logs the user out everywhere, including the session the request was made with.
+pub async fn revoke_all_sessions(
+    State(state): State<AppState>,
+    context: RequestContext,
+    jar: CookieJar,
+    AuthenticatedUser { token, claims }: AuthenticatedUser,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    if is_api_key(&token.token) || claims.is_delegated() {
+        return Err(AuthAPIError::Forbidden);
+    }
+    let user_id = claims.user_id().ok_or(AuthAPIError::InvalidToken)?;
+    let revoked = revoke_sessions_of(&state, &user_id).await?;
+
+    // The token used for this request may not belong to a recorded session,
+    // it is logged out all the same.
+    if !revoked.iter().any(|id| id == &claims.jti) {
+        ban(&state, &token.token).await?;
+    }
+    let event = AuditEvent::AllSessionsRevoked {
+        count: revoked.len(),
+    };
+    audit(&state, &context, Some(&user_id), event).await;
+
+    let jar = match token.source {
+        TokenSource::Cookie => jar
+            .remove(removal_cookie(&state.config))
+            .remove(csrf_removal_cookie(&state.config)),
+        TokenSource::Bearer => jar,
+    };
+    Ok((jar, StatusCode::NO_CONTENT))
+}
+
+/// Issues a token for the user, scoped to the organization if one is given, and records
+/// a session for it so the user can see and revoke it later.
+pub(crate) async fn start_session(
+    state: &AppState,
+    user: &User,
+    org_id: Option<&str>,
+    client: ClientInfo,
+) -> Result<Cookie<'static>, AuthAPIError> {
+    let auth_cookie = generate_auth_cookie(user, org_id, &state.config)
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+
+    let claims = validate_token(auth_cookie.value())
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+    let expires_at = i64::try_from(claims.exp)
+        .ok()
+        .and_then(|exp| DateTime::from_timestamp(exp, 0))
+        .ok_or(AuthAPIError::UnexpectedError)?;
+    let session = Session::new(
+        claims.jti,
+        user.id(),
+        auth_cookie.value().to_owned(),
+        expires_at,
+        client.user_agent,
+        client.ip,
+    );
+    state
+        .sessions
+        .write()
+        .await
+        .add_session(session)
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+
+    Ok(auth_cookie)
+}
+
+/// Logs out the token, whether or not it belongs to a recorded session.
+pub(crate) async fn end_session(
+    state: &AppState,
+    token: &str,
+    session_id: &str,
+) -> Result<(), AuthAPIError> {
+    ban(state, token).await?;
+    match state
+        .sessions
+        .write()
+        .await
+        .remove_session(session_id)
+        .await
+    {
+        Ok(_) | Err(SessionStoreError::SessionNotFound) => Ok(()),
+        Err(err) => Err(AuthAPIError::unexpected(err)),
+    }
+}
+
+/// Revokes every session of the user and returns their ids.
+pub(crate) async fn revoke_sessions_of(
+    state: &AppState,
+    user: &UserId,
+) -> Result<Vec<String>, AuthAPIError> {
+    let sessions = state
+        .sessions
+        .read()
+        .await
+        .get_sessions(user)
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+
+    for session in &sessions {
+        revoke(state, session).await?;
+    }
+    Ok(sessions.into_iter().map(|session| session.id).collect())
+}
+
+/// Revokes everything the user could authenticate with besides their password: sessions, API
+/// keys and OAuth refresh tokens. These are all tied to the [`UserId`], so they would otherwise
+/// keep working after a forced logout, or pass to whoever signs up with the email next.
+pub(crate) async fn revoke_credentials_of(
+    state: &AppState,
+    user: &UserId,
+) -> Result<(), AuthAPIError> {
+    revoke_sessions_of(state, user).await?;
+    state
+        .api_keys
+        .write()
+        .await
+        .revoke_all_for_user(user)
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+    state
+        .refresh_tokens
+        .write()
+        .await
+        .revoke_all_for_user(user)
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+    Ok(())
+}
+
+// Bans the session's token first, so that a failure never leaves a live token
+// without a session the user could see and revoke.
+async fn revoke(state: &AppState, session: &Session) -> Result<(), AuthAPIError> {
+    end_session(state, &session.token, &session.id).await
+}
+
+async fn ban(state: &AppState, token: &str) -> Result<(), AuthAPIError> {
+    let mut banned_tokens = state.banned_tokens.write().await;
+    if banned_tokens
+        .is_token_banned(token)
+        .await
+        .map_err(AuthAPIError::unexpected)?
+    {
+        return Ok(());
+    }
+    banned_tokens
+        .ban_token(token)
+        .await
+        .map_err(AuthAPIError::unexpected)
+}
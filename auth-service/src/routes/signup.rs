@@ -1,9 +1,11 @@
-use crate::domain::{AuthAPIError, Email, Password};
-use crate::{domain, AppState};
+use super::verify_email::send_verification_email;
+use crate::domain::{AuditEvent, AuthAPIError, Email, Password, User, UserId};
+use crate::utils::audit::audit;
+use crate::utils::extractors::{Json, RequestContext};
+use crate::AppState;
 use axum::extract::State;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use axum::Json;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
@@ -13,6 +15,10 @@ pub struct SignupRequest {
     pub password: String,
     #[serde(rename = "requires2FA")]
     pub requires_2fa: bool,
+    /// No longer accepted: organizations are only joined through `/invitations/accept`, which
+    /// also creates the accounts of tenants with their own emails.
+    #[serde(default, rename = "orgId")]
+    pub org_id: Option<String>,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -23,28 +29,71 @@ pub struct SignupResponse {
 #[axum::debug_handler]
 pub async fn signup(
     State(state): State<AppState>,
+    context: RequestContext,
     Json(request): Json<SignupRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
-    let email: Result<Email, String> = Email::from_str(request.email.trim());
-    if email.is_err() {
-        return Err(AuthAPIError::InvalidCredentials);
-    }
-    let password: Result<Password, String> = Password::from_str(request.password.trim());
-    if password.is_err() {
-        return Err(AuthAPIError::InvalidCredentials);
-    }
+    let id = Email::from_str(request.email.trim())
+        .ok()
+        .map(|email| UserId::new(None, email));
+    let result = create_user(&state, request).await;
+    let event = match &result {
+        Ok(()) => AuditEvent::SignupSucceeded,
+        Err(err) => AuditEvent::SignupFailed {
+            reason: err.code().to_owned(),
+        },
+    };
+    audit(&state, &context, id.as_ref(), event).await;
+    result?;
 
-    let user = domain::user::User::new(email.unwrap(), password.unwrap(), request.requires_2fa);
+    let response = Json(SignupResponse {
+        message: "User created successfully!".to_string(),
+    });
+    Ok((StatusCode::CREATED, response))
+}
 
-    let mut user_store = state.user_store.write().await;
+async fn create_user(state: &AppState, request: SignupRequest) -> Result<(), AuthAPIError> {
+    // Anyone could otherwise join an organization by guessing its id.
+    if request.org_id.is_some() {
+        return Err(AuthAPIError::invalid_field(
+            "orgId",
+            "Organizations are joined through an invitation",
+        ));
+    }
+    let user = new_user(&request.email, &request.password, request.requires_2fa)?;
 
-    if let Ok(_stored_user) = user_store.add_user(user).await {
-        let response = Json(SignupResponse {
-            message: "User created successfully!".to_string(),
-        });
+    state
+        .user_store
+        .write()
+        .await
+        .add_user(user.clone())
+        .await
+        .map_err(|_| AuthAPIError::UserAlreadyExists)?;
 
-        Ok((StatusCode::CREATED, response))
-    } else {
-        Err(AuthAPIError::UserAlreadyExists)
+    // The account is there either way; an admin can still verify the email.
+    if let Err(err) = send_verification_email(state, &user).await {
+        tracing::warn!(error = ?err, "Failed to send the email verification link");
     }
+    Ok(())
+}
+
+/// Validates the credentials of a new account, whichever way it signs up.
+pub(crate) fn new_user(
+    email: &str,
+    password: &str,
+    requires_2fa: bool,
+) -> Result<User, AuthAPIError> {
+    let (email, password) = match (
+        Email::from_str(email.trim()),
+        Password::from_str(password.trim()),
+    ) {
+        (Ok(email), Ok(password)) => (email, password),
+        (email, password) => {
+            return Err(AuthAPIError::invalid_fields([
+                ("email", email.err()),
+                ("password", password.err()),
+            ]))
+        }
+    };
+
+    Ok(User::new(email, password, requires_2fa))
 }
@@ -0,0 +1,316 @@
+use crate::domain::{AuditEvent, OAuthError, RefreshToken, Scope, UserId};
+use crate::utils::audit::audit;
+use crate::utils::auth::{generate_access_token, generate_client_token, generate_opaque_token};
+use crate::utils::client_auth::{authenticate_client, basic_credentials};
+use crate::utils::extractors::RequestContext;
+use crate::utils::oidc::{issuer, IdTokenClaims, ID_TOKEN_SIGNER};
+use crate::utils::{REFRESH_TOKEN_TTL_SECONDS, TOKEN_TTL_SECONDS};
+use crate::AppState;
+use axum::extract::State;
+use axum::http::{header, HeaderMap};
+use axum::response::IntoResponse;
+use axum::{Form, Json};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct AccessTokenRequest {
+    pub grant_type: String,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub scope: Option<String>,
+    pub refresh_token: Option<String>,
+    pub code_verifier: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct AccessTokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub scope: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+pub async fn token(
+    State(state): State<AppState>,
+    context: RequestContext,
+    headers: HeaderMap,
+    Form(request): Form<AccessTokenRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let grant_type = request.grant_type.clone();
+    // The client as claimed by the caller, which is all there is when authentication fails.
+    let client_id = basic_credentials(&headers)
+        .map(|(client_id, _)| client_id)
+        .or_else(|| request.client_id.clone());
+
+    let result = match grant_type.as_str() {
+        "authorization_code" => {
+            authorization_code_grant(state.clone(), &context, &headers, request)
+                .await
+                .map(IntoResponse::into_response)
+        }
+        "client_credentials" => {
+            client_credentials_grant(state.clone(), &context, &headers, request)
+                .await
+                .map(IntoResponse::into_response)
+        }
+        "refresh_token" => refresh_token_grant(state.clone(), &context, &headers, request)
+            .await
+            .map(IntoResponse::into_response),
+        _ => Err(OAuthError::UnsupportedGrantType),
+    };
+    if let Err(err) = &result {
+        let event = AuditEvent::TokenRequestFailed {
+            client_id,
+            grant_type,
+            reason: err.code().to_owned(),
+        };
+        audit(&state, &context, None, event).await;
+    }
+    result
+}
+
+async fn authorization_code_grant(
+    state: AppState,
+    context: &RequestContext,
+    headers: &HeaderMap,
+    request: AccessTokenRequest,
+) -> Result<impl IntoResponse, OAuthError> {
+    let client = authenticate_client(
+        &state,
+        headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+    let (Some(code), Some(redirect_uri)) = (request.code, request.redirect_uri) else {
+        return Err(OAuthError::InvalidRequest);
+    };
+
+    // The code is consumed even if the request turns out to be invalid, so it can't be replayed.
+    let code = state
+        .authorization_codes
+        .write()
+        .await
+        .take_code(&code)
+        .await
+        .map_err(|_| OAuthError::InvalidGrant)?;
+    if code.is_expired() || code.client_id != client.client_id || code.redirect_uri != redirect_uri
+    {
+        return Err(OAuthError::InvalidGrant);
+    }
+    if !code.verifies(request.code_verifier.as_deref()) {
+        return Err(OAuthError::InvalidGrant);
+    }
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&code.user)
+        .await
+        .map_err(|_| OAuthError::InvalidGrant)?;
+    if !user.is_active() {
+        return Err(OAuthError::AccountInactive);
+    }
+
+    let access_token =
+        generate_access_token(&user.id(), Some(&code.scope), Some(&client.client_id))
+            .map_err(|_| OAuthError::ServerError)?;
+
+    let refresh_token =
+        issue_refresh_token(&state, &client.client_id, &user.id(), &code.scope).await?;
+
+    let id_token = if code.scope.contains(Scope::OPENID) {
+        let mut claims = IdTokenClaims::new(
+            issuer(&state.config)?.to_owned(),
+            user.subject.clone(),
+            client.client_id.clone(),
+            code.nonce,
+        );
+        if code.scope.contains(Scope::EMAIL) {
+            claims.email = Some(user.email.as_ref().to_owned());
+            claims.email_verified = Some(user.email_verified);
+        }
+        Some(
+            ID_TOKEN_SIGNER
+                .sign(&claims)
+                .map_err(|_| OAuthError::ServerError)?,
+        )
+    } else {
+        None
+    };
+
+    let response = Json(AccessTokenResponse {
+        access_token,
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+        scope: code.scope.to_string(),
+        refresh_token: Some(refresh_token),
+        id_token,
+    });
+    let event = AuditEvent::TokenIssued {
+        client_id: client.client_id,
+        grant_type: "authorization_code".to_owned(),
+    };
+    audit(&state, context, Some(&user.id()), event).await;
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], response))
+}
+
+// Tokens for services acting on their own behalf, identified by their client id
+async fn client_credentials_grant(
+    state: AppState,
+    context: &RequestContext,
+    headers: &HeaderMap,
+    request: AccessTokenRequest,
+) -> Result<impl IntoResponse, OAuthError> {
+    let client = authenticate_client(
+        &state,
+        headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+    if !client.is_confidential() {
+        return Err(OAuthError::UnauthorizedClient);
+    }
+
+    // Without an explicit scope the client gets everything it was registered for.
+    let scope = match request.scope.as_deref() {
+        Some(scope) => Scope::parse(scope),
+        None => client.scope.clone(),
+    };
+    if !scope.is_subset_of(&client.scope) {
+        return Err(OAuthError::InvalidScope);
+    }
+
+    let access_token =
+        generate_client_token(&client.client_id, &scope).map_err(|_| OAuthError::ServerError)?;
+
+    let response = Json(AccessTokenResponse {
+        access_token,
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+        scope: scope.to_string(),
+        refresh_token: None,
+        id_token: None,
+    });
+    let event = AuditEvent::TokenIssued {
+        client_id: client.client_id,
+        grant_type: "client_credentials".to_owned(),
+    };
+    audit(&state, context, None, event).await;
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], response))
+}
+
+// Exchanges a refresh token for a new access token. Refresh tokens are rotated: the presented
+// token is revoked and a new one is returned alongside the access token.
+async fn refresh_token_grant(
+    state: AppState,
+    context: &RequestContext,
+    headers: &HeaderMap,
+    request: AccessTokenRequest,
+) -> Result<impl IntoResponse, OAuthError> {
+    let client = authenticate_client(
+        &state,
+        headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .await?;
+    let token = request.refresh_token.ok_or(OAuthError::InvalidRequest)?;
+
+    let refresh_token = state
+        .refresh_tokens
+        .read()
+        .await
+        .get_refresh_token(&token)
+        .await
+        .map_err(|_| OAuthError::InvalidGrant)?;
+    if refresh_token.client_id != client.client_id {
+        return Err(OAuthError::InvalidGrant);
+    }
+    state
+        .refresh_tokens
+        .write()
+        .await
+        .revoke_refresh_token(&token)
+        .await
+        .map_err(|_| OAuthError::InvalidGrant)?;
+    if refresh_token.is_expired() {
+        return Err(OAuthError::InvalidGrant);
+    }
+
+    // A narrower scope may be requested, but never a broader one.
+    let scope = match request.scope.as_deref() {
+        Some(scope) => Scope::parse(scope),
+        None => refresh_token.scope.clone(),
+    };
+    if !scope.is_subset_of(&refresh_token.scope) {
+        return Err(OAuthError::InvalidScope);
+    }
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&refresh_token.user)
+        .await
+        .map_err(|_| OAuthError::InvalidGrant)?;
+    if !user.is_active() {
+        return Err(OAuthError::AccountInactive);
+    }
+
+    let access_token = generate_access_token(&user.id(), Some(&scope), Some(&client.client_id))
+        .map_err(|_| OAuthError::ServerError)?;
+    let new_refresh_token =
+        issue_refresh_token(&state, &client.client_id, &user.id(), &refresh_token.scope).await?;
+
+    let response = Json(AccessTokenResponse {
+        access_token,
+        token_type: "Bearer".to_owned(),
+        expires_in: TOKEN_TTL_SECONDS,
+        scope: scope.to_string(),
+        refresh_token: Some(new_refresh_token),
+        id_token: None,
+    });
+    let event = AuditEvent::TokenIssued {
+        client_id: client.client_id,
+        grant_type: "refresh_token".to_owned(),
+    };
+    audit(&state, context, Some(&user.id()), event).await;
+
+    Ok(([(header::CACHE_CONTROL, "no-store")], response))
+}
+
+async fn issue_refresh_token(
+    state: &AppState,
+    client_id: &str,
+    user: &UserId,
+    scope: &Scope,
+) -> Result<String, OAuthError> {
+    let token = generate_opaque_token().map_err(|_| OAuthError::ServerError)?;
+    let refresh_token = RefreshToken::new(
+        token.clone(),
+        client_id.to_owned(),
+        user.clone(),
+        scope.clone(),
+        Utc::now() + Duration::seconds(REFRESH_TOKEN_TTL_SECONDS),
+    );
+    state
+        .refresh_tokens
+        .write()
+        .await
+        .add_refresh_token(refresh_token)
+        .await
+        .map_err(|_| OAuthError::ServerError)?;
+    Ok(token)
+}
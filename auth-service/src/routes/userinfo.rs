@@ -0,0 +1,53 @@
+use crate::domain::{AuditEvent, AuthAPIError, Scope};
+use crate::utils::audit::audit;
+use crate::utils::extractors::{AuthenticatedUser, RequestContext};
+use crate::AppState;
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct UserInfo {
+    pub sub: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+}
+
+pub async fn userinfo(
+    State(state): State<AppState>,
+    context: RequestContext,
+    AuthenticatedUser { claims, .. }: AuthenticatedUser,
+) -> Result<Json<UserInfo>, AuthAPIError> {
+    let scope = Scope::parse(claims.scope.as_deref().unwrap_or_default());
+    if !scope.contains(Scope::OPENID) {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    let user_id = claims.user_id().ok_or(AuthAPIError::InvalidToken)?;
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&user_id)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let event = AuditEvent::UserInfoRead {
+        client_id: claims.client_id.clone(),
+    };
+    audit(&state, &context, Some(&user_id), event).await;
+
+    let mut user_info = UserInfo {
+        sub: user.subject,
+        email: None,
+        email_verified: None,
+    };
+    if scope.contains(Scope::EMAIL) {
+        user_info.email = Some(user.email.as_ref().to_owned());
+        user_info.email_verified = Some(user.email_verified);
+    }
+
+    Ok(Json(user_info))
+}
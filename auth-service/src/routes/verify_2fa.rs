@@ -1,6 +1,173 @@
+use super::sessions::start_session;
+use crate::domain::{
+    AuditEvent, AuthAPIError, Email, PendingLogin, PendingLoginStoreError, User, UserStoreError,
+};
+use crate::utils::audit::audit;
+use crate::utils::auth::{generate_2fa_code, generate_opaque_token};
+use crate::utils::csrf::csrf_cookie;
+use crate::utils::extractors::{Json, RequestContext};
+use crate::utils::TWO_FA_CODE_TTL_SECONDS;
+use crate::AppState;
+use axum::extract::State;
 use axum::http::StatusCode;
-use axum::response::IntoResponse;
+use axum_extra::extract::cookie::Cookie;
+use axum_extra::extract::CookieJar;
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
-pub async fn verify_2fa() -> impl IntoResponse {
-    StatusCode::OK.into_response()
+#[derive(Deserialize)]
+pub struct Verify2FARequest {
+    pub email: String,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+    #[serde(rename = "2FACode")]
+    pub code: String,
+}
+
+/// Returned by `/login` with `206 Partial Content` for users with 2FA enabled.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct TwoFactorAuthResponse {
+    pub message: String,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+}
+
+/// Completes a login that `/login` left waiting for the code sent to the user. Unknown,
+/// expired or foreign login attempts and wrong codes all fail alike, so that the route tells
+/// nothing about accounts to anyone who has not passed the password check.
+pub async fn verify_2fa(
+    State(state): State<AppState>,
+    context: RequestContext,
+    jar: CookieJar,
+    Json(request): Json<Verify2FARequest>,
+) -> Result<(CookieJar, StatusCode), AuthAPIError> {
+    let email =
+        Email::from_str(&request.email).map_err(|err| AuthAPIError::invalid_field("email", err))?;
+    let login = find_pending_login(&state, &request.login_attempt_id, &email).await?;
+
+    // Only attempts at a code are recorded, there is nothing to audit before that.
+    let user_id = login.user.clone();
+    let result = complete_login(&state, &context, login, &request.code).await;
+    let event = match &result {
+        Ok(_) => AuditEvent::TwoFactorVerified,
+        Err(err) => AuditEvent::TwoFactorFailed {
+            reason: err.code().to_owned(),
+        },
+    };
+    audit(&state, &context, Some(&user_id), event).await;
+
+    let auth_cookie = result?;
+    state.metrics.login_succeeded();
+    let updated_jar = jar
+        .add(csrf_cookie(&state.config, auth_cookie.value()))
+        .add(auth_cookie);
+    Ok((updated_jar, StatusCode::OK))
+}
+
+/// Sends the user a code and records the login as pending until `/verify-2fa` receives it.
+/// Returns the id of the login attempt.
+pub(crate) async fn start_2fa(
+    state: &AppState,
+    user: &User,
+    org_id: Option<&str>,
+) -> Result<String, AuthAPIError> {
+    let login = PendingLogin::new(
+        generate_opaque_token().map_err(AuthAPIError::unexpected)?,
+        user.id(),
+        org_id.map(str::to_owned),
+        generate_2fa_code().map_err(AuthAPIError::unexpected)?,
+        Utc::now() + Duration::seconds(TWO_FA_CODE_TTL_SECONDS),
+    );
+    let content = format!(
+        "Your login code is {}. It expires in {} minutes.",
+        login.code,
+        TWO_FA_CODE_TTL_SECONDS / 60
+    );
+    state
+        .email_client
+        .send_email(&user.email, "Your login code", &content)
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+
+    let id = login.id.clone();
+    state
+        .pending_logins
+        .write()
+        .await
+        .add_pending_login(login)
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+    Ok(id)
+}
+
+async fn find_pending_login(
+    state: &AppState,
+    id: &str,
+    email: &Email,
+) -> Result<PendingLogin, AuthAPIError> {
+    match state
+        .pending_logins
+        .read()
+        .await
+        .get_pending_login(id)
+        .await
+    {
+        Ok(login) if &login.user.email == email => Ok(login),
+        Ok(_) | Err(PendingLoginStoreError::LoginNotFound) => {
+            Err(AuthAPIError::IncorrectCredentials)
+        }
+        Err(err) => Err(AuthAPIError::unexpected(err)),
+    }
+}
+
+// Each login attempt can be completed once and only takes a few wrong codes.
+async fn complete_login(
+    state: &AppState,
+    context: &RequestContext,
+    login: PendingLogin,
+    code: &str,
+) -> Result<Cookie<'static>, AuthAPIError> {
+    {
+        let mut pending_logins = state.pending_logins.write().await;
+        if !login.verifies(code) {
+            let failed_attempts = pending_logins
+                .record_failed_attempt(&login.id)
+                .await
+                .map_err(|err| match err {
+                    PendingLoginStoreError::LoginNotFound => AuthAPIError::IncorrectCredentials,
+                    err => AuthAPIError::unexpected(err),
+                })?;
+            if failed_attempts >= PendingLogin::MAX_ATTEMPTS {
+                let _ = pending_logins.remove_pending_login(&login.id).await;
+            }
+            return Err(AuthAPIError::IncorrectCredentials);
+        }
+        // Fails if a concurrent request completed the login first.
+        pending_logins
+            .remove_pending_login(&login.id)
+            .await
+            .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    }
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&login.user)
+        .await
+        .map_err(|err| match err {
+            UserStoreError::UserNotFound => AuthAPIError::IncorrectCredentials,
+            err => AuthAPIError::unexpected(err),
+        })?;
+    // The account may have been suspended since the password was checked.
+    user.ensure_active()?;
+
+    start_session(
+        state,
+        &user,
+        login.org_id.as_deref(),
+        context.client.clone(),
+    )
+    .await
 }
@@ -0,0 +1,69 @@
+use super::admin::modify_user;
+use crate::domain::{AuditEvent, AuthAPIError, Role, User};
+use crate::utils::audit::audit;
+use crate::utils::auth::{sign_email_verification, verify_email_verification_token};
+use crate::utils::extractors::{Json, RequestContext};
+use crate::utils::EMAIL_VERIFICATION_TTL_SECONDS;
+use crate::{AppConfig, AppState};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+/// Marks the email of the user as verified with the token of the link sent at signup.
+pub async fn verify_email(
+    State(state): State<AppState>,
+    context: RequestContext,
+    Json(request): Json<VerifyEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let id = verify_email_verification_token(&request.token).ok_or(AuthAPIError::InvalidToken)?;
+    let config = state.config.clone();
+    modify_user(&state, &id, |user| mark_email_verified(&config, user))
+        .await
+        .map_err(|err| match err {
+            // The account was deleted since the link was sent.
+            AuthAPIError::UserNotFound => AuthAPIError::InvalidToken,
+            err => err,
+        })?;
+    audit(&state, &context, Some(&id), AuditEvent::EmailVerified).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Marks the email of the user as verified. Configured admin emails only get the admin role
+/// now, when it is known that whoever signed up owns the email, and only the first time, so
+/// that an admin can still take the role away.
+pub(crate) fn mark_email_verified(config: &AppConfig, user: &mut User) {
+    if user.email_verified {
+        return;
+    }
+    user.email_verified = true;
+    if config.is_admin_email(&user.id()) && !user.has_role(Role::Admin) {
+        user.roles.push(Role::Admin);
+    }
+}
+
+/// Sends the user a link that verifies their email.
+pub(crate) async fn send_verification_email(
+    state: &AppState,
+    user: &User,
+) -> Result<(), AuthAPIError> {
+    let expires_at = Utc::now() + Duration::seconds(EMAIL_VERIFICATION_TTL_SECONDS);
+    let token = sign_email_verification(&user.id(), expires_at);
+    let content = format!(
+        "Verify your email with this token: {}. It expires in {} hours.",
+        token,
+        EMAIL_VERIFICATION_TTL_SECONDS / 3600
+    );
+    state
+        .email_client
+        .send_email(&user.email, "Verify your email", &content)
+        .await
+        .map_err(AuthAPIError::unexpected)
+}
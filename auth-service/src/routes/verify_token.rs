@@ -1,29 +1,77 @@
-use crate::utils::auth::validate_token;
+use crate::domain::{AuditEvent, AuthAPIError};
+use crate::utils::api_key::{authenticate_api_key, is_api_key};
+use crate::utils::audit::audit;
+use crate::utils::auth::{validate_token, Claims};
+use crate::utils::extractors::{ensure_user_active, AuthToken, Json, RequestContext};
 use crate::AppState;
 use axum::extract::State;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use axum::Json;
+use chrono::Utc;
 
 #[derive(serde::Deserialize)]
 pub struct TokenRequest {
     pub token: String,
 }
+
+// The token can be sent in the JSON body, which takes precedence, or like on any other
+// authenticated route as the `jwt` cookie or an `Authorization: Bearer` header.
 pub async fn verify_token(
     State(state): State<AppState>,
-    Json(token): Json<TokenRequest>,
+    context: RequestContext,
+    auth_token: Option<AuthToken>,
+    body: Option<Json<TokenRequest>>,
 ) -> impl IntoResponse {
-    let banned_tokens_store = &state.banned_tokens.read().await;
-    let is_banned = banned_tokens_store.is_token_banned(&token.token).await;
-    if is_banned.is_err() {
-        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    let token = match (body, auth_token) {
+        (Some(Json(request)), _) => request.token,
+        (None, Some(auth_token)) => auth_token.token,
+        (None, None) => return StatusCode::BAD_REQUEST.into_response(),
+    };
+
+    match verify(&state, &token).await {
+        // Let callers such as app-service authorize the request without decoding the token.
+        Ok(claims) => Json(claims).into_response(),
+        Err(err) => {
+            // Successful checks are not recorded: app-service verifies every request it serves.
+            let event = AuditEvent::TokenRejected {
+                reason: err.code().to_owned(),
+            };
+            audit(&state, &context, None, event).await;
+            match err {
+                AuthAPIError::UnexpectedError => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+                _ => StatusCode::UNAUTHORIZED.into_response(),
+            }
+        }
+    }
+}
+
+async fn verify(state: &AppState, token: &str) -> Result<Claims, AuthAPIError> {
+    if is_api_key(token) {
+        return authenticate_api_key(state, token).await;
     }
-    if is_banned.unwrap() {
-        return StatusCode::UNAUTHORIZED.into_response();
+
+    let is_banned = state
+        .banned_tokens
+        .read()
+        .await
+        .is_token_banned(token)
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+    if is_banned {
+        return Err(AuthAPIError::InvalidToken);
     }
-    let validation = validate_token(&token.token).await;
-    if validation.is_err() || !validation.unwrap() {
-        return StatusCode::UNAUTHORIZED.into_response();
+    let claims = validate_token(token)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+    if claims.is_user_token() {
+        ensure_user_active(state, &claims).await?;
     }
-    StatusCode::OK.into_response()
+    // Every verification counts as activity on the session the token belongs to, if any.
+    let _ = state
+        .sessions
+        .write()
+        .await
+        .touch_session(&claims.jti, Utc::now())
+        .await;
+    Ok(claims)
 }
@@ -0,0 +1,213 @@
+use crate::domain::{
+    AuditEvent, AuthAPIError, DeliveryAttempt, UsersRead, UsersWrite, Webhook, WebhookEventType,
+    WebhookStoreError,
+};
+use crate::utils::audit::audit;
+use crate::utils::auth::generate_opaque_token;
+use crate::utils::extractors::{
+    AuthenticatedUser, Json, Path, Query, RequestContext, RequirePermission,
+};
+use crate::AppState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{delete, get};
+use axum::Router;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use uuid::Uuid;
+
+const DEFAULT_DELIVERY_LIMIT: usize = 100;
+const MAX_DELIVERY_LIMIT: usize = 1000;
+
+/// Routes for the webhooks of an operator's namespace, mounted under `/admin/webhooks`.
+/// They notify other systems about users, so they take the same permissions as managing them.
+pub fn webhook_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_webhooks).post(create_webhook))
+        .route("/{id}", delete(remove_webhook))
+        .route("/{id}/deliveries", get(list_delivery_attempts))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    pub events: Vec<WebhookEventType>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct WebhookResponse {
+    pub id: String,
+    pub url: String,
+    pub events: Vec<WebhookEventType>,
+    pub created_at: DateTime<Utc>,
+    /// The key to check the signatures of deliveries with, only returned when the webhook
+    /// is created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+impl From<Webhook> for WebhookResponse {
+    fn from(webhook: Webhook) -> Self {
+        Self {
+            id: webhook.id,
+            url: webhook.url,
+            events: webhook.events,
+            created_at: webhook.created_at,
+            secret: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryLogParams {
+    pub limit: Option<usize>,
+}
+
+pub async fn create_webhook(
+    State(state): State<AppState>,
+    context: RequestContext,
+    RequirePermission { user: admin, .. }: RequirePermission<UsersWrite>,
+    Json(request): Json<CreateWebhookRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let url = Url::parse(request.url.trim());
+    let url_error = match &url {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => None,
+        Ok(_) => Some("Must be an http or https URL"),
+        Err(_) => Some("Must be an absolute URL"),
+    };
+    let events_error = request
+        .events
+        .is_empty()
+        .then_some("Must name at least one event");
+    let url = match url {
+        Ok(url) if url_error.is_none() && events_error.is_none() => url,
+        _ => {
+            return Err(AuthAPIError::invalid_fields([
+                ("url", url_error.map(str::to_owned)),
+                ("events", events_error.map(str::to_owned)),
+            ]))
+        }
+    };
+    let mut events = request.events;
+    events.sort();
+    events.dedup();
+
+    let secret = generate_opaque_token().map_err(AuthAPIError::unexpected)?;
+    let webhook = Webhook::new(
+        Uuid::new_v4().to_string(),
+        admin.claims.tenant.clone(),
+        url.to_string(),
+        format!("whsec_{}", secret),
+        events,
+    );
+    state
+        .webhooks
+        .write()
+        .await
+        .add_webhook(webhook.clone())
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+    let event = AuditEvent::WebhookCreated {
+        webhook_id: webhook.id.clone(),
+    };
+    audit_admin(&state, &context, &admin, event).await;
+
+    let response = Json(WebhookResponse {
+        secret: Some(webhook.secret.clone()),
+        ..webhook.into()
+    });
+    Ok((StatusCode::CREATED, response))
+}
+
+pub async fn list_webhooks(
+    State(state): State<AppState>,
+    RequirePermission { user: admin, .. }: RequirePermission<UsersRead>,
+) -> Result<Json<Vec<WebhookResponse>>, AuthAPIError> {
+    let webhooks = state
+        .webhooks
+        .read()
+        .await
+        .get_webhooks(admin.claims.tenant.as_deref())
+        .await
+        .map_err(AuthAPIError::unexpected)?;
+    Ok(Json(webhooks.into_iter().map(Into::into).collect()))
+}
+
+/// Removes the webhook; deliveries that were not made yet are dropped.
+pub async fn remove_webhook(
+    State(state): State<AppState>,
+    context: RequestContext,
+    RequirePermission { user: admin, .. }: RequirePermission<UsersWrite>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    find_webhook(&state, &admin, &id).await?;
+    state
+        .webhooks
+        .write()
+        .await
+        .remove_webhook(&id)
+        .await
+        .map_err(map_webhook_store_error)?;
+    let event = AuditEvent::WebhookRemoved { webhook_id: id };
+    audit_admin(&state, &context, &admin, event).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Lists the attempts to deliver events to the webhook, most recent first.
+pub async fn list_delivery_attempts(
+    State(state): State<AppState>,
+    RequirePermission { user: admin, .. }: RequirePermission<UsersRead>,
+    Path(id): Path<String>,
+    Query(params): Query<DeliveryLogParams>,
+) -> Result<Json<Vec<DeliveryAttempt>>, AuthAPIError> {
+    find_webhook(&state, &admin, &id).await?;
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_DELIVERY_LIMIT)
+        .clamp(1, MAX_DELIVERY_LIMIT);
+    let attempts = state
+        .webhooks
+        .read()
+        .await
+        .get_attempts(&id, limit)
+        .await
+        .map_err(map_webhook_store_error)?;
+    Ok(Json(attempts))
+}
+
+// Webhooks of other namespaces are treated as if they did not exist.
+async fn find_webhook(
+    state: &AppState,
+    admin: &AuthenticatedUser,
+    id: &str,
+) -> Result<Webhook, AuthAPIError> {
+    let webhook = state
+        .webhooks
+        .read()
+        .await
+        .get_webhook(id)
+        .await
+        .map_err(map_webhook_store_error)?;
+    if webhook.tenant != admin.claims.tenant {
+        return Err(AuthAPIError::WebhookNotFound);
+    }
+    Ok(webhook)
+}
+
+async fn audit_admin(
+    state: &AppState,
+    context: &RequestContext,
+    admin: &AuthenticatedUser,
+    event: AuditEvent,
+) {
+    audit(state, context, admin.claims.user_id().as_ref(), event).await;
+}
+
+fn map_webhook_store_error(err: WebhookStoreError) -> AuthAPIError {
+    match err {
+        WebhookStoreError::WebhookNotFound => AuthAPIError::WebhookNotFound,
+        err => AuthAPIError::unexpected(err),
+    }
+}
@@ -0,0 +1,78 @@
+use crate::domain::OAuthError;
+use crate::utils::oidc::{issuer, ID_TOKEN_SIGNER};
+use crate::AppState;
+use axum::extract::State;
+use axum::Json;
+use jsonwebtoken::jwk::JwkSet;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+    pub jwks_uri: String,
+    pub registration_endpoint: String,
+    pub introspection_endpoint: String,
+    pub revocation_endpoint: String,
+    pub response_types_supported: Vec<String>,
+    pub subject_types_supported: Vec<String>,
+    pub id_token_signing_alg_values_supported: Vec<String>,
+    pub scopes_supported: Vec<String>,
+    pub token_endpoint_auth_methods_supported: Vec<String>,
+    pub grant_types_supported: Vec<String>,
+    pub claims_supported: Vec<String>,
+    pub code_challenge_methods_supported: Vec<String>,
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+pub async fn openid_configuration(
+    State(state): State<AppState>,
+) -> Result<Json<OpenIdConfiguration>, OAuthError> {
+    let issuer = issuer(&state.config)?.to_owned();
+
+    Ok(Json(OpenIdConfiguration {
+        authorization_endpoint: format!("{}/authorize", issuer),
+        token_endpoint: format!("{}/token", issuer),
+        userinfo_endpoint: format!("{}/userinfo", issuer),
+        jwks_uri: format!("{}/.well-known/jwks.json", issuer),
+        registration_endpoint: format!("{}/clients", issuer),
+        introspection_endpoint: format!("{}/introspect", issuer),
+        revocation_endpoint: format!("{}/revoke", issuer),
+        response_types_supported: strings(&["code"]),
+        subject_types_supported: strings(&["public"]),
+        id_token_signing_alg_values_supported: strings(&["EdDSA"]),
+        scopes_supported: strings(&["openid", "email"]),
+        token_endpoint_auth_methods_supported: strings(&[
+            "client_secret_basic",
+            "client_secret_post",
+            "none",
+        ]),
+        grant_types_supported: strings(&[
+            "authorization_code",
+            "client_credentials",
+            "refresh_token",
+        ]),
+        claims_supported: strings(&[
+            "iss",
+            "sub",
+            "aud",
+            "exp",
+            "iat",
+            "nonce",
+            "email",
+            "email_verified",
+        ]),
+        // Required of public clients, see `authorize`.
+        code_challenge_methods_supported: strings(&["S256"]),
+        issuer,
+    }))
+}
+
+pub async fn jwks() -> Json<JwkSet> {
+    Json(ID_TOKEN_SIGNER.jwk_set())
+}
@@ -1,5 +1,32 @@
+pub mod hashmap_api_key_store;
+pub mod hashmap_authorization_code_store;
+pub mod hashmap_client_store;
+pub mod hashmap_invitation_store;
+pub mod hashmap_organization_store;
+pub mod hashmap_pending_login_store;
+pub mod hashmap_refresh_token_store;
+pub mod hashmap_session_store;
 pub mod hashmap_user_store;
 pub mod hashset_banned_token_store;
+pub mod instrumented_store;
+pub mod jsonl_audit_sink;
+pub mod mock_email_client;
+mod sqlite;
+pub mod sqlite_audit_sink;
+pub mod sqlite_webhook_store;
 
+pub use crate::services::hashmap_api_key_store::*;
+pub use crate::services::hashmap_authorization_code_store::*;
+pub use crate::services::hashmap_client_store::*;
+pub use crate::services::hashmap_invitation_store::*;
+pub use crate::services::hashmap_organization_store::*;
+pub use crate::services::hashmap_pending_login_store::*;
+pub use crate::services::hashmap_refresh_token_store::*;
+pub use crate::services::hashmap_session_store::*;
 pub use crate::services::hashmap_user_store::*;
 pub use crate::services::hashset_banned_token_store::*;
+pub use crate::services::instrumented_store::*;
+pub use crate::services::jsonl_audit_sink::*;
+pub use crate::services::mock_email_client::*;
+pub use crate::services::sqlite_audit_sink::*;
+pub use crate::services::sqlite_webhook_store::*;
@@ -0,0 +1,173 @@
+use crate::domain::{ApiKey, ApiKeyStore, ApiKeyStoreError, UserId};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct HashmapApiKeyStore {
+    api_keys: HashMap<String, ApiKey>,
+}
+
+impl HashmapApiKeyStore {
+    /// Creates a new `HashmapApiKeyStore` instance.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            api_keys: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ApiKeyStore for HashmapApiKeyStore {
+    async fn add_api_key(&mut self, api_key: ApiKey) -> Result<(), ApiKeyStoreError> {
+        if self.api_keys.contains_key(&api_key.id) {
+            return Err(ApiKeyStoreError::ApiKeyAlreadyExists);
+        }
+        self.api_keys.insert(api_key.id.clone(), api_key);
+        Ok(())
+    }
+
+    async fn get_api_key(&self, id: &str) -> Result<ApiKey, ApiKeyStoreError> {
+        self.api_keys
+            .get(id)
+            .filter(|api_key| !api_key.is_expired())
+            .cloned()
+            .ok_or(ApiKeyStoreError::ApiKeyNotFound)
+    }
+
+    async fn get_api_keys(&self, user: &UserId) -> Result<Vec<ApiKey>, ApiKeyStoreError> {
+        let mut api_keys: Vec<ApiKey> = self
+            .api_keys
+            .values()
+            .filter(|api_key| &api_key.user == user && !api_key.is_expired())
+            .cloned()
+            .collect();
+        api_keys.sort_by_key(|api_key| std::cmp::Reverse(api_key.created_at));
+        Ok(api_keys)
+    }
+
+    async fn touch_api_key(&mut self, id: &str, at: DateTime<Utc>) -> Result<(), ApiKeyStoreError> {
+        let api_key = self
+            .api_keys
+            .get_mut(id)
+            .ok_or(ApiKeyStoreError::ApiKeyNotFound)?;
+        api_key.last_used_at = api_key.last_used_at.max(Some(at));
+        Ok(())
+    }
+
+    async fn remove_api_key(&mut self, id: &str) -> Result<ApiKey, ApiKeyStoreError> {
+        self.api_keys
+            .remove(id)
+            .ok_or(ApiKeyStoreError::ApiKeyNotFound)
+    }
+
+    async fn revoke_all_for_user(&mut self, user: &UserId) -> Result<usize, ApiKeyStoreError> {
+        let before = self.api_keys.len();
+        self.api_keys.retain(|_, api_key| &api_key.user != user);
+        Ok(before - self.api_keys.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Email;
+    use chrono::Duration;
+    use std::str::FromStr;
+
+    fn api_key(id: &str, email: &str, expires_at: Option<DateTime<Utc>>) -> ApiKey {
+        ApiKey::new(
+            id.to_owned(),
+            Email::from_str(email).unwrap().into(),
+            format!("key {}", id),
+            "secret",
+            None,
+            expires_at,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_add_get_and_remove_api_key() {
+        let mut store = HashmapApiKeyStore::new();
+        let api_key = api_key("a", "test@test.com", None);
+        assert_eq!(store.add_api_key(api_key.clone()).await, Ok(()));
+        assert_eq!(
+            store.add_api_key(api_key.clone()).await,
+            Err(ApiKeyStoreError::ApiKeyAlreadyExists)
+        );
+        assert_eq!(store.get_api_key("a").await, Ok(api_key.clone()));
+        assert_eq!(store.remove_api_key("a").await, Ok(api_key));
+        assert_eq!(
+            store.get_api_key("a").await,
+            Err(ApiKeyStoreError::ApiKeyNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_api_keys_only_returns_live_keys_of_the_user() {
+        let mut store = HashmapApiKeyStore::new();
+        let expired = Some(Utc::now() - Duration::minutes(1));
+        store
+            .add_api_key(api_key("a", "test@test.com", None))
+            .await
+            .unwrap();
+        store
+            .add_api_key(api_key("b", "other@test.com", None))
+            .await
+            .unwrap();
+        store
+            .add_api_key(api_key("c", "test@test.com", expired))
+            .await
+            .unwrap();
+
+        let api_keys = store
+            .get_api_keys(&Email::from_str("test@test.com").unwrap().into())
+            .await
+            .unwrap();
+        let ids: Vec<&str> = api_keys.iter().map(|api_key| api_key.id.as_str()).collect();
+        assert_eq!(ids, vec!["a"]);
+        assert_eq!(
+            store.get_api_key("c").await,
+            Err(ApiKeyStoreError::ApiKeyNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoke_all_for_user() {
+        let mut store = HashmapApiKeyStore::new();
+        for (id, email) in [
+            ("a", "test@test.com"),
+            ("b", "test@test.com"),
+            ("c", "other@test.com"),
+        ] {
+            store.add_api_key(api_key(id, email, None)).await.unwrap();
+        }
+
+        let user = Email::from_str("test@test.com").unwrap().into();
+        assert_eq!(store.revoke_all_for_user(&user).await, Ok(2));
+        assert_eq!(store.get_api_keys(&user).await, Ok(vec![]));
+        assert!(store.get_api_key("c").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_touch_api_key_records_last_use() {
+        let mut store = HashmapApiKeyStore::new();
+        store
+            .add_api_key(api_key("a", "test@test.com", None))
+            .await
+            .unwrap();
+
+        let now = Utc::now();
+        assert_eq!(store.touch_api_key("a", now).await, Ok(()));
+        assert_eq!(
+            store.get_api_key("a").await.unwrap().last_used_at,
+            Some(now)
+        );
+        assert_eq!(
+            store.touch_api_key("b", now).await,
+            Err(ApiKeyStoreError::ApiKeyNotFound)
+        );
+    }
+}
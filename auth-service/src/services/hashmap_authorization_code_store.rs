@@ -0,0 +1,80 @@
+use crate::domain::{AuthorizationCode, AuthorizationCodeStore, AuthorizationCodeStoreError};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct HashmapAuthorizationCodeStore {
+    codes: HashMap<String, AuthorizationCode>,
+}
+
+impl HashmapAuthorizationCodeStore {
+    /// Creates a new `HashmapAuthorizationCodeStore` instance.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            codes: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl AuthorizationCodeStore for HashmapAuthorizationCodeStore {
+    async fn add_code(
+        &mut self,
+        code: AuthorizationCode,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        if self.codes.contains_key(&code.code) {
+            return Err(AuthorizationCodeStoreError::CodeAlreadyExists);
+        }
+        self.codes.insert(code.code.clone(), code);
+        Ok(())
+    }
+
+    async fn take_code(
+        &mut self,
+        code: &str,
+    ) -> Result<AuthorizationCode, AuthorizationCodeStoreError> {
+        self.codes
+            .remove(code)
+            .ok_or(AuthorizationCodeStoreError::CodeNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Email, Scope};
+    use chrono::{Duration, Utc};
+    use std::str::FromStr;
+
+    fn code(value: &str) -> AuthorizationCode {
+        AuthorizationCode::new(
+            value.to_owned(),
+            "client".to_owned(),
+            "http://localhost/callback".to_owned(),
+            Email::from_str("test@test.com").unwrap().into(),
+            Scope::parse("openid"),
+            None,
+            Utc::now() + Duration::seconds(60),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_code_can_only_be_taken_once() {
+        let mut store = HashmapAuthorizationCodeStore::new();
+        assert_eq!(store.add_code(code("abc")).await, Ok(()));
+        assert_eq!(
+            store.add_code(code("abc")).await,
+            Err(AuthorizationCodeStoreError::CodeAlreadyExists)
+        );
+        assert_eq!(
+            store.take_code("abc").await.map(|c| c.code),
+            Ok("abc".to_owned())
+        );
+        assert_eq!(
+            store.take_code("abc").await,
+            Err(AuthorizationCodeStoreError::CodeNotFound)
+        );
+    }
+}
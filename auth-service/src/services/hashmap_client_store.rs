@@ -0,0 +1,61 @@
+use crate::domain::{ClientStore, ClientStoreError, OAuthClient};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct HashmapClientStore {
+    clients: HashMap<String, OAuthClient>,
+}
+
+impl HashmapClientStore {
+    /// Creates a new `HashmapClientStore` instance.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            clients: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ClientStore for HashmapClientStore {
+    async fn add_client(&mut self, client: OAuthClient) -> Result<(), ClientStoreError> {
+        if self.clients.contains_key(&client.client_id) {
+            return Err(ClientStoreError::ClientAlreadyExists);
+        }
+        self.clients.insert(client.client_id.clone(), client);
+        Ok(())
+    }
+
+    async fn get_client(&self, client_id: &str) -> Result<OAuthClient, ClientStoreError> {
+        self.clients
+            .get(client_id)
+            .cloned()
+            .ok_or(ClientStoreError::ClientNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_get_client() {
+        let mut store = HashmapClientStore::new();
+        let client = OAuthClient::new(
+            "client".to_owned(),
+            vec!["http://localhost/callback".to_owned()],
+        );
+        assert_eq!(store.add_client(client.clone()).await, Ok(()));
+        assert_eq!(
+            store.add_client(client.clone()).await,
+            Err(ClientStoreError::ClientAlreadyExists)
+        );
+        assert_eq!(store.get_client("client").await, Ok(client));
+        assert_eq!(
+            store.get_client("unknown").await,
+            Err(ClientStoreError::ClientNotFound)
+        );
+    }
+}
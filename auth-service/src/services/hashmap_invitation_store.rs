@@ -0,0 +1,153 @@
+use crate::domain::{Invitation, InvitationStore, InvitationStoreError};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct HashmapInvitationStore {
+    invitations: HashMap<String, Invitation>,
+}
+
+impl HashmapInvitationStore {
+    /// Creates a new `HashmapInvitationStore` instance.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            invitations: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl InvitationStore for HashmapInvitationStore {
+    async fn add_invitation(&mut self, invitation: Invitation) -> Result<(), InvitationStoreError> {
+        // Expired invitations can never be accepted, drop them while we hold the write lock.
+        self.invitations
+            .retain(|_, invitation| !invitation.is_expired());
+
+        if self.invitations.contains_key(&invitation.id) {
+            return Err(InvitationStoreError::InvitationAlreadyExists);
+        }
+        self.invitations.insert(invitation.id.clone(), invitation);
+        Ok(())
+    }
+
+    async fn get_invitation(&self, id: &str) -> Result<Invitation, InvitationStoreError> {
+        self.invitations
+            .get(id)
+            .filter(|invitation| !invitation.is_expired())
+            .cloned()
+            .ok_or(InvitationStoreError::InvitationNotFound)
+    }
+
+    async fn get_invitations(&self, org_id: &str) -> Result<Vec<Invitation>, InvitationStoreError> {
+        let mut invitations: Vec<Invitation> = self
+            .invitations
+            .values()
+            .filter(|invitation| invitation.org_id == org_id && !invitation.is_expired())
+            .cloned()
+            .collect();
+        invitations.sort_by_key(|invitation| std::cmp::Reverse(invitation.created_at));
+        Ok(invitations)
+    }
+
+    async fn remove_invitation(&mut self, id: &str) -> Result<Invitation, InvitationStoreError> {
+        self.invitations
+            .remove(id)
+            .ok_or(InvitationStoreError::InvitationNotFound)
+    }
+
+    async fn take_invitation(&mut self, id: &str) -> Result<Invitation, InvitationStoreError> {
+        self.invitations
+            .remove(id)
+            .filter(|invitation| !invitation.is_expired())
+            .ok_or(InvitationStoreError::InvitationNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Email, Role};
+    use chrono::{Duration, Utc};
+    use std::str::FromStr;
+
+    fn invitation(id: &str, org_id: &str, expires_in: Duration) -> Invitation {
+        Invitation::new(
+            id.to_owned(),
+            org_id.to_owned(),
+            Email::from_str("invitee@test.com").unwrap(),
+            Role::User,
+            "owner@test.com".to_owned(),
+            Utc::now() + expires_in,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_add_get_and_remove_invitation() {
+        let mut store = HashmapInvitationStore::new();
+        let invitation = invitation("a", "acme", Duration::days(1));
+        assert_eq!(store.add_invitation(invitation.clone()).await, Ok(()));
+        assert_eq!(
+            store.add_invitation(invitation.clone()).await,
+            Err(InvitationStoreError::InvitationAlreadyExists)
+        );
+        assert_eq!(store.get_invitation("a").await, Ok(invitation.clone()));
+        assert_eq!(store.remove_invitation("a").await, Ok(invitation));
+        assert_eq!(
+            store.get_invitation("a").await,
+            Err(InvitationStoreError::InvitationNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_take_invitation_only_once() {
+        let mut store = HashmapInvitationStore::new();
+        let pending = invitation("a", "acme", Duration::days(1));
+        store.add_invitation(pending.clone()).await.unwrap();
+        store
+            .add_invitation(invitation("b", "acme", Duration::minutes(-1)))
+            .await
+            .unwrap();
+
+        assert_eq!(store.take_invitation("a").await, Ok(pending));
+        assert_eq!(
+            store.take_invitation("a").await,
+            Err(InvitationStoreError::InvitationNotFound)
+        );
+        assert_eq!(
+            store.take_invitation("b").await,
+            Err(InvitationStoreError::InvitationNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_invitations_are_not_returned() {
+        let mut store = HashmapInvitationStore::new();
+        store
+            .add_invitation(invitation("a", "acme", Duration::days(1)))
+            .await
+            .unwrap();
+        store
+            .add_invitation(invitation("b", "acme", Duration::minutes(-1)))
+            .await
+            .unwrap();
+        store
+            .add_invitation(invitation("c", "globex", Duration::days(1)))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get_invitation("b").await,
+            Err(InvitationStoreError::InvitationNotFound)
+        );
+        let ids: Vec<String> = store
+            .get_invitations("acme")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|invitation| invitation.id)
+            .collect();
+        assert_eq!(ids, vec!["a"]);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::domain::{Organization, OrganizationStore, OrganizationStoreError};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct HashmapOrganizationStore {
+    organizations: HashMap<String, Organization>,
+}
+
+impl HashmapOrganizationStore {
+    /// Creates a new `HashmapOrganizationStore` instance.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            organizations: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl OrganizationStore for HashmapOrganizationStore {
+    async fn add_organization(
+        &mut self,
+        organization: Organization,
+    ) -> Result<(), OrganizationStoreError> {
+        if self.organizations.contains_key(&organization.id) {
+            return Err(OrganizationStoreError::OrganizationAlreadyExists);
+        }
+        self.organizations
+            .insert(organization.id.clone(), organization);
+        Ok(())
+    }
+
+    async fn get_organization(&self, id: &str) -> Result<Organization, OrganizationStoreError> {
+        self.organizations
+            .get(id)
+            .cloned()
+            .ok_or(OrganizationStoreError::OrganizationNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_get_organization() {
+        let mut store = HashmapOrganizationStore::new();
+        let organization = Organization::new("acme".to_owned(), "Acme Corp".to_owned());
+        assert_eq!(store.add_organization(organization.clone()).await, Ok(()));
+        assert_eq!(
+            store.add_organization(organization.clone()).await,
+            Err(OrganizationStoreError::OrganizationAlreadyExists)
+        );
+        assert_eq!(store.get_organization("acme").await, Ok(organization));
+        assert_eq!(
+            store.get_organization("globex").await,
+            Err(OrganizationStoreError::OrganizationNotFound)
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use crate::domain::{PendingLogin, PendingLoginStore, PendingLoginStoreError};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct HashmapPendingLoginStore {
+    logins: HashMap<String, PendingLogin>,
+}
+
+impl HashmapPendingLoginStore {
+    /// Creates a new `HashmapPendingLoginStore` instance.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            logins: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl PendingLoginStore for HashmapPendingLoginStore {
+    async fn add_pending_login(
+        &mut self,
+        login: PendingLogin,
+    ) -> Result<(), PendingLoginStoreError> {
+        // Logins that were never completed pile up otherwise, drop them while we hold the lock.
+        self.logins.retain(|_, login| !login.is_expired());
+
+        if self.logins.contains_key(&login.id) {
+            return Err(PendingLoginStoreError::LoginAlreadyExists);
+        }
+        self.logins.insert(login.id.clone(), login);
+        Ok(())
+    }
+
+    async fn get_pending_login(&self, id: &str) -> Result<PendingLogin, PendingLoginStoreError> {
+        self.logins
+            .get(id)
+            .filter(|login| !login.is_expired())
+            .cloned()
+            .ok_or(PendingLoginStoreError::LoginNotFound)
+    }
+
+    async fn record_failed_attempt(&mut self, id: &str) -> Result<u32, PendingLoginStoreError> {
+        let login = self
+            .logins
+            .get_mut(id)
+            .ok_or(PendingLoginStoreError::LoginNotFound)?;
+        login.failed_attempts += 1;
+        Ok(login.failed_attempts)
+    }
+
+    async fn remove_pending_login(
+        &mut self,
+        id: &str,
+    ) -> Result<PendingLogin, PendingLoginStoreError> {
+        self.logins
+            .remove(id)
+            .ok_or(PendingLoginStoreError::LoginNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Email;
+    use chrono::{Duration, Utc};
+    use std::str::FromStr;
+
+    fn login(id: &str, expires_in: Duration) -> PendingLogin {
+        PendingLogin::new(
+            id.to_owned(),
+            Email::from_str("test@test.com").unwrap().into(),
+            None,
+            "123456".to_owned(),
+            Utc::now() + expires_in,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_add_get_and_remove_pending_login() {
+        let mut store = HashmapPendingLoginStore::new();
+        let pending = login("a", Duration::minutes(10));
+        assert_eq!(store.add_pending_login(pending.clone()).await, Ok(()));
+        assert_eq!(
+            store.add_pending_login(pending.clone()).await,
+            Err(PendingLoginStoreError::LoginAlreadyExists)
+        );
+        assert_eq!(store.record_failed_attempt("a").await, Ok(1));
+        assert_eq!(store.record_failed_attempt("a").await, Ok(2));
+        assert_eq!(
+            store
+                .get_pending_login("a")
+                .await
+                .map(|login| login.failed_attempts),
+            Ok(2)
+        );
+        assert_eq!(
+            store.remove_pending_login("a").await.map(|login| login.id),
+            Ok("a".to_owned())
+        );
+        assert_eq!(
+            store.get_pending_login("a").await,
+            Err(PendingLoginStoreError::LoginNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_logins_are_not_returned() {
+        let mut store = HashmapPendingLoginStore::new();
+        store
+            .add_pending_login(login("a", Duration::seconds(-1)))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_pending_login("a").await,
+            Err(PendingLoginStoreError::LoginNotFound)
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use crate::domain::{RefreshToken, RefreshTokenStore, RefreshTokenStoreError, UserId};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct HashmapRefreshTokenStore {
+    tokens: HashMap<String, RefreshToken>,
+}
+
+impl HashmapRefreshTokenStore {
+    /// Creates a new `HashmapRefreshTokenStore` instance.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            tokens: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl RefreshTokenStore for HashmapRefreshTokenStore {
+    async fn add_refresh_token(
+        &mut self,
+        token: RefreshToken,
+    ) -> Result<(), RefreshTokenStoreError> {
+        if self.tokens.contains_key(&token.token) {
+            return Err(RefreshTokenStoreError::TokenAlreadyExists);
+        }
+        self.tokens.insert(token.token.clone(), token);
+        Ok(())
+    }
+
+    async fn get_refresh_token(&self, token: &str) -> Result<RefreshToken, RefreshTokenStoreError> {
+        self.tokens
+            .get(token)
+            .cloned()
+            .ok_or(RefreshTokenStoreError::TokenNotFound)
+    }
+
+    async fn revoke_refresh_token(&mut self, token: &str) -> Result<(), RefreshTokenStoreError> {
+        self.tokens
+            .remove(token)
+            .map(|_| ())
+            .ok_or(RefreshTokenStoreError::TokenNotFound)
+    }
+
+    async fn revoke_all_for_user(
+        &mut self,
+        user: &UserId,
+    ) -> Result<usize, RefreshTokenStoreError> {
+        let before = self.tokens.len();
+        self.tokens.retain(|_, token| &token.user != user);
+        Ok(before - self.tokens.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Email, Scope};
+    use chrono::{Duration, Utc};
+    use std::str::FromStr;
+
+    fn refresh_token(value: &str) -> RefreshToken {
+        RefreshToken::new(
+            value.to_owned(),
+            "client".to_owned(),
+            Email::from_str("test@test.com").unwrap().into(),
+            Scope::parse("openid"),
+            Utc::now() + Duration::days(1),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_add_get_and_revoke_refresh_token() {
+        let mut store = HashmapRefreshTokenStore::new();
+        let token = refresh_token("abc");
+        assert_eq!(store.add_refresh_token(token.clone()).await, Ok(()));
+        assert_eq!(
+            store.add_refresh_token(token.clone()).await,
+            Err(RefreshTokenStoreError::TokenAlreadyExists)
+        );
+        assert_eq!(store.get_refresh_token("abc").await, Ok(token));
+        assert_eq!(store.revoke_refresh_token("abc").await, Ok(()));
+        assert_eq!(
+            store.get_refresh_token("abc").await,
+            Err(RefreshTokenStoreError::TokenNotFound)
+        );
+        assert_eq!(
+            store.revoke_refresh_token("abc").await,
+            Err(RefreshTokenStoreError::TokenNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoke_all_for_user() {
+        let mut store = HashmapRefreshTokenStore::new();
+        store.add_refresh_token(refresh_token("a")).await.unwrap();
+        store.add_refresh_token(refresh_token("b")).await.unwrap();
+        let other = RefreshToken {
+            user: Email::from_str("other@test.com").unwrap().into(),
+            ..refresh_token("c")
+        };
+        store.add_refresh_token(other.clone()).await.unwrap();
+
+        let user = Email::from_str("test@test.com").unwrap().into();
+        assert_eq!(store.revoke_all_for_user(&user).await, Ok(2));
+        assert_eq!(
+            store.get_refresh_token("a").await,
+            Err(RefreshTokenStoreError::TokenNotFound)
+        );
+        assert_eq!(store.get_refresh_token("c").await, Ok(other));
+    }
+}
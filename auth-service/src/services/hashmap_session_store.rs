@@ -0,0 +1,151 @@
+use crate::domain::{Session, SessionStore, SessionStoreError, UserId};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct HashmapSessionStore {
+    sessions: HashMap<String, Session>,
+}
+
+impl HashmapSessionStore {
+    /// Creates a new `HashmapSessionStore` instance.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            sessions: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl SessionStore for HashmapSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        // Expired sessions are of no use to anyone, drop them while we hold the write lock.
+        self.sessions.retain(|_, session| !session.is_expired());
+
+        if self.sessions.contains_key(&session.id) {
+            return Err(SessionStoreError::SessionAlreadyExists);
+        }
+        self.sessions.insert(session.id.clone(), session);
+        Ok(())
+    }
+
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError> {
+        self.sessions
+            .get(id)
+            .filter(|session| !session.is_expired())
+            .cloned()
+            .ok_or(SessionStoreError::SessionNotFound)
+    }
+
+    async fn get_sessions(&self, user: &UserId) -> Result<Vec<Session>, SessionStoreError> {
+        let mut sessions: Vec<Session> = self
+            .sessions
+            .values()
+            .filter(|session| &session.user == user && !session.is_expired())
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.created_at));
+        Ok(sessions)
+    }
+
+    async fn touch_session(
+        &mut self,
+        id: &str,
+        at: DateTime<Utc>,
+    ) -> Result<(), SessionStoreError> {
+        let session = self
+            .sessions
+            .get_mut(id)
+            .ok_or(SessionStoreError::SessionNotFound)?;
+        session.last_seen = session.last_seen.max(at);
+        Ok(())
+    }
+
+    async fn remove_session(&mut self, id: &str) -> Result<Session, SessionStoreError> {
+        self.sessions
+            .remove(id)
+            .ok_or(SessionStoreError::SessionNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Email;
+    use chrono::Duration;
+    use std::str::FromStr;
+
+    fn session(id: &str, email: &str, expires_in: Duration) -> Session {
+        Session::new(
+            id.to_owned(),
+            Email::from_str(email).unwrap().into(),
+            format!("token-{}", id),
+            Utc::now() + expires_in,
+            Some("test-agent".to_owned()),
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_add_get_and_remove_session() {
+        let mut store = HashmapSessionStore::new();
+        let session = session("a", "test@test.com", Duration::minutes(10));
+        assert_eq!(store.add_session(session.clone()).await, Ok(()));
+        assert_eq!(
+            store.add_session(session.clone()).await,
+            Err(SessionStoreError::SessionAlreadyExists)
+        );
+        assert_eq!(store.get_session("a").await, Ok(session.clone()));
+        assert_eq!(store.remove_session("a").await, Ok(session));
+        assert_eq!(
+            store.get_session("a").await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+        assert_eq!(
+            store.remove_session("a").await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_sessions_only_returns_live_sessions_of_the_user() {
+        let mut store = HashmapSessionStore::new();
+        store
+            .add_session(session("a", "test@test.com", Duration::minutes(10)))
+            .await
+            .unwrap();
+        store
+            .add_session(session("b", "other@test.com", Duration::minutes(10)))
+            .await
+            .unwrap();
+        store
+            .add_session(session("c", "test@test.com", Duration::minutes(-1)))
+            .await
+            .unwrap();
+
+        let sessions = store
+            .get_sessions(&Email::from_str("test@test.com").unwrap().into())
+            .await
+            .unwrap();
+        let ids: Vec<&str> = sessions.iter().map(|session| session.id.as_str()).collect();
+        assert_eq!(ids, vec!["a"]);
+    }
+
+    #[tokio::test]
+    async fn test_touch_session_updates_last_seen() {
+        let mut store = HashmapSessionStore::new();
+        let session = session("a", "test@test.com", Duration::minutes(10));
+        store.add_session(session.clone()).await.unwrap();
+
+        let later = session.last_seen + Duration::seconds(30);
+        assert_eq!(store.touch_session("a", later).await, Ok(()));
+        assert_eq!(store.get_session("a").await.unwrap().last_seen, later);
+        assert_eq!(
+            store.touch_session("b", later).await,
+            Err(SessionStoreError::SessionNotFound)
+        );
+    }
+}
@@ -1,11 +1,19 @@
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use crate::domain::{
+    DomainEvent, Email, EventOutbox, OutboxEntry, Password, Role, User, UserId, UserPage,
+    UserQuery, UserStore, UserStoreError,
+};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use uuid::Uuid;
 
+/// Keeps the users and their outbox together, so events are as durable as the users are.
 #[derive(Debug, Default)]
 #[non_exhaustive]
 pub struct HashmapUserStore {
-    users: HashMap<Email, User>,
+    users: HashMap<UserId, User>,
+    outbox: Vec<OutboxEntry>,
 }
 
 impl HashmapUserStore {
@@ -14,38 +22,97 @@ impl HashmapUserStore {
     pub fn new() -> Self {
         Self {
             users: HashMap::new(),
+            outbox: Vec::new(),
         }
     }
+
+    fn record(&mut self, event: DomainEvent) {
+        self.outbox
+            .push(OutboxEntry::new(Uuid::new_v4().to_string(), event));
+    }
+}
+
+#[async_trait]
+impl EventOutbox for HashmapUserStore {
+    async fn pending_events(
+        &self,
+        now: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<OutboxEntry>, UserStoreError> {
+        // Entries are kept in the order they were recorded.
+        Ok(self
+            .outbox
+            .iter()
+            .filter(|entry| entry.parked_at.is_none() && entry.next_attempt_at <= now)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn complete_event(&mut self, id: &str) -> Result<(), UserStoreError> {
+        self.outbox.retain(|entry| entry.id != id);
+        Ok(())
+    }
+
+    async fn retry_event(
+        &mut self,
+        id: &str,
+        next_attempt_at: DateTime<Utc>,
+    ) -> Result<(), UserStoreError> {
+        if let Some(entry) = self.outbox.iter_mut().find(|entry| entry.id == id) {
+            entry.attempts += 1;
+            entry.next_attempt_at = next_attempt_at;
+        }
+        Ok(())
+    }
+
+    async fn park_event(&mut self, id: &str, at: DateTime<Utc>) -> Result<(), UserStoreError> {
+        if let Some(entry) = self.outbox.iter_mut().find(|entry| entry.id == id) {
+            entry.attempts += 1;
+            entry.parked_at = Some(at);
+        }
+        Ok(())
+    }
+
+    async fn parked_events(&self, limit: usize) -> Result<Vec<OutboxEntry>, UserStoreError> {
+        Ok(self
+            .outbox
+            .iter()
+            .filter(|entry| entry.parked_at.is_some())
+            .take(limit)
+            .cloned()
+            .collect())
+    }
 }
 
 #[async_trait]
 impl UserStore for HashmapUserStore {
     /// Adds a new user to the store.
     async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        if Some(&user) == self.users.get(&user.email.clone()) {
-            return Err(UserStoreError::UserAlreadyExists);
-        } else {
-            self.users.insert(user.email.clone(), user);
+        match self.users.entry(user.id()) {
+            Entry::Occupied(_) => Err(UserStoreError::UserAlreadyExists),
+            Entry::Vacant(entry) => {
+                let event = DomainEvent::UserCreated {
+                    user: entry.key().clone(),
+                };
+                entry.insert(user);
+                self.record(event);
+                Ok(())
+            }
         }
-
-        Ok(())
     }
 
-    /// Retrieves a user by email.
-    async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        match self.users.get(email) {
+    /// Retrieves a user by id.
+    async fn get_user(&self, id: &UserId) -> Result<User, UserStoreError> {
+        match self.users.get(id) {
             Some(user) => Ok(user.clone()),
             None => Err(UserStoreError::UserNotFound),
         }
     }
 
     /// Validates user credentials.
-    async fn validate_user(
-        &self,
-        email: &Email,
-        password: &Password,
-    ) -> Result<(), UserStoreError> {
-        match self.users.get(email) {
+    async fn validate_user(&self, id: &UserId, password: &Password) -> Result<(), UserStoreError> {
+        match self.users.get(id) {
             Some(user) => {
                 if &user.password == password {
                     Ok(())
@@ -56,11 +123,94 @@ impl UserStore for HashmapUserStore {
             None => Err(UserStoreError::UserNotFound),
         }
     }
+
+    /// Replaces the roles of a user.
+    async fn set_roles(&mut self, id: &UserId, roles: Vec<Role>) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(id).ok_or(UserStoreError::UserNotFound)?;
+        user.roles = roles;
+        Ok(())
+    }
+
+    /// Lists users ordered by email.
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        let needle = query.email_contains.as_deref().map(str::to_lowercase);
+        let mut users: Vec<&User> = self
+            .users
+            .values()
+            .filter(|user| user.tenant == query.tenant)
+            .filter(|user| match &needle {
+                Some(needle) => user.email.as_ref().to_lowercase().contains(needle),
+                None => true,
+            })
+            .collect();
+        users.sort_by(|a, b| a.email.as_ref().cmp(b.email.as_ref()));
+
+        Ok(UserPage {
+            total: users.len(),
+            users: users
+                .into_iter()
+                .skip(query.offset)
+                .take(query.limit)
+                .cloned()
+                .collect(),
+        })
+    }
+
+    /// Replaces an existing user.
+    async fn update_user(&mut self, user: User) -> Result<(), UserStoreError> {
+        let stored = self
+            .users
+            .get_mut(&user.id())
+            .ok_or(UserStoreError::UserNotFound)?;
+        let verified = user.email_verified && !stored.email_verified;
+        *stored = user;
+        if verified {
+            let user = stored.id();
+            self.record(DomainEvent::UserVerified { user });
+        }
+        Ok(())
+    }
+
+    /// Re-keys a user under the new email. Fails if another user of the namespace has it.
+    async fn change_email(&mut self, id: &UserId, email: Email) -> Result<User, UserStoreError> {
+        let new_id = UserId::new(id.tenant.clone(), email.clone());
+        if self.users.contains_key(&new_id) {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+        let mut user = self.users.remove(id).ok_or(UserStoreError::UserNotFound)?;
+        let previous_email = std::mem::replace(&mut user.email, email);
+        self.users.insert(new_id.clone(), user.clone());
+        self.record(DomainEvent::UserEmailChanged {
+            user: new_id,
+            previous_email,
+        });
+        Ok(user)
+    }
+
+    /// Removes a user and returns it.
+    async fn delete_user(&mut self, id: &UserId) -> Result<User, UserStoreError> {
+        let user = self.users.remove(id).ok_or(UserStoreError::UserNotFound)?;
+        self.record(DomainEvent::UserDeleted { user: id.clone() });
+        Ok(user)
+    }
+
+    /// Lists the members of an organization ordered by email.
+    async fn get_members(&self, org_id: &str) -> Result<Vec<User>, UserStoreError> {
+        let mut members: Vec<User> = self
+            .users
+            .values()
+            .filter(|user| user.membership(org_id).is_some())
+            .cloned()
+            .collect();
+        members.sort_by(|a, b| a.email.as_ref().cmp(b.email.as_ref()));
+        Ok(members)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::{Email, Membership};
     use std::str::FromStr;
 
     #[tokio::test]
@@ -84,9 +234,9 @@ mod tests {
         let password = Password::from_str("password").unwrap();
         let user = User::new(email.clone(), password, false);
         store.add_user(user.clone()).await.unwrap();
-        assert_eq!(store.get_user(&email).await, Ok(user));
+        assert_eq!(store.get_user(&email.clone().into()).await, Ok(user));
         assert_eq!(
-            store.get_user(&wrong_email).await,
+            store.get_user(&wrong_email.clone().into()).await,
             Err(UserStoreError::UserNotFound)
         );
     }
@@ -101,14 +251,311 @@ mod tests {
 
         let user = User::new(email.clone(), password.clone(), false);
         store.add_user(user).await.unwrap();
-        assert_eq!(store.validate_user(&email, &password).await, Ok(()));
         assert_eq!(
-            store.validate_user(&email, &wrong_password).await,
+            store.validate_user(&email.clone().into(), &password).await,
+            Ok(())
+        );
+        assert_eq!(
+            store
+                .validate_user(&email.clone().into(), &wrong_password)
+                .await,
             Err(UserStoreError::InvalidCredentials)
         );
         assert_eq!(
-            store.validate_user(&wrong_email, &password).await,
+            store
+                .validate_user(&wrong_email.clone().into(), &password)
+                .await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_roles() {
+        let mut store = HashmapUserStore::new();
+        let email = Email::from_str("test@test.com").unwrap();
+        let user = User::new(
+            email.clone(),
+            Password::from_str("password").unwrap(),
+            false,
+        );
+        assert_eq!(user.roles, vec![Role::User]);
+        store.add_user(user).await.unwrap();
+
+        assert_eq!(
+            store
+                .set_roles(&email.clone().into(), vec![Role::User, Role::Admin])
+                .await,
+            Ok(())
+        );
+        assert!(store
+            .get_user(&email.clone().into())
+            .await
+            .unwrap()
+            .has_role(Role::Admin));
+        assert_eq!(
+            store
+                .set_roles(&Email::from_str("t@test.com").unwrap().into(), vec![])
+                .await,
             Err(UserStoreError::UserNotFound)
         );
     }
+
+    #[tokio::test]
+    async fn test_add_user_rejects_existing_email() {
+        let mut store = HashmapUserStore::new();
+        let email = Email::from_str("test@test.com").unwrap();
+        let user = User::new(
+            email.clone(),
+            Password::from_str("password").unwrap(),
+            false,
+        );
+        store.add_user(user.clone()).await.unwrap();
+
+        let other = User::new(
+            email.clone(),
+            Password::from_str("password2").unwrap(),
+            true,
+        );
+        assert_eq!(
+            store.add_user(other).await,
+            Err(UserStoreError::UserAlreadyExists)
+        );
+        assert_eq!(store.get_user(&email.clone().into()).await, Ok(user));
+    }
+
+    #[tokio::test]
+    async fn test_list_users() {
+        let mut store = HashmapUserStore::new();
+        for email in ["carol@b.com", "alice@a.com", "bob@a.com"] {
+            let user = User::new(
+                Email::from_str(email).unwrap(),
+                Password::from_str("password").unwrap(),
+                false,
+            );
+            store.add_user(user).await.unwrap();
+        }
+        let emails = |page: UserPage| -> Vec<String> {
+            page.users
+                .iter()
+                .map(|user| user.email.as_ref().to_owned())
+                .collect()
+        };
+
+        let page = store
+            .list_users(&UserQuery {
+                tenant: None,
+                email_contains: None,
+                offset: 1,
+                limit: 1,
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(emails(page), vec!["bob@a.com"]);
+
+        let page = store
+            .list_users(&UserQuery {
+                tenant: None,
+                email_contains: Some("@A.".to_owned()),
+                offset: 0,
+                limit: 10,
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(emails(page), vec!["alice@a.com", "bob@a.com"]);
+    }
+
+    #[tokio::test]
+    async fn test_update_and_delete_user() {
+        let mut store = HashmapUserStore::new();
+        let email = Email::from_str("test@test.com").unwrap();
+        let user = User::new(
+            email.clone(),
+            Password::from_str("password").unwrap(),
+            false,
+        );
+        assert_eq!(
+            store.update_user(user.clone()).await,
+            Err(UserStoreError::UserNotFound)
+        );
+        store.add_user(user.clone()).await.unwrap();
+
+        let mut updated = user.clone();
+        updated.requires_2fa = true;
+        assert_eq!(store.update_user(updated.clone()).await, Ok(()));
+        assert_eq!(
+            store.get_user(&email.clone().into()).await,
+            Ok(updated.clone())
+        );
+
+        assert_eq!(store.delete_user(&email.clone().into()).await, Ok(updated));
+        assert_eq!(
+            store.delete_user(&email.clone().into()).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_same_email_in_different_tenants() {
+        let mut store = HashmapUserStore::new();
+        let email = Email::from_str("test@test.com").unwrap();
+        let password = Password::from_str("password").unwrap();
+        let global = User::new(email.clone(), password.clone(), false);
+        let acme = global.clone().with_tenant(Some("acme".to_owned()));
+        store.add_user(global.clone()).await.unwrap();
+        store.add_user(acme.clone()).await.unwrap();
+        assert_eq!(
+            store.add_user(acme.clone()).await,
+            Err(UserStoreError::UserAlreadyExists)
+        );
+
+        assert_eq!(store.get_user(&global.id()).await, Ok(global));
+        assert_eq!(store.get_user(&acme.id()).await, Ok(acme));
+        let page = store
+            .list_users(&UserQuery {
+                tenant: Some("acme".to_owned()),
+                email_contains: None,
+                offset: 0,
+                limit: 10,
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.total, 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_members() {
+        let mut store = HashmapUserStore::new();
+        let mut member = User::new(
+            Email::from_str("member@test.com").unwrap(),
+            Password::from_str("password").unwrap(),
+            false,
+        );
+        member.join(Membership::new("acme".to_owned(), vec![Role::User]));
+        let outsider = User::new(
+            Email::from_str("outsider@test.com").unwrap(),
+            Password::from_str("password").unwrap(),
+            false,
+        );
+        store.add_user(member.clone()).await.unwrap();
+        store.add_user(outsider).await.unwrap();
+
+        assert_eq!(store.get_members("acme").await, Ok(vec![member]));
+        assert_eq!(store.get_members("globex").await, Ok(vec![]));
+    }
+
+    #[tokio::test]
+    async fn test_records_events_with_mutations() {
+        let mut store = HashmapUserStore::new();
+        let mut user = User::new(
+            Email::from_str("test@test.com").unwrap(),
+            Password::from_str("password").unwrap(),
+            false,
+        );
+        let id = user.id();
+        store.add_user(user.clone()).await.unwrap();
+        user.requires_2fa = true;
+        store.update_user(user.clone()).await.unwrap();
+        user.email_verified = true;
+        store.update_user(user.clone()).await.unwrap();
+        store.update_user(user).await.unwrap();
+        store.delete_user(&id).await.unwrap();
+        // Failed mutations record nothing.
+        assert!(store.delete_user(&id).await.is_err());
+
+        let events: Vec<DomainEvent> = store
+            .pending_events(Utc::now(), 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.event)
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                DomainEvent::UserCreated { user: id.clone() },
+                DomainEvent::UserVerified { user: id.clone() },
+                DomainEvent::UserDeleted { user: id },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_change_email() {
+        let mut store = HashmapUserStore::new();
+        let user = User::new(
+            Email::from_str("old@test.com").unwrap(),
+            Password::from_str("password").unwrap(),
+            false,
+        );
+        let taken = User::new(
+            Email::from_str("taken@test.com").unwrap(),
+            Password::from_str("password").unwrap(),
+            false,
+        );
+        let old_id = user.id();
+        store.add_user(user).await.unwrap();
+        store.add_user(taken).await.unwrap();
+        let now = Utc::now();
+        for entry in store.pending_events(now, 10).await.unwrap() {
+            store.complete_event(&entry.id).await.unwrap();
+        }
+
+        let result = store
+            .change_email(&old_id, Email::from_str("taken@test.com").unwrap())
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserAlreadyExists));
+        let moved = store
+            .change_email(&old_id, Email::from_str("new@test.com").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(moved.email.as_ref(), "new@test.com");
+        assert_eq!(
+            store.get_user(&old_id).await,
+            Err(UserStoreError::UserNotFound)
+        );
+        assert_eq!(store.get_user(&moved.id()).await, Ok(moved.clone()));
+
+        let events: Vec<DomainEvent> = store
+            .pending_events(Utc::now(), 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.event)
+            .collect();
+        assert_eq!(
+            events,
+            vec![DomainEvent::UserEmailChanged {
+                user: moved.id(),
+                previous_email: old_id.email,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retries_and_completes_events() {
+        let mut store = HashmapUserStore::new();
+        for email in ["a@test.com", "b@test.com"] {
+            let user = User::new(
+                Email::from_str(email).unwrap(),
+                Password::from_str("password").unwrap(),
+                false,
+            );
+            store.add_user(user).await.unwrap();
+        }
+        let now = Utc::now();
+        let entries = store.pending_events(now, 10).await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(store.pending_events(now, 1).await.unwrap(), entries[..1]);
+
+        let later = now + chrono::Duration::minutes(1);
+        store.retry_event(&entries[0].id, later).await.unwrap();
+        store.complete_event(&entries[1].id).await.unwrap();
+        assert_eq!(store.pending_events(now, 10).await, Ok(vec![]));
+        let retried = store.pending_events(later, 10).await.unwrap();
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].id, entries[0].id);
+        assert_eq!(retried[0].attempts, 1);
+    }
 }
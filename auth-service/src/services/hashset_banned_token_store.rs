@@ -33,4 +33,8 @@ impl BannedTokenStore for HashSetBannedTokenStore {
     async fn is_token_banned(&self, token: &str) -> Result<bool, crate::domain::TokenStoreError> {
         Ok(self.banned_tokens.contains(token))
     }
+
+    async fn count(&self) -> Result<usize, crate::domain::TokenStoreError> {
+        Ok(self.banned_tokens.len())
+    }
 }
@@ -0,0 +1,306 @@
+use crate::domain::{
+    BannedTokenStore, Email, EventOutbox, OutboxEntry, Password, Role, TokenStoreError, User,
+    UserId, UserPage, UserQuery, UserStore, UserStoreError,
+};
+use crate::utils::metrics::Metrics;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::sync::Arc;
+use tracing::{Instrument, Span};
+
+// Timed for the metrics and traced, so that slow store calls show up in the trace of the
+// request that made them. Calls outside of any span, like the polling of the outbox
+// dispatcher, are not traced, as each would start a trace of its own.
+async fn observe<T>(
+    metrics: &Metrics,
+    store: &'static str,
+    operation: &'static str,
+    future: impl Future<Output = T>,
+) -> T {
+    let span = if Span::current().is_none() {
+        Span::none()
+    } else {
+        tracing::info_span!(
+            "store",
+            otel.name = format!("{}.{}", store, operation),
+            store,
+            operation,
+        )
+    };
+    metrics
+        .time_store_operation(store, operation, future)
+        .instrument(span)
+        .await
+}
+
+/// A [`UserStore`] that records how long each operation of the store it wraps takes, and
+/// traces it.
+#[non_exhaustive]
+pub struct InstrumentedUserStore {
+    inner: Box<dyn UserStore>,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedUserStore {
+    const STORE: &'static str = "users";
+
+    /// Creates a new `InstrumentedUserStore` instance.
+    #[must_use]
+    pub fn new(inner: Box<dyn UserStore>, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait]
+impl EventOutbox for InstrumentedUserStore {
+    async fn pending_events(
+        &self,
+        now: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<OutboxEntry>, UserStoreError> {
+        observe(
+            &self.metrics,
+            Self::STORE,
+            "pending_events",
+            self.inner.pending_events(now, limit),
+        )
+        .await
+    }
+
+    async fn complete_event(&mut self, id: &str) -> Result<(), UserStoreError> {
+        observe(
+            &self.metrics,
+            Self::STORE,
+            "complete_event",
+            self.inner.complete_event(id),
+        )
+        .await
+    }
+
+    async fn retry_event(
+        &mut self,
+        id: &str,
+        next_attempt_at: DateTime<Utc>,
+    ) -> Result<(), UserStoreError> {
+        observe(
+            &self.metrics,
+            Self::STORE,
+            "retry_event",
+            self.inner.retry_event(id, next_attempt_at),
+        )
+        .await
+    }
+
+    async fn park_event(&mut self, id: &str, at: DateTime<Utc>) -> Result<(), UserStoreError> {
+        observe(
+            &self.metrics,
+            Self::STORE,
+            "park_event",
+            self.inner.park_event(id, at),
+        )
+        .await
+    }
+
+    async fn parked_events(&self, limit: usize) -> Result<Vec<OutboxEntry>, UserStoreError> {
+        observe(
+            &self.metrics,
+            Self::STORE,
+            "parked_events",
+            self.inner.parked_events(limit),
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl UserStore for InstrumentedUserStore {
+    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
+        observe(
+            &self.metrics,
+            Self::STORE,
+            "add_user",
+            self.inner.add_user(user),
+        )
+        .await
+    }
+
+    async fn get_user(&self, id: &UserId) -> Result<User, UserStoreError> {
+        observe(
+            &self.metrics,
+            Self::STORE,
+            "get_user",
+            self.inner.get_user(id),
+        )
+        .await
+    }
+
+    async fn validate_user(&self, id: &UserId, password: &Password) -> Result<(), UserStoreError> {
+        observe(
+            &self.metrics,
+            Self::STORE,
+            "validate_user",
+            self.inner.validate_user(id, password),
+        )
+        .await
+    }
+
+    async fn set_roles(&mut self, id: &UserId, roles: Vec<Role>) -> Result<(), UserStoreError> {
+        observe(
+            &self.metrics,
+            Self::STORE,
+            "set_roles",
+            self.inner.set_roles(id, roles),
+        )
+        .await
+    }
+
+    async fn list_users(&self, query: &UserQuery) -> Result<UserPage, UserStoreError> {
+        observe(
+            &self.metrics,
+            Self::STORE,
+            "list_users",
+            self.inner.list_users(query),
+        )
+        .await
+    }
+
+    async fn update_user(&mut self, user: User) -> Result<(), UserStoreError> {
+        observe(
+            &self.metrics,
+            Self::STORE,
+            "update_user",
+            self.inner.update_user(user),
+        )
+        .await
+    }
+
+    async fn change_email(&mut self, id: &UserId, email: Email) -> Result<User, UserStoreError> {
+        observe(
+            &self.metrics,
+            Self::STORE,
+            "change_email",
+            self.inner.change_email(id, email),
+        )
+        .await
+    }
+
+    async fn delete_user(&mut self, id: &UserId) -> Result<User, UserStoreError> {
+        observe(
+            &self.metrics,
+            Self::STORE,
+            "delete_user",
+            self.inner.delete_user(id),
+        )
+        .await
+    }
+
+    async fn get_members(&self, org_id: &str) -> Result<Vec<User>, UserStoreError> {
+        observe(
+            &self.metrics,
+            Self::STORE,
+            "get_members",
+            self.inner.get_members(org_id),
+        )
+        .await
+    }
+
+    // Not timed, so that the probes do not skew the latencies of real operations.
+    async fn health_check(&self) -> Result<(), UserStoreError> {
+        self.inner.health_check().await
+    }
+}
+
+/// A [`BannedTokenStore`] that records how long each operation of the store it wraps takes,
+/// traces it and keeps count of the banned tokens.
+#[non_exhaustive]
+pub struct InstrumentedBannedTokenStore {
+    inner: Box<dyn BannedTokenStore>,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedBannedTokenStore {
+    const STORE: &'static str = "banned_tokens";
+
+    /// Creates a new `InstrumentedBannedTokenStore` instance.
+    #[must_use]
+    pub fn new(inner: Box<dyn BannedTokenStore>, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait]
+impl BannedTokenStore for InstrumentedBannedTokenStore {
+    async fn ban_token(&mut self, token: &str) -> Result<(), TokenStoreError> {
+        observe(
+            &self.metrics,
+            Self::STORE,
+            "ban_token",
+            self.inner.ban_token(token),
+        )
+        .await?;
+        // Keep the gauge current between scrapes.
+        let count = self.inner.count().await?;
+        self.metrics.set_banned_tokens(count);
+        Ok(())
+    }
+
+    async fn is_token_banned(&self, token: &str) -> Result<bool, TokenStoreError> {
+        observe(
+            &self.metrics,
+            Self::STORE,
+            "is_token_banned",
+            self.inner.is_token_banned(token),
+        )
+        .await
+    }
+
+    async fn count(&self) -> Result<usize, TokenStoreError> {
+        observe(&self.metrics, Self::STORE, "count", self.inner.count()).await
+    }
+
+    async fn health_check(&self) -> Result<(), TokenStoreError> {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{HashSetBannedTokenStore, HashmapUserStore};
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn test_passes_operations_through_and_times_them() {
+        let metrics = Arc::new(Metrics::new());
+        let mut users =
+            InstrumentedUserStore::new(Box::new(HashmapUserStore::new()), metrics.clone());
+        let user = User::new(
+            crate::domain::Email::from_str("test@test.com").unwrap(),
+            Password::from_str("password").unwrap(),
+            false,
+        );
+        users.add_user(user.clone()).await.unwrap();
+        assert_eq!(users.get_user(&user.id()).await, Ok(user));
+
+        let mut tokens = InstrumentedBannedTokenStore::new(
+            Box::new(HashSetBannedTokenStore::new()),
+            metrics.clone(),
+        );
+        tokens.ban_token("token").await.unwrap();
+        assert_eq!(tokens.is_token_banned("token").await, Ok(true));
+
+        let text = metrics.encode().unwrap();
+        assert!(
+            text.contains("operation=\"add_user\",store=\"users\""),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("operation=\"ban_token\",store=\"banned_tokens\""),
+            "{}",
+            text
+        );
+        assert!(text.contains("auth_banned_tokens 1"), "{}", text);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::domain::{AuditQuery, AuditRecord, AuditSink, AuditSinkError};
+use async_trait::async_trait;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+
+/// Appends audit records to a file, one JSON object per line, e.g. for a log shipper to pick
+/// up. The file is only ever opened for appending.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct JsonLinesAuditSink {
+    path: PathBuf,
+}
+
+impl JsonLinesAuditSink {
+    /// Creates a new `JsonLinesAuditSink` instance. The file is created on the first record.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl AuditSink for JsonLinesAuditSink {
+    async fn record(&mut self, record: AuditRecord) -> Result<(), AuditSinkError> {
+        let mut line = serde_json::to_vec(&record).map_err(|_| AuditSinkError::UnexpectedError)?;
+        line.push(b'\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(|_| AuditSinkError::UnexpectedError)?;
+        file.write_all(&line)
+            .await
+            .map_err(|_| AuditSinkError::UnexpectedError)?;
+        file.flush()
+            .await
+            .map_err(|_| AuditSinkError::UnexpectedError)
+    }
+
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, AuditSinkError> {
+        let contents = match tokio::fs::read_to_string(&self.path).await {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(_) => return Err(AuditSinkError::UnexpectedError),
+        };
+
+        // Lines are in the order they were written. A line cut short, e.g. by a crash while
+        // writing it, is skipped rather than failing the whole query.
+        Ok(contents
+            .lines()
+            .rev()
+            .filter_map(|line| serde_json::from_str::<AuditRecord>(line).ok())
+            .filter(|record| record.matches(query))
+            .take(query.limit)
+            .collect())
+    }
+
+    /// Opens the file the way `record` does, without writing to it.
+    async fn health_check(&self) -> Result<(), AuditSinkError> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map(|_| ())
+            .map_err(|_| AuditSinkError::UnexpectedError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{AuditEvent, Email};
+    use std::str::FromStr;
+    use uuid::Uuid;
+
+    fn record(email: &str, event: AuditEvent) -> AuditRecord {
+        AuditRecord::new(
+            Uuid::new_v4().to_string(),
+            Some(&Email::from_str(email).unwrap().into()),
+            "request".to_owned(),
+            None,
+            Some("test".to_owned()),
+            event,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_appends_and_queries_records() {
+        let path = std::env::temp_dir().join(format!("audit-{}.jsonl", Uuid::new_v4()));
+        let mut sink = JsonLinesAuditSink::new(&path);
+        let query = AuditQuery {
+            limit: 10,
+            ..AuditQuery::default()
+        };
+        assert_eq!(sink.query(&query).await, Ok(Vec::new()));
+
+        let first = record("a@example.com", AuditEvent::LoginSucceeded);
+        let second = record("b@example.com", AuditEvent::Logout);
+        sink.record(first.clone()).await.unwrap();
+        sink.record(second.clone()).await.unwrap();
+        tokio::fs::write(
+            &path,
+            [tokio::fs::read(&path).await.unwrap(), b"{\"id\":".to_vec()].concat(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            sink.query(&query).await,
+            Ok(vec![second.clone(), first.clone()])
+        );
+        let query = AuditQuery {
+            user: Some("a@example.com".to_owned()),
+            ..query
+        };
+        assert_eq!(sink.query(&query).await, Ok(vec![first]));
+
+        // Records written by another instance, e.g. before a restart, are kept.
+        let sink = JsonLinesAuditSink::new(&path);
+        assert_eq!(sink.query(&query).await.unwrap().len(), 1);
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
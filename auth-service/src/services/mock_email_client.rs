@@ -0,0 +1,34 @@
+use crate::domain::{Email, EmailClient, EmailClientError};
+use async_trait::async_trait;
+
+/// Logs emails instead of sending them, for development and tests until an email provider
+/// is configured.
+#[derive(Debug, Default, Clone)]
+#[non_exhaustive]
+pub struct MockEmailClient;
+
+impl MockEmailClient {
+    /// Creates a new `MockEmailClient` instance.
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl EmailClient for MockEmailClient {
+    async fn send_email(
+        &self,
+        recipient: &Email,
+        subject: &str,
+        content: &str,
+    ) -> Result<(), EmailClientError> {
+        tracing::info!(
+            recipient = recipient.as_ref(),
+            subject,
+            content,
+            "Sending email"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use rusqlite::Connection;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Any failure talking to SQLite; stores map it to their own `UnexpectedError`.
+#[derive(Debug, PartialEq)]
+pub struct DatabaseError;
+
+/// A SQLite connection shared by the clones of a store. Calls block, so they run on the
+/// blocking thread pool.
+#[derive(Debug, Clone)]
+pub(crate) struct Database {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl Database {
+    /// Opens the database at `path`, or one that only lives in memory if there is none, and
+    /// runs `schema` on it.
+    pub(crate) fn open(path: Option<&Path>, schema: &str) -> Result<Self, DatabaseError> {
+        let connection = match path {
+            Some(path) => Connection::open(path),
+            None => Connection::open_in_memory(),
+        }
+        .map_err(|_| DatabaseError)?;
+        connection
+            .execute_batch(schema)
+            .map_err(|_| DatabaseError)?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    pub(crate) async fn run<T, F>(&self, f: F) -> Result<T, DatabaseError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection.lock().map_err(|_| DatabaseError)?;
+            f(&mut connection).map_err(|_| DatabaseError)
+        })
+        .await
+        .map_err(|_| DatabaseError)?
+    }
+
+    /// Runs a trivial query, to check that the database can be used.
+    pub(crate) async fn ping(&self) -> Result<(), DatabaseError> {
+        self.run(|connection| connection.query_row("SELECT 1", [], |_| Ok(())))
+            .await
+    }
+}
@@ -0,0 +1,209 @@
+use super::sqlite::{Database, DatabaseError};
+use crate::domain::{AuditQuery, AuditRecord, AuditSink, AuditSinkError};
+use async_trait::async_trait;
+use rusqlite::params;
+use std::path::Path;
+
+// Triggers keep the table append-only for anyone with access to the database file, too.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS audit_records (
+        id TEXT PRIMARY KEY,
+        at INTEGER NOT NULL,
+        user TEXT,
+        tenant TEXT,
+        record TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS audit_records_user_at ON audit_records (user, at);
+    CREATE INDEX IF NOT EXISTS audit_records_at ON audit_records (at);
+    CREATE TRIGGER IF NOT EXISTS audit_records_no_update BEFORE UPDATE ON audit_records
+    BEGIN SELECT RAISE(ABORT, 'audit records are append-only'); END;
+    CREATE TRIGGER IF NOT EXISTS audit_records_no_delete BEFORE DELETE ON audit_records
+    BEGIN SELECT RAISE(ABORT, 'audit records are append-only'); END;
+";
+
+/// Keeps audit records in a SQLite database, indexed by user and time for the admin API.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SqliteAuditSink {
+    database: Database,
+}
+
+impl SqliteAuditSink {
+    /// Opens the database at `path`, creating it and the table if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AuditSinkError> {
+        Self::with_database(Database::open(Some(path.as_ref()), SCHEMA))
+    }
+
+    /// A database that only lives as long as the sink, e.g. for tests.
+    pub fn in_memory() -> Result<Self, AuditSinkError> {
+        Self::with_database(Database::open(None, SCHEMA))
+    }
+
+    fn with_database(database: Result<Database, DatabaseError>) -> Result<Self, AuditSinkError> {
+        Ok(Self {
+            database: database?,
+        })
+    }
+}
+
+impl From<DatabaseError> for AuditSinkError {
+    fn from(_: DatabaseError) -> Self {
+        AuditSinkError::UnexpectedError
+    }
+}
+
+#[async_trait]
+impl AuditSink for SqliteAuditSink {
+    async fn record(&mut self, record: AuditRecord) -> Result<(), AuditSinkError> {
+        let json = serde_json::to_string(&record).map_err(|_| AuditSinkError::UnexpectedError)?;
+        self.database
+            .run(move |connection| {
+                connection.execute(
+                    "INSERT INTO audit_records (id, at, user, tenant, record)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        record.id,
+                        record.at.timestamp_micros(),
+                        record.user,
+                        record.tenant,
+                        json
+                    ],
+                )
+            })
+            .await
+            .map(|_| ())
+            .map_err(AuditSinkError::from)
+    }
+
+    async fn health_check(&self) -> Result<(), AuditSinkError> {
+        Ok(self.database.ping().await?)
+    }
+
+    async fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, AuditSinkError> {
+        let query = query.clone();
+        let rows = self
+            .database
+            .run(move |connection| {
+                let mut statement = connection.prepare(
+                    "SELECT record FROM audit_records
+                     WHERE tenant IS ?1
+                       AND (?2 IS NULL OR user = ?2)
+                       AND (?3 IS NULL OR at >= ?3)
+                       AND (?4 IS NULL OR at <= ?4)
+                     ORDER BY at DESC, rowid DESC
+                     LIMIT ?5",
+                )?;
+                let rows = statement.query_map(
+                    params![
+                        query.tenant,
+                        query.user,
+                        query.from.map(|from| from.timestamp_micros()),
+                        query.to.map(|to| to.timestamp_micros()),
+                        i64::try_from(query.limit).unwrap_or(i64::MAX)
+                    ],
+                    |row| row.get::<_, String>(0),
+                )?;
+                rows.collect::<rusqlite::Result<Vec<String>>>()
+            })
+            .await?;
+
+        rows.iter()
+            .map(|row| serde_json::from_str(row).map_err(|_| AuditSinkError::UnexpectedError))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{AuditEvent, Email, UserId};
+    use chrono::Duration;
+    use std::str::FromStr;
+
+    fn record(tenant: Option<&str>, email: &str, event: AuditEvent) -> AuditRecord {
+        let user = UserId::new(tenant.map(str::to_owned), Email::from_str(email).unwrap());
+        AuditRecord::new(
+            uuid::Uuid::new_v4().to_string(),
+            Some(&user),
+            "request".to_owned(),
+            "127.0.0.1".parse().ok(),
+            None,
+            event,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_records_and_queries_by_user_and_time() {
+        let mut sink = SqliteAuditSink::in_memory().unwrap();
+        let first = record(None, "a@example.com", AuditEvent::LoginSucceeded);
+        let second = record(None, "b@example.com", AuditEvent::LoginSucceeded);
+        let third = record(None, "a@example.com", AuditEvent::Logout);
+        for record in [&first, &second, &third] {
+            sink.record(record.clone()).await.unwrap();
+        }
+
+        let query = AuditQuery {
+            limit: 10,
+            ..AuditQuery::default()
+        };
+        assert_eq!(
+            sink.query(&query).await,
+            Ok(vec![third.clone(), second.clone(), first.clone()])
+        );
+        let query = AuditQuery {
+            user: Some("a@example.com".to_owned()),
+            ..query
+        };
+        assert_eq!(
+            sink.query(&query).await,
+            Ok(vec![third.clone(), first.clone()])
+        );
+        let query = AuditQuery {
+            to: Some(third.at - Duration::microseconds(1)),
+            ..query
+        };
+        assert_eq!(sink.query(&query).await, Ok(vec![first.clone()]));
+        let query = AuditQuery {
+            from: Some(third.at),
+            to: None,
+            limit: 1,
+            ..query
+        };
+        assert_eq!(sink.query(&query).await, Ok(vec![third]));
+    }
+
+    #[tokio::test]
+    async fn test_queries_one_tenant() {
+        let mut sink = SqliteAuditSink::in_memory().unwrap();
+        let global = record(None, "a@example.com", AuditEvent::LoginSucceeded);
+        let tenant = record(Some("acme"), "a@example.com", AuditEvent::LoginSucceeded);
+        sink.record(global.clone()).await.unwrap();
+        sink.record(tenant.clone()).await.unwrap();
+
+        let query = AuditQuery {
+            tenant: Some("acme".to_owned()),
+            limit: 10,
+            ..AuditQuery::default()
+        };
+        assert_eq!(sink.query(&query).await, Ok(vec![tenant]));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_changes_to_records() {
+        let mut sink = SqliteAuditSink::in_memory().unwrap();
+        sink.record(record(None, "a@example.com", AuditEvent::Logout))
+            .await
+            .unwrap();
+
+        let result = sink
+            .database
+            .run(|connection| connection.execute("DELETE FROM audit_records", []))
+            .await;
+        assert_eq!(result, Err(DatabaseError));
+        let result = sink
+            .database
+            .run(|connection| connection.execute("UPDATE audit_records SET user = NULL", []))
+            .await;
+        assert_eq!(result, Err(DatabaseError));
+    }
+}
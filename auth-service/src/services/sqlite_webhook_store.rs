@@ -0,0 +1,479 @@
+use super::sqlite::{Database, DatabaseError};
+use crate::domain::{
+    DeliveryAttempt, DeliveryStatus, Webhook, WebhookDelivery, WebhookStore, WebhookStoreError,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, OptionalExtension, Row};
+use std::path::Path;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS webhooks (
+        id TEXT PRIMARY KEY,
+        tenant TEXT,
+        url TEXT NOT NULL,
+        secret TEXT NOT NULL,
+        events TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS webhook_deliveries (
+        id TEXT PRIMARY KEY,
+        webhook_id TEXT NOT NULL,
+        event_id TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        status TEXT NOT NULL,
+        attempts INTEGER NOT NULL,
+        next_attempt_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS webhook_deliveries_due
+        ON webhook_deliveries (status, next_attempt_at);
+    CREATE TABLE IF NOT EXISTS webhook_attempts (
+        delivery_id TEXT NOT NULL,
+        webhook_id TEXT NOT NULL,
+        event_id TEXT NOT NULL,
+        at INTEGER NOT NULL,
+        status_code INTEGER,
+        error TEXT,
+        succeeded INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS webhook_attempts_webhook ON webhook_attempts (webhook_id, at);
+";
+
+const WEBHOOK_COLUMNS: &str = "id, tenant, url, secret, events, created_at";
+const DELIVERY_COLUMNS: &str =
+    "id, webhook_id, event_id, payload, status, attempts, next_attempt_at";
+
+/// Keeps webhooks and their outbox in a SQLite database, so that deliveries survive restarts.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SqliteWebhookStore {
+    database: Database,
+}
+
+impl SqliteWebhookStore {
+    /// Opens the database at `path`, creating it and the tables if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, WebhookStoreError> {
+        Ok(Self {
+            database: Database::open(Some(path.as_ref()), SCHEMA)?,
+        })
+    }
+
+    /// A database that only lives as long as the store, e.g. for tests.
+    pub fn in_memory() -> Result<Self, WebhookStoreError> {
+        Ok(Self {
+            database: Database::open(None, SCHEMA)?,
+        })
+    }
+}
+
+impl From<DatabaseError> for WebhookStoreError {
+    fn from(_: DatabaseError) -> Self {
+        WebhookStoreError::UnexpectedError
+    }
+}
+
+fn to_micros(at: DateTime<Utc>) -> i64 {
+    at.timestamp_micros()
+}
+
+fn from_micros(micros: i64) -> rusqlite::Result<DateTime<Utc>> {
+    DateTime::from_timestamp_micros(micros)
+        .ok_or(rusqlite::Error::IntegralValueOutOfRange(0, micros))
+}
+
+// Enums and lists are kept as JSON, like they are serialized everywhere else.
+fn from_json<T: serde::de::DeserializeOwned>(index: usize, json: &str) -> rusqlite::Result<T> {
+    serde_json::from_str(json).map_err(|err| {
+        rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(err))
+    })
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> rusqlite::Result<String> {
+    serde_json::to_string(value)
+        .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))
+}
+
+fn webhook_from_row(row: &Row) -> rusqlite::Result<Webhook> {
+    Ok(Webhook {
+        id: row.get(0)?,
+        tenant: row.get(1)?,
+        url: row.get(2)?,
+        secret: row.get(3)?,
+        events: from_json(4, &row.get::<_, String>(4)?)?,
+        created_at: from_micros(row.get(5)?)?,
+    })
+}
+
+fn delivery_from_row(row: &Row) -> rusqlite::Result<WebhookDelivery> {
+    Ok(WebhookDelivery {
+        id: row.get(0)?,
+        webhook_id: row.get(1)?,
+        event_id: row.get(2)?,
+        payload: row.get(3)?,
+        status: from_json(4, &row.get::<_, String>(4)?)?,
+        attempts: row.get(5)?,
+        next_attempt_at: from_micros(row.get(6)?)?,
+    })
+}
+
+fn attempt_from_row(row: &Row) -> rusqlite::Result<DeliveryAttempt> {
+    Ok(DeliveryAttempt {
+        delivery_id: row.get(0)?,
+        webhook_id: row.get(1)?,
+        event_id: row.get(2)?,
+        at: from_micros(row.get(3)?)?,
+        status_code: row.get(4)?,
+        error: row.get(5)?,
+        succeeded: row.get(6)?,
+    })
+}
+
+#[async_trait]
+impl WebhookStore for SqliteWebhookStore {
+    async fn add_webhook(&mut self, webhook: Webhook) -> Result<(), WebhookStoreError> {
+        let inserted = self
+            .database
+            .run(move |connection| {
+                connection.execute(
+                    "INSERT OR IGNORE INTO webhooks (id, tenant, url, secret, events, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        webhook.id,
+                        webhook.tenant,
+                        webhook.url,
+                        webhook.secret,
+                        to_json(&webhook.events)?,
+                        to_micros(webhook.created_at)
+                    ],
+                )
+            })
+            .await?;
+        match inserted {
+            0 => Err(WebhookStoreError::WebhookAlreadyExists),
+            _ => Ok(()),
+        }
+    }
+
+    async fn get_webhook(&self, id: &str) -> Result<Webhook, WebhookStoreError> {
+        let id = id.to_owned();
+        self.database
+            .run(move |connection| {
+                connection
+                    .query_row(
+                        &format!("SELECT {WEBHOOK_COLUMNS} FROM webhooks WHERE id = ?1"),
+                        params![id],
+                        webhook_from_row,
+                    )
+                    .optional()
+            })
+            .await?
+            .ok_or(WebhookStoreError::WebhookNotFound)
+    }
+
+    async fn get_webhooks(&self, tenant: Option<&str>) -> Result<Vec<Webhook>, WebhookStoreError> {
+        let tenant = tenant.map(str::to_owned);
+        Ok(self
+            .database
+            .run(move |connection| {
+                connection
+                    .prepare(&format!(
+                        "SELECT {WEBHOOK_COLUMNS} FROM webhooks WHERE tenant IS ?1
+                         ORDER BY created_at DESC, rowid DESC"
+                    ))?
+                    .query_map(params![tenant], webhook_from_row)?
+                    .collect()
+            })
+            .await?)
+    }
+
+    async fn remove_webhook(&mut self, id: &str) -> Result<Webhook, WebhookStoreError> {
+        let webhook = self.get_webhook(id).await?;
+        let id = id.to_owned();
+        self.database
+            .run(move |connection| {
+                let transaction = connection.transaction()?;
+                transaction.execute("DELETE FROM webhooks WHERE id = ?1", params![id])?;
+                transaction.execute(
+                    "DELETE FROM webhook_deliveries WHERE webhook_id = ?1 AND status = ?2",
+                    params![id, to_json(&DeliveryStatus::Pending)?],
+                )?;
+                transaction.commit()
+            })
+            .await?;
+        Ok(webhook)
+    }
+
+    async fn enqueue_deliveries(
+        &mut self,
+        deliveries: Vec<WebhookDelivery>,
+    ) -> Result<(), WebhookStoreError> {
+        Ok(self
+            .database
+            .run(move |connection| {
+                let transaction = connection.transaction()?;
+                for delivery in deliveries {
+                    transaction.execute(
+                        &format!(
+                            "INSERT OR IGNORE INTO webhook_deliveries ({DELIVERY_COLUMNS})
+                             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+                        ),
+                        params![
+                            delivery.id,
+                            delivery.webhook_id,
+                            delivery.event_id,
+                            delivery.payload,
+                            to_json(&delivery.status)?,
+                            delivery.attempts,
+                            to_micros(delivery.next_attempt_at)
+                        ],
+                    )?;
+                }
+                transaction.commit()
+            })
+            .await?)
+    }
+
+    async fn due_deliveries(
+        &self,
+        now: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<WebhookDelivery>, WebhookStoreError> {
+        Ok(self
+            .database
+            .run(move |connection| {
+                connection
+                    .prepare(&format!(
+                        "SELECT {DELIVERY_COLUMNS} FROM webhook_deliveries
+                         WHERE status = ?1 AND next_attempt_at <= ?2
+                         ORDER BY next_attempt_at, rowid
+                         LIMIT ?3"
+                    ))?
+                    .query_map(
+                        params![
+                            to_json(&DeliveryStatus::Pending)?,
+                            to_micros(now),
+                            i64::try_from(limit).unwrap_or(i64::MAX)
+                        ],
+                        delivery_from_row,
+                    )?
+                    .collect()
+            })
+            .await?)
+    }
+
+    async fn record_attempt(
+        &mut self,
+        delivery: WebhookDelivery,
+        attempt: DeliveryAttempt,
+    ) -> Result<(), WebhookStoreError> {
+        Ok(self
+            .database
+            .run(move |connection| {
+                let transaction = connection.transaction()?;
+                transaction.execute(
+                    "UPDATE webhook_deliveries SET status = ?2, attempts = ?3, next_attempt_at = ?4
+                     WHERE id = ?1",
+                    params![
+                        delivery.id,
+                        to_json(&delivery.status)?,
+                        delivery.attempts,
+                        to_micros(delivery.next_attempt_at)
+                    ],
+                )?;
+                transaction.execute(
+                    "INSERT INTO webhook_attempts
+                     (delivery_id, webhook_id, event_id, at, status_code, error, succeeded)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        attempt.delivery_id,
+                        attempt.webhook_id,
+                        attempt.event_id,
+                        to_micros(attempt.at),
+                        attempt.status_code,
+                        attempt.error,
+                        attempt.succeeded
+                    ],
+                )?;
+                transaction.commit()
+            })
+            .await?)
+    }
+
+    async fn get_attempts(
+        &self,
+        webhook_id: &str,
+        limit: usize,
+    ) -> Result<Vec<DeliveryAttempt>, WebhookStoreError> {
+        let webhook_id = webhook_id.to_owned();
+        Ok(self
+            .database
+            .run(move |connection| {
+                connection
+                    .prepare(
+                        "SELECT delivery_id, webhook_id, event_id, at, status_code, error, succeeded
+                         FROM webhook_attempts WHERE webhook_id = ?1
+                         ORDER BY at DESC, rowid DESC
+                         LIMIT ?2",
+                    )?
+                    .query_map(
+                        params![webhook_id, i64::try_from(limit).unwrap_or(i64::MAX)],
+                        attempt_from_row,
+                    )?
+                    .collect()
+            })
+            .await?)
+    }
+
+    async fn health_check(&self) -> Result<(), WebhookStoreError> {
+        Ok(self.database.ping().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::WebhookEventType;
+    use chrono::{Duration, SubsecRound};
+
+    // The database keeps microseconds, so the fixtures do too to compare equal after a round trip.
+    fn webhook(id: &str, tenant: Option<&str>) -> Webhook {
+        let webhook = Webhook::new(
+            id.to_owned(),
+            tenant.map(str::to_owned),
+            "http://localhost/hook".to_owned(),
+            "secret".to_owned(),
+            vec![WebhookEventType::UserCreated],
+        );
+        Webhook {
+            created_at: webhook.created_at.trunc_subsecs(6),
+            ..webhook
+        }
+    }
+
+    fn delivery(id: &str, webhook_id: &str) -> WebhookDelivery {
+        let delivery = WebhookDelivery::new(
+            id.to_owned(),
+            webhook_id.to_owned(),
+            format!("event-{}", id),
+            "{}".to_owned(),
+        );
+        WebhookDelivery {
+            next_attempt_at: delivery.next_attempt_at.trunc_subsecs(6),
+            ..delivery
+        }
+    }
+
+    fn attempt(delivery: &WebhookDelivery, succeeded: bool) -> DeliveryAttempt {
+        DeliveryAttempt {
+            delivery_id: delivery.id.clone(),
+            webhook_id: delivery.webhook_id.clone(),
+            event_id: delivery.event_id.clone(),
+            at: Utc::now(),
+            status_code: Some(if succeeded { 200 } else { 500 }),
+            error: None,
+            succeeded,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_get_and_remove_webhook() {
+        let mut store = SqliteWebhookStore::in_memory().unwrap();
+        let hook = webhook("a", None);
+        assert_eq!(store.add_webhook(hook.clone()).await, Ok(()));
+        assert_eq!(
+            store.add_webhook(hook.clone()).await,
+            Err(WebhookStoreError::WebhookAlreadyExists)
+        );
+        store.add_webhook(webhook("b", Some("acme"))).await.unwrap();
+
+        assert_eq!(store.get_webhook("a").await, Ok(hook.clone()));
+        assert_eq!(store.get_webhooks(None).await, Ok(vec![hook.clone()]));
+        assert_eq!(store.remove_webhook("a").await, Ok(hook));
+        assert_eq!(
+            store.get_webhook("a").await,
+            Err(WebhookStoreError::WebhookNotFound)
+        );
+        assert_eq!(
+            store.remove_webhook("a").await,
+            Err(WebhookStoreError::WebhookNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_due_deliveries_and_attempts() {
+        let mut store = SqliteWebhookStore::in_memory().unwrap();
+        store.add_webhook(webhook("a", None)).await.unwrap();
+        let first = delivery("1", "a");
+        let second = delivery("2", "a");
+        store
+            .enqueue_deliveries(vec![first.clone(), second.clone()])
+            .await
+            .unwrap();
+
+        let now = Utc::now();
+        assert_eq!(
+            store.due_deliveries(now, 10).await,
+            Ok(vec![first.clone(), second.clone()])
+        );
+        assert_eq!(store.due_deliveries(now, 1).await.unwrap().len(), 1);
+
+        // A failed attempt postpones the delivery, a successful one completes it.
+        let retry = WebhookDelivery {
+            attempts: 1,
+            next_attempt_at: now + Duration::minutes(1),
+            ..first.clone()
+        };
+        store
+            .record_attempt(retry, attempt(&first, false))
+            .await
+            .unwrap();
+        let delivered = WebhookDelivery {
+            attempts: 1,
+            status: DeliveryStatus::Delivered,
+            ..second.clone()
+        };
+        store
+            .record_attempt(delivered, attempt(&second, true))
+            .await
+            .unwrap();
+        assert_eq!(store.due_deliveries(now, 10).await, Ok(vec![]));
+        let due = store
+            .due_deliveries(now + Duration::minutes(2), 10)
+            .await
+            .unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].attempts, 1);
+
+        let attempts = store.get_attempts("a", 10).await.unwrap();
+        let succeeded: Vec<bool> = attempts.iter().map(|attempt| attempt.succeeded).collect();
+        assert_eq!(succeeded, vec![true, false]);
+    }
+
+    #[tokio::test]
+    async fn test_remove_webhook_drops_pending_deliveries() {
+        let mut store = SqliteWebhookStore::in_memory().unwrap();
+        store.add_webhook(webhook("a", None)).await.unwrap();
+        store
+            .enqueue_deliveries(vec![delivery("1", "a")])
+            .await
+            .unwrap();
+
+        store.remove_webhook("a").await.unwrap();
+        assert_eq!(store.due_deliveries(Utc::now(), 10).await, Ok(vec![]));
+    }
+
+    #[tokio::test]
+    async fn test_skips_deliveries_that_are_already_queued() {
+        let mut store = SqliteWebhookStore::in_memory().unwrap();
+        store.add_webhook(webhook("a", None)).await.unwrap();
+        let first = delivery("1", "a");
+        store.enqueue_deliveries(vec![first.clone()]).await.unwrap();
+        let again = WebhookDelivery {
+            payload: "{\"retried\":true}".to_owned(),
+            ..first.clone()
+        };
+        store.enqueue_deliveries(vec![again]).await.unwrap();
+
+        assert_eq!(store.due_deliveries(Utc::now(), 10).await, Ok(vec![first]));
+    }
+}
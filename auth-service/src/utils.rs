@@ -1,4 +1,17 @@
-pub(crate) mod auth;
+pub mod api_key;
+pub mod audit;
+pub mod auth;
+pub mod client_auth;
 pub mod constants;
+pub mod csrf;
+pub mod extractors;
+pub mod metrics;
+pub mod oidc;
+pub mod outbox;
+pub mod problem;
+pub mod shutdown;
+pub mod telemetry;
+pub mod tls;
+pub mod webhooks;
 
 pub use crate::utils::constants::*;
@@ -0,0 +1,56 @@
+use super::auth::{api_key_claims, generate_opaque_token, Claims, GenerateTokenError};
+use crate::domain::{ApiKey, ApiKeyStoreError, AuthAPIError};
+use crate::AppState;
+use chrono::Utc;
+use uuid::Uuid;
+
+/// Whether the token presented by the caller is an API key rather than a JWT.
+pub fn is_api_key(token: &str) -> bool {
+    ApiKey::parse(token).is_some()
+}
+
+// Create the id and secret of a new API key
+pub fn generate_api_key() -> Result<(String, String), GenerateTokenError> {
+    let id = Uuid::new_v4().simple().to_string()[..12].to_owned();
+    Ok((id, generate_opaque_token()?))
+}
+
+/// Authenticates an API key and returns the claims it stands for.
+///
+/// Unknown, expired and revoked keys as well as keys of inactive users are rejected with
+/// `AuthAPIError::InvalidToken`, like an invalid JWT.
+pub async fn authenticate_api_key(state: &AppState, key: &str) -> Result<Claims, AuthAPIError> {
+    let (id, secret) = ApiKey::parse(key).ok_or(AuthAPIError::InvalidToken)?;
+    let api_key = state
+        .api_keys
+        .read()
+        .await
+        .get_api_key(id)
+        .await
+        .map_err(|err| match err {
+            ApiKeyStoreError::ApiKeyNotFound => AuthAPIError::InvalidToken,
+            err => AuthAPIError::unexpected(err),
+        })?;
+    if !api_key.verify_secret(secret) {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&api_key.user)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+    if !user.is_active() {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    let _ = state
+        .api_keys
+        .write()
+        .await
+        .touch_api_key(id, Utc::now())
+        .await;
+    api_key_claims(&user, &api_key).map_err(AuthAPIError::unexpected)
+}
@@ -0,0 +1,27 @@
+use super::extractors::RequestContext;
+use crate::domain::{AuditEvent, AuditRecord, UserId};
+use crate::AppState;
+use uuid::Uuid;
+
+/// Records that `event` happened to or was done by `user`.
+///
+/// A failing sink is reported but does not fail the request, so that the audit log being
+/// unavailable does not lock everyone out.
+pub async fn audit(
+    state: &AppState,
+    context: &RequestContext,
+    user: Option<&UserId>,
+    event: AuditEvent,
+) {
+    let record = AuditRecord::new(
+        Uuid::new_v4().to_string(),
+        user,
+        context.request_id.clone(),
+        context.client.ip,
+        context.client.user_agent.clone(),
+        event,
+    );
+    if let Err(err) = state.audit_sink.write().await.record(record).await {
+        tracing::error!(error = ?err, "Failed to write audit record");
+    }
+}
@@ -1,16 +1,499 @@
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
+use super::constants::{JWT_SECRET, TOKEN_TTL_SECONDS};
+use crate::domain::{permissions_of, ApiKey, Email, Role, Scope, User, UserId};
+use crate::AppConfig;
 use axum_extra::extract::cookie::Cookie;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use uuid::Uuid;
 
-pub async fn generate_auth_cookie<'a>(token: &'a str) -> Result<Cookie<'a>, String> {
-    Ok(Cookie::new(JWT_COOKIE_NAME, token.to_owned()))
+/// Who a token was issued to: a user (`sub` is their email) or an OAuth client acting
+/// on its own behalf (`sub` is its client id).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    #[default]
+    User,
+    Client,
 }
 
-pub async fn validate_token(token: &str) -> Result<bool, String> {
-    // In a real implementation, you would decode and validate the JWT token here.
-    // For simplicity, we will just check if the token matches a predefined secret.
-    if token.to_owned().eq_ignore_ascii_case("test") {
-        Ok(true)
-    } else {
-        Ok(false)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub exp: usize,
+    pub iat: usize,
+    pub jti: String,
+    #[serde(default)]
+    pub kind: TokenKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<Role>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permissions: Vec<String>,
+    /// The namespace `sub` is unique in, see [`UserId`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// The organization the user is acting in; `roles` include the roles they have there.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<String>,
+}
+
+impl Claims {
+    pub fn is_user_token(&self) -> bool {
+        self.kind == TokenKind::User
+    }
+
+    pub fn is_client_token(&self) -> bool {
+        self.kind == TokenKind::Client
+    }
+
+    /// Whether `/token` issued the token to an OAuth client acting for the user, rather than
+    /// the user logging in themselves.
+    pub fn is_delegated(&self) -> bool {
+        self.client_id.is_some() || self.scope.is_some()
+    }
+
+    /// The user the token was issued to, `None` for client tokens.
+    pub fn user_id(&self) -> Option<UserId> {
+        if !self.is_user_token() {
+            return None;
+        }
+        let email = Email::from_str(&self.sub).ok()?;
+        Some(UserId::new(self.tenant.clone(), email))
+    }
+
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|granted| granted == permission)
+    }
+}
+
+#[derive(Debug)]
+pub enum GenerateTokenError {
+    TokenError(jsonwebtoken::errors::Error),
+    UnexpectedError,
+}
+
+// Create cookie with a new JWT auth token
+pub async fn generate_auth_cookie(
+    user: &User,
+    org_id: Option<&str>,
+    config: &AppConfig,
+) -> Result<Cookie<'static>, GenerateTokenError> {
+    let token = generate_auth_token(user, org_id)?;
+    Ok(auth_cookie(config, token))
+}
+
+/// The cookie carrying `token`, which browsers drop once the token has expired.
+pub fn auth_cookie(config: &AppConfig, token: String) -> Cookie<'static> {
+    let mut cookie = session_cookie(config, config.cookies.name(), token);
+    cookie.set_max_age(time::Duration::seconds(TOKEN_TTL_SECONDS));
+    cookie
+}
+
+/// The cookie to pass to `CookieJar::remove` to clear the auth cookie. Browsers only clear
+/// a cookie with the same name, path and domain.
+pub fn removal_cookie(config: &AppConfig) -> Cookie<'static> {
+    session_cookie(config, config.cookies.name(), String::new())
+}
+
+// A cookie with the configured attributes, which every cookie of a browser session shares.
+pub(crate) fn session_cookie(
+    config: &AppConfig,
+    name: &'static str,
+    value: String,
+) -> Cookie<'static> {
+    let settings = &config.cookies;
+    let cookie = Cookie::build((name, value))
+        .http_only(settings.http_only)
+        .secure(config.secure_cookies())
+        .same_site(settings.same_site);
+    let cookie = match (&settings.domain, settings.host_prefix) {
+        (_, true) => cookie.path("/"),
+        (None, false) => cookie.path(settings.path.clone()),
+        (Some(domain), false) => cookie.path(settings.path.clone()).domain(domain.clone()),
+    };
+    cookie.build()
+}
+
+// Create JWT auth token for a browser session, carrying the user's roles and permissions
+// in the organization they act in
+pub fn generate_auth_token(
+    user: &User,
+    org_id: Option<&str>,
+) -> Result<String, GenerateTokenError> {
+    let roles = user.roles_in(org_id);
+    let claims = Claims {
+        permissions: permissions_of(&roles),
+        roles,
+        tenant: user.tenant.clone(),
+        org_id: org_id.map(str::to_owned),
+        ..new_claims(user.email.as_ref(), TokenKind::User)?
+    };
+    create_token(&claims).map_err(GenerateTokenError::TokenError)
+}
+
+// Create JWT access token, optionally bound to the OAuth client and scope it was granted for.
+// OAuth access tokens are limited to their scope and never carry the user's permissions.
+pub fn generate_access_token(
+    user: &UserId,
+    scope: Option<&Scope>,
+    client_id: Option<&str>,
+) -> Result<String, GenerateTokenError> {
+    let claims = Claims {
+        scope: scope.map(Scope::to_string),
+        client_id: client_id.map(str::to_owned),
+        tenant: user.tenant.clone(),
+        ..new_claims(user.email.as_ref(), TokenKind::User)?
+    };
+    create_token(&claims).map_err(GenerateTokenError::TokenError)
+}
+
+// The claims an API key stands for: the user's current roles and, if the key is scoped, only
+// those of their permissions it is scoped to. The key id takes the place of the `jti`.
+pub fn api_key_claims(user: &User, api_key: &ApiKey) -> Result<Claims, GenerateTokenError> {
+    let mut permissions = permissions_of(&user.roles);
+    if let Some(scope) = &api_key.scope {
+        permissions.retain(|permission| scope.contains(permission));
+    }
+    Ok(Claims {
+        jti: api_key.id.clone(),
+        scope: api_key.scope.as_ref().map(Scope::to_string),
+        roles: user.roles.clone(),
+        permissions,
+        tenant: user.tenant.clone(),
+        ..new_claims(user.email.as_ref(), TokenKind::User)?
+    })
+}
+
+// Create JWT access token for an OAuth client authenticating as itself
+pub fn generate_client_token(client_id: &str, scope: &Scope) -> Result<String, GenerateTokenError> {
+    let claims = Claims {
+        scope: Some(scope.to_string()),
+        client_id: Some(client_id.to_owned()),
+        ..new_claims(client_id, TokenKind::Client)?
+    };
+    create_token(&claims).map_err(GenerateTokenError::TokenError)
+}
+
+// The claims every token has: who it is for, a unique id and its lifetime
+fn new_claims(subject: &str, kind: TokenKind) -> Result<Claims, GenerateTokenError> {
+    let iat = Utc::now().timestamp();
+    let exp = iat
+        .checked_add(TOKEN_TTL_SECONDS)
+        .ok_or(GenerateTokenError::UnexpectedError)?;
+
+    Ok(Claims {
+        sub: subject.to_owned(),
+        exp: usize::try_from(exp).map_err(|_| GenerateTokenError::UnexpectedError)?,
+        iat: usize::try_from(iat).map_err(|_| GenerateTokenError::UnexpectedError)?,
+        jti: Uuid::new_v4().to_string(),
+        kind,
+        scope: None,
+        client_id: None,
+        roles: Vec::new(),
+        permissions: Vec::new(),
+        tenant: None,
+        org_id: None,
+    })
+}
+
+// Check if JWT auth token is valid by decoding it using the JWT secret
+pub async fn validate_token(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+    decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(JWT_SECRET.as_bytes()),
+        &Validation::default(),
+    )
+    .map(|data| data.claims)
+}
+
+// Create JWT auth token by encoding claims using the JWT secret
+fn create_token(claims: &Claims) -> Result<String, jsonwebtoken::errors::Error> {
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(JWT_SECRET.as_bytes()),
+    )
+}
+
+// Create an unguessable, URL-safe value for single-use codes and opaque tokens
+pub fn generate_opaque_token() -> Result<String, GenerateTokenError> {
+    let mut bytes = [0u8; 32];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| GenerateTokenError::UnexpectedError)?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// A random six-digit code for 2FA, see [`PendingLogin`](crate::domain::PendingLogin).
+pub fn generate_2fa_code() -> Result<String, GenerateTokenError> {
+    let mut bytes = [0u8; 4];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| GenerateTokenError::UnexpectedError)?;
+    Ok(format!("{:06}", u32::from_be_bytes(bytes) % 1_000_000))
+}
+
+// Create the token of an invite link: the invitation id and a signature over it, so that
+// ids cannot be guessed or altered. Expiry and revocation are checked against the store.
+pub fn sign_invitation(id: &str) -> String {
+    let signature = hmac::sign(&invitation_key(), id.as_bytes());
+    format!("{}.{}", id, URL_SAFE_NO_PAD.encode(signature.as_ref()))
+}
+
+// Check the signature of an invite link token and return the invitation id
+pub fn verify_invitation_token(token: &str) -> Option<&str> {
+    let (id, signature) = token.rsplit_once('.')?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+    hmac::verify(&invitation_key(), id.as_bytes(), &signature).ok()?;
+    Some(id)
+}
+
+// Create the token of an email verification link: the user and its expiry, signed, so that
+// nothing has to be stored until the link is followed.
+pub fn sign_email_verification(id: &UserId, expires_at: DateTime<Utc>) -> String {
+    let payload = serde_json::json!([id.tenant, id.email.as_ref(), expires_at.timestamp()]);
+    let payload = URL_SAFE_NO_PAD.encode(payload.to_string());
+    let signature = hmac::sign(&email_verification_key(), payload.as_bytes());
+    format!("{}.{}", payload, URL_SAFE_NO_PAD.encode(signature.as_ref()))
+}
+
+// Check the signature and expiry of an email verification token and return the user whose
+// email it verifies
+pub fn verify_email_verification_token(token: &str) -> Option<UserId> {
+    let (payload, signature) = token.split_once('.')?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+    hmac::verify(&email_verification_key(), payload.as_bytes(), &signature).ok()?;
+    let payload = URL_SAFE_NO_PAD.decode(payload).ok()?;
+    let (tenant, email, expires_at): (Option<String>, String, i64) =
+        serde_json::from_slice(&payload).ok()?;
+    if expires_at <= Utc::now().timestamp() {
+        return None;
+    }
+    Some(UserId::new(tenant, Email::from_str(&email).ok()?))
+}
+
+// Domain-separated from the JWT signatures and from each other, as they use the same secret.
+fn purpose_key(purpose: &[u8]) -> hmac::Key {
+    let mut secret = purpose.to_vec();
+    secret.extend_from_slice(JWT_SECRET.as_bytes());
+    hmac::Key::new(hmac::HMAC_SHA256, &secret)
+}
+
+fn invitation_key() -> hmac::Key {
+    purpose_key(b"invitation:")
+}
+
+fn email_verification_key() -> hmac::Key {
+    purpose_key(b"email-verification:")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Password;
+    use crate::utils::JWT_COOKIE_NAME;
+    use crate::CookieSettings;
+    use axum_extra::extract::cookie::SameSite;
+    use std::str::FromStr;
+
+    fn user(email: &str) -> User {
+        User::new(
+            Email::from_str(email).unwrap(),
+            Password::from_str("password").unwrap(),
+            false,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_generate_auth_cookie() {
+        let config = AppConfig::default();
+        let cookie = generate_auth_cookie(&user("test@example.com"), None, &config)
+            .await
+            .unwrap();
+        assert_eq!(cookie.name(), JWT_COOKIE_NAME);
+        assert_eq!(cookie.value().split('.').count(), 3);
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.secure(), Some(false));
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(
+            cookie.max_age(),
+            Some(time::Duration::seconds(TOKEN_TTL_SECONDS))
+        );
+    }
+
+    #[test]
+    fn test_host_prefix_overrides_path_domain_and_secure() {
+        let settings = CookieSettings::default()
+            .with_path("/auth")
+            .with_domain("example.com")
+            .with_secure(false)
+            .with_host_prefix();
+        let config = AppConfig::default().with_cookie_settings(settings);
+        let cookie = auth_cookie(&config, "token".to_owned());
+        assert_eq!(cookie.name(), "__Host-jwt");
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.domain(), None);
+    }
+
+    #[test]
+    fn test_removal_cookie_matches_the_auth_cookie() {
+        let settings = CookieSettings::default()
+            .with_path("/auth")
+            .with_domain("example.com")
+            .with_same_site(SameSite::Strict)
+            .with_secure(true);
+        let config = AppConfig::default().with_cookie_settings(settings);
+        let cookie = auth_cookie(&config, "token".to_owned());
+        let removal = removal_cookie(&config);
+        assert_eq!(removal.name(), cookie.name());
+        assert_eq!(removal.path(), cookie.path());
+        assert_eq!(removal.domain(), cookie.domain());
+        assert_eq!(removal.secure(), cookie.secure());
+        assert_eq!(removal.same_site(), cookie.same_site());
+        assert_eq!(removal.value(), "");
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_valid_token() {
+        let token = generate_auth_token(&user("test@example.com"), None).unwrap();
+        let claims = validate_token(&token).await.unwrap();
+        assert_eq!(claims.sub, "test@example.com");
+        assert!(claims.exp > claims.iat);
+        assert_eq!(claims.scope, None);
+        assert!(claims.is_user_token());
+        assert_eq!(claims.roles, vec![Role::User]);
+        assert!(claims.permissions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_auth_token_carries_permissions_of_roles() {
+        let admin = user("admin@example.com").with_roles(vec![Role::User, Role::Admin]);
+        let claims = validate_token(&generate_auth_token(&admin, None).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(claims.roles, vec![Role::User, Role::Admin]);
+        assert!(claims.has_permission("users:read"));
+        assert!(claims.has_permission("users:write"));
+        assert!(!claims.has_permission("users:delete"));
+    }
+
+    #[test]
+    fn test_api_key_claims_are_limited_to_the_scope() {
+        let admin = user("admin@example.com").with_roles(vec![Role::User, Role::Admin]);
+        let unscoped = ApiKey::new(
+            "a".to_owned(),
+            admin.id(),
+            "all".to_owned(),
+            "s",
+            None,
+            None,
+        );
+        let claims = api_key_claims(&admin, &unscoped).unwrap();
+        assert_eq!(claims.jti, "a");
+        assert_eq!(claims.permissions, vec!["users:read", "users:write"]);
+
+        let scoped = ApiKey::new(
+            "b".to_owned(),
+            admin.id(),
+            "read-only".to_owned(),
+            "s",
+            Some(Scope::parse("users:read")),
+            None,
+        );
+        let claims = api_key_claims(&admin, &scoped).unwrap();
+        assert_eq!(claims.permissions, vec!["users:read"]);
+        assert_eq!(claims.scope.as_deref(), Some("users:read"));
+    }
+
+    #[tokio::test]
+    async fn test_access_tokens_do_not_carry_permissions() {
+        let email = Email::from_str("admin@example.com").unwrap();
+        let token =
+            generate_access_token(&email.into(), Some(&Scope::parse("openid")), Some("client"))
+                .unwrap();
+        let claims = validate_token(&token).await.unwrap();
+        assert!(claims.roles.is_empty());
+        assert!(claims.permissions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_client_tokens_are_distinguishable() {
+        let token = generate_client_token("service", &Scope::parse("users:read")).unwrap();
+        let claims = validate_token(&token).await.unwrap();
+        assert!(claims.is_client_token());
+        assert_eq!(claims.sub, "service");
+        assert_eq!(claims.client_id.as_deref(), Some("service"));
+        assert_eq!(claims.scope.as_deref(), Some("users:read"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_invalid_token() {
+        assert!(validate_token("invalid_token").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tokens_are_unique() {
+        let user = user("test@example.com");
+        assert_ne!(
+            generate_auth_token(&user, None).unwrap(),
+            generate_auth_token(&user, None).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_opaque_tokens_are_url_safe() {
+        let token = generate_opaque_token().unwrap();
+        assert_eq!(token.len(), 43);
+        assert!(token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    }
+
+    #[test]
+    fn test_invitation_tokens_are_signed() {
+        let token = sign_invitation("invitation-id");
+        assert_eq!(verify_invitation_token(&token), Some("invitation-id"));
+        let forged = token.replacen("invitation-id", "other-id", 1);
+        assert_eq!(verify_invitation_token(&forged), None);
+        assert_eq!(verify_invitation_token("invitation-id"), None);
+    }
+
+    #[test]
+    fn test_email_verification_tokens_are_signed_and_expire() {
+        let id = UserId::new(Some("acme".to_owned()), Email::from_str("a@b.com").unwrap());
+        let token = sign_email_verification(&id, Utc::now() + chrono::Duration::minutes(1));
+        assert_eq!(verify_email_verification_token(&token), Some(id.clone()));
+        let (payload, signature) = token.split_once('.').unwrap();
+        let other = UserId::from(Email::from_str("a@b.com").unwrap());
+        let other = sign_email_verification(&other, Utc::now() + chrono::Duration::minutes(1));
+        let forged = format!("{}.{}", other.split_once('.').unwrap().0, signature);
+        assert_eq!(verify_email_verification_token(&forged), None);
+        assert_eq!(verify_email_verification_token(payload), None);
+        // Invitation signatures do not pass for verification links.
+        assert_eq!(
+            verify_email_verification_token(&sign_invitation(payload)),
+            None
+        );
+
+        let expired = sign_email_verification(&id, Utc::now() - chrono::Duration::seconds(1));
+        assert_eq!(verify_email_verification_token(&expired), None);
+    }
+
+    #[test]
+    fn test_2fa_codes_have_six_digits() {
+        let code = generate_2fa_code().unwrap();
+        assert_eq!(code.len(), 6);
+        assert!(code.chars().all(|c| c.is_ascii_digit()));
     }
 }
@@ -0,0 +1,53 @@
+use crate::domain::{OAuthClient, OAuthError};
+use crate::AppState;
+use axum::http::{header, HeaderMap};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+// Credentials sent with HTTP Basic authentication (client_secret_basic)
+pub(crate) fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let encoded = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (client_id, client_secret) = decoded.split_once(':')?;
+    Some((client_id.to_owned(), client_secret.to_owned()))
+}
+
+/// Authenticates the OAuth client making a request to one of the OAuth endpoints.
+///
+/// Confidential clients must present their secret, either with HTTP Basic authentication or
+/// in the request body. Public clients identify themselves with their `client_id` only.
+pub async fn authenticate_client(
+    state: &AppState,
+    headers: &HeaderMap,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+) -> Result<OAuthClient, OAuthError> {
+    let (client_id, client_secret) = match basic_credentials(headers) {
+        Some((client_id, client_secret)) => (client_id, Some(client_secret)),
+        None => (
+            client_id.ok_or(OAuthError::InvalidClient)?.to_owned(),
+            client_secret.map(str::to_owned),
+        ),
+    };
+
+    let client = state
+        .oauth_clients
+        .read()
+        .await
+        .get_client(&client_id)
+        .await
+        .map_err(|_| OAuthError::InvalidClient)?;
+
+    if client.is_confidential() {
+        match client_secret {
+            Some(client_secret) if client.verify_secret(&client_secret) => {}
+            _ => return Err(OAuthError::InvalidClient),
+        }
+    }
+
+    Ok(client)
+}
@@ -2,7 +2,16 @@ use dotenvy::dotenv;
 use lazy_static::lazy_static;
 use std::env as std_env;
 
-pub const JWT_COOKIE_NAME: &str = "jwt";
+pub use auth_token::JWT_COOKIE_NAME;
+pub const CSRF_COOKIE_NAME: &str = "csrf_token";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+pub const TOKEN_TTL_SECONDS: i64 = 600; // 10 minutes
+pub const AUTHORIZATION_CODE_TTL_SECONDS: i64 = 60;
+pub const TWO_FA_CODE_TTL_SECONDS: i64 = 600; // 10 minutes
+pub const REFRESH_TOKEN_TTL_SECONDS: i64 = 1_209_600; // 14 days
+pub const INVITATION_TTL_SECONDS: i64 = 604_800; // 7 days
+pub const EMAIL_VERIFICATION_TTL_SECONDS: i64 = 172_800; // 2 days
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
 
 // Define a lazily evaluated static. lazy_static is needed because std_env::var is not a const function.
 lazy_static! {
@@ -19,7 +28,29 @@ fn set_token() -> String {
 }
 
 pub mod env {
+    pub use auth_token::env::{AUTH_TOKEN_PRECEDENCE_ENV_VAR, COOKIE_HOST_PREFIX_ENV_VAR};
+
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
+    pub const OIDC_ISSUER_ENV_VAR: &str = "OIDC_ISSUER";
+    pub const OIDC_SIGNING_KEY_ENV_VAR: &str = "OIDC_SIGNING_KEY";
+    pub const CLIENT_REGISTRATION_TOKEN_ENV_VAR: &str = "CLIENT_REGISTRATION_TOKEN";
+    pub const METRICS_TOKEN_ENV_VAR: &str = "METRICS_TOKEN";
+    pub const ADMIN_EMAILS_ENV_VAR: &str = "ADMIN_EMAILS";
+    pub const EMAIL_UNIQUENESS_ENV_VAR: &str = "EMAIL_UNIQUENESS";
+    pub const AUDIT_LOG_PATH_ENV_VAR: &str = "AUDIT_LOG_PATH";
+    pub const AUDIT_DATABASE_PATH_ENV_VAR: &str = "AUDIT_DATABASE_PATH";
+    pub const WEBHOOK_DATABASE_PATH_ENV_VAR: &str = "WEBHOOK_DATABASE_PATH";
+    pub const OTEL_EXPORTER_OTLP_ENDPOINT_ENV_VAR: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+    pub const SHUTDOWN_TIMEOUT_SECS_ENV_VAR: &str = "SHUTDOWN_TIMEOUT_SECS";
+    pub const TLS_CERT_PATH_ENV_VAR: &str = "TLS_CERT_PATH";
+    pub const TLS_KEY_PATH_ENV_VAR: &str = "TLS_KEY_PATH";
+    pub const TLS_REDIRECT_ADDRESS_ENV_VAR: &str = "TLS_REDIRECT_ADDRESS";
+    pub const COOKIE_HTTP_ONLY_ENV_VAR: &str = "COOKIE_HTTP_ONLY";
+    pub const COOKIE_SECURE_ENV_VAR: &str = "COOKIE_SECURE";
+    pub const COOKIE_SAME_SITE_ENV_VAR: &str = "COOKIE_SAME_SITE";
+    pub const COOKIE_PATH_ENV_VAR: &str = "COOKIE_PATH";
+    pub const COOKIE_DOMAIN_ENV_VAR: &str = "COOKIE_DOMAIN";
+    pub const ERROR_FORMAT_ENV_VAR: &str = "ERROR_FORMAT";
 }
 
 pub mod prod {
@@ -0,0 +1,140 @@
+use super::auth::session_cookie;
+use super::constants::{CSRF_HEADER, JWT_SECRET, TOKEN_TTL_SECONDS};
+use super::extractors::{AuthToken, TokenSource};
+use crate::domain::AuthAPIError;
+use crate::{AppConfig, AppState};
+use axum::extract::{Request, State};
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum_extra::extract::cookie::Cookie;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::hmac;
+
+// Routes that never act on the session of the auth cookie: they start one, or authenticate
+// the caller some other way, or only read the token.
+const EXEMPT_PATHS: &[&str] = &[
+    "/signup",
+    "/login",
+    "/change-password",
+    "/verify-2fa",
+    "/verify-email",
+    "/verify-token",
+    "/token",
+    "/clients",
+    "/introspect",
+    "/revoke",
+    "/userinfo",
+    "/invitations/accept",
+];
+
+/// Rejects requests that change state on behalf of the auth cookie unless they carry the
+/// session's CSRF token in the `X-CSRF-Token` header. Only pages that can read the CSRF
+/// cookie know the token, which a cross-site form or script cannot.
+///
+/// Requests authenticated with an `Authorization: Bearer` header are let through: browsers
+/// never add one on their own.
+pub async fn verify_csrf(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if request.method().is_safe() || EXEMPT_PATHS.contains(&request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let auth_token = AuthToken::from_headers(
+        request.headers(),
+        state.config.token_precedence,
+        state.config.cookies.name(),
+    );
+    if let Some(AuthToken {
+        token,
+        source: TokenSource::Cookie,
+    }) = auth_token
+    {
+        if !has_csrf_token(request.headers(), &token) {
+            return AuthAPIError::InvalidCsrfToken.into_response();
+        }
+    }
+    next.run(request).await
+}
+
+fn has_csrf_token(headers: &HeaderMap, auth_token: &str) -> bool {
+    headers
+        .get(CSRF_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|csrf_token| verify_csrf_token(auth_token, csrf_token))
+}
+
+/// The CSRF token of the session `auth_token` stands for. It is derived from the auth token,
+/// so it changes with every login and is worthless for any other session.
+pub fn csrf_token(auth_token: &str) -> String {
+    let signature = hmac::sign(&csrf_key(), auth_token.as_bytes());
+    URL_SAFE_NO_PAD.encode(signature.as_ref())
+}
+
+fn verify_csrf_token(auth_token: &str, csrf_token: &str) -> bool {
+    URL_SAFE_NO_PAD
+        .decode(csrf_token)
+        .is_ok_and(|signature| hmac::verify(&csrf_key(), auth_token.as_bytes(), &signature).is_ok())
+}
+
+fn csrf_key() -> hmac::Key {
+    // Domain-separated from the JWT signatures that use the same secret.
+    let mut secret = b"csrf:".to_vec();
+    secret.extend_from_slice(JWT_SECRET.as_bytes());
+    hmac::Key::new(hmac::HMAC_SHA256, &secret)
+}
+
+/// The cookie carrying the CSRF token of the session `auth_token` stands for, set next to
+/// the auth cookie. Unlike that one, scripts can read it to send the token back.
+pub fn csrf_cookie(config: &AppConfig, auth_token: &str) -> Cookie<'static> {
+    let mut cookie = session_cookie(config, config.cookies.csrf_name(), csrf_token(auth_token));
+    cookie.set_http_only(false);
+    cookie.set_max_age(time::Duration::seconds(TOKEN_TTL_SECONDS));
+    cookie
+}
+
+/// The cookie to pass to `CookieJar::remove` to clear the CSRF cookie along with the auth
+/// cookie.
+pub fn csrf_removal_cookie(config: &AppConfig) -> Cookie<'static> {
+    let mut cookie = session_cookie(config, config.cookies.csrf_name(), String::new());
+    cookie.set_http_only(false);
+    cookie
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::CSRF_COOKIE_NAME;
+    use crate::CookieSettings;
+    use axum::http::HeaderValue;
+
+    fn headers(csrf_token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CSRF_HEADER, HeaderValue::from_str(csrf_token).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_csrf_token_only_matches_its_session() {
+        let token = csrf_token("session-a");
+        assert!(has_csrf_token(&headers(&token), "session-a"));
+        assert!(!has_csrf_token(&headers(&token), "session-b"));
+        assert!(!has_csrf_token(&headers("not base64!"), "session-a"));
+        assert!(!has_csrf_token(&HeaderMap::new(), "session-a"));
+    }
+
+    #[test]
+    fn test_csrf_cookie_is_readable_by_scripts() {
+        let cookie = csrf_cookie(&AppConfig::default(), "session");
+        assert_eq!(cookie.name(), CSRF_COOKIE_NAME);
+        assert_eq!(cookie.value(), csrf_token("session"));
+        assert_eq!(cookie.http_only(), Some(false));
+        assert_eq!(cookie.path(), Some("/"));
+
+        let settings = CookieSettings::default().with_host_prefix();
+        let config = AppConfig::default().with_cookie_settings(settings);
+        let cookie = csrf_cookie(&config, "session");
+        assert_eq!(cookie.name(), "__Host-csrf_token");
+        assert_eq!(cookie.secure(), Some(true));
+    }
+}
@@ -0,0 +1,290 @@
+use super::api_key::{authenticate_api_key, is_api_key};
+use super::auth::{validate_token, Claims};
+use super::constants::REQUEST_ID_HEADER;
+use super::problem::Problem;
+use crate::domain::{AuthAPIError, Permission, UserStoreError};
+use crate::AppState;
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{
+    ConnectInfo, FromRequest, FromRequestParts, OptionalFromRequest, OptionalFromRequestParts,
+    Request,
+};
+use axum::http::request::Parts;
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use std::convert::Infallible;
+use std::marker::PhantomData;
+use std::net::{IpAddr, SocketAddr};
+use uuid::Uuid;
+
+// Shared with app-service, which looks for the token the same way.
+pub use auth_token::{AuthToken, TokenPrecedence, TokenSource};
+
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+impl FromRequestParts<AppState> for AuthToken {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        AuthToken::from_headers(
+            &parts.headers,
+            state.config.token_precedence,
+            state.config.cookies.name(),
+        )
+        .ok_or(AuthAPIError::MissingToken)
+    }
+}
+
+impl OptionalFromRequestParts<AppState> for AuthToken {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        Ok(AuthToken::from_headers(
+            &parts.headers,
+            state.config.token_precedence,
+            state.config.cookies.name(),
+        ))
+    }
+}
+
+/// A valid, non-revoked user token or API key. Rejects client tokens, which do not act for
+/// a user.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub token: AuthToken,
+    pub claims: Claims,
+}
+
+impl FromRequestParts<AppState> for AuthenticatedUser {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token =
+            <AuthToken as FromRequestParts<AppState>>::from_request_parts(parts, state).await?;
+
+        if is_api_key(&token.token) {
+            let claims = authenticate_api_key(state, &token.token).await?;
+            return Ok(AuthenticatedUser { token, claims });
+        }
+
+        let is_banned = state
+            .banned_tokens
+            .read()
+            .await
+            .is_token_banned(&token.token)
+            .await
+            .map_err(AuthAPIError::unexpected)?;
+        if is_banned {
+            return Err(AuthAPIError::InvalidToken);
+        }
+
+        let claims = validate_token(&token.token)
+            .await
+            .map_err(|_| AuthAPIError::InvalidToken)?;
+        if !claims.is_user_token() {
+            return Err(AuthAPIError::InvalidToken);
+        }
+        ensure_user_active(state, &claims).await?;
+
+        Ok(AuthenticatedUser { token, claims })
+    }
+}
+
+/// Fails unless the user a token was issued to still exists and is active. Tokens outlive a
+/// suspension until they expire, so this is checked on every use rather than at login only.
+pub(crate) async fn ensure_user_active(
+    state: &AppState,
+    claims: &Claims,
+) -> Result<(), AuthAPIError> {
+    let user_id = claims.user_id().ok_or(AuthAPIError::InvalidToken)?;
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&user_id)
+        .await
+        .map_err(|err| match err {
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            err => AuthAPIError::unexpected(err),
+        })?;
+    user.ensure_active()
+}
+
+/// An [`AuthenticatedUser`] whose token grants the permission `P`, e.g.
+/// `RequirePermission<UsersRead>`. Rejects everyone else with `AuthAPIError::Forbidden`.
+#[derive(Debug, Clone)]
+pub struct RequirePermission<P> {
+    pub user: AuthenticatedUser,
+    permission: PhantomData<P>,
+}
+
+impl<P> FromRequestParts<AppState> for RequirePermission<P>
+where
+    P: Permission + Send + Sync,
+{
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let user = AuthenticatedUser::from_request_parts(parts, state).await?;
+        if !user.claims.has_permission(P::NAME) {
+            return Err(AuthAPIError::Forbidden);
+        }
+
+        Ok(RequirePermission {
+            user,
+            permission: PhantomData,
+        })
+    }
+}
+
+/// Where a request came from, as far as we can tell. Both fields are best effort.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip: Option<IpAddr>,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        // Only present when the server was started with connect info, see `Application::build`.
+        let ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(address)| address.ip());
+
+        Ok(ClientInfo { user_agent, ip })
+    }
+}
+
+/// Identifies a request and where it came from, e.g. for audit records.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestContext {
+    /// The `X-Request-Id` sent by the caller, e.g. a proxy, or a generated one. Ids that are
+    /// too long or contain anything but printable ASCII are replaced, as they end up in logs.
+    pub request_id: String,
+    pub client: ClientInfo,
+}
+
+/// The `X-Request-Id` of a request, unless it is missing or one that [`RequestContext`]
+/// replaces.
+pub(crate) fn valid_request_id(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LENGTH
+                && id.chars().all(|c| c.is_ascii_graphic())
+        })
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for RequestContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let request_id = valid_request_id(&parts.headers)
+            .map(str::to_owned)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        let client = ClientInfo::from_request_parts(parts, state).await?;
+
+        Ok(RequestContext { request_id, client })
+    }
+}
+
+/// [`axum::Json`], rejecting bodies that do not parse with a [`Problem`] rather than plain
+/// text. Responds like [`axum::Json`] as well.
+#[derive(Debug, Clone, Copy, Default, FromRequest)]
+#[from_request(via(axum::Json), rejection(Problem))]
+pub struct Json<T>(pub T);
+
+/// Like for [`axum::Json`], the body is optional when there is no `Content-Type`.
+impl<T, S> OptionalFromRequest<S> for Json<T>
+where
+    axum::Json<T>: OptionalFromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = Problem;
+
+    async fn from_request(request: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        let json = <axum::Json<T> as OptionalFromRequest<S>>::from_request(request, state).await?;
+        Ok(json.map(|axum::Json(value)| Json(value)))
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+/// [`axum::extract::Path`], rejecting parameters that do not parse with a [`Problem`].
+#[derive(Debug, FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(Problem))]
+pub struct Path<T>(pub T);
+
+/// [`axum::extract::Query`], rejecting query strings that do not parse with a [`Problem`].
+#[derive(Debug, FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(Problem))]
+pub struct Query<T>(pub T);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    #[tokio::test]
+    async fn test_request_id_is_propagated_or_generated() {
+        let request_id = |value: Option<&str>| {
+            let mut request = Request::builder();
+            if let Some(value) = value {
+                request = request.header(REQUEST_ID_HEADER, value);
+            }
+            let mut parts = request.body(()).unwrap().into_parts().0;
+            async move {
+                RequestContext::from_request_parts(&mut parts, &())
+                    .await
+                    .unwrap()
+                    .request_id
+            }
+        };
+
+        assert_eq!(request_id(Some("abc-123")).await, "abc-123");
+        for value in [None, Some("a b"), Some(&"a".repeat(200))] {
+            let id = request_id(value).await;
+            assert!(Uuid::parse_str(&id).is_ok(), "{value:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        assert_eq!(
+            TokenPrecedence::parse("Bearer"),
+            Some(TokenPrecedence::BearerFirst)
+        );
+        assert_eq!(
+            TokenPrecedence::parse("cookie"),
+            Some(TokenPrecedence::CookieFirst)
+        );
+        assert_eq!(TokenPrecedence::parse("header"), None);
+    }
+}
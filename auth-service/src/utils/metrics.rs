@@ -0,0 +1,256 @@
+use crate::domain::AuthAPIError;
+use axum::extract::MatchedPath;
+use axum::http::{Request, Response, StatusCode};
+use prometheus::{
+    exponential_buckets, Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    Opts, Registry, TextEncoder,
+};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+
+/// The route label of requests that no route matched, e.g. for the static assets, so that
+/// arbitrary paths do not each get their own series.
+pub const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// The Prometheus metrics of one instance of the service.
+///
+/// Every instance has its own registry, so that apps running side by side, e.g. in tests,
+/// do not count each other's requests.
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    login_successes: IntCounter,
+    login_failures: IntCounterVec,
+    banned_tokens: IntGauge,
+    store_operation_duration: HistogramVec,
+}
+
+impl Metrics {
+    /// Creates a new `Metrics` instance with all metrics registered.
+    #[must_use]
+    pub fn new() -> Self {
+        let requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled"),
+            &["method", "route", "status"],
+        )
+        .expect("Invalid metric");
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time taken to handle HTTP requests",
+            ),
+            &["method", "route", "status"],
+        )
+        .expect("Invalid metric");
+        let login_successes = IntCounter::new("auth_login_successes_total", "Successful logins")
+            .expect("Invalid metric");
+        let login_failures = IntCounterVec::new(
+            Opts::new(
+                "auth_login_failures_total",
+                "Failed logins by the error code",
+            ),
+            &["reason"],
+        )
+        .expect("Invalid metric");
+        let banned_tokens =
+            IntGauge::new("auth_banned_tokens", "Tokens that are banned").expect("Invalid metric");
+        // Stores are mostly in memory, so the buckets go from 10µs up to about a second.
+        let store_operation_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "auth_store_operation_duration_seconds",
+                "Time taken by store operations",
+            )
+            .buckets(exponential_buckets(0.000_01, 4.0, 9).expect("Invalid buckets")),
+            &["store", "operation"],
+        )
+        .expect("Invalid metric");
+
+        let registry = Registry::new();
+        for collector in [
+            Box::new(requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(request_duration.clone()),
+            Box::new(login_successes.clone()),
+            Box::new(login_failures.clone()),
+            Box::new(banned_tokens.clone()),
+            Box::new(store_operation_duration.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("Metric registered twice");
+        }
+
+        Self {
+            registry,
+            requests,
+            request_duration,
+            login_successes,
+            login_failures,
+            banned_tokens,
+            store_operation_duration,
+        }
+    }
+
+    pub fn observe_request(
+        &self,
+        method: &str,
+        route: &str,
+        status: StatusCode,
+        elapsed: Duration,
+    ) {
+        let labels = [method, route, status.as_str()];
+        self.requests.with_label_values(&labels).inc();
+        self.request_duration
+            .with_label_values(&labels)
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn login_succeeded(&self) {
+        self.login_successes.inc();
+    }
+
+    pub fn login_failed(&self, err: &AuthAPIError) {
+        self.login_failures.with_label_values(&[err.code()]).inc();
+    }
+
+    pub fn set_banned_tokens(&self, count: usize) {
+        self.banned_tokens
+            .set(i64::try_from(count).unwrap_or(i64::MAX));
+    }
+
+    /// Runs a store operation and records how long it took.
+    pub async fn time_store_operation<T>(
+        &self,
+        store: &str,
+        operation: &str,
+        future: impl Future<Output = T>,
+    ) -> T {
+        let start = Instant::now();
+        let output = future.await;
+        self.store_operation_duration
+            .with_label_values(&[store, operation])
+            .observe(start.elapsed().as_secs_f64());
+        output
+    }
+
+    /// Renders all metrics in the Prometheus text format.
+    pub fn encode(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        String::from_utf8(buffer).map_err(|err| prometheus::Error::Msg(err.to_string()))
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Counts and times every request by its method, route and status.
+///
+/// The route is the path pattern the request matched, e.g. `/sessions/{id}`, so it has to
+/// be added with `Router::layer` for the matched path to be known.
+#[derive(Clone)]
+pub struct MetricsLayer {
+    metrics: Arc<Metrics>,
+}
+
+impl MetricsLayer {
+    /// Creates a new `MetricsLayer` instance.
+    #[must_use]
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = MetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MetricsService {
+            inner,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MetricsService<S> {
+    inner: S,
+    metrics: Arc<Metrics>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for MetricsService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let method = request.method().clone();
+        let route = request
+            .extensions()
+            .get::<MatchedPath>()
+            .map_or(UNMATCHED_ROUTE, MatchedPath::as_str)
+            .to_owned();
+        let metrics = self.metrics.clone();
+        let start = Instant::now();
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await?;
+            metrics.observe_request(method.as_str(), &route, response.status(), start.elapsed());
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encodes_login_failures_by_reason() {
+        let metrics = Metrics::new();
+        metrics.login_failed(&AuthAPIError::IncorrectCredentials);
+        metrics.login_failed(&AuthAPIError::IncorrectCredentials);
+        metrics.login_failed(&AuthAPIError::AccountSuspended);
+        metrics.login_succeeded();
+
+        let text = metrics.encode().unwrap();
+        let incorrect = format!(
+            "auth_login_failures_total{{reason=\"{}\"}} 2",
+            AuthAPIError::IncorrectCredentials.code()
+        );
+        assert!(text.contains(&incorrect), "{}", text);
+        assert!(text.contains("auth_login_successes_total 1"), "{}", text);
+    }
+
+    #[tokio::test]
+    async fn test_times_store_operations() {
+        let metrics = Metrics::new();
+        let value = metrics
+            .time_store_operation("users", "get_user", async { 42 })
+            .await;
+        assert_eq!(value, 42);
+        let text = metrics.encode().unwrap();
+        assert!(
+            text.contains(
+                "auth_store_operation_duration_seconds_count{operation=\"get_user\",store=\"users\"} 1"
+            ),
+            "{}",
+            text
+        );
+    }
+}
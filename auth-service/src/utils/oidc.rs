@@ -0,0 +1,160 @@
+use super::constants::{env, TOKEN_TTL_SECONDS};
+use crate::domain::OAuthError;
+use crate::AppConfig;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use chrono::Utc;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse,
+};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use lazy_static::lazy_static;
+use ring::digest::{digest, SHA256};
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde::{Deserialize, Serialize};
+use std::env as std_env;
+
+lazy_static! {
+    pub static ref ID_TOKEN_SIGNER: IdTokenSigner = IdTokenSigner::from_env();
+}
+
+/// Claims of an OpenID Connect ID token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+}
+
+impl IdTokenClaims {
+    pub fn new(issuer: String, subject: String, audience: String, nonce: Option<String>) -> Self {
+        let iat = Utc::now().timestamp();
+        Self {
+            iss: issuer,
+            sub: subject,
+            aud: audience,
+            exp: (iat + TOKEN_TTL_SECONDS) as usize,
+            iat: iat as usize,
+            nonce,
+            email: None,
+            email_verified: None,
+        }
+    }
+}
+
+/// Signs ID tokens with an Ed25519 key whose public half is published at the JWKS endpoint.
+///
+/// The key is read from `OIDC_SIGNING_KEY` (base64 encoded PKCS#8 document). When it is not set
+/// a key is generated at startup, which is fine for a single instance but means relying parties
+/// will reject ID tokens issued before a restart.
+pub struct IdTokenSigner {
+    key_id: String,
+    encoding_key: EncodingKey,
+    public_key: Vec<u8>,
+}
+
+impl IdTokenSigner {
+    fn from_env() -> Self {
+        let pkcs8 = match std_env::var(env::OIDC_SIGNING_KEY_ENV_VAR) {
+            Ok(encoded) if !encoded.is_empty() => STANDARD
+                .decode(encoded.trim())
+                .expect("OIDC_SIGNING_KEY must be base64 encoded."),
+            _ => Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+                .expect("Failed to generate OIDC signing key.")
+                .as_ref()
+                .to_vec(),
+        };
+        Self::from_pkcs8(&pkcs8)
+    }
+
+    pub fn from_pkcs8(pkcs8: &[u8]) -> Self {
+        let key_pair =
+            Ed25519KeyPair::from_pkcs8(pkcs8).expect("OIDC_SIGNING_KEY must be an Ed25519 key.");
+        let public_key = key_pair.public_key().as_ref().to_vec();
+        let key_id = URL_SAFE_NO_PAD.encode(&digest(&SHA256, &public_key).as_ref()[..12]);
+
+        Self {
+            key_id,
+            encoding_key: EncodingKey::from_ed_der(pkcs8),
+            public_key,
+        }
+    }
+
+    pub fn sign(&self, claims: &IdTokenClaims) -> Result<String, jsonwebtoken::errors::Error> {
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(self.key_id.clone());
+        encode(&header, claims, &self.encoding_key)
+    }
+
+    pub fn jwk_set(&self) -> JwkSet {
+        JwkSet {
+            keys: vec![Jwk {
+                common: CommonParameters {
+                    public_key_use: Some(PublicKeyUse::Signature),
+                    key_algorithm: Some(KeyAlgorithm::EdDSA),
+                    key_id: Some(self.key_id.clone()),
+                    ..CommonParameters::default()
+                },
+                algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(&self.public_key),
+                }),
+            }],
+        }
+    }
+}
+
+// The configured issuer. It is never taken from the request, e.g. the `Host` header, which
+// would let callers choose the `iss` of ID tokens; `Application::build` requires it.
+pub fn issuer(config: &AppConfig) -> Result<&str, OAuthError> {
+    config.oidc_issuer.as_deref().ok_or(OAuthError::ServerError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{decode, decode_header, DecodingKey, Validation};
+
+    #[test]
+    fn test_id_token_verifies_with_published_jwk() {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let signer = IdTokenSigner::from_pkcs8(pkcs8.as_ref());
+        let claims = IdTokenClaims::new(
+            "http://localhost:3000".to_owned(),
+            "test@example.com".to_owned(),
+            "client".to_owned(),
+            Some("nonce".to_owned()),
+        );
+        let token = signer.sign(&claims).unwrap();
+
+        let kid = decode_header(&token).unwrap().kid.unwrap();
+        let jwks = signer.jwk_set();
+        let jwk = jwks.find(&kid).expect("signing key should be published");
+
+        let mut validation = Validation::new(Algorithm::EdDSA);
+        validation.set_audience(&["client"]);
+        validation.set_issuer(&["http://localhost:3000"]);
+        let decoded =
+            decode::<IdTokenClaims>(&token, &DecodingKey::from_jwk(jwk).unwrap(), &validation)
+                .unwrap();
+        assert_eq!(decoded.claims, claims);
+    }
+
+    #[test]
+    fn test_issuer_is_not_guessed() {
+        assert_eq!(issuer(&AppConfig::default()), Err(OAuthError::ServerError));
+        let config = AppConfig::default().with_oidc_issuer("https://auth.example.com/");
+        assert_eq!(issuer(&config), Ok("https://auth.example.com"));
+    }
+}
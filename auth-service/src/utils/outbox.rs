@@ -0,0 +1,226 @@
+use crate::app_state::{OutboxSettings, UserStoreType};
+use crate::domain::{EventHandler, OutboxEntry, UserStoreError};
+use crate::utils::shutdown::ShutdownHandle;
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+const EVENTS_PER_POLL: usize = 100;
+
+/// Starts handing the events in the outbox of the user store to `handlers` in the
+/// background until `shutdown` is triggered, after which the events that are due are
+/// handed over one last time. Events that still fail after `settings.max_attempts` are
+/// parked.
+pub fn spawn_dispatcher(
+    users: UserStoreType,
+    handlers: Vec<Arc<dyn EventHandler>>,
+    settings: OutboxSettings,
+    shutdown: ShutdownHandle,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(settings.poll_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            let stopping = tokio::select! {
+                _ = interval.tick() => false,
+                () = shutdown.wait() => true,
+            };
+            if let Err(err) = dispatch_pending(&users, &handlers, &settings).await {
+                tracing::error!(error = ?err, "Failed to dispatch domain events");
+            }
+            if stopping {
+                return;
+            }
+        }
+    })
+}
+
+async fn dispatch_pending(
+    users: &UserStoreType,
+    handlers: &[Arc<dyn EventHandler>],
+    settings: &OutboxSettings,
+) -> Result<(), UserStoreError> {
+    let pending = users
+        .read()
+        .await
+        .pending_events(Utc::now(), EVENTS_PER_POLL)
+        .await?;
+    for entry in pending {
+        // The store is not locked while the handlers run.
+        let attempts = entry.attempts + 1;
+        if handle(handlers, &entry).await {
+            users.write().await.complete_event(&entry.id).await?;
+        } else if attempts >= settings.max_attempts {
+            tracing::error!(event_id = %entry.id, attempts, "Parked domain event after its last attempt");
+            users
+                .write()
+                .await
+                .park_event(&entry.id, Utc::now())
+                .await?;
+        } else {
+            let next_attempt_at = Utc::now() + backoff_delay(settings.backoff, attempts);
+            users
+                .write()
+                .await
+                .retry_event(&entry.id, next_attempt_at)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+// Every handler gets the entry, even after one of them failed, so that one broken handler
+// does not hold up the others more than the retries do.
+async fn handle(handlers: &[Arc<dyn EventHandler>], entry: &OutboxEntry) -> bool {
+    let mut handled = true;
+    for handler in handlers {
+        if let Err(err) = handler.handle(entry).await {
+            tracing::warn!(
+                handler = handler.name(),
+                event_id = %entry.id,
+                error = ?err,
+                "Event handler failed"
+            );
+            handled = false;
+        }
+    }
+    handled
+}
+
+/// The wait before retry number `attempts`: `base` doubled for each retry before it, up to
+/// an hour.
+pub(crate) fn backoff_delay(base: Duration, attempts: u32) -> chrono::Duration {
+    let delay = 2u32
+        .checked_pow(attempts.saturating_sub(1))
+        .and_then(|factor| base.checked_mul(factor))
+        .map_or(MAX_BACKOFF, |delay| delay.min(MAX_BACKOFF));
+    chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{DomainEvent, Email, EventHandlerError, Password, User};
+    use crate::services::HashmapUserStore;
+    use async_trait::async_trait;
+    use std::str::FromStr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use tokio::sync::RwLock;
+
+    // Fails the first `failures` events it gets and remembers the rest.
+    #[derive(Default)]
+    struct RecordingHandler {
+        failures: AtomicUsize,
+        handled: Mutex<Vec<DomainEvent>>,
+    }
+
+    #[async_trait]
+    impl EventHandler for RecordingHandler {
+        fn name(&self) -> &'static str {
+            "recording"
+        }
+
+        async fn handle(&self, entry: &OutboxEntry) -> Result<(), EventHandlerError> {
+            if self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok()
+            {
+                return Err(EventHandlerError::UnexpectedError);
+            }
+            self.handled.lock().unwrap().push(entry.event.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_every_handler_succeeded() {
+        let users: UserStoreType = Arc::new(RwLock::new(Box::new(HashmapUserStore::new())));
+        let user = User::new(
+            Email::from_str("test@test.com").unwrap(),
+            Password::from_str("password").unwrap(),
+            false,
+        );
+        users.write().await.add_user(user.clone()).await.unwrap();
+
+        let flaky = Arc::new(RecordingHandler {
+            failures: AtomicUsize::new(1),
+            ..RecordingHandler::default()
+        });
+        let steady = Arc::new(RecordingHandler::default());
+        let handlers: Vec<Arc<dyn EventHandler>> = vec![flaky.clone(), steady.clone()];
+        let settings = OutboxSettings::new(Duration::from_millis(10), Duration::ZERO, 5);
+
+        dispatch_pending(&users, &handlers, &settings)
+            .await
+            .unwrap();
+        assert!(flaky.handled.lock().unwrap().is_empty());
+        let pending = users
+            .read()
+            .await
+            .pending_events(Utc::now(), 10)
+            .await
+            .unwrap();
+        assert_eq!(pending[0].attempts, 1);
+
+        dispatch_pending(&users, &handlers, &settings)
+            .await
+            .unwrap();
+        let created = DomainEvent::UserCreated { user: user.id() };
+        assert_eq!(*flaky.handled.lock().unwrap(), vec![created.clone()]);
+        // Delivered at least once: the handler that succeeded the first time got it again.
+        assert_eq!(
+            *steady.handled.lock().unwrap(),
+            vec![created.clone(), created]
+        );
+        let pending = users.read().await.pending_events(Utc::now(), 10).await;
+        assert_eq!(pending, Ok(vec![]));
+    }
+
+    #[tokio::test]
+    async fn test_parks_events_after_the_last_attempt() {
+        let users: UserStoreType = Arc::new(RwLock::new(Box::new(HashmapUserStore::new())));
+        let user = User::new(
+            Email::from_str("test@test.com").unwrap(),
+            Password::from_str("password").unwrap(),
+            false,
+        );
+        users.write().await.add_user(user.clone()).await.unwrap();
+
+        let broken = Arc::new(RecordingHandler {
+            failures: AtomicUsize::new(usize::MAX),
+            ..RecordingHandler::default()
+        });
+        let handlers: Vec<Arc<dyn EventHandler>> = vec![broken];
+        let settings = OutboxSettings::new(Duration::from_millis(10), Duration::ZERO, 3);
+
+        for _ in 0..5 {
+            dispatch_pending(&users, &handlers, &settings)
+                .await
+                .unwrap();
+        }
+        let users = users.read().await;
+        let pending = users.pending_events(Utc::now(), 10).await;
+        assert_eq!(pending, Ok(vec![]));
+        let parked = users.parked_events(10).await.unwrap();
+        assert_eq!(parked.len(), 1);
+        assert_eq!(
+            parked[0].event,
+            DomainEvent::UserCreated { user: user.id() }
+        );
+        assert_eq!(parked[0].attempts, 3);
+        assert!(parked[0].parked_at.is_some());
+    }
+
+    #[test]
+    fn test_backs_off_exponentially_up_to_an_hour() {
+        let base = Duration::from_secs(30);
+        assert_eq!(backoff_delay(base, 1), chrono::Duration::seconds(30));
+        assert_eq!(backoff_delay(base, 3), chrono::Duration::seconds(120));
+        assert_eq!(backoff_delay(base, 40), chrono::Duration::hours(1));
+    }
+}
@@ -0,0 +1,157 @@
+use super::constants::REQUEST_ID_HEADER;
+use crate::domain::FieldError;
+use crate::{AppState, ErrorFormat, ErrorResponse};
+use axum::extract::rejection::{JsonRejection, PathRejection, QueryRejection};
+use axum::extract::{Request, State};
+use axum::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+/// Prefixes the code of a problem to make its `type`, which is not meant to be dereferenced.
+pub const PROBLEM_TYPE_PREFIX: &str = "urn:auth-service:problem:";
+
+/// An error response as defined by RFC 9457, with the `code` of the error and the fields that
+/// failed validation as extensions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    /// The same as the end of `type`, but easier to match on.
+    pub code: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    /// The id of the request, which is also sent as `X-Request-Id`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl Problem {
+    pub fn new(
+        code: &str,
+        status: StatusCode,
+        title: impl Into<String>,
+        detail: impl Into<String>,
+    ) -> Self {
+        Self {
+            problem_type: format!("{}{}", PROBLEM_TYPE_PREFIX, code),
+            code: code.to_owned(),
+            title: title.into(),
+            status: status.as_u16(),
+            detail: detail.into(),
+            instance: None,
+            errors: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_errors(mut self, errors: Vec<FieldError>) -> Self {
+        self.errors = errors;
+        self
+    }
+}
+
+impl From<JsonRejection> for Problem {
+    fn from(rejection: JsonRejection) -> Self {
+        let (code, title) = match &rejection {
+            JsonRejection::JsonDataError(_) => ("invalid_body", "Invalid body"),
+            JsonRejection::MissingJsonContentType(_) => {
+                ("unsupported_media_type", "Unsupported media type")
+            }
+            _ => ("malformed_body", "Malformed body"),
+        };
+        Self::new(code, rejection.status(), title, rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for Problem {
+    fn from(rejection: PathRejection) -> Self {
+        let title = "Invalid path parameter";
+        Self::new(
+            "invalid_path",
+            rejection.status(),
+            title,
+            rejection.body_text(),
+        )
+    }
+}
+
+impl From<QueryRejection> for Problem {
+    fn from(rejection: QueryRejection) -> Self {
+        let title = "Invalid query";
+        Self::new(
+            "invalid_query",
+            rejection.status(),
+            title,
+            rejection.body_text(),
+        )
+    }
+}
+
+/// Answers requests with a method the route does not have.
+pub async fn method_not_allowed() -> Problem {
+    Problem::new(
+        "method_not_allowed",
+        StatusCode::METHOD_NOT_ALLOWED,
+        "Method not allowed",
+        "The resource does not support this method.",
+    )
+}
+
+impl IntoResponse for Problem {
+    /// The problem is kept in the extensions of the response, so that [`render_problems`] can
+    /// complete it.
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut response = (status, [(CONTENT_TYPE, PROBLEM_JSON)], Json(&self)).into_response();
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+/// Adds the id of the request to the problems returned by the handlers, or turns them into the
+/// legacy `{"error": "..."}` body if the service is configured so. Has to run inside
+/// [`assign_request_id`](super::telemetry::assign_request_id).
+pub async fn render_problems(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+    let mut response = next.run(request).await;
+    let Some(mut problem) = response.extensions_mut().remove::<Problem>() else {
+        return response;
+    };
+
+    let (mut parts, _) = response.into_parts();
+    let rendered = match state.config.error_format {
+        ErrorFormat::Problem => {
+            problem.instance = request_id;
+            problem.into_response()
+        }
+        ErrorFormat::Legacy => {
+            let legacy = parts
+                .extensions
+                .remove::<ErrorResponse>()
+                .unwrap_or(ErrorResponse {
+                    error: problem.title,
+                });
+            Json(legacy).into_response()
+        }
+    };
+    // Keeps the headers set along with the error, e.g. cookies.
+    let (rendered, body) = rendered.into_parts();
+    parts.headers.remove(CONTENT_LENGTH);
+    parts.headers.extend(rendered.headers);
+    Response::from_parts(parts, body)
+}
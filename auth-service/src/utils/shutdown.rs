@@ -0,0 +1,99 @@
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Tells a server or a background task to stop. Clones share the same signal.
+#[derive(Debug, Clone)]
+pub struct ShutdownHandle {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl ShutdownHandle {
+    /// Creates a new `ShutdownHandle` instance.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            sender: Arc::new(watch::Sender::new(false)),
+        }
+    }
+
+    /// Starts the shutdown. Calling it again does nothing.
+    pub fn shutdown(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Resolves once `shutdown` was called, right away if it already was.
+    pub async fn wait(&self) {
+        let mut receiver = self.sender.subscribe();
+        // Only fails once the sender is dropped, and `self` holds on to it.
+        let _ = receiver.wait_for(|stopped| *stopped).await;
+    }
+}
+
+impl Default for ShutdownHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Resolves when the process is asked to stop, with SIGINT (Ctrl+C) or, on Unix, SIGTERM as
+/// sent by `docker stop`.
+pub async fn os_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %err, "Failed to listen for SIGINT");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                tracing::error!(error = %err, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_wakes_every_waiter_once_shut_down() {
+        let handle = ShutdownHandle::new();
+        let waiter = tokio::spawn({
+            let handle = handle.clone();
+            async move { handle.wait().await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!waiter.is_finished());
+        assert!(!handle.is_shutdown());
+
+        handle.clone().shutdown();
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(handle.is_shutdown());
+        // Later waiters return right away.
+        tokio::time::timeout(Duration::from_secs(1), handle.wait())
+            .await
+            .unwrap();
+    }
+}
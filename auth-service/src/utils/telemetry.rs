@@ -0,0 +1,195 @@
+use super::constants::{env, REQUEST_ID_HEADER};
+use super::extractors::valid_request_id;
+use super::metrics::UNMATCHED_ROUTE;
+use axum::extract::{MatchedPath, Request};
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_http::HeaderExtractor;
+use opentelemetry_otlp::{ExporterBuildError, Protocol, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing::{Span, Subscriber};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// The filter used when `RUST_LOG` is not set.
+const DEFAULT_LOG_FILTER: &str = "info";
+const SERVICE_NAME: &str = "auth-service";
+
+/// Exports the spans that are still buffered when it is dropped.
+#[must_use = "spans are only exported while the guard is alive"]
+pub struct TelemetryGuard {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(err) = provider.shutdown() {
+                eprintln!("Failed to export the remaining spans: {}", err);
+            }
+        }
+    }
+}
+
+/// Logs to stdout as JSON lines, filtered by `RUST_LOG`, and exports spans over OTLP if
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` is set.
+///
+/// Every line carries the fields of the spans it was logged in, so the lines logged while
+/// handling a request have its `request_id`.
+pub fn init_tracing() -> TelemetryGuard {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
+    let provider = std::env::var(env::OTEL_EXPORTER_OTLP_ENDPOINT_ENV_VAR)
+        .ok()
+        .filter(|endpoint| !endpoint.is_empty())
+        .and_then(|endpoint| match tracer_provider(&endpoint) {
+            Ok(provider) => Some(provider),
+            Err(err) => {
+                eprintln!("Failed to set up the OTLP exporter: {}", err);
+                None
+            }
+        });
+    if let Err(err) = subscriber(std::io::stdout, filter, provider.as_ref()).try_init() {
+        eprintln!("Failed to install the tracing subscriber: {}", err);
+    }
+    TelemetryGuard { provider }
+}
+
+/// Exports spans in batches to the OTLP/HTTP collector at `endpoint`, e.g.
+/// `http://localhost:4318`, as JSON.
+pub fn tracer_provider(endpoint: &str) -> Result<SdkTracerProvider, ExporterBuildError> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_protocol(Protocol::HttpJson)
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder_empty()
+                .with_service_name(SERVICE_NAME)
+                .build(),
+        )
+        .build())
+}
+
+/// Writes JSON lines to `writer` and, given a provider, exports spans through it.
+pub fn subscriber<W>(
+    writer: W,
+    filter: EnvFilter,
+    provider: Option<&SdkTracerProvider>,
+) -> impl Subscriber + Send + Sync
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let logs = tracing_subscriber::fmt::layer()
+        .json()
+        .flatten_event(true)
+        .with_current_span(false)
+        .with_span_list(true)
+        .with_writer(writer);
+    let traces = provider
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME)));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(logs)
+        .with(traces)
+}
+
+/// Makes sure every request has a usable `X-Request-Id`, generating one if the caller did not
+/// send one, and returns it with the response so callers can quote it.
+pub async fn assign_request_id(mut request: Request, next: Next) -> Response {
+    let request_id = match valid_request_id(request.headers()) {
+        Some(id) => id.to_owned(),
+        None => Uuid::new_v4().to_string(),
+    };
+    // Generated ids and the ids that passed the checks are always valid header values.
+    let value = HeaderValue::from_str(&request_id).expect("Invalid request id");
+    request
+        .headers_mut()
+        .insert(REQUEST_ID_HEADER, value.clone());
+    let mut response = next.run(request).await;
+    response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    response
+}
+
+/// The span a request is handled in, continuing the trace of the caller if it sent a W3C
+/// `traceparent` header. Only the path is recorded, as query strings can carry codes and
+/// tokens.
+pub fn make_span<B>(request: &axum::http::Request<B>) -> Span {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_ROUTE, MatchedPath::as_str);
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let span = tracing::info_span!(
+        "request",
+        otel.name = format!("{} {}", request.method(), route),
+        otel.kind = "server",
+        method = %request.method(),
+        route,
+        path = request.uri().path(),
+        request_id,
+    );
+    let parent = TraceContextPropagator::new().extract(&HeaderExtractor(request.headers()));
+    // Fails only when nothing exports spans.
+    let _ = span.set_parent(parent);
+    span
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_logs_json_lines_with_the_request_id() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = subscriber(move || writer.clone(), EnvFilter::new("info"), None);
+        tracing::subscriber::with_default(subscriber, || {
+            let request = axum::http::Request::builder()
+                .uri("/login?next=secret")
+                .header(REQUEST_ID_HEADER, "abc-123")
+                .body(())
+                .unwrap();
+            let _span = make_span(&request).entered();
+            tracing::info!(code = "incorrect_credentials", "Request failed");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+        assert_eq!(line["message"], "Request failed");
+        assert_eq!(line["code"], "incorrect_credentials");
+        assert_eq!(line["spans"][0]["request_id"], "abc-123");
+        assert_eq!(line["spans"][0]["path"], "/login");
+        assert_eq!(line["spans"][0]["route"], UNMATCHED_ROUTE);
+    }
+}
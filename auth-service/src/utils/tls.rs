@@ -0,0 +1,268 @@
+use crate::app_state::TlsSettings;
+use crate::utils::shutdown::ShutdownHandle;
+use axum::http::header::HOST;
+use axum::http::uri::{Authority, PathAndQuery};
+use axum::http::{HeaderMap, StatusCode, Uri};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::serve::Listener;
+use axum::Router;
+use rustls::crypto::{ring, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use rustls::ServerConfig;
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// Connections that finished their handshake but were not picked up by the server yet.
+const HANDSHAKE_BACKLOG: usize = 128;
+
+/// Hands out the certificate that was loaded last, so that a renewed one is used for new
+/// connections without a restart.
+#[derive(Debug)]
+pub struct CertificateResolver {
+    key: RwLock<Arc<CertifiedKey>>,
+}
+
+impl CertificateResolver {
+    /// Creates a new `CertificateResolver` instance.
+    #[must_use]
+    pub fn new(key: CertifiedKey) -> Self {
+        Self {
+            key: RwLock::new(Arc::new(key)),
+        }
+    }
+
+    pub fn set(&self, key: CertifiedKey) {
+        *self.key.write().expect("Certificate lock poisoned") = Arc::new(key);
+    }
+}
+
+impl ResolvesServerCert for CertificateResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.key.read().expect("Certificate lock poisoned").clone())
+    }
+}
+
+/// Reads the PEM encoded certificate chain and private key, and checks that they belong
+/// together.
+pub fn load_certified_key(cert_path: &Path, key_path: &Path) -> io::Result<CertifiedKey> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+        .map_err(|err| invalid_data(format!("{}: {}", cert_path.display(), err)))?;
+    if certs.is_empty() {
+        return Err(invalid_data(format!(
+            "{}: no certificate found",
+            cert_path.display()
+        )));
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|err| invalid_data(format!("{}: {}", key_path.display(), err)))?;
+    CertifiedKey::from_der(certs, key, &provider()).map_err(invalid_data)
+}
+
+fn provider() -> CryptoProvider {
+    ring::default_provider()
+}
+
+fn invalid_data(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// Accepts TLS connections for `axum::serve`. Handshakes happen in the background, so that a
+/// slow client does not hold up the others.
+pub struct TlsListener {
+    local_addr: SocketAddr,
+    connections: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+}
+
+impl TlsListener {
+    /// Creates a new `TlsListener` instance.
+    pub fn new(listener: TcpListener, resolver: Arc<CertificateResolver>) -> io::Result<Self> {
+        let mut config = ServerConfig::builder_with_provider(Arc::new(provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(invalid_data)?
+            .with_no_client_auth()
+            .with_cert_resolver(resolver);
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+        let local_addr = listener.local_addr()?;
+        let (sender, connections) = mpsc::channel(HANDSHAKE_BACKLOG);
+        tokio::spawn(accept_connections(
+            listener,
+            TlsAcceptor::from(Arc::new(config)),
+            sender,
+        ));
+        Ok(Self {
+            local_addr,
+            connections,
+        })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            // Only happens once the listener is dropped, i.e. never while it is used.
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+// Runs until the `TlsListener` is dropped, closing the socket with it.
+async fn accept_connections(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    sender: mpsc::Sender<(TlsStream<TcpStream>, SocketAddr)>,
+) {
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            () = sender.closed() => return,
+        };
+        let (stream, peer) = match accepted {
+            Ok(connection) => connection,
+            Err(err) => {
+                // E.g. too many open files; retrying right away would spin.
+                tracing::warn!(error = %err, "Failed to accept connection");
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => {
+                    let _ = sender.send((stream, peer)).await;
+                }
+                Ok(Err(err)) => tracing::debug!(error = %err, %peer, "TLS handshake failed"),
+                Err(_) => tracing::debug!(%peer, "TLS handshake timed out"),
+            }
+        });
+    }
+}
+
+/// Loads the certificate again whenever its files change, until `shutdown` is triggered. A
+/// certificate that fails to load is logged and the previous one kept, so that the key can be
+/// replaced after the certificate.
+pub fn spawn_reloader(
+    resolver: Arc<CertificateResolver>,
+    settings: TlsSettings,
+    shutdown: ShutdownHandle,
+) -> JoinHandle<()> {
+    // Read right away rather than in the task, which may only start after the files changed.
+    let mut loaded = read_files(&settings);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(settings.reload_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                () = shutdown.wait() => return,
+            }
+            let current = read_files(&settings);
+            if current == loaded {
+                continue;
+            }
+            match load_certified_key(&settings.cert_path, &settings.key_path) {
+                Ok(key) => {
+                    resolver.set(key);
+                    loaded = current;
+                    tracing::info!("Reloaded the TLS certificate");
+                }
+                Err(err) => {
+                    tracing::error!(error = %err, "Failed to reload the TLS certificate");
+                }
+            }
+        }
+    })
+}
+
+// Compared by content, as modification times can be too coarse to tell two writes apart.
+// Follows symlinks, so a certificate swapped in by re-pointing one counts as changed.
+fn read_files(settings: &TlsSettings) -> Option<(Vec<u8>, Vec<u8>)> {
+    let cert = std::fs::read(&settings.cert_path).ok()?;
+    let key = std::fs::read(&settings.key_path).ok()?;
+    Some((cert, key))
+}
+
+/// Answers every request with a permanent redirect to the same URL over HTTPS on
+/// `https_port`.
+pub fn redirect_router(https_port: u16) -> Router {
+    Router::new().fallback(move |headers: HeaderMap, uri: Uri| async move {
+        redirect_to_https(&headers, &uri, https_port)
+    })
+}
+
+fn redirect_to_https(headers: &HeaderMap, uri: &Uri, https_port: u16) -> Response {
+    let Some(host) = headers
+        .get(HOST)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<Authority>().ok())
+    else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let authority = match https_port {
+        443 => host.host().to_owned(),
+        port => format!("{}:{}", host.host(), port),
+    };
+    let path = uri.path_and_query().map_or("/", PathAndQuery::as_str);
+    Redirect::permanent(&format!("https://{}{}", authority, path)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::header::LOCATION;
+    use axum::http::HeaderValue;
+
+    fn redirect(host: Option<&'static str>, uri: &str, https_port: u16) -> Response {
+        let mut headers = HeaderMap::new();
+        if let Some(host) = host {
+            headers.insert(HOST, HeaderValue::from_static(host));
+        }
+        redirect_to_https(&headers, &uri.parse().unwrap(), https_port)
+    }
+
+    #[test]
+    fn test_redirects_to_the_same_url_over_https() {
+        let response = redirect(Some("example.com:80"), "/login?next=%2F", 443);
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            response.headers()[LOCATION],
+            "https://example.com/login?next=%2F"
+        );
+
+        let response = redirect(Some("localhost:3080"), "/", 3000);
+        assert_eq!(response.headers()[LOCATION], "https://localhost:3000/");
+    }
+
+    #[test]
+    fn test_rejects_requests_without_a_valid_host() {
+        assert_eq!(redirect(None, "/", 443).status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            redirect(Some("evil.com/path"), "/", 443).status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use super::outbox::backoff_delay;
+use crate::app_state::{WebhookSettings, WebhookStoreType};
+use crate::domain::{
+    DeliveryAttempt, DeliveryStatus, DomainEvent, Email, EventHandler, EventHandlerError,
+    OutboxEntry, UserId, WebhookDelivery, WebhookEvent, WebhookEventType, WebhookStoreError,
+};
+use crate::utils::shutdown::ShutdownHandle;
+use crate::AppState;
+use async_trait::async_trait;
+use chrono::Utc;
+use ring::hmac;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+pub const WEBHOOK_ID_HEADER: &str = "webhook-id";
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "webhook-timestamp";
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "webhook-signature";
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+const DELIVERIES_PER_POLL: usize = 100;
+
+// Sign the timestamp along with the body, so that receivers can reject old deliveries that
+// are replayed to them.
+pub fn sign_payload(secret: &str, timestamp: i64, payload: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let signature = hmac::sign(&key, format!("{}.{}", timestamp, payload).as_bytes());
+    let hex: String = signature
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("v1={}", hex)
+}
+
+/// Puts the user lifecycle events in the outbox of every webhook of the user's namespace
+/// that subscribed to them.
+#[derive(Clone)]
+pub struct WebhookEventHandler {
+    webhooks: WebhookStoreType,
+}
+
+impl WebhookEventHandler {
+    /// Creates a new `WebhookEventHandler` instance.
+    #[must_use]
+    pub fn new(webhooks: WebhookStoreType) -> Self {
+        Self { webhooks }
+    }
+
+    async fn enqueue(
+        &self,
+        entry: &OutboxEntry,
+        event_type: WebhookEventType,
+        user: &UserId,
+        previous_email: Option<&Email>,
+    ) -> Result<(), WebhookStoreError> {
+        let subscribed: Vec<_> = self
+            .webhooks
+            .read()
+            .await
+            .get_webhooks(user.tenant.as_deref())
+            .await?
+            .into_iter()
+            .filter(|webhook| webhook.subscribes_to(event_type))
+            .collect();
+        if subscribed.is_empty() {
+            return Ok(());
+        }
+
+        let mut event = WebhookEvent {
+            created_at: entry.created_at,
+            ..WebhookEvent::new(entry.id.clone(), event_type, user)
+        };
+        event.data.previous_email = previous_email.map(|email| email.as_ref().to_owned());
+        let payload =
+            serde_json::to_string(&event).map_err(|_| WebhookStoreError::UnexpectedError)?;
+        // Ids derived from the event make handling it again a no-op for the webhooks that
+        // already have their delivery.
+        let deliveries = subscribed
+            .into_iter()
+            .map(|webhook| {
+                WebhookDelivery::new(
+                    format!("{}:{}", event.id, webhook.id),
+                    webhook.id,
+                    event.id.clone(),
+                    payload.clone(),
+                )
+            })
+            .collect();
+        self.webhooks
+            .write()
+            .await
+            .enqueue_deliveries(deliveries)
+            .await
+    }
+}
+
+#[async_trait]
+impl EventHandler for WebhookEventHandler {
+    fn name(&self) -> &'static str {
+        "webhooks"
+    }
+
+    async fn handle(&self, entry: &OutboxEntry) -> Result<(), EventHandlerError> {
+        let (event_type, user, previous_email) = match &entry.event {
+            DomainEvent::UserCreated { user } => (WebhookEventType::UserCreated, user, None),
+            DomainEvent::UserVerified { user } => (WebhookEventType::UserVerified, user, None),
+            DomainEvent::UserEmailChanged {
+                user,
+                previous_email,
+            } => (
+                WebhookEventType::UserEmailChanged,
+                user,
+                Some(previous_email),
+            ),
+            DomainEvent::UserDeleted { user } => (WebhookEventType::UserDeleted, user, None),
+        };
+        self.enqueue(entry, event_type, user, previous_email)
+            .await
+            .map_err(|_| EventHandlerError::UnexpectedError)
+    }
+}
+
+/// Starts making the queued deliveries in the background until `shutdown` is triggered, after
+/// which the deliveries that are due are made one last time.
+pub fn spawn_dispatcher(state: &AppState, shutdown: ShutdownHandle) -> JoinHandle<()> {
+    let webhooks = state.webhooks.clone();
+    let settings = state.config.webhooks;
+    tokio::spawn(async move {
+        let client = match reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            // Receivers are called from inside our network, so they must not send us elsewhere.
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+        {
+            Ok(client) => client,
+            Err(err) => {
+                tracing::error!(error = ?err, "Failed to create webhook client");
+                return;
+            }
+        };
+        let mut interval = tokio::time::interval(settings.poll_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            let stopping = tokio::select! {
+                _ = interval.tick() => false,
+                () = shutdown.wait() => true,
+            };
+            if let Err(err) = dispatch_due(&webhooks, &client, &settings).await {
+                tracing::error!(error = ?err, "Failed to dispatch webhook deliveries");
+            }
+            if stopping {
+                return;
+            }
+        }
+    })
+}
+
+async fn dispatch_due(
+    webhooks: &WebhookStoreType,
+    client: &reqwest::Client,
+    settings: &WebhookSettings,
+) -> Result<(), WebhookStoreError> {
+    let due = webhooks
+        .read()
+        .await
+        .due_deliveries(Utc::now(), DELIVERIES_PER_POLL)
+        .await?;
+    for delivery in due {
+        // The store is not locked while waiting for the receiver.
+        let webhook = webhooks
+            .read()
+            .await
+            .get_webhook(&delivery.webhook_id)
+            .await;
+        let attempt = match webhook {
+            Ok(webhook) => deliver(client, &webhook.url, &webhook.secret, &delivery).await,
+            // Removed after the delivery was read from the outbox.
+            Err(WebhookStoreError::WebhookNotFound) => DeliveryAttempt {
+                error: Some("webhook was removed".to_owned()),
+                ..failed_attempt(&delivery)
+            },
+            Err(err) => return Err(err),
+        };
+        let delivery = after_attempt(delivery, &attempt, settings);
+        webhooks
+            .write()
+            .await
+            .record_attempt(delivery, attempt)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn deliver(
+    client: &reqwest::Client,
+    url: &str,
+    secret: &str,
+    delivery: &WebhookDelivery,
+) -> DeliveryAttempt {
+    let timestamp = Utc::now().timestamp();
+    let response = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(WEBHOOK_ID_HEADER, &delivery.event_id)
+        .header(WEBHOOK_TIMESTAMP_HEADER, timestamp.to_string())
+        .header(
+            WEBHOOK_SIGNATURE_HEADER,
+            sign_payload(secret, timestamp, &delivery.payload),
+        )
+        .body(delivery.payload.clone())
+        .send()
+        .await;
+    match response {
+        Ok(response) => DeliveryAttempt {
+            status_code: Some(response.status().as_u16()),
+            succeeded: response.status().is_success(),
+            ..failed_attempt(delivery)
+        },
+        Err(err) => DeliveryAttempt {
+            error: Some(err.to_string()),
+            ..failed_attempt(delivery)
+        },
+    }
+}
+
+fn failed_attempt(delivery: &WebhookDelivery) -> DeliveryAttempt {
+    DeliveryAttempt {
+        delivery_id: delivery.id.clone(),
+        webhook_id: delivery.webhook_id.clone(),
+        event_id: delivery.event_id.clone(),
+        at: Utc::now(),
+        status_code: None,
+        error: None,
+        succeeded: false,
+    }
+}
+
+// Retry with exponential backoff until the last attempt, then give up.
+fn after_attempt(
+    delivery: WebhookDelivery,
+    attempt: &DeliveryAttempt,
+    settings: &WebhookSettings,
+) -> WebhookDelivery {
+    let attempts = delivery.attempts + 1;
+    let status = if attempt.succeeded {
+        DeliveryStatus::Delivered
+    } else if attempts >= settings.max_attempts {
+        DeliveryStatus::Failed
+    } else {
+        DeliveryStatus::Pending
+    };
+    WebhookDelivery {
+        status,
+        attempts,
+        next_attempt_at: attempt.at + backoff_delay(settings.backoff, attempts),
+        ..delivery
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signs_timestamp_and_payload() {
+        let signature = sign_payload("secret", 1_700_000_000, "{}");
+        assert!(signature.starts_with("v1="));
+        assert_eq!(signature.len(), 3 + 64);
+        assert_eq!(signature, sign_payload("secret", 1_700_000_000, "{}"));
+        assert_ne!(signature, sign_payload("secret", 1_700_000_001, "{}"));
+        assert_ne!(signature, sign_payload("other", 1_700_000_000, "{}"));
+    }
+
+    #[test]
+    fn test_backs_off_exponentially_and_gives_up() {
+        let settings = WebhookSettings::new(Duration::from_secs(1), Duration::from_secs(30), 3);
+        let delivery = WebhookDelivery::new(
+            "1".to_owned(),
+            "hook".to_owned(),
+            "event".to_owned(),
+            "{}".to_owned(),
+        );
+        let attempt = failed_attempt(&delivery);
+
+        let delivery = after_attempt(delivery, &attempt, &settings);
+        assert_eq!(delivery.status, DeliveryStatus::Pending);
+        assert_eq!(
+            delivery.next_attempt_at - attempt.at,
+            chrono::Duration::seconds(30)
+        );
+        let delivery = after_attempt(delivery, &attempt, &settings);
+        assert_eq!(
+            delivery.next_attempt_at - attempt.at,
+            chrono::Duration::seconds(60)
+        );
+        let delivery = after_attempt(delivery, &attempt, &settings);
+        assert_eq!(delivery.status, DeliveryStatus::Failed);
+    }
+
+    #[test]
+    fn test_marks_successful_attempt_delivered() {
+        let delivery = WebhookDelivery::new(
+            "1".to_owned(),
+            "hook".to_owned(),
+            "event".to_owned(),
+            "{}".to_owned(),
+        );
+        let attempt = DeliveryAttempt {
+            status_code: Some(204),
+            succeeded: true,
+            ..failed_attempt(&delivery)
+        };
+        let delivery = after_attempt(delivery, &attempt, &WebhookSettings::default());
+        assert_eq!(delivery.status, DeliveryStatus::Delivered);
+        assert_eq!(delivery.attempts, 1);
+    }
+}
@@ -0,0 +1,291 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::{Role, UserStatus};
+use auth_service::routes::{AdminUser, ApiKeyResponse, PasswordResetResponse, UserListResponse};
+use auth_service::AppConfig;
+
+const ADMIN_EMAIL: &str = "admin@example.com";
+
+const CLIENT_ID: &str = "mobile-app";
+const REDIRECT_URI: &str = "com.example.app:/callback";
+
+// Starts an app where `ADMIN_EMAIL` is an admin and returns their token.
+async fn app_with_admin() -> (TestApp, String) {
+    let app = TestApp::with_config(AppConfig::default().with_admin_email(ADMIN_EMAIL)).await;
+    app.register_oauth_client(CLIENT_ID, REDIRECT_URI).await;
+    let token = app
+        .signup_verified_and_login(ADMIN_EMAIL, "password123")
+        .await;
+    (app, token)
+}
+
+async fn assert_token_is_revoked(app: &TestApp, token: &str) {
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+// Everything a user can authenticate with besides their password.
+struct Credentials {
+    token: String,
+    api_key: String,
+    refresh_token: String,
+}
+
+async fn signup_with_credentials(app: &TestApp, email: &str) -> Credentials {
+    let token = app.signup_and_login(email, "password123").await;
+    let response = app
+        .post_api_key(&token, &serde_json::json!({ "name": "ci" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let api_key: ApiKeyResponse = response.json().await.unwrap();
+    let tokens = app
+        .authorization_code_tokens(CLIENT_ID, REDIRECT_URI, "openid")
+        .await;
+    Credentials {
+        token,
+        api_key: api_key.key.unwrap(),
+        refresh_token: tokens.refresh_token.unwrap(),
+    }
+}
+
+async fn refresh(app: &TestApp, refresh_token: &str) -> reqwest::Response {
+    app.post_token(&serde_json::json!({
+        "grant_type": "refresh_token",
+        "refresh_token": refresh_token,
+        "client_id": CLIENT_ID,
+    }))
+    .await
+}
+
+async fn assert_credentials_are_revoked(app: &TestApp, credentials: &Credentials) {
+    assert_token_is_revoked(app, &credentials.token).await;
+    assert_token_is_revoked(app, &credentials.api_key).await;
+    let response = refresh(app, &credentials.refresh_token).await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn should_return_403_for_regular_users() {
+    let (app, _) = app_with_admin().await;
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    assert_eq!(app.get_admin("/users", &token).await.status().as_u16(), 403);
+    let path = format!("/users/{}/disable", ADMIN_EMAIL);
+    assert_eq!(app.post_admin(&path, &token).await.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn should_return_401_for_invalid_tokens() {
+    let (app, _) = app_with_admin().await;
+    let response = app.get_admin("/users", "invalid").await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_list_users_with_pagination_and_search() {
+    let (app, token) = app_with_admin().await;
+    for i in 0..3 {
+        app.signup_and_login(&format!("user{}@search.test", i), "password123")
+            .await;
+    }
+
+    let response = app.get_admin("/users?per_page=2", &token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let list: UserListResponse = response.json().await.unwrap();
+    assert_eq!(list.total, 4);
+    assert_eq!(list.page, 1);
+    assert_eq!(list.users.len(), 2);
+    assert_eq!(list.users[0].email, ADMIN_EMAIL);
+    assert!(list.users[0].roles.contains(&Role::Admin));
+
+    let response = app.get_admin("/users?per_page=2&page=2", &token).await;
+    let list: UserListResponse = response.json().await.unwrap();
+    let emails: Vec<&str> = list.users.iter().map(|user| user.email.as_str()).collect();
+    assert_eq!(emails, vec!["user1@search.test", "user2@search.test"]);
+
+    let response = app.get_admin("/users?search=SEARCH.test", &token).await;
+    let list: UserListResponse = response.json().await.unwrap();
+    assert_eq!(list.total, 3);
+}
+
+#[tokio::test]
+async fn should_get_and_update_a_user() {
+    let (app, token) = app_with_admin().await;
+    let email = get_random_email();
+    let credentials = signup_with_credentials(&app, &email).await;
+
+    let response = app.get_admin(&format!("/users/{}", email), &token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let user: AdminUser = response.json().await.unwrap();
+    assert_eq!(user.roles, vec![Role::User]);
+    assert!(!user.email_verified);
+
+    let response = app
+        .patch_admin(
+            &format!("/users/{}", email),
+            &token,
+            &serde_json::json!({ "emailVerified": true, "roles": ["user", "admin"] }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let user: AdminUser = response.json().await.unwrap();
+    assert!(user.email_verified);
+    assert_eq!(user.roles, vec![Role::User, Role::Admin]);
+
+    // Tokens and keys issued with the old roles are revoked.
+    assert_credentials_are_revoked(&app, &credentials).await;
+    let user_token = app.login(&email, "password123").await;
+    let response = app.get_admin("/users", &user_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_change_a_users_email() {
+    let (app, token) = app_with_admin().await;
+    let email = get_random_email();
+    let credentials = signup_with_credentials(&app, &email).await;
+    let taken = get_random_email();
+    app.signup_and_login(&taken, "password123").await;
+
+    let path = format!("/users/{}", email);
+    let response = app
+        .patch_admin(&path, &token, &serde_json::json!({ "email": taken }))
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+    let response = app
+        .patch_admin(
+            &path,
+            &token,
+            &serde_json::json!({ "email": "not an email" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let new_email = get_random_email();
+    let response = app
+        .patch_admin(&path, &token, &serde_json::json!({ "email": new_email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let user: AdminUser = response.json().await.unwrap();
+    assert_eq!(user.email, new_email);
+
+    assert_eq!(app.get_admin(&path, &token).await.status().as_u16(), 404);
+    app.login(&new_email, "password123").await;
+    // Whoever signs up with the old email does not inherit anything.
+    app.signup_and_login(&email, "password456").await;
+    assert_credentials_are_revoked(&app, &credentials).await;
+}
+
+#[tokio::test]
+async fn should_return_404_for_unknown_users() {
+    let (app, token) = app_with_admin().await;
+    let path = format!("/users/{}", get_random_email());
+    assert_eq!(app.get_admin(&path, &token).await.status().as_u16(), 404);
+    assert_eq!(app.delete_admin(&path, &token).await.status().as_u16(), 404);
+    let response = app.post_admin(&format!("{}/logout", path), &token).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app.get_admin("/users/not-an-email", &token).await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn should_disable_and_enable_a_user() {
+    let (app, token) = app_with_admin().await;
+    let email = get_random_email();
+    let user_token = app.signup_and_login(&email, "password123").await;
+
+    let response = app
+        .post_admin(&format!("/users/{}/disable", email), &token)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let user: AdminUser = response.json().await.unwrap();
+    assert_eq!(user.status, UserStatus::Suspended);
+    assert_token_is_revoked(&app, &user_token).await;
+
+    let credentials = serde_json::json!({ "email": email, "password": "password123" });
+    let response = app.post_login(&credentials).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app
+        .post_admin(&format!("/users/{}/enable", email), &token)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(app.post_login(&credentials).await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_delete_a_user() {
+    let (app, token) = app_with_admin().await;
+    let email = get_random_email();
+    let credentials = signup_with_credentials(&app, &email).await;
+
+    let response = app.delete_admin(&format!("/users/{}", email), &token).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.get_admin(&format!("/users/{}", email), &token).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    // The email can be used to sign up again, without inheriting anything.
+    app.signup_and_login(&email, "password456").await;
+    assert_credentials_are_revoked(&app, &credentials).await;
+}
+
+#[tokio::test]
+async fn should_force_a_password_reset() {
+    let (app, token) = app_with_admin().await;
+    let email = get_random_email();
+    let credentials = signup_with_credentials(&app, &email).await;
+
+    let response = app
+        .post_admin(&format!("/users/{}/password-reset", email), &token)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let reset: PasswordResetResponse = response.json().await.unwrap();
+    assert_credentials_are_revoked(&app, &credentials).await;
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The temporary password only works to choose a new one.
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": reset.temporary_password }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app.get_admin(&format!("/users/{}", email), &token).await;
+    let user: AdminUser = response.json().await.unwrap();
+    assert!(user.must_change_password);
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "email": email,
+            "password": reset.temporary_password,
+            "newPassword": "password456",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+    app.login(&email, "password456").await;
+}
+
+#[tokio::test]
+async fn should_force_logout() {
+    let (app, token) = app_with_admin().await;
+    let email = get_random_email();
+    let first = signup_with_credentials(&app, &email).await;
+    let second = app.login(&email, "password123").await;
+
+    let response = app
+        .post_admin(&format!("/users/{}/logout", email), &token)
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+    assert_credentials_are_revoked(&app, &first).await;
+    assert_token_is_revoked(&app, &second).await;
+
+    // The user can log in again.
+    app.login(&email, "password123").await;
+}
@@ -0,0 +1,205 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::routes::{ApiKeyResponse, UserListResponse};
+use auth_service::utils::problem::Problem;
+use auth_service::AppConfig;
+use chrono::{Duration, Utc};
+
+const ADMIN_EMAIL: &str = "admin@example.com";
+
+async fn create_key(app: &TestApp, token: &str, body: serde_json::Value) -> ApiKeyResponse {
+    let response = app.post_api_key(token, &body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    response
+        .json()
+        .await
+        .expect("Could not deserialize response body to ApiKeyResponse")
+}
+
+async fn verify(app: &TestApp, token: &str) -> reqwest::Response {
+    app.post_verify_token(&serde_json::json!({ "token": token }))
+        .await
+}
+
+#[tokio::test]
+async fn should_create_key_shown_only_once() {
+    let app = TestApp::new().await;
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let created = create_key(&app, &token, serde_json::json!({ "name": "ci" })).await;
+    let key = created
+        .key
+        .clone()
+        .expect("The key should be returned once");
+    assert!(key.starts_with(&format!("ak_{}_", created.id)));
+    assert_eq!(created.expires_at, None);
+
+    let response = app.get_api_keys(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let listed: Vec<ApiKeyResponse> = response.json().await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].id, created.id);
+    assert_eq!(listed[0].key, None);
+}
+
+#[tokio::test]
+async fn should_accept_key_in_extractor_and_verify_token() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let token = app.signup_and_login(&email, "password123").await;
+    let key = create_key(&app, &token, serde_json::json!({ "name": "ci" }))
+        .await
+        .key
+        .unwrap();
+
+    let response = verify(&app, &key).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let claims: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(claims["sub"], email);
+    let response = app.post_verify_token_with_bearer(&key).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Routes behind the shared extractor accept the key as a Bearer token.
+    let response = app.get_api_keys(&key).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let listed: Vec<ApiKeyResponse> = response.json().await.unwrap();
+    assert!(listed[0].last_used_at.is_some());
+
+    // A wrong secret is not accepted.
+    let (prefix, _) = key.rsplit_once('_').unwrap();
+    let forged = format!("{}_{}", prefix, "x".repeat(43));
+    assert_eq!(verify(&app, &forged).await.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_limit_key_to_its_scopes() {
+    let app = TestApp::with_config(AppConfig::default().with_admin_email(ADMIN_EMAIL)).await;
+    let token = app
+        .signup_verified_and_login(ADMIN_EMAIL, "password123")
+        .await;
+    let key = create_key(
+        &app,
+        &token,
+        serde_json::json!({ "name": "reporting", "scopes": ["users:read"] }),
+    )
+    .await;
+    assert_eq!(key.scopes, Some(vec!["users:read".to_owned()]));
+    let key = key.key.unwrap();
+
+    let response = app.get_admin("/users", &key).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let _: UserListResponse = response.json().await.unwrap();
+    let path = format!("/users/{}/logout", ADMIN_EMAIL);
+    assert_eq!(app.post_admin(&path, &key).await.status().as_u16(), 403);
+
+    // Keys cannot mint other keys or be traded for a token.
+    let response = app
+        .post_api_key(&key, &serde_json::json!({ "name": "escalate" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app
+        .post_orgs("/switch", &key, &serde_json::json!({ "orgId": "acme" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn should_reject_scopes_the_user_does_not_have() {
+    let app = TestApp::new().await;
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    let response = app
+        .post_api_key(
+            &token,
+            &serde_json::json!({ "name": "ci", "scopes": ["users:write"] }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    for (body, field) in [
+        (serde_json::json!({ "name": " " }), "name"),
+        (
+            serde_json::json!({ "name": "ci", "expires_at": Utc::now() - Duration::days(1) }),
+            "expires_at",
+        ),
+    ] {
+        let response = app.post_api_key(&token, &body).await;
+        assert_eq!(response.status().as_u16(), 400, "{body}");
+        let problem: Problem = response.json().await.unwrap();
+        let fields: Vec<&str> = problem.errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec![field], "{body}");
+    }
+}
+
+#[tokio::test]
+async fn should_not_create_keys_with_oauth_access_tokens() {
+    let app = TestApp::new().await;
+    app.register_oauth_client("relying-party", "http://localhost/callback")
+        .await;
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+    let tokens = app
+        .authorization_code_tokens("relying-party", "http://localhost/callback", "openid")
+        .await;
+
+    // A key without scopes would carry every permission of the user.
+    let response = app
+        .post_api_key(&tokens.access_token, &serde_json::json!({ "name": "ci" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app
+        .post_orgs(
+            "/switch",
+            &tokens.access_token,
+            &serde_json::json!({ "orgId": "acme" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn should_revoke_key() {
+    let app = TestApp::new().await;
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    let created = create_key(
+        &app,
+        &token,
+        serde_json::json!({ "name": "ci", "expires_at": Utc::now() + Duration::days(30) }),
+    )
+    .await;
+    let key = created.key.unwrap();
+
+    // Keys of other users look missing.
+    let other = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    let response = app.delete_api_key(&created.id, &other).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app.delete_api_key(&created.id, &token).await;
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(verify(&app, &key).await.status().as_u16(), 401);
+    assert_eq!(app.get_sessions(Some(&key)).await.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_reject_keys_of_suspended_users() {
+    let app = TestApp::with_config(AppConfig::default().with_admin_email(ADMIN_EMAIL)).await;
+    let admin = app
+        .signup_verified_and_login(ADMIN_EMAIL, "password123")
+        .await;
+    let email = get_random_email();
+    let token = app.signup_and_login(&email, "password123").await;
+    let key = create_key(&app, &token, serde_json::json!({ "name": "ci" }))
+        .await
+        .key
+        .unwrap();
+
+    let path = format!("/users/{}/disable", email);
+    assert_eq!(app.post_admin(&path, &admin).await.status().as_u16(), 200);
+    assert_eq!(verify(&app, &key).await.status().as_u16(), 401);
+}
@@ -0,0 +1,115 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::{AuditEvent, AuditRecord};
+use auth_service::AppConfig;
+use chrono::{SecondsFormat, Utc};
+
+const ADMIN_EMAIL: &str = "admin@example.com";
+
+async fn audit_log(app: &TestApp, token: &str, query: &[(&str, &str)]) -> Vec<AuditRecord> {
+    let response = app.get_audit_log(token, &query).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json()
+        .await
+        .expect("Could not deserialize response body to Vec<AuditRecord>")
+}
+
+#[tokio::test]
+async fn should_record_logins_with_request_context() {
+    let app = TestApp::with_config(AppConfig::default().with_admin_email(ADMIN_EMAIL)).await;
+    let admin = app
+        .signup_verified_and_login(ADMIN_EMAIL, "password123")
+        .await;
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/login", &app.address))
+        .header("X-Request-Id", "req-42")
+        .header("User-Agent", "audit-test")
+        .json(&serde_json::json!({ "email": email, "password": "wrong-password" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+
+    let records = audit_log(&app, &admin, &[("user", &email)]).await;
+    let events: Vec<&AuditEvent> = records.iter().map(|record| &record.event).collect();
+    assert_eq!(
+        events,
+        vec![
+            &AuditEvent::LoginFailed {
+                reason: "incorrect_credentials".to_owned()
+            },
+            &AuditEvent::LoginSucceeded,
+            &AuditEvent::SignupSucceeded,
+        ]
+    );
+    let failed = &records[0];
+    assert_eq!(failed.user.as_deref(), Some(email.as_str()));
+    assert_eq!(failed.request_id, "req-42");
+    assert_eq!(failed.user_agent.as_deref(), Some("audit-test"));
+    assert_eq!(failed.ip, Some("127.0.0.1".parse().unwrap()));
+    // Requests without an id get a generated one.
+    assert!(!records[1].request_id.is_empty());
+    assert_ne!(records[1].request_id, records[2].request_id);
+}
+
+#[tokio::test]
+async fn should_filter_by_time_range() {
+    let app = TestApp::with_config(AppConfig::default().with_admin_email(ADMIN_EMAIL)).await;
+    let admin = app
+        .signup_verified_and_login(ADMIN_EMAIL, "password123")
+        .await;
+    let email = get_random_email();
+    let token = app.signup_and_login(&email, "password123").await;
+
+    let between = Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true);
+    app.logout_with_bearer(&token).await;
+
+    let records = audit_log(&app, &admin, &[("user", &email), ("from", &between)]).await;
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].event, AuditEvent::Logout);
+    let records = audit_log(&app, &admin, &[("user", &email), ("to", &between)]).await;
+    assert_eq!(records.len(), 2);
+    let records = audit_log(&app, &admin, &[("user", &email), ("limit", "1")]).await;
+    assert_eq!(records[0].event, AuditEvent::Logout);
+    assert_eq!(records.len(), 1);
+}
+
+#[tokio::test]
+async fn should_record_admin_actions_against_the_admin() {
+    let app = TestApp::with_config(AppConfig::default().with_admin_email(ADMIN_EMAIL)).await;
+    let admin = app
+        .signup_verified_and_login(ADMIN_EMAIL, "password123")
+        .await;
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+
+    let path = format!("/users/{}/disable", email);
+    assert_eq!(app.post_admin(&path, &admin).await.status().as_u16(), 200);
+    audit_log(&app, &admin, &[]).await;
+
+    let records = audit_log(&app, &admin, &[("user", ADMIN_EMAIL)]).await;
+    let events: Vec<&AuditEvent> = records.iter().take(2).map(|record| &record.event).collect();
+    assert_eq!(
+        events,
+        vec![
+            &AuditEvent::AuditLogRead,
+            &AuditEvent::UserDisabled { target: email }
+        ]
+    );
+}
+
+#[tokio::test]
+async fn should_require_users_read() {
+    let app = TestApp::new().await;
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    let response = app
+        .get_audit_log(&token, &[("user", "a@example.com")])
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
@@ -0,0 +1,66 @@
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn should_replace_the_password_and_revoke_sessions() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let token = app.signup_and_login(&email, "password123").await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "newPassword": "password456",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    app.login(&email, "password456").await;
+}
+
+#[tokio::test]
+async fn should_return_401_for_wrong_or_unknown_credentials() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+
+    for (email, password) in [
+        (email.as_str(), "wrong-password"),
+        ("nobody@example.com", "password123"),
+    ] {
+        let response = app
+            .post_change_password(&serde_json::json!({
+                "email": email,
+                "password": password,
+                "newPassword": "password456",
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+}
+
+#[tokio::test]
+async fn should_return_400_for_an_unchanged_or_short_password() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+
+    for new_password in ["password123", "short"] {
+        let response = app
+            .post_change_password(&serde_json::json!({
+                "email": email,
+                "password": "password123",
+                "newPassword": new_password,
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 400);
+    }
+}
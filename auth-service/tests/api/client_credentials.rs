@@ -0,0 +1,221 @@
+use crate::helpers::TestApp;
+use auth_service::routes::{AccessTokenResponse, RegisterClientResponse};
+use auth_service::utils::auth::validate_token;
+use auth_service::{AppConfig, ErrorResponse};
+
+const REGISTRATION_TOKEN: &str = "initial-access-token";
+
+async fn app_with_registration() -> TestApp {
+    TestApp::with_config(AppConfig::default().with_client_registration_token(REGISTRATION_TOKEN))
+        .await
+}
+
+async fn register_service(app: &TestApp, scope: &str) -> RegisterClientResponse {
+    let response = app
+        .post_clients(
+            &serde_json::json!({ "scope": scope }),
+            Some(REGISTRATION_TOKEN),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    response
+        .json::<RegisterClientResponse>()
+        .await
+        .expect("Could not deserialize registration response")
+}
+
+async fn request_client_token(
+    app: &TestApp,
+    client: &RegisterClientResponse,
+    scope: Option<&str>,
+) -> reqwest::Response {
+    let mut form = vec![("grant_type", "client_credentials")];
+    if let Some(scope) = scope {
+        form.push(("scope", scope));
+    }
+    app.http_client
+        .post(format!("{}/token", &app.address))
+        .basic_auth(&client.client_id, client.client_secret.as_deref())
+        .form(&form)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn should_return_401_if_registration_token_missing_or_wrong() {
+    let app = TestApp::new().await;
+    let response = app
+        .post_clients(&serde_json::json!({}), Some(REGISTRATION_TOKEN))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let app = app_with_registration().await;
+    let response = app.post_clients(&serde_json::json!({}), None).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_clients(&serde_json::json!({}), Some("wrong-token"))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "invalid_token"
+    );
+}
+
+#[tokio::test]
+async fn should_register_confidential_client_with_secret() {
+    let app = app_with_registration().await;
+    let client = register_service(&app, "users:read users:write").await;
+
+    assert!(client.client_secret.is_some());
+    assert_eq!(client.scope, "users:read users:write");
+    assert_eq!(client.token_endpoint_auth_method, "client_secret_basic");
+
+    let stored = app
+        .state
+        .oauth_clients
+        .read()
+        .await
+        .get_client(&client.client_id)
+        .await
+        .expect("Client should be stored");
+    assert!(stored.is_confidential());
+    assert_ne!(stored.secret_hash, client.client_secret);
+}
+
+#[tokio::test]
+async fn should_reject_public_client_without_redirect_uri() {
+    let app = app_with_registration().await;
+    let response = app
+        .post_clients(
+            &serde_json::json!({ "token_endpoint_auth_method": "none" }),
+            Some(REGISTRATION_TOKEN),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "invalid_redirect_uri"
+    );
+}
+
+#[tokio::test]
+async fn should_issue_client_token_with_client_id_subject() {
+    let app = app_with_registration().await;
+    let client = register_service(&app, "users:read users:write").await;
+
+    let response = request_client_token(&app, &client, Some("users:read")).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let tokens = response.json::<AccessTokenResponse>().await.unwrap();
+    assert_eq!(tokens.token_type, "Bearer");
+    assert_eq!(tokens.scope, "users:read");
+    assert_eq!(tokens.id_token, None);
+
+    let claims = validate_token(&tokens.access_token)
+        .await
+        .expect("Client token should be valid");
+    assert!(claims.is_client_token());
+    assert_eq!(claims.sub, client.client_id);
+    assert_eq!(claims.scope.as_deref(), Some("users:read"));
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": tokens.access_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_default_to_registered_scope() {
+    let app = app_with_registration().await;
+    let client = register_service(&app, "users:read users:write").await;
+
+    let tokens = request_client_token(&app, &client, None)
+        .await
+        .json::<AccessTokenResponse>()
+        .await
+        .unwrap();
+    assert_eq!(tokens.scope, "users:read users:write");
+}
+
+#[tokio::test]
+async fn should_accept_client_secret_post() {
+    let app = app_with_registration().await;
+    let client = register_service(&app, "users:read").await;
+
+    let response = app
+        .post_token(&serde_json::json!({
+            "grant_type": "client_credentials",
+            "client_id": client.client_id,
+            "client_secret": client.client_secret,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_return_401_if_client_secret_is_wrong() {
+    let app = app_with_registration().await;
+    let client = register_service(&app, "users:read").await;
+
+    let response = app
+        .post_token(&serde_json::json!({
+            "grant_type": "client_credentials",
+            "client_id": client.client_id,
+            "client_secret": "wrong-secret",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "invalid_client"
+    );
+}
+
+#[tokio::test]
+async fn should_return_400_if_scope_exceeds_registration() {
+    let app = app_with_registration().await;
+    let client = register_service(&app, "users:read").await;
+
+    let response = request_client_token(&app, &client, Some("users:read users:delete")).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "invalid_scope"
+    );
+}
+
+#[tokio::test]
+async fn should_not_issue_client_tokens_to_public_clients() {
+    let app = TestApp::new().await;
+    app.register_oauth_client("spa", "http://127.0.0.1:8080/callback")
+        .await;
+
+    let response = app
+        .post_token(&serde_json::json!({
+            "grant_type": "client_credentials",
+            "client_id": "spa",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "unauthorized_client"
+    );
+}
+
+#[tokio::test]
+async fn client_tokens_are_not_accepted_as_user_tokens() {
+    let app = app_with_registration().await;
+    let client = register_service(&app, "openid email").await;
+
+    let tokens = request_client_token(&app, &client, None)
+        .await
+        .json::<AccessTokenResponse>()
+        .await
+        .unwrap();
+
+    let response = app.get_userinfo(Some(&tokens.access_token)).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
@@ -0,0 +1,131 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::utils::csrf::csrf_token;
+use auth_service::utils::{CSRF_COOKIE_NAME, CSRF_HEADER};
+use reqwest::header::{
+    ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD,
+    ORIGIN,
+};
+use reqwest::Method;
+
+#[tokio::test]
+async fn should_set_a_csrf_cookie_for_the_session() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    app.post_signup(&serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    }))
+    .await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123"
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == app.state.config.cookies.name())
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    let cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == CSRF_COOKIE_NAME)
+        .expect("No CSRF cookie found");
+    assert_eq!(cookie.value(), csrf_token(&token));
+    // Read by the pages to send the token back.
+    assert!(!cookie.http_only());
+    assert_eq!(cookie.path(), Some("/"));
+}
+
+#[tokio::test]
+async fn should_reject_cookie_authenticated_requests_without_the_csrf_token() {
+    let app = TestApp::new().await;
+    let other_session = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    // Replaces the cookie of the other session in the client.
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    // Like a form posted from another site, which the browser adds the cookie to.
+    let response = app
+        .http_client
+        .post(format!("{}/logout", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app
+        .http_client
+        .post(format!("{}/logout", &app.address))
+        .header(CSRF_HEADER, csrf_token(&other_session))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        app.state()
+            .banned_tokens
+            .read()
+            .await
+            .is_token_banned(&token)
+            .await,
+        Ok(false)
+    );
+
+    let response = app
+        .http_client
+        .post(format!("{}/logout", &app.address))
+        .header(CSRF_HEADER, csrf_token(&token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_not_require_the_csrf_token_with_bearer_auth_or_safe_methods() {
+    let app = TestApp::new().await;
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    // The cookie is sent along, but the header is the token used.
+    let response = app.get_sessions(None).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app
+        .http_client
+        .delete(format!("{}/sessions", &app.address))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 204);
+}
+
+#[tokio::test]
+async fn should_allow_the_app_service_to_send_the_csrf_token() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .request(Method::OPTIONS, format!("{}/logout", &app.address))
+        .header(ORIGIN, "http://localhost:8000")
+        .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+        .header(ACCESS_CONTROL_REQUEST_HEADERS, CSRF_HEADER)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert!(response.status().is_success());
+    let allowed = response.headers()[ACCESS_CONTROL_ALLOW_HEADERS]
+        .to_str()
+        .unwrap();
+    assert!(allowed.contains(CSRF_HEADER));
+}
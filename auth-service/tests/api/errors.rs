@@ -0,0 +1,135 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::FieldError;
+use auth_service::utils::problem::{Problem, PROBLEM_JSON};
+use auth_service::utils::REQUEST_ID_HEADER;
+use auth_service::{AppConfig, ErrorFormat, ErrorResponse};
+use reqwest::header::CONTENT_TYPE;
+
+const ADMIN_EMAIL: &str = "admin@example.com";
+
+async fn problem_of(response: reqwest::Response) -> Problem {
+    assert_eq!(response.headers()[CONTENT_TYPE], PROBLEM_JSON);
+    let request_id = response.headers()[REQUEST_ID_HEADER]
+        .to_str()
+        .unwrap()
+        .to_owned();
+    let problem = response
+        .json::<Problem>()
+        .await
+        .expect("Could not deserialize response body to Problem");
+    assert_eq!(problem.instance, Some(request_id));
+    problem
+}
+
+#[tokio::test]
+async fn should_tell_errors_with_the_same_status_apart_by_code() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "wrong-password" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    let problem = problem_of(response).await;
+    assert_eq!(problem.code, "incorrect_credentials");
+    assert_eq!(
+        problem.problem_type,
+        "urn:auth-service:problem:incorrect_credentials"
+    );
+    assert_eq!(problem.status, 401);
+
+    let response = app.logout_with_bearer("not-a-token").await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(problem_of(response).await.code, "invalid_token");
+}
+
+#[tokio::test]
+async fn should_report_every_invalid_field() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": "not an email", "password": "short" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    let problem = problem_of(response).await;
+    assert_eq!(problem.code, "validation_failed");
+    assert_eq!(
+        problem.errors,
+        vec![
+            FieldError::new("email", "Invalid email format"),
+            FieldError::new("password", "Password must be at least 8 characters long"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn should_report_rejected_requests_as_problems() {
+    let app = TestApp::with_config(AppConfig::default().with_admin_email(ADMIN_EMAIL)).await;
+    let admin = app
+        .signup_verified_and_login(ADMIN_EMAIL, "password123")
+        .await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/login", &app.address))
+        .header(CONTENT_TYPE, "application/json")
+        .body("{")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(problem_of(response).await.code, "malformed_body");
+
+    let response = app
+        .http_client
+        .post(format!("{}/login", &app.address))
+        .body("email=someone@example.com")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 415);
+    assert_eq!(problem_of(response).await.code, "unsupported_media_type");
+
+    for path in ["/login", "/invitations/accept"] {
+        let response = app
+            .http_client
+            .get(format!("{}{}", &app.address, path))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 405);
+        assert_eq!(problem_of(response).await.code, "method_not_allowed");
+    }
+
+    let response = app.get_admin("/users?page=first", &admin).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(problem_of(response).await.code, "invalid_query");
+}
+
+#[tokio::test]
+async fn should_keep_the_legacy_format_behind_the_flag() {
+    let app =
+        TestApp::with_config(AppConfig::default().with_error_format(ErrorFormat::Legacy)).await;
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": "not an email", "password": "short" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Invalid credentials"
+    );
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "wrong-password" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Unauthorized"
+    );
+}
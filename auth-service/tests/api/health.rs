@@ -0,0 +1,60 @@
+use crate::helpers::TestApp;
+use auth_service::routes::{HealthResponse, HealthStatus};
+use auth_service::services::JsonLinesAuditSink;
+
+#[tokio::test]
+async fn should_be_live() {
+    let app = TestApp::new().await;
+    let response = app.get_health("live").await;
+    assert_eq!(response.status().as_u16(), 200);
+    let health: HealthResponse = response.json().await.unwrap();
+    assert_eq!(health.status, HealthStatus::Ok);
+}
+
+#[tokio::test]
+async fn should_be_ready_when_every_store_is() {
+    let app = TestApp::new().await;
+    let response = app.get_health("ready").await;
+    assert_eq!(response.status().as_u16(), 200);
+    let health: HealthResponse = response.json().await.unwrap();
+    assert_eq!(health.status, HealthStatus::Ok);
+    let components: Vec<(&str, HealthStatus)> = health
+        .components
+        .iter()
+        .map(|(name, component)| (name.as_str(), component.status))
+        .collect();
+    assert_eq!(
+        components,
+        vec![
+            ("api_keys", HealthStatus::Ok),
+            ("audit", HealthStatus::Ok),
+            ("authorization_codes", HealthStatus::Ok),
+            ("banned_tokens", HealthStatus::Ok),
+            ("invitations", HealthStatus::Ok),
+            ("oauth_clients", HealthStatus::Ok),
+            ("organizations", HealthStatus::Ok),
+            ("pending_logins", HealthStatus::Ok),
+            ("refresh_tokens", HealthStatus::Ok),
+            ("sessions", HealthStatus::Ok),
+            ("users", HealthStatus::Ok),
+            ("webhooks", HealthStatus::Ok),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn should_not_be_ready_when_a_store_is_unavailable() {
+    let app = TestApp::new().await;
+    *app.state.audit_sink.write().await =
+        Box::new(JsonLinesAuditSink::new("/nonexistent/audit.jsonl"));
+
+    let response = app.get_health("ready").await;
+    assert_eq!(response.status().as_u16(), 503);
+    let health: HealthResponse = response.json().await.unwrap();
+    assert_eq!(health.status, HealthStatus::Unavailable);
+    assert_eq!(health.components["audit"].status, HealthStatus::Unavailable);
+    assert_eq!(health.components["users"].status, HealthStatus::Ok);
+
+    // Still live: restarting would not help.
+    assert_eq!(app.get_health("live").await.status().as_u16(), 200);
+}
@@ -1,51 +1,132 @@
-use auth_service::services::HashmapUserStore;
-use auth_service::utils::test;
-use auth_service::Application;
-use reqwest::cookie::Jar;
+use auth_service::domain::{Email, OAuthClient, Scope};
+use auth_service::routes::{AccessTokenResponse, SwitchOrganizationResponse};
+use auth_service::services::{
+    HashSetBannedTokenStore, HashmapUserStore, InstrumentedBannedTokenStore, InstrumentedUserStore,
+};
+use auth_service::utils::auth::sign_email_verification;
+use auth_service::utils::metrics::Metrics;
+use auth_service::utils::shutdown::ShutdownHandle;
+use auth_service::utils::{test, CSRF_HEADER};
+use auth_service::{AppConfig, Application};
+use chrono::Utc;
+use reqwest::cookie::{CookieStore, Jar};
+use reqwest::Url;
+use std::str::FromStr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 use uuid::Uuid;
 
 pub struct TestApp {
     pub address: String,
+    pub redirect_address: Option<String>,
     pub cookie_jar: Arc<Jar>,
     pub http_client: reqwest::Client,
     pub state: auth_service::AppState,
+    pub shutdown: ShutdownHandle,
+    server: JoinHandle<Result<(), std::io::Error>>,
 }
 
+/// The PKCE verifier public clients redeem their codes with in the tests, and its `S256`
+/// challenge (the example of RFC 7636).
+pub const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+pub const CODE_CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
 impl TestApp {
     pub async fn new() -> Self {
+        Self::with_config(AppConfig::default()).await
+    }
+
+    pub async fn with_config(config: AppConfig) -> Self {
+        let scheme = if config.tls.is_some() {
+            "https"
+        } else {
+            "http"
+        };
+        // The app is its own issuer, like in a deployment, which takes knowing the port up front.
+        let listen_address = std::net::TcpListener::bind(test::APP_ADDRESS)
+            .and_then(|listener| listener.local_addr())
+            .expect("Failed to reserve a port")
+            .to_string();
+        let config = match config.oidc_issuer {
+            Some(_) => config,
+            None => config.with_oidc_issuer(format!("{}://{}", scheme, listen_address)),
+        };
         let cookie_jar = Arc::new(Jar::default());
-        let user_store = HashmapUserStore::new();
+        let metrics = Arc::new(Metrics::new());
+        let user_store =
+            InstrumentedUserStore::new(Box::new(HashmapUserStore::new()), metrics.clone());
+        let banned_tokens = InstrumentedBannedTokenStore::new(
+            Box::new(HashSetBannedTokenStore::new()),
+            metrics.clone(),
+        );
         let app_state = auth_service::AppState {
             user_store: Arc::new(RwLock::new(Box::new(user_store))),
-            banned_tokens: Arc::new(RwLock::new(Box::new(
-                auth_service::services::HashSetBannedTokenStore::new(),
+            banned_tokens: Arc::new(RwLock::new(Box::new(banned_tokens))),
+            oauth_clients: Arc::new(RwLock::new(Box::new(
+                auth_service::services::HashmapClientStore::new(),
+            ))),
+            authorization_codes: Arc::new(RwLock::new(Box::new(
+                auth_service::services::HashmapAuthorizationCodeStore::new(),
+            ))),
+            refresh_tokens: Arc::new(RwLock::new(Box::new(
+                auth_service::services::HashmapRefreshTokenStore::new(),
+            ))),
+            sessions: Arc::new(RwLock::new(Box::new(
+                auth_service::services::HashmapSessionStore::new(),
             ))),
+            pending_logins: Arc::new(RwLock::new(Box::new(
+                auth_service::services::HashmapPendingLoginStore::new(),
+            ))),
+            organizations: Arc::new(RwLock::new(Box::new(
+                auth_service::services::HashmapOrganizationStore::new(),
+            ))),
+            invitations: Arc::new(RwLock::new(Box::new(
+                auth_service::services::HashmapInvitationStore::new(),
+            ))),
+            api_keys: Arc::new(RwLock::new(Box::new(
+                auth_service::services::HashmapApiKeyStore::new(),
+            ))),
+            audit_sink: Arc::new(RwLock::new(Box::new(
+                auth_service::services::SqliteAuditSink::in_memory()
+                    .expect("Failed to open audit database"),
+            ))),
+            webhooks: Arc::new(RwLock::new(Box::new(
+                auth_service::services::SqliteWebhookStore::in_memory()
+                    .expect("Failed to open webhook database"),
+            ))),
+            email_client: Arc::new(auth_service::services::MockEmailClient::new()),
+            metrics,
+            config: Arc::new(config),
         };
 
-        let app = Application::build(app_state.clone(), test::APP_ADDRESS)
+        let app = Application::build(app_state.clone(), &listen_address)
             .await
             .expect("Failed to build app");
 
-        let address = format!("http://{}", app.address.clone());
+        let address = format!("{}://{}", scheme, app.address.clone());
+        let redirect_address = app.redirect_address.clone();
+        let shutdown = app.shutdown_handle();
 
         // Run the auth service in a separate async task
         // to avoid blocking the main test thread.
-        #[allow(clippy::let_underscore_future)]
-        let _ = tokio::spawn(app.run());
+        let server = tokio::spawn(app.run());
 
         let http_client = reqwest::Client::builder()
             .cookie_provider(cookie_jar.clone())
+            .redirect(reqwest::redirect::Policy::none())
             .build()
             .unwrap(); // Create a Reqwest http client instance
 
         // Create new `TestApp` instance and return it
         TestApp {
             address,
+            redirect_address,
             cookie_jar,
             http_client,
             state: app_state,
+            shutdown,
+            server,
         }
     }
 
@@ -53,9 +134,15 @@ impl TestApp {
         &self.state
     }
 
+    /// Shuts the server down and waits until it has stopped.
+    pub async fn stop(self) -> Result<(), std::io::Error> {
+        self.shutdown.shutdown();
+        self.server.await.expect("The server panicked")
+    }
+
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/", &self.address))
+            .get(format!("{}/", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -66,7 +153,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/signup", &self.address))
+            .post(format!("{}/signup", &self.address))
             .json(body)
             .send()
             .await
@@ -78,33 +165,102 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             .json(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
-    pub async fn logout(&self) -> reqwest::Response {
+    pub async fn post_change_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
         self.http_client
-            .post(&format!("{}/logout", &self.address))
+            .post(format!("{}/change-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn logout(&self) -> reqwest::Response {
+        self.with_csrf_token(self.http_client.post(format!("{}/logout", &self.address)))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn logout_with_bearer(&self, token: &str) -> reqwest::Response {
+        // The cookie left by a login may still be the one used, see `TokenPrecedence`.
+        self.with_csrf_token(self.http_client.post(format!("{}/logout", &self.address)))
+            .bearer_auth(token)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
-    pub async fn verify_2fa(&self, token: &str) -> reqwest::Response {
-        let verify_2fa_body = serde_json::json!({
-            "token": token,
+    // Sends the token of the CSRF cookie back, like the pages do for cookie-authenticated
+    // requests that change state.
+    fn with_csrf_token(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let url = Url::parse(&self.address).expect("Failed to parse URL");
+        let csrf_name = self.state.config.cookies.csrf_name();
+        let csrf_token = self.cookie_jar.cookies(&url).and_then(|cookies| {
+            cookies.to_str().ok().and_then(|cookies| {
+                cookies.split("; ").find_map(|cookie| {
+                    cookie
+                        .strip_prefix(csrf_name)
+                        .and_then(|rest| rest.strip_prefix('='))
+                        .map(str::to_owned)
+                })
+            })
         });
+        match csrf_token {
+            Some(csrf_token) => request.header(CSRF_HEADER, csrf_token),
+            None => request,
+        }
+    }
+
+    pub async fn post_verify_2fa<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/verify-2fa", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// The code sent for a login that waits for 2FA, as the user would find it in their email.
+    pub async fn pending_2fa_code(&self, login_attempt_id: &str) -> String {
+        self.state
+            .pending_logins
+            .read()
+            .await
+            .get_pending_login(login_attempt_id)
+            .await
+            .expect("No pending login")
+            .code
+    }
 
+    pub async fn get_health(&self, probe: &str) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/verify-2fa", &self.address))
-            .json(&verify_2fa_body)
+            .get(format!("{}/health/{}", &self.address, probe))
             .send()
             .await
             .expect("Failed to execute request.")
     }
+
+    pub async fn get_metrics(&self, token: Option<&str>) -> reqwest::Response {
+        let mut request = self.http_client.get(format!("{}/metrics", &self.address));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
     pub async fn post_verify_token<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -116,6 +272,442 @@ impl TestApp {
             .await
             .expect("Failed to execute request.")
     }
+
+    pub async fn post_verify_token_with_bearer(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/verify-token", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_openid_configuration(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!(
+                "{}/.well-known/openid-configuration",
+                &self.address
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_authorize<Query>(&self, query: &Query) -> reqwest::Response
+    where
+        Query: serde::Serialize,
+    {
+        self.http_client
+            .get(format!("{}/authorize", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_token<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/token", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_userinfo(&self, access_token: Option<&str>) -> reqwest::Response {
+        let mut request = self.http_client.get(format!("{}/userinfo", &self.address));
+        if let Some(access_token) = access_token {
+            request = request.bearer_auth(access_token);
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions(&self, token: Option<&str>) -> reqwest::Response {
+        let mut request = self.http_client.get(format!("{}/sessions", &self.address));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn delete_session(&self, id: &str, token: Option<&str>) -> reqwest::Response {
+        let mut request = self.with_csrf_token(
+            self.http_client
+                .delete(format!("{}/sessions/{}", &self.address, id)),
+        );
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn delete_sessions(&self, token: Option<&str>) -> reqwest::Response {
+        let mut request = self.with_csrf_token(
+            self.http_client
+                .delete(format!("{}/sessions", &self.address)),
+        );
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin(&self, path: &str, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin{}", &self.address, path))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_audit_log<Query>(&self, token: &str, query: &Query) -> reqwest::Response
+    where
+        Query: serde::Serialize,
+    {
+        self.http_client
+            .get(format!("{}/admin/audit", &self.address))
+            .query(query)
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin(&self, path: &str, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/admin{}", &self.address, path))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn patch_admin<Body>(&self, path: &str, token: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .patch(format!("{}/admin{}", &self.address, path))
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_admin(&self, path: &str, token: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/admin{}", &self.address, path))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_orgs(&self, path: &str, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/orgs{}", &self.address, path))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_orgs<Body>(&self, path: &str, token: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/orgs{}", &self.address, path))
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn create_org(&self, token: &str, id: &str) {
+        let response = self
+            .post_orgs(
+                "",
+                token,
+                &serde_json::json!({ "id": id, "name": "Acme Inc." }),
+            )
+            .await;
+        assert_eq!(response.status().as_u16(), 201);
+    }
+
+    // Switches to the organization and returns the new token.
+    pub async fn switch_org(&self, token: &str, org_id: &str) -> String {
+        let response = self
+            .post_orgs("/switch", token, &serde_json::json!({ "orgId": org_id }))
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+        response
+            .json::<SwitchOrganizationResponse>()
+            .await
+            .expect("Could not deserialize response body to SwitchOrganizationResponse")
+            .token
+    }
+
+    pub async fn get_invitations(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/invitations", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_invitations<Body>(
+        &self,
+        path: &str,
+        token: Option<&str>,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .http_client
+            .post(format!("{}/invitations{}", &self.address, path))
+            .json(body);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn delete_invitation(&self, id: &str, token: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/invitations/{}", &self.address, id))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_api_key<Body>(&self, token: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/api-keys", &self.address))
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_api_keys(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/api-keys", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_api_key(&self, id: &str, token: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/api-keys/{}", &self.address, id))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_webhook<Body>(&self, token: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/admin/webhooks", &self.address))
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_clients<Body>(&self, body: &Body, token: Option<&str>) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .http_client
+            .post(format!("{}/clients", &self.address))
+            .json(body);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn register_oauth_client(&self, client_id: &str, redirect_uri: &str) {
+        self.state
+            .oauth_clients
+            .write()
+            .await
+            .add_client(OAuthClient::new(
+                client_id.to_owned(),
+                vec![redirect_uri.to_owned()],
+            ))
+            .await
+            .expect("Failed to register OAuth client");
+    }
+
+    pub async fn register_confidential_client(&self, client_id: &str, secret: &str, scope: &str) {
+        self.state
+            .oauth_clients
+            .write()
+            .await
+            .add_client(OAuthClient::confidential(
+                client_id.to_owned(),
+                secret,
+                vec![],
+                Scope::parse(scope),
+            ))
+            .await
+            .expect("Failed to register OAuth client");
+    }
+
+    pub async fn post_introspect(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        token: &str,
+    ) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/introspect", &self.address))
+            .basic_auth(client_id, Some(client_secret))
+            .form(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_revoke<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/revoke", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Runs the authorization code flow for the logged in user and a public client.
+    pub async fn authorization_code_tokens(
+        &self,
+        client_id: &str,
+        redirect_uri: &str,
+        scope: &str,
+    ) -> AccessTokenResponse {
+        let response = self
+            .get_authorize(&serde_json::json!({
+                "response_type": "code",
+                "client_id": client_id,
+                "redirect_uri": redirect_uri,
+                "scope": scope,
+                "code_challenge": CODE_CHALLENGE,
+                "code_challenge_method": "S256",
+            }))
+            .await;
+        let location = reqwest::Url::parse(
+            response
+                .headers()
+                .get("location")
+                .expect("Authorization should redirect")
+                .to_str()
+                .unwrap(),
+        )
+        .unwrap();
+        let code = location
+            .query_pairs()
+            .find(|(key, _)| key == "code")
+            .map(|(_, value)| value.into_owned())
+            .expect("Authorization should return a code");
+
+        let response = self
+            .post_token(&serde_json::json!({
+                "grant_type": "authorization_code",
+                "code": code,
+                "redirect_uri": redirect_uri,
+                "client_id": client_id,
+                "code_verifier": CODE_VERIFIER,
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+        response
+            .json::<AccessTokenResponse>()
+            .await
+            .expect("Could not deserialize token response")
+    }
+
+    pub async fn signup_and_login(&self, email: &str, password: &str) -> String {
+        let response = self
+            .post_signup(&serde_json::json!({
+                "email": email,
+                "password": password,
+                "requires2FA": false
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 201);
+
+        self.login(email, password).await
+    }
+
+    // Signs up and follows the email verification link, which makes configured admin emails
+    // admins, before logging in.
+    pub async fn signup_verified_and_login(&self, email: &str, password: &str) -> String {
+        self.signup_and_login(email, password).await;
+        let response = self.post_verify_email(email).await;
+        assert_eq!(response.status().as_u16(), 204);
+        self.login(email, password).await
+    }
+
+    // Posts the token of the verification link sent to the email.
+    pub async fn post_verify_email(&self, email: &str) -> reqwest::Response {
+        let id = Email::from_str(email).unwrap().into();
+        let token = sign_email_verification(&id, Utc::now() + chrono::Duration::hours(1));
+        self.http_client
+            .post(format!("{}/verify-email", &self.address))
+            .json(&serde_json::json!({ "token": token }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Logs in and returns the JWT, which also replaces the auth cookie of the client.
+    pub async fn login(&self, email: &str, password: &str) -> String {
+        let response = self
+            .post_login(&serde_json::json!({
+                "email": email,
+                "password": password
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+
+        let token = response
+            .cookies()
+            .find(|cookie| cookie.name() == self.state.config.cookies.name())
+            .expect("No auth cookie found")
+            .value()
+            .to_owned();
+        token
+    }
+}
+
+pub fn get_random_org_id() -> String {
+    format!("org-{}", Uuid::new_v4())
 }
 
 pub fn get_random_email() -> String {
@@ -0,0 +1,142 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::routes::{AccessTokenResponse, IntrospectionResponse};
+use auth_service::ErrorResponse;
+
+const RESOURCE_SERVER: &str = "resource-server";
+const SECRET: &str = "resource-server-secret";
+
+async fn app_with_resource_server() -> TestApp {
+    let app = TestApp::new().await;
+    app.register_confidential_client(RESOURCE_SERVER, SECRET, "introspect")
+        .await;
+    app
+}
+
+#[tokio::test]
+async fn should_return_claims_for_active_token() {
+    let app = app_with_resource_server().await;
+    let email = get_random_email();
+    let token = app.signup_and_login(&email, "password123").await;
+
+    let response = app.post_introspect(RESOURCE_SERVER, SECRET, &token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get("cache-control").unwrap(), "no-store");
+
+    let introspection = response.json::<IntrospectionResponse>().await.unwrap();
+    assert!(introspection.active);
+    assert_eq!(introspection.revoked, Some(false));
+    assert_eq!(introspection.sub.as_deref(), Some(email.as_str()));
+    assert_eq!(introspection.token_type.as_deref(), Some("Bearer"));
+    assert!(introspection.jti.is_some());
+    assert!(introspection.exp.unwrap() > introspection.iat.unwrap());
+}
+
+#[tokio::test]
+async fn should_return_scope_and_client_for_client_token() {
+    let app = app_with_resource_server().await;
+    app.register_confidential_client("worker", "worker-secret", "jobs:run")
+        .await;
+
+    let tokens = app
+        .http_client
+        .post(format!("{}/token", &app.address))
+        .basic_auth("worker", Some("worker-secret"))
+        .form(&[("grant_type", "client_credentials")])
+        .send()
+        .await
+        .unwrap()
+        .json::<AccessTokenResponse>()
+        .await
+        .unwrap();
+
+    let introspection = app
+        .post_introspect(RESOURCE_SERVER, SECRET, &tokens.access_token)
+        .await
+        .json::<IntrospectionResponse>()
+        .await
+        .unwrap();
+    assert!(introspection.active);
+    assert_eq!(introspection.sub.as_deref(), Some("worker"));
+    assert_eq!(introspection.client_id.as_deref(), Some("worker"));
+    assert_eq!(introspection.scope.as_deref(), Some("jobs:run"));
+}
+
+#[tokio::test]
+async fn should_report_revoked_token_as_inactive() {
+    let app = app_with_resource_server().await;
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    assert_eq!(app.logout().await.status().as_u16(), 200);
+
+    let introspection = app
+        .post_introspect(RESOURCE_SERVER, SECRET, &token)
+        .await
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+    assert_eq!(
+        introspection,
+        serde_json::json!({ "active": false, "revoked": true })
+    );
+}
+
+#[tokio::test]
+async fn should_report_invalid_token_as_inactive() {
+    let app = app_with_resource_server().await;
+
+    let response = app
+        .post_introspect(RESOURCE_SERVER, SECRET, "not-a-token")
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.json::<serde_json::Value>().await.unwrap(),
+        serde_json::json!({ "active": false })
+    );
+}
+
+#[tokio::test]
+async fn should_return_401_without_client_credentials() {
+    let app = app_with_resource_server().await;
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let response = app
+        .post_introspect(RESOURCE_SERVER, "wrong-secret", &token)
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "invalid_client"
+    );
+
+    let response = app
+        .http_client
+        .post(format!("{}/introspect", &app.address))
+        .form(&[("token", token.as_str())])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_not_allow_public_clients_to_introspect() {
+    let app = TestApp::new().await;
+    app.register_oauth_client("spa", "http://127.0.0.1:8080/callback")
+        .await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/introspect", &app.address))
+        .form(&[("token", "anything"), ("client_id", "spa")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "unauthorized_client"
+    );
+}
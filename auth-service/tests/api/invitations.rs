@@ -0,0 +1,196 @@
+use crate::helpers::{get_random_email, get_random_org_id, TestApp};
+use auth_service::domain::Role;
+use auth_service::routes::InvitationResponse;
+
+// Signs up an owner, creates an organization and returns a token scoped to it.
+async fn org_owner(app: &TestApp, org_id: &str) -> String {
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    app.create_org(&token, org_id).await;
+    app.switch_org(&token, org_id).await
+}
+
+async fn invite(app: &TestApp, token: &str, email: &str) -> InvitationResponse {
+    let response = app
+        .post_invitations("", Some(token), &serde_json::json!({ "email": email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    response
+        .json()
+        .await
+        .expect("Could not deserialize response body to InvitationResponse")
+}
+
+async fn accept(app: &TestApp, token: &str, password: &str) -> reqwest::Response {
+    app.post_invitations(
+        "/accept",
+        None,
+        &serde_json::json!({ "token": token, "password": password }),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn should_sign_up_invitee_into_organization() {
+    let app = TestApp::new().await;
+    let org_id = get_random_org_id();
+    let owner = org_owner(&app, &org_id).await;
+    let email = get_random_email();
+
+    let invitation = invite(&app, &owner, &email).await;
+    assert_eq!(invitation.org_id, org_id);
+    assert_eq!(invitation.role, Role::User);
+    assert!(invitation.expires_at > invitation.created_at);
+    let token = invitation.token.clone().expect("No invitation token");
+
+    let response = app.get_invitations(&owner).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let pending: Vec<InvitationResponse> = response.json().await.unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].id, invitation.id);
+    assert_eq!(pending[0].token, None);
+
+    assert_eq!(
+        accept(&app, &token, "password123").await.status().as_u16(),
+        201
+    );
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "orgId": org_id,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Invitations can only be used once.
+    assert_eq!(
+        accept(&app, &token, "password123").await.status().as_u16(),
+        404
+    );
+    let pending: Vec<InvitationResponse> = app.get_invitations(&owner).await.json().await.unwrap();
+    assert!(pending.is_empty());
+}
+
+#[tokio::test]
+async fn should_add_existing_account_with_its_password() {
+    let app = TestApp::new().await;
+    let org_id = get_random_org_id();
+    let owner = org_owner(&app, &org_id).await;
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+
+    let token = invite(&app, &owner, &email).await.token.unwrap();
+    assert_eq!(
+        accept(&app, &token, "wrong-password")
+            .await
+            .status()
+            .as_u16(),
+        401
+    );
+    assert_eq!(
+        accept(&app, &token, "password123").await.status().as_u16(),
+        200
+    );
+
+    // Members cannot be invited again.
+    let response = app
+        .post_invitations("", Some(&owner), &serde_json::json!({ "email": email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+}
+
+#[tokio::test]
+async fn should_require_members_write_in_the_organization() {
+    let app = TestApp::new().await;
+    let org_id = get_random_org_id();
+    let owner = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    app.create_org(&owner, &org_id).await;
+    let body = serde_json::json!({ "email": get_random_email() });
+
+    // Not scoped to the organization yet.
+    let response = app.post_invitations("", Some(&owner), &body).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let owner = app.switch_org(&owner, &org_id).await;
+    let response = app
+        .post_invitations(
+            "",
+            Some(&owner),
+            &serde_json::json!({ "email": get_random_email(), "role": "admin" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    // Plain members cannot invite.
+    let email = get_random_email();
+    let token = invite(&app, &owner, &email).await.token.unwrap();
+    accept(&app, &token, "password123").await;
+    let member = app.login(&email, "password123").await;
+    let member = app.switch_org(&member, &org_id).await;
+    let response = app.post_invitations("", Some(&member), &body).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(app.get_invitations(&member).await.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn should_revoke_pending_invitation() {
+    let app = TestApp::new().await;
+    let org_id = get_random_org_id();
+    let owner = org_owner(&app, &org_id).await;
+    let invitation = invite(&app, &owner, &get_random_email()).await;
+
+    // Invitations of other organizations look missing.
+    let other = org_owner(&app, &get_random_org_id()).await;
+    assert_eq!(
+        app.delete_invitation(&invitation.id, &other)
+            .await
+            .status()
+            .as_u16(),
+        404
+    );
+
+    assert_eq!(
+        app.delete_invitation(&invitation.id, &owner)
+            .await
+            .status()
+            .as_u16(),
+        204
+    );
+    let token = invitation.token.unwrap();
+    assert_eq!(
+        accept(&app, &token, "password123").await.status().as_u16(),
+        404
+    );
+}
+
+#[tokio::test]
+async fn should_reject_forged_tokens_and_invalid_passwords() {
+    let app = TestApp::new().await;
+    let owner = org_owner(&app, &get_random_org_id()).await;
+    let invitation = invite(&app, &owner, &get_random_email()).await;
+    let token = invitation.token.unwrap();
+
+    let forged = format!("{}.{}", invitation.id, "AAAA");
+    assert_eq!(
+        accept(&app, &forged, "password123").await.status().as_u16(),
+        404
+    );
+    assert_eq!(
+        accept(&app, &invitation.id, "password123")
+            .await
+            .status()
+            .as_u16(),
+        404
+    );
+
+    // The signup validation applies; the invitation stays usable.
+    assert_eq!(accept(&app, &token, "short").await.status().as_u16(), 400);
+    assert_eq!(
+        accept(&app, &token, "password123").await.status().as_u16(),
+        201
+    );
+}
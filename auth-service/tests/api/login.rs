@@ -1,5 +1,9 @@
 use crate::helpers::{get_random_email, TestApp};
-use auth_service::utils::JWT_COOKIE_NAME;
+use auth_service::utils::problem::PROBLEM_JSON;
+use auth_service::utils::{JWT_COOKIE_NAME, TOKEN_TTL_SECONDS};
+use auth_service::{AppConfig, CookieSettings};
+use reqwest::header::CONTENT_TYPE;
+use std::time::Duration;
 
 #[tokio::test]
 async fn should_return_422_if_malformed_credentials() {
@@ -11,6 +15,7 @@ async fn should_return_422_if_malformed_credentials() {
         }))
         .await;
     assert_eq!(response.status().as_u16(), 422);
+    assert_eq!(response.headers()[CONTENT_TYPE], PROBLEM_JSON);
 }
 
 #[tokio::test]
@@ -84,3 +89,44 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
 
     assert!(!auth_cookie.value().is_empty());
 }
+
+#[tokio::test]
+async fn should_set_the_configured_cookie_attributes() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "password123" }))
+        .await;
+    let cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(cookie.http_only());
+    assert!(cookie.same_site_lax());
+    assert!(!cookie.secure());
+    assert_eq!(cookie.path(), Some("/"));
+    assert_eq!(cookie.domain(), None);
+    assert_eq!(
+        cookie.max_age(),
+        Some(Duration::from_secs(TOKEN_TTL_SECONDS as u64))
+    );
+
+    let settings = CookieSettings::default()
+        .with_domain("example.com")
+        .with_path("/auth")
+        .with_secure(true);
+    let app = TestApp::with_config(AppConfig::default().with_cookie_settings(settings)).await;
+    app.signup_and_login(&email, "password123").await;
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "password123" }))
+        .await;
+    let cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(cookie.secure());
+    assert_eq!(cookie.path(), Some("/auth"));
+    assert_eq!(cookie.domain(), Some("example.com"));
+}
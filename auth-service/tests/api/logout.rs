@@ -1,8 +1,10 @@
-use crate::helpers::TestApp;
-use auth_service::utils::JWT_COOKIE_NAME;
-use reqwest::cookie::CookieStore;
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::utils::csrf::csrf_token;
+use auth_service::utils::extractors::TokenPrecedence;
+use auth_service::utils::{CSRF_COOKIE_NAME, CSRF_HEADER, JWT_COOKIE_NAME};
+use auth_service::{AppConfig, CookieSettings};
+use reqwest::header::COOKIE;
 use reqwest::Url;
-use std::io::BufRead;
 
 #[tokio::test]
 async fn should_return_400_if_jwt_cookie_missing() {
@@ -22,13 +24,19 @@ async fn should_return_400_if_jwt_cookie_missing() {
 async fn should_return_401_if_invalid_token() {
     let app = TestApp::new().await;
 
-    // add invalid cookie
+    // add invalid cookie, along with its CSRF cookie so that the request is not rejected
+    // for lacking the CSRF token
+    let url = Url::parse("http://127.0.0.1").expect("Failed to parse URL");
     app.cookie_jar.add_cookie_str(
         &format!(
             "{}=invalid; HttpOnly; SameSite=Lax; Secure; Path=/",
             JWT_COOKIE_NAME
         ),
-        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+        &url,
+    );
+    app.cookie_jar.add_cookie_str(
+        &format!("{}={}; Path=/", CSRF_COOKIE_NAME, csrf_token("invalid")),
+        &url,
     );
 
     let response = app.logout().await;
@@ -44,7 +52,7 @@ async fn should_return_200_if_valid_jwt_cookie() {
     let test_case = serde_json::json!({
         "password": "password123",
         "email": test_email,
-        "requires2FA": true
+        "requires2FA": false
     });
 
     let response = app.post_signup(&test_case).await;
@@ -91,7 +99,7 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
     let test_case = serde_json::json!({
         "password": "password123",
         "email": test_email,
-        "requires2FA": true
+        "requires2FA": false
     });
 
     let response = app.post_signup(&test_case).await;
@@ -110,3 +118,100 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
     let second_logout_response = app.logout().await;
     assert_eq!(second_logout_response.status().as_u16(), 400);
 }
+
+#[tokio::test]
+async fn should_return_200_if_valid_bearer_token() {
+    let app = TestApp::new().await;
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    // A client without the cookie, e.g. a mobile app holding the token.
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("{}/logout", &app.address))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.cookies().all(|c| c.name() != JWT_COOKIE_NAME));
+
+    assert_eq!(
+        app.state()
+            .banned_tokens
+            .read()
+            .await
+            .is_token_banned(&token)
+            .await,
+        Ok(true)
+    );
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_bearer_token() {
+    let app = TestApp::new().await;
+    let response = app.logout_with_bearer("invalid").await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_prefer_configured_token_source() {
+    // By default the header wins over the cookie left in the jar by the login.
+    let app = TestApp::new().await;
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+    let response = app.logout_with_bearer("invalid").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let app = TestApp::with_config(
+        AppConfig::default().with_token_precedence(TokenPrecedence::CookieFirst),
+    )
+    .await;
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+    let response = app.logout_with_bearer("invalid").await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_clear_a_host_prefixed_cookie_with_matching_attributes() {
+    let settings = CookieSettings::default().with_host_prefix();
+    let app = TestApp::with_config(AppConfig::default().with_cookie_settings(settings)).await;
+    let email = get_random_email();
+    let token = app.signup_and_login(&email, "password123").await;
+
+    // The client's cookie jar does not send `Secure` cookies over plain HTTP.
+    let response = reqwest::Client::new()
+        .post(format!("{}/logout", app.address))
+        .header(
+            COOKIE,
+            format!(
+                "__Host-jwt={}; __Host-csrf_token={}",
+                token,
+                csrf_token(&token)
+            ),
+        )
+        .header(CSRF_HEADER, csrf_token(&token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+
+    let cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == "__Host-jwt")
+        .expect("The cookie was not cleared");
+    assert_eq!(cookie.value(), "");
+    assert_eq!(cookie.max_age(), Some(std::time::Duration::ZERO));
+    assert!(cookie.secure());
+    assert!(cookie.http_only());
+    assert_eq!(cookie.path(), Some("/"));
+    assert_eq!(cookie.domain(), None);
+    let cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == "__Host-csrf_token")
+        .expect("The CSRF cookie was not cleared");
+    assert_eq!(cookie.value(), "");
+    assert!(!cookie.http_only());
+}
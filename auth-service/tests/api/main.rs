@@ -1,7 +1,31 @@
+mod admin;
+mod api_keys;
+mod audit;
+mod change_password;
+mod client_credentials;
+mod csrf;
+mod errors;
+mod health;
 mod helpers;
+mod introspect;
+mod invitations;
 mod login;
 mod logout;
+mod metrics;
+mod oidc;
+mod organizations;
+mod request_id;
+mod revoke;
+mod roles;
 mod root;
+mod security_headers;
+mod sessions;
+mod shutdown;
 mod signup;
+mod telemetry;
+mod tls;
+mod user_status;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
+mod webhooks;
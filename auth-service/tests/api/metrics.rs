@@ -0,0 +1,147 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::AppConfig;
+
+const METRICS_TOKEN: &str = "metrics-token";
+
+async fn app_with_metrics() -> TestApp {
+    TestApp::with_config(AppConfig::default().with_metrics_token(METRICS_TOKEN)).await
+}
+
+async fn scrape(app: &TestApp) -> String {
+    let response = app.get_metrics(Some(METRICS_TOKEN)).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .headers()
+        .get("content-type")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("text/plain"));
+    response.text().await.unwrap()
+}
+
+// The value of the sample with exactly these name and labels, e.g. `http_requests_total{...}`.
+fn sample(text: &str, series: &str) -> Option<f64> {
+    text.lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .map(|value| value.parse().unwrap())
+}
+
+#[tokio::test]
+async fn should_count_requests_by_route_and_status() {
+    let app = app_with_metrics().await;
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+    // Static assets are served by the fallback, which no route matches.
+    let response = app
+        .http_client
+        .get(format!("{}/app.js", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app.delete_session("unknown", None).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let text = scrape(&app).await;
+    assert_eq!(
+        sample(
+            &text,
+            r#"http_requests_total{method="POST",route="/signup",status="201"}"#
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(
+            &text,
+            r#"http_request_duration_seconds_count{method="POST",route="/login",status="200"}"#
+        ),
+        Some(1.0)
+    );
+    // Paths with ids are counted by their pattern.
+    assert_eq!(
+        sample(
+            &text,
+            r#"http_requests_total{method="DELETE",route="/sessions/{id}",status="404"}"#
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(
+            &text,
+            r#"http_requests_total{method="GET",route="unmatched",status="200"}"#
+        ),
+        Some(1.0)
+    );
+}
+
+#[tokio::test]
+async fn should_count_logins_by_outcome_and_reason() {
+    let app = app_with_metrics().await;
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+    let wrong_password = serde_json::json!({ "email": email, "password": "wrongpassword" });
+    app.post_login(&wrong_password).await;
+    app.post_login(&wrong_password).await;
+    let invalid = serde_json::json!({ "email": "not an email", "password": "password123" });
+    app.post_login(&invalid).await;
+
+    let text = scrape(&app).await;
+    assert_eq!(sample(&text, "auth_login_successes_total"), Some(1.0));
+    assert_eq!(
+        sample(
+            &text,
+            r#"auth_login_failures_total{reason="incorrect_credentials"}"#
+        ),
+        Some(2.0)
+    );
+    assert_eq!(
+        sample(
+            &text,
+            r#"auth_login_failures_total{reason="validation_failed"}"#
+        ),
+        Some(1.0)
+    );
+}
+
+#[tokio::test]
+async fn should_report_banned_tokens_and_store_latencies() {
+    let app = app_with_metrics().await;
+    let text = scrape(&app).await;
+    assert_eq!(sample(&text, "auth_banned_tokens"), Some(0.0));
+
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    assert_eq!(app.logout_with_bearer(&token).await.status().as_u16(), 200);
+
+    let text = scrape(&app).await;
+    assert_eq!(sample(&text, "auth_banned_tokens"), Some(1.0));
+    assert_eq!(
+        sample(
+            &text,
+            r#"auth_store_operation_duration_seconds_count{operation="add_user",store="users"}"#
+        ),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(
+            &text,
+            r#"auth_store_operation_duration_seconds_count{operation="ban_token",store="banned_tokens"}"#
+        ),
+        Some(1.0)
+    );
+}
+
+#[tokio::test]
+async fn should_require_the_metrics_token() {
+    let app = app_with_metrics().await;
+    assert_eq!(app.get_metrics(None).await.status().as_u16(), 400);
+    let response = app.get_metrics(Some("wrong")).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Not served at all without a token to check.
+    let app = TestApp::new().await;
+    let response = app.get_metrics(Some(METRICS_TOKEN)).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
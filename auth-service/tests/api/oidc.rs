@@ -0,0 +1,512 @@
+use crate::helpers::{get_random_email, TestApp, CODE_CHALLENGE, CODE_VERIFIER};
+use auth_service::domain::{Email, OAuthClient, Scope};
+use auth_service::routes::{AccessTokenResponse, OpenIdConfiguration, UserInfo};
+use auth_service::utils::oidc::IdTokenClaims;
+use auth_service::ErrorResponse;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use reqwest::Url;
+use std::str::FromStr;
+
+const CLIENT_ID: &str = "local-rp";
+const REDIRECT_URI: &str = "http://127.0.0.1:8080/callback";
+
+/// A minimal relying party that drives the authorization code flow the way an
+/// off-the-shelf OpenID Connect library would, using only the discovery document.
+struct RelyingParty<'a> {
+    app: &'a TestApp,
+    configuration: OpenIdConfiguration,
+}
+
+impl<'a> RelyingParty<'a> {
+    async fn discover(app: &'a TestApp) -> RelyingParty<'a> {
+        let response = app.get_openid_configuration().await;
+        assert_eq!(response.status().as_u16(), 200);
+        let configuration = response
+            .json::<OpenIdConfiguration>()
+            .await
+            .expect("Could not deserialize discovery document");
+        RelyingParty { app, configuration }
+    }
+
+    async fn authorize(&self, scope: &str, state: &str, nonce: &str) -> Url {
+        let response = self
+            .app
+            .http_client
+            .get(&self.configuration.authorization_endpoint)
+            .query(&[
+                ("response_type", "code"),
+                ("client_id", CLIENT_ID),
+                ("redirect_uri", REDIRECT_URI),
+                ("scope", scope),
+                ("state", state),
+                ("nonce", nonce),
+                ("code_challenge", CODE_CHALLENGE),
+                ("code_challenge_method", "S256"),
+            ])
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert!(response.status().is_redirection());
+        location(&response)
+    }
+
+    async fn exchange_code(&self, code: &str) -> reqwest::Response {
+        self.app
+            .http_client
+            .post(&self.configuration.token_endpoint)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", REDIRECT_URI),
+                ("client_id", CLIENT_ID),
+                ("code_verifier", CODE_VERIFIER),
+            ])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    async fn verify_id_token(&self, id_token: &str) -> IdTokenClaims {
+        let jwks = self
+            .app
+            .http_client
+            .get(&self.configuration.jwks_uri)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .json::<JwkSet>()
+            .await
+            .expect("Could not deserialize JWK set");
+
+        let header = decode_header(id_token).expect("ID token should have a valid header");
+        assert_eq!(header.alg, Algorithm::EdDSA);
+        let jwk = jwks
+            .find(&header.kid.expect("ID token should name its signing key"))
+            .expect("Signing key should be published in the JWK set");
+
+        let mut validation = Validation::new(Algorithm::EdDSA);
+        validation.set_audience(&[CLIENT_ID]);
+        validation.set_issuer(&[&self.configuration.issuer]);
+        decode::<IdTokenClaims>(id_token, &DecodingKey::from_jwk(jwk).unwrap(), &validation)
+            .expect("ID token should verify against the published key")
+            .claims
+    }
+
+    async fn userinfo(&self, access_token: &str) -> reqwest::Response {
+        self.app
+            .http_client
+            .get(&self.configuration.userinfo_endpoint)
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+}
+
+fn location(response: &reqwest::Response) -> Url {
+    let location = response
+        .headers()
+        .get("location")
+        .expect("Redirect should have a location")
+        .to_str()
+        .unwrap();
+    Url::parse(location)
+        .or_else(|_| Url::parse("http://localhost").unwrap().join(location))
+        .expect("Location should be a valid URL")
+}
+
+fn query_param(url: &Url, name: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+async fn logged_in_app() -> (TestApp, String) {
+    let app = TestApp::new().await;
+    app.register_oauth_client(CLIENT_ID, REDIRECT_URI).await;
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+    (app, email)
+}
+
+#[tokio::test]
+async fn discovery_document_points_at_this_service() {
+    let app = TestApp::new().await;
+    let rp = RelyingParty::discover(&app).await;
+
+    assert_eq!(rp.configuration.issuer, app.address);
+    assert_eq!(
+        rp.configuration.authorization_endpoint,
+        format!("{}/authorize", app.address)
+    );
+    assert_eq!(
+        rp.configuration.token_endpoint,
+        format!("{}/token", app.address)
+    );
+    assert_eq!(
+        rp.configuration.userinfo_endpoint,
+        format!("{}/userinfo", app.address)
+    );
+    assert!(rp
+        .configuration
+        .response_types_supported
+        .contains(&"code".to_owned()));
+    assert!(rp
+        .configuration
+        .scopes_supported
+        .contains(&"openid".to_owned()));
+}
+
+#[tokio::test]
+async fn authorization_code_flow_issues_verifiable_id_token() {
+    let (app, email) = logged_in_app().await;
+    let rp = RelyingParty::discover(&app).await;
+
+    let callback = rp
+        .authorize("openid email", "af0ifjsldkj", "n-0S6_WzA2Mj")
+        .await;
+    assert_eq!(callback.as_str().split('?').next(), Some(REDIRECT_URI));
+    assert_eq!(
+        query_param(&callback, "state").as_deref(),
+        Some("af0ifjsldkj")
+    );
+    let code = query_param(&callback, "code").expect("Callback should carry a code");
+
+    let response = rp.exchange_code(&code).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get("cache-control").unwrap(), "no-store");
+    let tokens = response
+        .json::<AccessTokenResponse>()
+        .await
+        .expect("Could not deserialize token response");
+    assert_eq!(tokens.token_type, "Bearer");
+    assert_eq!(tokens.scope, "openid email");
+
+    let claims = rp
+        .verify_id_token(&tokens.id_token.expect("ID token should be issued"))
+        .await;
+    assert_ne!(claims.sub, email);
+    assert_eq!(claims.nonce.as_deref(), Some("n-0S6_WzA2Mj"));
+    assert_eq!(claims.email.as_deref(), Some(email.as_str()));
+    assert_eq!(claims.email_verified, Some(false));
+
+    let response = rp.userinfo(&tokens.access_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let user_info = response
+        .json::<UserInfo>()
+        .await
+        .expect("Could not deserialize userinfo response");
+    assert_eq!(user_info.sub, claims.sub);
+    assert_eq!(user_info.email.as_deref(), Some(email.as_str()));
+    assert_eq!(user_info.email_verified, Some(false));
+}
+
+#[tokio::test]
+async fn subject_is_kept_when_the_email_changes() {
+    let (app, email) = logged_in_app().await;
+    let rp = RelyingParty::discover(&app).await;
+    let subject = id_token_subject(&rp).await;
+
+    let new_email = get_random_email();
+    app.state()
+        .user_store
+        .write()
+        .await
+        .change_email(
+            &Email::from_str(&email).unwrap().into(),
+            Email::from_str(&new_email).unwrap(),
+        )
+        .await
+        .unwrap();
+    app.login(&new_email, "password123").await;
+
+    assert_eq!(id_token_subject(&rp).await, subject);
+}
+
+async fn id_token_subject(rp: &RelyingParty<'_>) -> String {
+    let callback = rp.authorize("openid", "state", "nonce").await;
+    let code = query_param(&callback, "code").unwrap();
+    let tokens = rp
+        .exchange_code(&code)
+        .await
+        .json::<AccessTokenResponse>()
+        .await
+        .unwrap();
+    rp.verify_id_token(&tokens.id_token.unwrap()).await.sub
+}
+
+#[tokio::test]
+async fn email_claims_require_email_scope() {
+    let (app, _email) = logged_in_app().await;
+    let rp = RelyingParty::discover(&app).await;
+
+    let callback = rp.authorize("openid", "state", "nonce").await;
+    let code = query_param(&callback, "code").unwrap();
+    let tokens = rp
+        .exchange_code(&code)
+        .await
+        .json::<AccessTokenResponse>()
+        .await
+        .unwrap();
+
+    let claims = rp.verify_id_token(&tokens.id_token.unwrap()).await;
+    assert_eq!(claims.email, None);
+    assert_eq!(claims.email_verified, None);
+
+    let user_info = rp
+        .userinfo(&tokens.access_token)
+        .await
+        .json::<UserInfo>()
+        .await
+        .unwrap();
+    assert_eq!(user_info.email, None);
+}
+
+#[tokio::test]
+async fn authorization_code_cannot_be_redeemed_twice() {
+    let (app, _email) = logged_in_app().await;
+    let rp = RelyingParty::discover(&app).await;
+
+    let callback = rp.authorize("openid", "state", "nonce").await;
+    let code = query_param(&callback, "code").unwrap();
+
+    assert_eq!(rp.exchange_code(&code).await.status().as_u16(), 200);
+
+    let response = rp.exchange_code(&code).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "invalid_grant"
+    );
+}
+
+#[tokio::test]
+async fn token_endpoint_rejects_mismatched_redirect_uri() {
+    let (app, _email) = logged_in_app().await;
+    let rp = RelyingParty::discover(&app).await;
+
+    let callback = rp.authorize("openid", "state", "nonce").await;
+    let code = query_param(&callback, "code").unwrap();
+
+    let response = app
+        .post_token(&serde_json::json!({
+            "grant_type": "authorization_code",
+            "code": code,
+            "redirect_uri": "http://127.0.0.1:8080/other",
+            "client_id": CLIENT_ID,
+            "code_verifier": CODE_VERIFIER,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "invalid_grant"
+    );
+}
+
+#[tokio::test]
+async fn token_endpoint_rejects_unsupported_grant_type() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_token(&serde_json::json!({ "grant_type": "password" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "unsupported_grant_type"
+    );
+}
+
+#[tokio::test]
+async fn authorize_does_not_redirect_to_unregistered_uri() {
+    let (app, _email) = logged_in_app().await;
+
+    let response = app
+        .get_authorize(&serde_json::json!({
+            "response_type": "code",
+            "client_id": CLIENT_ID,
+            "redirect_uri": "http://evil.example.com/callback",
+            "scope": "openid",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .get_authorize(&serde_json::json!({
+            "response_type": "code",
+            "client_id": "unknown-client",
+            "redirect_uri": REDIRECT_URI,
+            "scope": "openid",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn authorize_reports_errors_to_the_client() {
+    let (app, _email) = logged_in_app().await;
+
+    let response = app
+        .get_authorize(&serde_json::json!({
+            "response_type": "token",
+            "client_id": CLIENT_ID,
+            "redirect_uri": REDIRECT_URI,
+            "scope": "openid",
+            "state": "abc",
+        }))
+        .await;
+    assert!(response.status().is_redirection());
+    let callback = location(&response);
+    assert_eq!(
+        query_param(&callback, "error").as_deref(),
+        Some("unsupported_response_type")
+    );
+    assert_eq!(query_param(&callback, "state").as_deref(), Some("abc"));
+}
+
+#[tokio::test]
+async fn authorize_sends_anonymous_users_to_login() {
+    let app = TestApp::new().await;
+    app.register_oauth_client(CLIENT_ID, REDIRECT_URI).await;
+
+    let response = app
+        .get_authorize(&serde_json::json!({
+            "response_type": "code",
+            "client_id": CLIENT_ID,
+            "redirect_uri": REDIRECT_URI,
+            "scope": "openid",
+            "code_challenge": CODE_CHALLENGE,
+            "code_challenge_method": "S256",
+        }))
+        .await;
+    assert!(response.status().is_redirection());
+    let login = location(&response);
+    assert_eq!(login.path(), "/");
+    assert!(query_param(&login, "return_to")
+        .unwrap()
+        .starts_with("/authorize?"));
+}
+
+#[tokio::test]
+async fn userinfo_requires_openid_access_token() {
+    let app = TestApp::new().await;
+    let response = app.get_userinfo(None).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let session_token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let response = app.get_userinfo(Some("invalid")).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // A browser session token was not issued for any OpenID Connect scope.
+    let response = app.get_userinfo(Some(&session_token)).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn confidential_clients_must_authenticate_to_redeem_codes() {
+    let app = TestApp::new().await;
+    app.state
+        .oauth_clients
+        .write()
+        .await
+        .add_client(OAuthClient::confidential(
+            CLIENT_ID.to_owned(),
+            "s3cr3t",
+            vec![REDIRECT_URI.to_owned()],
+            Scope::default(),
+        ))
+        .await
+        .unwrap();
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+    let rp = RelyingParty::discover(&app).await;
+
+    let callback = rp.authorize("openid", "state", "nonce").await;
+    let code = query_param(&callback, "code").unwrap();
+
+    let response = rp.exchange_code(&code).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "invalid_client"
+    );
+}
+
+#[tokio::test]
+async fn authorize_requires_pkce_for_public_clients() {
+    let (app, _email) = logged_in_app().await;
+
+    for (challenge, method) in [
+        (None, None),
+        (Some(CODE_CHALLENGE), Some("plain")),
+        (Some("too-short"), Some("S256")),
+    ] {
+        let mut query = vec![
+            ("response_type", "code"),
+            ("client_id", CLIENT_ID),
+            ("redirect_uri", REDIRECT_URI),
+            ("scope", "openid"),
+        ];
+        query.extend(challenge.map(|challenge| ("code_challenge", challenge)));
+        query.extend(method.map(|method| ("code_challenge_method", method)));
+        let response = app.get_authorize(&query).await;
+        assert!(response.status().is_redirection());
+        let callback = location(&response);
+        assert_eq!(
+            query_param(&callback, "error").as_deref(),
+            Some("invalid_request"),
+            "Failed for {:?}",
+            (challenge, method)
+        );
+        assert_eq!(query_param(&callback, "code"), None);
+    }
+}
+
+#[tokio::test]
+async fn token_endpoint_rejects_wrong_code_verifier() {
+    let (app, _email) = logged_in_app().await;
+    let rp = RelyingParty::discover(&app).await;
+
+    let wrong_verifier = "a".repeat(43);
+    for verifier in [None, Some(wrong_verifier.as_str())] {
+        let callback = rp.authorize("openid", "state", "nonce").await;
+        let code = query_param(&callback, "code").unwrap();
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code.as_str()),
+            ("redirect_uri", REDIRECT_URI),
+            ("client_id", CLIENT_ID),
+        ];
+        form.extend(verifier.map(|verifier| ("code_verifier", verifier)));
+        let response = app.post_token(&form).await;
+        assert_eq!(response.status().as_u16(), 400);
+        assert_eq!(
+            response.json::<ErrorResponse>().await.unwrap().error,
+            "invalid_grant"
+        );
+    }
+}
+
+#[tokio::test]
+async fn discovery_does_not_trust_the_host_header() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/.well-known/openid-configuration", &app.address))
+        .header("host", "evil.example.com")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let configuration = response
+        .json::<OpenIdConfiguration>()
+        .await
+        .expect("Could not deserialize discovery document");
+    assert_eq!(configuration.issuer, app.address);
+    assert_eq!(configuration.code_challenge_methods_supported, vec!["S256"]);
+}
@@ -0,0 +1,251 @@
+use crate::helpers::{get_random_email, get_random_org_id, TestApp};
+use auth_service::domain::Role;
+use auth_service::routes::{InvitationResponse, MemberResponse, OrganizationResponse};
+use auth_service::utils::problem::Problem;
+use auth_service::{AppConfig, EmailUniqueness};
+
+async fn claims(app: &TestApp, token: &str) -> serde_json::Value {
+    let response = app.post_verify_token_with_bearer(token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn should_create_organization_with_creator_as_org_admin() {
+    let app = TestApp::new().await;
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let response = app
+        .post_orgs("", &token, &serde_json::json!({ "name": "Acme Inc." }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let created: OrganizationResponse = response.json().await.unwrap();
+    assert_eq!(created.id, "acme-inc");
+    assert_eq!(created.roles, vec![Role::User, Role::OrgAdmin]);
+
+    let response = app.get_orgs("", &token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let listed: Vec<OrganizationResponse> = response.json().await.unwrap();
+    assert_eq!(listed, vec![created]);
+}
+
+#[tokio::test]
+async fn should_reject_invalid_or_duplicate_organizations() {
+    let app = TestApp::new().await;
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    let id = get_random_org_id();
+    app.create_org(&token, &id).await;
+
+    let response = app
+        .post_orgs(
+            "",
+            &token,
+            &serde_json::json!({ "id": id, "name": "Again" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    for (body, field) in [
+        (
+            serde_json::json!({ "id": "Not/A/Slug", "name": "Acme" }),
+            "id",
+        ),
+        (serde_json::json!({ "name": "  " }), "name"),
+    ] {
+        let response = app.post_orgs("", &token, &body).await;
+        assert_eq!(response.status().as_u16(), 400, "{body}");
+        let problem: Problem = response.json().await.unwrap();
+        let fields: Vec<&str> = problem.errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec![field], "{body}");
+    }
+}
+
+#[tokio::test]
+async fn should_switch_organization_and_revoke_previous_token() {
+    let app = TestApp::new().await;
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    let id = get_random_org_id();
+    app.create_org(&token, &id).await;
+
+    let org_token = app.switch_org(&token, &id).await;
+    let claims = claims(&app, &org_token).await;
+    assert_eq!(claims["org_id"], id);
+    assert_eq!(
+        claims["permissions"],
+        serde_json::json!(["members:read", "members:write"])
+    );
+    assert_eq!(
+        app.post_verify_token_with_bearer(&token)
+            .await
+            .status()
+            .as_u16(),
+        401
+    );
+
+    let response = app
+        .post_orgs(
+            "/switch",
+            &org_token,
+            &serde_json::json!({ "orgId": "unknown" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn should_manage_members_with_org_scoped_token() {
+    let app = TestApp::new().await;
+    let owner = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    let member_email = get_random_email();
+    app.signup_and_login(&member_email, "password123").await;
+    let id = get_random_org_id();
+    app.create_org(&owner, &id).await;
+    let path = format!("/{id}/members");
+    let body = serde_json::json!({ "email": member_email, "roles": ["user"] });
+
+    // The token has to be scoped to the organization first.
+    let response = app.post_orgs(&path, &owner, &body).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let owner = app.switch_org(&owner, &id).await;
+    let response = app.post_orgs(&path, &owner, &body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_orgs(&path, &owner).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let members: Vec<MemberResponse> = response.json().await.unwrap();
+    assert_eq!(members.len(), 2);
+    assert!(members
+        .iter()
+        .any(|member| member.email == member_email && member.roles == vec![Role::User]));
+
+    // Organization admins cannot hand out global roles.
+    let response = app
+        .post_orgs(
+            &path,
+            &owner,
+            &serde_json::json!({ "email": member_email, "roles": ["admin"] }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    // Plain members can neither manage nor list members.
+    let member = app
+        .post_login(&serde_json::json!({
+            "email": member_email,
+            "password": "password123",
+            "orgId": id,
+        }))
+        .await;
+    assert_eq!(member.status().as_u16(), 200);
+    let member = member
+        .cookies()
+        .find(|cookie| cookie.name() == auth_service::utils::JWT_COOKIE_NAME)
+        .unwrap()
+        .value()
+        .to_owned();
+    assert_eq!(app.get_orgs(&path, &member).await.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn should_reject_login_to_organization_without_membership() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let owner = app.signup_and_login(&email, "password123").await;
+    let id = get_random_org_id();
+    app.create_org(&owner, &id).await;
+
+    let outsider = get_random_email();
+    app.signup_and_login(&outsider, "password123").await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": outsider,
+            "password": "password123",
+            "orgId": id,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn should_not_join_organizations_by_signing_up() {
+    let app = TestApp::new().await;
+    let owner = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    let id = get_random_org_id();
+    app.create_org(&owner, &id).await;
+
+    let email = get_random_email();
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false,
+            "orgId": id,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    let problem: Problem = response.json().await.unwrap();
+    assert_eq!(problem.errors[0].field, "orgId");
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_allow_same_email_in_each_tenant() {
+    let app = TestApp::with_config(
+        AppConfig::default().with_email_uniqueness(EmailUniqueness::PerTenant),
+    )
+    .await;
+    let owner = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    let (acme, globex) = (get_random_org_id(), get_random_org_id());
+    app.create_org(&owner, &acme).await;
+    app.create_org(&owner, &globex).await;
+
+    // Accounts in a tenant are created by accepting its invitations.
+    let email = get_random_email();
+    let mut token = owner;
+    for (org_id, password) in [(&acme, "password-acme"), (&globex, "password-globex")] {
+        token = app.switch_org(&token, org_id).await;
+        let response = app
+            .post_invitations("", Some(&token), &serde_json::json!({ "email": email }))
+            .await;
+        let invitation: InvitationResponse = response.json().await.unwrap();
+        let response = app
+            .post_invitations(
+                "/accept",
+                None,
+                &serde_json::json!({ "token": invitation.token, "password": password }),
+            )
+            .await;
+        assert_eq!(response.status().as_u16(), 201);
+    }
+
+    for (org_id, password, status) in [
+        (&acme, "password-acme", 200),
+        (&globex, "password-globex", 200),
+        (&acme, "password-globex", 401),
+    ] {
+        let response = app
+            .post_login(&serde_json::json!({
+                "email": email,
+                "password": password,
+                "orgId": org_id,
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), status);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::AuditRecord;
+use auth_service::utils::REQUEST_ID_HEADER;
+use auth_service::AppConfig;
+
+const ADMIN_EMAIL: &str = "admin@example.com";
+
+fn request_id(response: &reqwest::Response) -> String {
+    response
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .expect("No request id returned")
+        .to_str()
+        .unwrap()
+        .to_owned()
+}
+
+#[tokio::test]
+async fn should_return_the_request_id_sent() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+    let response = app
+        .http_client
+        .post(format!("{}/login", &app.address))
+        .header(REQUEST_ID_HEADER, "req-42")
+        .json(&serde_json::json!({ "email": email, "password": "wrong-password" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(request_id(&response), "req-42");
+}
+
+#[tokio::test]
+async fn should_generate_a_request_id_and_use_it_everywhere() {
+    let app = TestApp::with_config(AppConfig::default().with_admin_email(ADMIN_EMAIL)).await;
+    let admin = app
+        .signup_verified_and_login(ADMIN_EMAIL, "password123")
+        .await;
+
+    let email = get_random_email();
+    let invalid = ["", "a b", &"a".repeat(200)];
+    let mut generated = Vec::new();
+    for value in invalid {
+        let mut request = app.http_client.post(format!("{}/signup", &app.address));
+        if !value.is_empty() {
+            request = request.header(REQUEST_ID_HEADER, value);
+        }
+        let response = request
+            .json(&serde_json::json!({
+                "email": email,
+                "password": "password123",
+                "requires2FA": false
+            }))
+            .send()
+            .await
+            .unwrap();
+        let id = request_id(&response);
+        assert!(uuid::Uuid::parse_str(&id).is_ok(), "{}", id);
+        generated.push(id);
+    }
+    generated.dedup();
+    assert_eq!(generated.len(), invalid.len());
+
+    // The id in the response is the one the request was audited with.
+    let records: Vec<AuditRecord> = app
+        .get_audit_log(&admin, &[("user", email.as_str())])
+        .await
+        .json()
+        .await
+        .unwrap();
+    let mut audited: Vec<String> = records.into_iter().map(|r| r.request_id).collect();
+    audited.sort();
+    generated.sort();
+    assert_eq!(audited, generated);
+}
@@ -0,0 +1,190 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::routes::AccessTokenResponse;
+use auth_service::ErrorResponse;
+
+const CLIENT_ID: &str = "mobile-app";
+const REDIRECT_URI: &str = "com.example.app:/callback";
+
+async fn app_with_tokens() -> (TestApp, AccessTokenResponse) {
+    let app = TestApp::new().await;
+    app.register_oauth_client(CLIENT_ID, REDIRECT_URI).await;
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+    let tokens = app
+        .authorization_code_tokens(CLIENT_ID, REDIRECT_URI, "openid")
+        .await;
+    (app, tokens)
+}
+
+async fn refresh(app: &TestApp, refresh_token: &str) -> reqwest::Response {
+    app.post_token(&serde_json::json!({
+        "grant_type": "refresh_token",
+        "refresh_token": refresh_token,
+        "client_id": CLIENT_ID,
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn refresh_token_grant_rotates_refresh_token() {
+    let (app, tokens) = app_with_tokens().await;
+    let refresh_token = tokens
+        .refresh_token
+        .expect("Refresh token should be issued");
+
+    let response = refresh(&app, &refresh_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let refreshed = response.json::<AccessTokenResponse>().await.unwrap();
+    assert_eq!(refreshed.scope, "openid");
+    let new_refresh_token = refreshed.refresh_token.unwrap();
+    assert_ne!(new_refresh_token, refresh_token);
+
+    let response = refresh(&app, &refresh_token).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "invalid_grant"
+    );
+
+    assert_eq!(
+        refresh(&app, &new_refresh_token).await.status().as_u16(),
+        200
+    );
+}
+
+#[tokio::test]
+async fn should_revoke_access_token() {
+    let (app, tokens) = app_with_tokens().await;
+
+    let response = app
+        .post_revoke(&serde_json::json!({
+            "token": tokens.access_token,
+            "token_type_hint": "access_token",
+            "client_id": CLIENT_ID,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": tokens.access_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        app.get_userinfo(Some(&tokens.access_token))
+            .await
+            .status()
+            .as_u16(),
+        401
+    );
+}
+
+#[tokio::test]
+async fn should_revoke_refresh_token() {
+    let (app, tokens) = app_with_tokens().await;
+    let refresh_token = tokens.refresh_token.unwrap();
+
+    let response = app
+        .post_revoke(&serde_json::json!({
+            "token": refresh_token,
+            "token_type_hint": "refresh_token",
+            "client_id": CLIENT_ID,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(refresh(&app, &refresh_token).await.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn should_find_token_despite_wrong_hint() {
+    let (app, tokens) = app_with_tokens().await;
+    let refresh_token = tokens.refresh_token.unwrap();
+
+    let response = app
+        .post_revoke(&serde_json::json!({
+            "token": refresh_token,
+            "token_type_hint": "access_token",
+            "client_id": CLIENT_ID,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(refresh(&app, &refresh_token).await.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn should_return_200_for_unknown_token() {
+    let app = TestApp::new().await;
+    app.register_oauth_client(CLIENT_ID, REDIRECT_URI).await;
+
+    let response = app
+        .post_revoke(&serde_json::json!({
+            "token": "unknown",
+            "client_id": CLIENT_ID,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_revoke_browser_session_token() {
+    let app = TestApp::new().await;
+    app.register_oauth_client(CLIENT_ID, REDIRECT_URI).await;
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let response = app
+        .post_revoke(&serde_json::json!({ "token": token, "client_id": CLIENT_ID }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_not_revoke_tokens_of_other_clients() {
+    let (app, tokens) = app_with_tokens().await;
+    app.register_oauth_client("other-app", "com.example.other:/callback")
+        .await;
+
+    let response = app
+        .post_revoke(&serde_json::json!({
+            "token": tokens.access_token,
+            "client_id": "other-app",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": tokens.access_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        refresh(&app, &tokens.refresh_token.unwrap())
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+}
+
+#[tokio::test]
+async fn should_return_401_for_unknown_client() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .post_revoke(&serde_json::json!({
+            "token": "anything",
+            "client_id": "unknown-client",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "invalid_client"
+    );
+}
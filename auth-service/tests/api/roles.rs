@@ -0,0 +1,128 @@
+use crate::helpers::{get_random_email, get_random_org_id, TestApp};
+use auth_service::domain::{Email, Role, UserId};
+use auth_service::routes::InvitationResponse;
+use auth_service::utils::auth::{sign_email_verification, Claims};
+use auth_service::{AppConfig, EmailUniqueness};
+use chrono::Utc;
+use std::str::FromStr;
+
+async fn verified_claims(app: &TestApp, token: &str) -> Claims {
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json()
+        .await
+        .expect("Could not deserialize response body to Claims")
+}
+
+#[tokio::test]
+async fn regular_users_have_no_permissions() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let token = app.signup_and_login(&email, "password123").await;
+
+    let claims = verified_claims(&app, &token).await;
+    assert_eq!(claims.sub, email);
+    assert_eq!(claims.roles, vec![Role::User]);
+    assert!(claims.permissions.is_empty());
+}
+
+#[tokio::test]
+async fn configured_admin_emails_become_admins_once_verified() {
+    let email = get_random_email();
+    let app =
+        TestApp::with_config(AppConfig::default().with_admin_email(email.to_uppercase())).await;
+    // Whoever registers the email first does not get the role before proving they own it.
+    let token = app.signup_and_login(&email, "password123").await;
+    assert_eq!(verified_claims(&app, &token).await.roles, vec![Role::User]);
+
+    let token = app
+        .signup_verified_and_login(&get_random_email(), "password123")
+        .await;
+    assert!(verified_claims(&app, &token).await.permissions.is_empty());
+
+    let response = app.post_verify_email(&email).await;
+    assert_eq!(response.status().as_u16(), 204);
+    let token = app.login(&email, "password123").await;
+    let claims = verified_claims(&app, &token).await;
+    assert_eq!(claims.roles, vec![Role::User, Role::Admin]);
+    assert!(claims.has_permission("users:read"));
+    assert!(claims.has_permission("users:write"));
+}
+
+#[tokio::test]
+async fn admin_emails_are_not_admins_in_a_tenant() {
+    let email = get_random_email();
+    let app = TestApp::with_config(
+        AppConfig::default()
+            .with_admin_email(&email)
+            .with_email_uniqueness(EmailUniqueness::PerTenant),
+    )
+    .await;
+    let owner = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    let org_id = get_random_org_id();
+    app.create_org(&owner, &org_id).await;
+    let owner = app.switch_org(&owner, &org_id).await;
+    let response = app
+        .post_invitations("", Some(&owner), &serde_json::json!({ "email": email }))
+        .await;
+    let invitation: InvitationResponse = response.json().await.unwrap();
+    let response = app
+        .post_invitations(
+            "/accept",
+            None,
+            &serde_json::json!({ "token": invitation.token, "password": "password123" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let id = UserId::new(Some(org_id), Email::from_str(&email).unwrap());
+    let token = sign_email_verification(&id, Utc::now() + chrono::Duration::hours(1));
+    let response = app
+        .http_client
+        .post(format!("{}/verify-email", &app.address))
+        .json(&serde_json::json!({ "token": token }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    assert!(!app
+        .state()
+        .user_store
+        .read()
+        .await
+        .get_user(&id)
+        .await
+        .unwrap()
+        .has_role(Role::Admin));
+}
+
+#[tokio::test]
+async fn role_changes_apply_to_tokens_issued_afterwards() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let before = app.signup_and_login(&email, "password123").await;
+
+    app.state()
+        .user_store
+        .write()
+        .await
+        .set_roles(
+            &Email::from_str(&email).unwrap().into(),
+            vec![Role::User, Role::Admin],
+        )
+        .await
+        .unwrap();
+    let after = app.login(&email, "password123").await;
+
+    assert!(!verified_claims(&app, &before)
+        .await
+        .has_permission("users:read"));
+    assert!(verified_claims(&app, &after)
+        .await
+        .has_permission("users:read"));
+}
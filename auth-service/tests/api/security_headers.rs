@@ -0,0 +1,69 @@
+use crate::helpers::TestApp;
+use auth_service::AppConfig;
+use reqwest::header::{
+    CONTENT_SECURITY_POLICY, REFERRER_POLICY, STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS,
+    X_FRAME_OPTIONS,
+};
+use security_headers::SecurityHeaderSettings;
+use std::time::Duration;
+
+// The nonce of the `Content-Security-Policy` of the response.
+fn nonce(response: &reqwest::Response) -> String {
+    let policy = response.headers()[CONTENT_SECURITY_POLICY]
+        .to_str()
+        .unwrap();
+    let start = policy.find("'nonce-").expect("No nonce in the policy") + "'nonce-".len();
+    let end = start + policy[start..].find('\'').unwrap();
+    policy[start..end].to_owned()
+}
+
+#[tokio::test]
+async fn should_allow_the_scripts_of_the_page_with_a_nonce() {
+    let app = TestApp::new().await;
+
+    let response = app.get_root().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let nonce = nonce(&response);
+    let page = response.text().await.unwrap();
+    assert_eq!(
+        page.matches(&format!("<script nonce=\"{}\"", nonce))
+            .count(),
+        page.matches("<script").count()
+    );
+
+    let response = app.get_root().await;
+    assert_ne!(self::nonce(&response), nonce);
+}
+
+#[tokio::test]
+async fn should_add_the_security_headers_to_every_response() {
+    let app = TestApp::new().await;
+
+    let response = app.logout().await;
+    assert_eq!(response.status().as_u16(), 400);
+    let headers = response.headers();
+    assert!(headers.contains_key(CONTENT_SECURITY_POLICY));
+    assert_eq!(headers[X_CONTENT_TYPE_OPTIONS], "nosniff");
+    assert_eq!(headers[X_FRAME_OPTIONS], "DENY");
+    assert_eq!(headers[REFERRER_POLICY], "no-referrer");
+    // Only sent over HTTPS by default.
+    assert!(!headers.contains_key(STRICT_TRANSPORT_SECURITY));
+}
+
+#[tokio::test]
+async fn should_send_the_configured_security_headers() {
+    let settings = SecurityHeaderSettings::new()
+        .with_content_security_policy(Some("default-src 'self'".to_owned()))
+        .with_hsts_max_age(Some(Duration::from_secs(600)))
+        .with_frame_options(Some("SAMEORIGIN".to_owned()))
+        .with_referrer_policy(None);
+    let app = TestApp::with_config(AppConfig::default().with_security_headers(settings)).await;
+
+    let response = app.get_root().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let headers = response.headers();
+    assert_eq!(headers[CONTENT_SECURITY_POLICY], "default-src 'self'");
+    assert_eq!(headers[STRICT_TRANSPORT_SECURITY], "max-age=600");
+    assert_eq!(headers[X_FRAME_OPTIONS], "SAMEORIGIN");
+    assert!(!headers.contains_key(REFERRER_POLICY));
+}
@@ -0,0 +1,224 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::routes::{ApiKeyResponse, SessionResponse};
+use auth_service::utils::JWT_COOKIE_NAME;
+
+async fn sessions(app: &TestApp, token: Option<&str>) -> Vec<SessionResponse> {
+    let response = app.get_sessions(token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .json()
+        .await
+        .expect("Could not deserialize response body to sessions")
+}
+
+#[tokio::test]
+async fn should_return_400_if_token_missing() {
+    let app = TestApp::new().await;
+    assert_eq!(app.get_sessions(None).await.status().as_u16(), 400);
+    assert_eq!(app.delete_sessions(None).await.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn should_list_sessions_created_at_login() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let first = app.signup_and_login(&email, "password123").await;
+    let _second = app.login(&email, "password123").await;
+
+    // The cookie now carries the second session.
+    let listed = sessions(&app, None).await;
+    assert_eq!(listed.len(), 2);
+    assert_eq!(listed.iter().filter(|session| session.current).count(), 1);
+    for session in &listed {
+        assert!(session.created_at <= session.last_seen);
+        assert!(session.expires_at > session.created_at);
+        assert_eq!(session.ip.as_deref(), Some("127.0.0.1"));
+    }
+
+    // Sessions of other users are not listed.
+    let other = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    assert_eq!(sessions(&app, Some(&other)).await.len(), 1);
+    assert_eq!(sessions(&app, Some(&first)).await.len(), 2);
+}
+
+#[tokio::test]
+async fn should_record_user_agent() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let token = app.signup_and_login(&email, "password123").await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/login", &app.address))
+        .header("User-Agent", "session-test/1.0")
+        .json(&serde_json::json!({ "email": email, "password": "password123" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+
+    let listed = sessions(&app, Some(&token)).await;
+    assert!(listed
+        .iter()
+        .any(|session| session.user_agent.as_deref() == Some("session-test/1.0")));
+}
+
+#[tokio::test]
+async fn should_update_last_seen_on_verify_token() {
+    let app = TestApp::new().await;
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    let before = sessions(&app, Some(&token)).await.remove(0);
+
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let after = sessions(&app, Some(&token)).await.remove(0);
+    assert!(after.last_seen > before.last_seen);
+}
+
+#[tokio::test]
+async fn should_revoke_a_single_session() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let first = app.signup_and_login(&email, "password123").await;
+    let second = app.login(&email, "password123").await;
+
+    let listed = sessions(&app, Some(&second)).await;
+    let first_session = listed.iter().find(|session| !session.current).unwrap();
+
+    let response = app.delete_session(&first_session.id, Some(&second)).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    // The revoked token is banned, the other session keeps working.
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": first }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    let listed = sessions(&app, Some(&second)).await;
+    assert_eq!(listed.len(), 1);
+    assert!(listed[0].current);
+
+    let response = app.delete_session(&first_session.id, Some(&second)).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn should_return_404_for_sessions_of_other_users() {
+    let app = TestApp::new().await;
+    let victim = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    let victim_session = sessions(&app, Some(&victim)).await.remove(0);
+
+    let attacker = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    let response = app
+        .delete_session(&victim_session.id, Some(&attacker))
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": victim }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_clear_cookie_when_revoking_current_session() {
+    let app = TestApp::new().await;
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+    let current = sessions(&app, None).await.remove(0);
+
+    let response = app.delete_session(&current.id, None).await;
+    assert_eq!(response.status().as_u16(), 204);
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(auth_cookie.value().is_empty());
+}
+
+#[tokio::test]
+async fn should_log_out_everywhere() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let first = app.signup_and_login(&email, "password123").await;
+    let second = app.login(&email, "password123").await;
+    let other = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let response = app.delete_sessions(Some(&first)).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    for token in [&first, &second] {
+        let response = app
+            .post_verify_token(&serde_json::json!({ "token": token }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+    assert_eq!(app.get_sessions(Some(&first)).await.status().as_u16(), 401);
+
+    // Other users are not affected.
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": other }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_remove_session_on_logout() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let first = app.signup_and_login(&email, "password123").await;
+    let second = app.login(&email, "password123").await;
+
+    let response = app.logout_with_bearer(&second).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let listed = sessions(&app, Some(&first)).await;
+    assert_eq!(listed.len(), 1);
+    assert!(listed[0].current);
+}
+
+#[tokio::test]
+async fn should_not_manage_sessions_with_oauth_access_tokens_or_api_keys() {
+    let app = TestApp::new().await;
+    app.register_oauth_client("relying-party", "http://localhost/callback")
+        .await;
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    let access_token = app
+        .authorization_code_tokens("relying-party", "http://localhost/callback", "openid")
+        .await
+        .access_token;
+    let response = app
+        .post_api_key(&token, &serde_json::json!({ "name": "ci" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let key: ApiKeyResponse = response.json().await.unwrap();
+    let key = key.key.unwrap();
+    let id = sessions(&app, Some(&token)).await[0].id.clone();
+
+    for credential in [&access_token, &key] {
+        let response = app.get_sessions(Some(credential)).await;
+        assert_eq!(response.status().as_u16(), 403);
+        let response = app.delete_session(&id, Some(credential)).await;
+        assert_eq!(response.status().as_u16(), 403);
+        let response = app.delete_sessions(Some(credential)).await;
+        assert_eq!(response.status().as_u16(), 403);
+    }
+
+    // The login of the user is left untouched.
+    assert_eq!(sessions(&app, Some(&token)).await.len(), 1);
+}
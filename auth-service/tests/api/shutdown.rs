@@ -0,0 +1,53 @@
+use crate::helpers::{get_random_email, TestApp};
+use crate::webhooks::{create_webhook, Receiver, ADMIN_EMAIL};
+use auth_service::{AppConfig, OutboxSettings, ShutdownSettings, WebhookSettings};
+use std::time::Duration;
+
+#[tokio::test]
+async fn should_stop_serving_once_shut_down() {
+    let app = TestApp::new().await;
+    let http_client = app.http_client.clone();
+    let address = app.address.clone();
+    assert_eq!(app.get_root().await.status().as_u16(), 200);
+
+    tokio::time::timeout(Duration::from_secs(5), app.stop())
+        .await
+        .expect("The server did not stop")
+        .expect("The server failed");
+
+    let result = http_client.get(format!("{}/", address)).send().await;
+    assert!(result.is_err(), "The server still answers: {:?}", result);
+}
+
+#[tokio::test]
+async fn should_deliver_pending_events_on_shutdown() {
+    // The background tasks poll once at startup and then not for an hour, so the new
+    // user's event can only be delivered by their final pass.
+    let hour = Duration::from_secs(60 * 60);
+    let config = AppConfig::default()
+        .with_admin_email(ADMIN_EMAIL)
+        .with_outbox_settings(OutboxSettings::new(hour, hour, 12))
+        .with_webhook_settings(WebhookSettings::new(hour, hour, 5))
+        .with_shutdown_settings(ShutdownSettings::new(Duration::from_secs(5)));
+    let app = TestApp::with_config(config).await;
+    let admin = app
+        .signup_verified_and_login(ADMIN_EMAIL, "password123")
+        .await;
+    let mut receiver = Receiver::spawn(0).await;
+    create_webhook(&app, &admin, &receiver.url, &["user.created"]).await;
+
+    let email = get_random_email();
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.stop().await.expect("The server failed");
+
+    // The admin's own signup may be delivered first, unless the startup poll got to it.
+    while !receiver.next().await.1.contains(&email) {}
+}
@@ -1,6 +1,7 @@
 use crate::helpers::{get_random_email, TestApp};
 use auth_service::routes::SignupResponse;
-use auth_service::ErrorResponse;
+use auth_service::utils::problem::{Problem, PROBLEM_JSON};
+use reqwest::header::CONTENT_TYPE;
 
 #[tokio::test]
 async fn should_return_422_if_malformed_input() {
@@ -42,6 +43,7 @@ async fn should_return_422_if_malformed_input() {
             "Failed for input: {:?}",
             test_case
         );
+        assert_eq!(response.headers()[CONTENT_TYPE], PROBLEM_JSON);
     }
 }
 
@@ -99,17 +101,26 @@ async fn should_return_400_if_invalid_input() {
         }),
     ];
 
-    for i in test_cases.iter() {
+    let invalid_fields = ["password", "email", "email", "password"];
+
+    for (i, field) in test_cases.iter().zip(invalid_fields) {
         let response = app.post_signup(i).await;
         assert_eq!(response.status().as_u16(), 400, "Failed for input: {:?}", i);
 
+        let problem = response
+            .json::<Problem>()
+            .await
+            .expect("Could not deserialize response body to Problem");
+        assert_eq!(problem.code, "validation_failed");
         assert_eq!(
-            response
-                .json::<ErrorResponse>()
-                .await
-                .expect("Could not deserialize response body to ErrorResponse")
-                .error,
-            "Invalid credentials".to_owned()
+            problem
+                .errors
+                .iter()
+                .map(|error| error.field.as_str())
+                .collect::<Vec<_>>(),
+            vec![field],
+            "Failed for input: {:?}",
+            i
         );
     }
 }
@@ -131,10 +142,10 @@ async fn should_return_409_if_email_already_exists() {
 
     assert_eq!(
         response
-            .json::<ErrorResponse>()
+            .json::<Problem>()
             .await
-            .expect("Could not deserialize response body to ErrorResponse")
-            .error,
-        "User already exists".to_owned()
+            .expect("Could not deserialize response body to Problem")
+            .code,
+        "user_already_exists".to_owned()
     );
 }
@@ -0,0 +1,108 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::utils::telemetry::{subscriber, tracer_provider};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::EnvFilter;
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const PARENT_SPAN_ID: &str = "00f067aa0ba902b7";
+
+// An OTLP/HTTP collector that keeps the spans it is sent.
+#[derive(Clone, Default)]
+struct Collector {
+    spans: Arc<Mutex<Vec<Value>>>,
+}
+
+impl Collector {
+    async fn spawn(&self) -> String {
+        let router = Router::new()
+            .route("/v1/traces", post(collect))
+            .with_state(self.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        url
+    }
+
+    fn spans_named(&self, name: &str) -> Vec<Value> {
+        self.spans
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|span| span["name"] == name)
+            .cloned()
+            .collect()
+    }
+}
+
+async fn collect(State(collector): State<Collector>, Json(body): Json<Value>) -> StatusCode {
+    let spans = body["resourceSpans"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|resource| resource["scopeSpans"].as_array().into_iter().flatten())
+        .flat_map(|scope| scope["spans"].as_array().into_iter().flatten())
+        .cloned();
+    collector.spans.lock().unwrap().extend(spans);
+    StatusCode::OK
+}
+
+#[tokio::test]
+async fn should_export_spans_continuing_the_callers_trace() {
+    let collector = Collector::default();
+    let provider = tracer_provider(&collector.spawn().await).unwrap();
+    // The app runs on the runtime of the test, which is this thread.
+    let _subscriber = tracing::subscriber::set_default(subscriber(
+        std::io::sink,
+        EnvFilter::new("info"),
+        Some(&provider),
+    ));
+    let app = TestApp::new().await;
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/verify-token", &app.address))
+        .header(
+            "traceparent",
+            format!("00-{}-{}-01", TRACE_ID, PARENT_SPAN_ID),
+        )
+        .json(&serde_json::json!({ "token": token }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Flushing waits for the collector, which needs this thread to answer.
+    tokio::task::spawn_blocking(move || provider.force_flush())
+        .await
+        .unwrap()
+        .unwrap();
+
+    let requests = collector.spans_named("POST /verify-token");
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    assert_eq!(request["traceId"], TRACE_ID);
+    assert_eq!(request["parentSpanId"], PARENT_SPAN_ID);
+
+    let banned_checks = collector.spans_named("banned_tokens.is_token_banned");
+    let check = banned_checks
+        .iter()
+        .find(|span| span["traceId"] == TRACE_ID)
+        .expect("No span for the store operation");
+    assert_eq!(check["parentSpanId"], request["spanId"]);
+
+    // Requests without a traceparent start traces of their own.
+    let signups = collector.spans_named("POST /signup");
+    assert_eq!(signups.len(), 1);
+    assert_ne!(signups[0]["traceId"], TRACE_ID);
+    assert!(!collector.spans_named("users.add_user").is_empty());
+    // Background polling is not traced.
+    assert!(collector.spans_named("users.pending_events").is_empty());
+}
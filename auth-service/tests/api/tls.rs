@@ -0,0 +1,134 @@
+use crate::helpers::TestApp;
+use auth_service::{AppConfig, TlsSettings};
+use reqwest::header::LOCATION;
+use std::path::PathBuf;
+use std::time::Duration;
+use uuid::Uuid;
+
+// A self-signed certificate for localhost, written to a fresh directory.
+struct Certificate {
+    dir: PathBuf,
+    pem: String,
+}
+
+impl Certificate {
+    fn generate() -> Self {
+        let dir = std::env::temp_dir().join(format!("auth-service-tls-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut certificate = Self {
+            dir,
+            pem: String::new(),
+        };
+        certificate.renew();
+        certificate
+    }
+
+    fn renew(&mut self) {
+        let generated = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+        self.pem = generated.cert.pem();
+        std::fs::write(self.dir.join("cert.pem"), &self.pem).unwrap();
+        std::fs::write(self.dir.join("key.pem"), generated.key_pair.serialize_pem()).unwrap();
+    }
+
+    fn settings(&self) -> TlsSettings {
+        TlsSettings::new(self.dir.join("cert.pem"), self.dir.join("key.pem"))
+    }
+}
+
+impl Drop for Certificate {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+// The server is reached as localhost, the name in the certificate.
+fn client_trusting(app: &TestApp, pem: &str) -> reqwest::Client {
+    let address = app.address.trim_start_matches("https://").parse().unwrap();
+    reqwest::Client::builder()
+        .add_root_certificate(reqwest::Certificate::from_pem(pem.as_bytes()).unwrap())
+        .resolve("localhost", address)
+        .build()
+        .unwrap()
+}
+
+fn url(app: &TestApp, path: &str) -> String {
+    let port = app.address.rsplit(':').next().unwrap();
+    format!("https://localhost:{}{}", port, path)
+}
+
+#[tokio::test]
+async fn should_serve_https_with_the_configured_certificate() {
+    let certificate = Certificate::generate();
+    let app = TestApp::with_config(AppConfig::default().with_tls(certificate.settings())).await;
+
+    let response = client_trusting(&app, &certificate.pem)
+        .get(url(&app, "/health/live"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Plain HTTP is not served on the same port.
+    let plain = reqwest::Client::new()
+        .get(app.address.replacen("https://", "http://", 1))
+        .send()
+        .await;
+    assert!(plain.is_err());
+}
+
+#[tokio::test]
+async fn should_pick_up_a_renewed_certificate() {
+    let mut certificate = Certificate::generate();
+    let settings = certificate
+        .settings()
+        .with_reload_interval(Duration::from_millis(20));
+    let app = TestApp::with_config(AppConfig::default().with_tls(settings)).await;
+    let old_pem = certificate.pem.clone();
+
+    certificate.renew();
+
+    let mut reloaded = false;
+    for _ in 0..100 {
+        // A new client for every attempt, so that no connection is reused.
+        let result = client_trusting(&app, &certificate.pem)
+            .get(url(&app, "/health/live"))
+            .send()
+            .await;
+        if result.is_ok() {
+            reloaded = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(reloaded, "The renewed certificate was not served");
+
+    let old = client_trusting(&app, &old_pem)
+        .get(url(&app, "/health/live"))
+        .send()
+        .await;
+    assert!(old.is_err());
+}
+
+#[tokio::test]
+async fn should_redirect_http_to_https() {
+    let certificate = Certificate::generate();
+    let settings = certificate.settings().with_redirect_address("127.0.0.1:0");
+    let app = TestApp::with_config(AppConfig::default().with_tls(settings)).await;
+    let redirect_address = app.redirect_address.clone().unwrap();
+    let port = app.address.rsplit(':').next().unwrap();
+
+    let response = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+        .get(format!("http://{}/login?next=%2F", redirect_address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 308);
+    assert_eq!(
+        response.headers()[LOCATION],
+        format!("https://127.0.0.1:{}/login?next=%2F", port).as_str()
+    );
+}
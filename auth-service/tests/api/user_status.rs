@@ -0,0 +1,154 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::{Email, UserStatus};
+use auth_service::routes::TwoFactorAuthResponse;
+use std::str::FromStr;
+
+const CLIENT_ID: &str = "mobile-app";
+const REDIRECT_URI: &str = "com.example.app:/callback";
+
+async fn set_status(app: &TestApp, email: &str, status: UserStatus) {
+    let mut user_store = app.state().user_store.write().await;
+    let mut user = user_store
+        .get_user(&Email::from_str(email).unwrap().into())
+        .await
+        .unwrap();
+    user.status = status;
+    user_store.update_user(user).await.unwrap();
+}
+
+async fn verify_token_status(app: &TestApp, token: &str) -> u16 {
+    app.post_verify_token(&serde_json::json!({ "token": token }))
+        .await
+        .status()
+        .as_u16()
+}
+
+// The stable code of the error, which the OAuth endpoints send as `error`.
+async fn error_code(response: reqwest::Response) -> String {
+    let body = response
+        .json::<serde_json::Value>()
+        .await
+        .expect("Could not deserialize the response body");
+    body.get("code")
+        .or(body.get("error"))
+        .and_then(serde_json::Value::as_str)
+        .expect("No error code in the response body")
+        .to_owned()
+}
+
+#[tokio::test]
+async fn login_rejects_inactive_accounts_with_distinct_errors() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+    let credentials = serde_json::json!({ "email": email, "password": "password123" });
+
+    set_status(&app, &email, UserStatus::Suspended).await;
+    let response = app.post_login(&credentials).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_code(response).await, "account_suspended");
+
+    set_status(&app, &email, UserStatus::PendingVerification).await;
+    let response = app.post_login(&credentials).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_code(response).await, "account_not_verified");
+
+    // Wrong passwords are still reported as such.
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "wrong-password" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    set_status(&app, &email, UserStatus::Active).await;
+    assert_eq!(app.post_login(&credentials).await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn verify_2fa_rejects_accounts_suspended_since_the_login() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": true,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+    let login: TwoFactorAuthResponse = response.json().await.unwrap();
+    let code = app.pending_2fa_code(&login.login_attempt_id).await;
+    set_status(&app, &email, UserStatus::Suspended).await;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login.login_attempt_id,
+            "2FACode": code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_code(response).await, "account_suspended");
+}
+
+#[tokio::test]
+async fn authenticated_routes_reject_tokens_of_inactive_users() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let token = app.signup_and_login(&email, "password123").await;
+
+    set_status(&app, &email, UserStatus::Suspended).await;
+    let response = app.get_sessions(Some(&token)).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_code(response).await, "account_suspended");
+
+    set_status(&app, &email, UserStatus::PendingVerification).await;
+    let response = app.get_sessions(Some(&token)).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_code(response).await, "account_not_verified");
+
+    set_status(&app, &email, UserStatus::Active).await;
+    assert_eq!(app.get_sessions(Some(&token)).await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn verify_token_treats_tokens_of_inactive_users_as_invalid() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let token = app.signup_and_login(&email, "password123").await;
+
+    set_status(&app, &email, UserStatus::Suspended).await;
+    assert_eq!(verify_token_status(&app, &token).await, 401);
+
+    // Tokens are not revoked by the status alone, so they work again after reactivation.
+    set_status(&app, &email, UserStatus::Active).await;
+    assert_eq!(verify_token_status(&app, &token).await, 200);
+
+    set_status(&app, &email, UserStatus::PendingVerification).await;
+    assert_eq!(verify_token_status(&app, &token).await, 401);
+}
+
+#[tokio::test]
+async fn refresh_rejects_inactive_accounts() {
+    let app = TestApp::new().await;
+    app.register_oauth_client(CLIENT_ID, REDIRECT_URI).await;
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+    let tokens = app
+        .authorization_code_tokens(CLIENT_ID, REDIRECT_URI, "openid")
+        .await;
+
+    set_status(&app, &email, UserStatus::Suspended).await;
+    let response = app
+        .post_token(&serde_json::json!({
+            "grant_type": "refresh_token",
+            "refresh_token": tokens.refresh_token.unwrap(),
+            "client_id": CLIENT_ID,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_code(response).await, "account_inactive");
+}
@@ -1,10 +1,130 @@
-use crate::helpers::TestApp;
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::{AuditEvent, AuditQuery, PendingLogin};
+use auth_service::routes::TwoFactorAuthResponse;
+
+// Signs up a user with 2FA enabled and logs in, which leaves the login waiting for the code.
+async fn start_login(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": true,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&serde_json::json!({ "email": email, "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != app.state().config.cookies.name()));
+    let login: TwoFactorAuthResponse = response.json().await.unwrap();
+    login.login_attempt_id
+}
+
+async fn verify(app: &TestApp, email: &str, login_attempt_id: &str, code: &str) -> u16 {
+    app.post_verify_2fa(&serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": code,
+    }))
+    .await
+    .status()
+    .as_u16()
+}
+
+async fn two_factor_events(app: &TestApp) -> Vec<AuditEvent> {
+    let query = AuditQuery {
+        limit: 100,
+        ..AuditQuery::default()
+    };
+    app.state()
+        .audit_sink
+        .read()
+        .await
+        .query(&query)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|record| record.event)
+        .filter(|event| {
+            matches!(
+                event,
+                AuditEvent::TwoFactorVerified | AuditEvent::TwoFactorFailed { .. }
+            )
+        })
+        .collect()
+}
 
 #[tokio::test]
-async fn verify_2fa_returns_200() {
+async fn should_complete_the_login_with_the_code() {
     let app = TestApp::new().await;
+    let email = get_random_email();
+    let login_attempt_id = start_login(&app, &email).await;
+    let code = app.pending_2fa_code(&login_attempt_id).await;
 
-    let response = app.verify_2fa("2fa_token").await;
-
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code,
+        }))
+        .await;
     assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .cookies()
+        .any(|cookie| cookie.name() == app.state().config.cookies.name()));
+    assert_eq!(
+        two_factor_events(&app).await,
+        vec![AuditEvent::TwoFactorVerified]
+    );
+
+    // Each login attempt can only be completed once.
+    assert_eq!(verify(&app, &email, &login_attempt_id, &code).await, 401);
+}
+
+#[tokio::test]
+async fn should_fail_alike_without_a_matching_login() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let login_attempt_id = start_login(&app, &email).await;
+    let code = app.pending_2fa_code(&login_attempt_id).await;
+
+    // Unknown users, other users and unknown login attempts cannot be told apart.
+    assert_eq!(
+        verify(&app, "nobody@example.com", &login_attempt_id, &code).await,
+        401
+    );
+    assert_eq!(
+        verify(&app, &get_random_email(), "attempt", "123456").await,
+        401
+    );
+    assert_eq!(verify(&app, &email, "attempt", &code).await, 401);
+    // No code was checked, so there is nothing to audit.
+    assert!(two_factor_events(&app).await.is_empty());
+
+    assert_eq!(verify(&app, &email, &login_attempt_id, &code).await, 200);
+}
+
+#[tokio::test]
+async fn should_drop_the_login_after_too_many_wrong_codes() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    let login_attempt_id = start_login(&app, &email).await;
+    let code = app.pending_2fa_code(&login_attempt_id).await;
+    let wrong_code = if code == "000000" { "111111" } else { "000000" };
+
+    for _ in 0..PendingLogin::MAX_ATTEMPTS {
+        assert_eq!(
+            verify(&app, &email, &login_attempt_id, wrong_code).await,
+            401
+        );
+    }
+    assert_eq!(verify(&app, &email, &login_attempt_id, &code).await, 401);
+    assert_eq!(
+        two_factor_events(&app).await.len(),
+        PendingLogin::MAX_ATTEMPTS as usize
+    );
 }
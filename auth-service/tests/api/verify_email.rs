@@ -0,0 +1,38 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::Email;
+use std::str::FromStr;
+
+#[tokio::test]
+async fn should_verify_the_email_of_the_link() {
+    let app = TestApp::new().await;
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+
+    assert_eq!(app.post_verify_email(&email).await.status().as_u16(), 204);
+    let user = app
+        .state()
+        .user_store
+        .read()
+        .await
+        .get_user(&Email::from_str(&email).unwrap().into())
+        .await
+        .unwrap();
+    assert!(user.email_verified);
+}
+
+#[tokio::test]
+async fn should_return_401_for_invalid_links() {
+    let app = TestApp::new().await;
+    let response = app
+        .http_client
+        .post(format!("{}/verify-email", &app.address))
+        .json(&serde_json::json!({ "token": "forged.token" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Links of accounts that no longer exist do not work either.
+    let response = app.post_verify_email(&get_random_email()).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
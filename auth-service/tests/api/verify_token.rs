@@ -1,5 +1,7 @@
-use crate::helpers::TestApp;
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::utils::problem::PROBLEM_JSON;
 use auth_service::utils::JWT_COOKIE_NAME;
+use reqwest::header::CONTENT_TYPE;
 
 #[tokio::test]
 async fn should_return_422_if_malformed_input() {
@@ -11,6 +13,7 @@ async fn should_return_422_if_malformed_input() {
         }))
         .await;
     assert_eq!(response.status().as_u16(), 422);
+    assert_eq!(response.headers()[CONTENT_TYPE], PROBLEM_JSON);
 }
 
 #[tokio::test]
@@ -21,7 +24,7 @@ async fn should_return_200_valid_token() {
     let test_case = serde_json::json!({
         "password": "password123",
         "email": test_email,
-        "requires2FA": true
+        "requires2FA": false
     });
 
     let response = app.post_signup(&test_case).await;
@@ -71,7 +74,7 @@ async fn should_return_401_if_banned_token() {
     let test_case = serde_json::json!({
         "password": "password123",
         "email": test_email,
-        "requires2FA": true
+        "requires2FA": false
     });
     let response = app.post_signup(&test_case).await;
     assert_eq!(response.status().as_u16(), 201);
@@ -100,3 +103,35 @@ async fn should_return_401_if_banned_token() {
         .await;
     assert_eq!(response.status().as_u16(), 401);
 }
+
+#[tokio::test]
+async fn should_return_200_for_valid_bearer_token() {
+    let app = TestApp::new().await;
+    let token = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let response = app.post_verify_token_with_bearer(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_return_401_for_invalid_bearer_token() {
+    let app = TestApp::new().await;
+
+    let response = app.post_verify_token_with_bearer("invalid").await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn should_return_400_if_no_token_is_sent() {
+    let app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/verify-token", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 400);
+}
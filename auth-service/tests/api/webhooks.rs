@@ -0,0 +1,327 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::{DeliveryAttempt, WebhookEvent, WebhookEventType};
+use auth_service::routes::WebhookResponse;
+use auth_service::utils::problem::Problem;
+use auth_service::utils::webhooks::{
+    WEBHOOK_ID_HEADER, WEBHOOK_SIGNATURE_HEADER, WEBHOOK_TIMESTAMP_HEADER,
+};
+use auth_service::{AppConfig, OutboxSettings, WebhookSettings};
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use chrono::Utc;
+use ring::hmac;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+pub(crate) const ADMIN_EMAIL: &str = "admin@example.com";
+
+pub(crate) type Delivery = (HeaderMap, String);
+
+// A downstream system that answers the first `failures` deliveries with an error.
+pub(crate) struct Receiver {
+    pub(crate) url: String,
+    requests: mpsc::UnboundedReceiver<Delivery>,
+}
+
+impl Receiver {
+    pub(crate) async fn spawn(failures: usize) -> Self {
+        let (sender, requests) = mpsc::unbounded_channel();
+        let failures = Arc::new(AtomicUsize::new(failures));
+        let router = Router::new()
+            .route("/hook", post(receive))
+            .with_state((sender, failures));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        Self { url, requests }
+    }
+
+    pub(crate) async fn next(&mut self) -> Delivery {
+        tokio::time::timeout(Duration::from_secs(5), self.requests.recv())
+            .await
+            .expect("No delivery received")
+            .unwrap()
+    }
+}
+
+async fn receive(
+    State((sender, failures)): State<(mpsc::UnboundedSender<Delivery>, Arc<AtomicUsize>)>,
+    headers: HeaderMap,
+    body: String,
+) -> StatusCode {
+    sender.send((headers, body)).unwrap();
+    match failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)) {
+        Ok(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        Err(_) => StatusCode::NO_CONTENT,
+    }
+}
+
+async fn app_with_fast_webhooks() -> TestApp {
+    let outbox = OutboxSettings::new(Duration::from_millis(20), Duration::from_millis(50), 5);
+    let settings = WebhookSettings::new(Duration::from_millis(20), Duration::from_millis(50), 5);
+    let config = AppConfig::default()
+        .with_admin_email(ADMIN_EMAIL)
+        .with_outbox_settings(outbox)
+        .with_webhook_settings(settings);
+    TestApp::with_config(config).await
+}
+
+// Waits for the event of the admin's own signup to be handled, so that it cannot reach the
+// webhooks the test creates afterwards.
+async fn signup_admin(app: &TestApp) -> String {
+    let token = app
+        .signup_verified_and_login(ADMIN_EMAIL, "password123")
+        .await;
+    for _ in 0..100 {
+        let pending = app
+            .state()
+            .user_store
+            .read()
+            .await
+            .pending_events(Utc::now() + chrono::Duration::days(1), 1)
+            .await
+            .unwrap();
+        if pending.is_empty() {
+            return token;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("The signup event was not handled");
+}
+
+pub(crate) async fn create_webhook(
+    app: &TestApp,
+    token: &str,
+    url: &str,
+    events: &[&str],
+) -> WebhookResponse {
+    let response = app
+        .post_webhook(token, &serde_json::json!({ "url": url, "events": events }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    response
+        .json()
+        .await
+        .expect("Could not deserialize response body to WebhookResponse")
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> &'a str {
+    headers.get(name).unwrap().to_str().unwrap()
+}
+
+fn assert_signed(secret: &str, headers: &HeaderMap, body: &str) {
+    let timestamp = header(headers, WEBHOOK_TIMESTAMP_HEADER);
+    let signature = header(headers, WEBHOOK_SIGNATURE_HEADER)
+        .strip_prefix("v1=")
+        .expect("Unknown signature version");
+    let signature: Vec<u8> = (0..signature.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&signature[i..i + 2], 16).unwrap())
+        .collect();
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hmac::verify(
+        &key,
+        format!("{}.{}", timestamp, body).as_bytes(),
+        &signature,
+    )
+    .expect("Invalid signature");
+}
+
+#[tokio::test]
+async fn should_deliver_signed_events_for_new_users() {
+    let app = app_with_fast_webhooks().await;
+    let admin = signup_admin(&app).await;
+    let mut receiver = Receiver::spawn(0).await;
+    let webhook = create_webhook(&app, &admin, &receiver.url, &["user.created"]).await;
+    let secret = webhook.secret.expect("No secret returned");
+
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+
+    let (headers, body) = receiver.next().await;
+    assert_signed(&secret, &headers, &body);
+    let event: WebhookEvent = serde_json::from_str(&body).unwrap();
+    assert_eq!(event.event_type, WebhookEventType::UserCreated);
+    assert_eq!(event.data.email, email);
+    assert_eq!(header(&headers, WEBHOOK_ID_HEADER), event.id);
+
+    // The secret is only shown once.
+    let response = app.get_admin("/webhooks", &admin).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let webhooks: Vec<WebhookResponse> = response.json().await.unwrap();
+    assert_eq!(webhooks.len(), 1);
+    assert_eq!(webhooks[0].id, webhook.id);
+    assert_eq!(webhooks[0].secret, None);
+}
+
+#[tokio::test]
+async fn should_retry_failed_deliveries_and_log_attempts() {
+    let app = app_with_fast_webhooks().await;
+    let admin = signup_admin(&app).await;
+    let mut receiver = Receiver::spawn(2).await;
+    let webhook = create_webhook(&app, &admin, &receiver.url, &["user.created"]).await;
+
+    app.signup_and_login(&get_random_email(), "password123")
+        .await;
+
+    let mut bodies = Vec::new();
+    for _ in 0..3 {
+        let (headers, body) = receiver.next().await;
+        assert_signed(webhook.secret.as_deref().unwrap(), &headers, &body);
+        bodies.push(body);
+    }
+    // Retries carry the same event, so receivers can tell them apart from new ones.
+    assert!(bodies.iter().all(|body| body == &bodies[0]));
+
+    let path = format!("/webhooks/{}/deliveries", webhook.id);
+    let mut attempts: Vec<DeliveryAttempt> = Vec::new();
+    for _ in 0..50 {
+        attempts = app.get_admin(&path, &admin).await.json().await.unwrap();
+        if attempts.len() == 3 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let status_codes: Vec<Option<u16>> = attempts.iter().map(|a| a.status_code).collect();
+    assert_eq!(status_codes, vec![Some(204), Some(500), Some(500)]);
+    assert!(attempts[0].succeeded);
+    assert!(attempts
+        .iter()
+        .all(|a| a.delivery_id == attempts[0].delivery_id));
+}
+
+#[tokio::test]
+async fn should_notify_about_verified_and_deleted_users() {
+    let app = app_with_fast_webhooks().await;
+    let admin = signup_admin(&app).await;
+    let mut receiver = Receiver::spawn(0).await;
+    create_webhook(
+        &app,
+        &admin,
+        &receiver.url,
+        &["user.verified", "user.deleted"],
+    )
+    .await;
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+
+    let path = format!("/users/{}", email);
+    let verified = serde_json::json!({ "emailVerified": true });
+    let response = app.patch_admin(&path, &admin, &verified).await;
+    assert_eq!(response.status().as_u16(), 200);
+    // Already verified, so there is nothing new to tell.
+    app.patch_admin(&path, &admin, &verified).await;
+    let response = app.delete_admin(&path, &admin).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let mut events = Vec::new();
+    for _ in 0..2 {
+        let (_, body) = receiver.next().await;
+        let event: WebhookEvent = serde_json::from_str(&body).unwrap();
+        assert_eq!(event.data.email, email);
+        events.push(event.event_type);
+    }
+    assert_eq!(
+        events,
+        vec![
+            WebhookEventType::UserVerified,
+            WebhookEventType::UserDeleted
+        ]
+    );
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(receiver.requests.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn should_notify_about_changed_emails() {
+    let app = app_with_fast_webhooks().await;
+    let admin = signup_admin(&app).await;
+    let mut receiver = Receiver::spawn(0).await;
+    let webhook = create_webhook(
+        &app,
+        &admin,
+        &receiver.url,
+        &["user.email_changed", "user.deleted", "user.email_changed"],
+    )
+    .await;
+    assert_eq!(
+        webhook.events,
+        vec![
+            WebhookEventType::UserEmailChanged,
+            WebhookEventType::UserDeleted
+        ]
+    );
+    let email = get_random_email();
+    app.signup_and_login(&email, "password123").await;
+
+    let new_email = get_random_email();
+    let response = app
+        .patch_admin(
+            &format!("/users/{}", email),
+            &admin,
+            &serde_json::json!({ "email": new_email }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let (_, body) = receiver.next().await;
+    let event: WebhookEvent = serde_json::from_str(&body).unwrap();
+    assert_eq!(event.event_type, WebhookEventType::UserEmailChanged);
+    assert_eq!(event.data.email, new_email);
+    assert_eq!(event.data.previous_email, Some(email));
+}
+
+#[tokio::test]
+async fn should_remove_webhooks() {
+    let app = app_with_fast_webhooks().await;
+    let admin = signup_admin(&app).await;
+    let webhook = create_webhook(&app, &admin, "https://example.com/hook", &["user.created"]).await;
+
+    let path = format!("/webhooks/{}", webhook.id);
+    assert_eq!(app.delete_admin(&path, &admin).await.status().as_u16(), 204);
+    assert_eq!(app.delete_admin(&path, &admin).await.status().as_u16(), 404);
+    let response = app.get_admin(&format!("{}/deliveries", path), &admin).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn should_reject_invalid_webhooks_and_non_admins() {
+    let app = app_with_fast_webhooks().await;
+    let admin = signup_admin(&app).await;
+
+    let invalid = [
+        (
+            serde_json::json!({ "url": "not a url", "events": ["user.created"] }),
+            "url",
+        ),
+        (
+            serde_json::json!({ "url": "ftp://example.com/hook", "events": ["user.created"] }),
+            "url",
+        ),
+        (
+            serde_json::json!({ "url": "https://example.com/hook", "events": [] }),
+            "events",
+        ),
+    ];
+    for (body, field) in invalid {
+        let response = app.post_webhook(&admin, &body).await;
+        assert_eq!(response.status().as_u16(), 400, "{}", body);
+        let problem: Problem = response.json().await.unwrap();
+        let fields: Vec<_> = problem.errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec![field], "{}", body);
+    }
+
+    let user = app
+        .signup_and_login(&get_random_email(), "password123")
+        .await;
+    let body = serde_json::json!({ "url": "https://example.com/hook", "events": ["user.created"] });
+    assert_eq!(app.post_webhook(&user, &body).await.status().as_u16(), 403);
+    assert_eq!(
+        app.get_admin("/webhooks", &user).await.status().as_u16(),
+        403
+    );
+}
@@ -0,0 +1,175 @@
+//! The token a caller presents to auth-service or app-service, read from the `jwt` cookie or
+//! an `Authorization: Bearer` header.
+//!
+//! Both services look for it the same way, configured with the same environment variables:
+//! `AUTH_TOKEN_PRECEDENCE` picks the [`TokenPrecedence`] and `COOKIE_HOST_PREFIX` the name of
+//! the cookie, see [`cookie_name`]. The services read them once at startup.
+
+use cookie::Cookie;
+use http::header::{AUTHORIZATION, COOKIE};
+use http::HeaderMap;
+
+pub const JWT_COOKIE_NAME: &str = "jwt";
+/// The name of the cookie when it is locked to the host, see [`cookie_name`].
+pub const HOST_PREFIXED_JWT_COOKIE_NAME: &str = "__Host-jwt";
+
+pub mod env {
+    pub const AUTH_TOKEN_PRECEDENCE_ENV_VAR: &str = "AUTH_TOKEN_PRECEDENCE";
+    pub const COOKIE_HOST_PREFIX_ENV_VAR: &str = "COOKIE_HOST_PREFIX";
+}
+
+/// The name of the auth cookie. With the `__Host-` prefix, browsers only accept it if it is
+/// `Secure`, has `Path=/` and no `Domain`.
+pub fn cookie_name(host_prefix: bool) -> &'static str {
+    if host_prefix {
+        HOST_PREFIXED_JWT_COOKIE_NAME
+    } else {
+        JWT_COOKIE_NAME
+    }
+}
+
+/// Where a caller can present its token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenSource {
+    Cookie,
+    Bearer,
+}
+
+/// Which token wins when a request carries both a `jwt` cookie and an `Authorization` header.
+///
+/// The header wins by default: it is set deliberately by the caller, while the cookie is sent
+/// along by the browser with every request (e.g. when an OpenID Connect relying party running
+/// in the same browser calls `/userinfo`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TokenPrecedence {
+    CookieFirst,
+    #[default]
+    BearerFirst,
+}
+
+impl TokenPrecedence {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "cookie" => Some(Self::CookieFirst),
+            "bearer" => Some(Self::BearerFirst),
+            _ => None,
+        }
+    }
+}
+
+/// The raw token presented by the caller, read from the cookie named `cookie_name` or from an
+/// `Authorization: Bearer` header according to the [`TokenPrecedence`].
+///
+/// The token is not validated; the services decide what a valid token means for them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthToken {
+    pub token: String,
+    pub source: TokenSource,
+}
+
+impl AuthToken {
+    pub fn from_headers(
+        headers: &HeaderMap,
+        precedence: TokenPrecedence,
+        cookie_name: &str,
+    ) -> Option<Self> {
+        let cookie = || {
+            headers
+                .get_all(COOKIE)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(Cookie::split_parse)
+                .filter_map(Result::ok)
+                .find(|cookie| cookie.name() == cookie_name)
+                .map(|cookie| AuthToken {
+                    token: cookie.value().to_owned(),
+                    source: TokenSource::Cookie,
+                })
+        };
+        let bearer = || {
+            headers
+                .get(AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .map(str::trim)
+                .filter(|token| !token.is_empty())
+                .map(|token| AuthToken {
+                    token: token.to_owned(),
+                    source: TokenSource::Bearer,
+                })
+        };
+
+        match precedence {
+            TokenPrecedence::CookieFirst => cookie().or_else(bearer),
+            TokenPrecedence::BearerFirst => bearer().or_else(cookie),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::Request;
+
+    fn headers(cookie: Option<&str>, authorization: Option<&str>) -> HeaderMap {
+        let mut request = Request::builder();
+        if let Some(cookie) = cookie {
+            request = request.header(COOKIE, format!("other=x; {}={}", JWT_COOKIE_NAME, cookie));
+        }
+        if let Some(authorization) = authorization {
+            request = request.header(AUTHORIZATION, authorization);
+        }
+        request.body(()).unwrap().into_parts().0.headers
+    }
+
+    #[test]
+    fn test_reads_cookie_or_bearer() {
+        let token = AuthToken::from_headers(
+            &headers(Some("a"), None),
+            TokenPrecedence::BearerFirst,
+            JWT_COOKIE_NAME,
+        )
+        .unwrap();
+        assert_eq!(token.token, "a");
+        assert_eq!(token.source, TokenSource::Cookie);
+
+        let token = AuthToken::from_headers(
+            &headers(None, Some("Bearer b")),
+            TokenPrecedence::CookieFirst,
+            JWT_COOKIE_NAME,
+        )
+        .unwrap();
+        assert_eq!(token.token, "b");
+        assert_eq!(token.source, TokenSource::Bearer);
+    }
+
+    #[test]
+    fn test_precedence_when_both_are_present() {
+        let headers = headers(Some("a"), Some("Bearer b"));
+        assert_eq!(
+            AuthToken::from_headers(&headers, TokenPrecedence::CookieFirst, JWT_COOKIE_NAME)
+                .unwrap()
+                .token,
+            "a"
+        );
+        assert_eq!(
+            AuthToken::from_headers(&headers, TokenPrecedence::BearerFirst, JWT_COOKIE_NAME)
+                .unwrap()
+                .token,
+            "b"
+        );
+    }
+
+    #[test]
+    fn test_ignores_other_authorization_schemes_and_cookies() {
+        let headers = headers(Some("a"), Some("Basic dXNlcjpwYXNz"));
+        assert_eq!(
+            AuthToken::from_headers(
+                &headers,
+                TokenPrecedence::BearerFirst,
+                HOST_PREFIXED_JWT_COOKIE_NAME
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,427 @@
+//! The security headers sent by auth-service and app-service: `Content-Security-Policy`,
+//! `Strict-Transport-Security`, `X-Frame-Options`, `Referrer-Policy` and
+//! `X-Content-Type-Options`.
+//!
+//! Both services add them with [`SecurityHeadersLayer`], configured with the same environment
+//! variables, see [`SecurityHeaderSettings::from_env`].
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use http::header::{HeaderName, HeaderValue, InvalidHeaderValue};
+use http::header::{
+    CONTENT_SECURITY_POLICY, REFERRER_POLICY, STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS,
+    X_FRAME_OPTIONS,
+};
+use http::{Request, Response};
+use pin_project_lite::pin_project;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Replaced with the nonce of the response in the `Content-Security-Policy`.
+pub const NONCE_PLACEHOLDER: &str = "{nonce}";
+
+/// Only scripts carrying the nonce of the page may run, along with the scripts they load.
+pub const DEFAULT_CONTENT_SECURITY_POLICY: &str = "script-src 'nonce-{nonce}' 'strict-dynamic'; \
+     object-src 'none'; base-uri 'none'; frame-ancestors 'none'";
+
+pub const DEFAULT_HSTS_MAX_AGE: Duration = Duration::from_secs(31_536_000); // 1 year
+
+// Turns off a header when used as the value of its environment variable.
+const DISABLED: &str = "off";
+
+pub mod env {
+    pub const CONTENT_SECURITY_POLICY_ENV_VAR: &str = "CONTENT_SECURITY_POLICY";
+    pub const HSTS_MAX_AGE_SECS_ENV_VAR: &str = "HSTS_MAX_AGE_SECS";
+    pub const X_FRAME_OPTIONS_ENV_VAR: &str = "X_FRAME_OPTIONS";
+    pub const REFERRER_POLICY_ENV_VAR: &str = "REFERRER_POLICY";
+    pub const X_CONTENT_TYPE_OPTIONS_ENV_VAR: &str = "X_CONTENT_TYPE_OPTIONS";
+}
+
+/// Which security headers are sent, and with what values. `None` leaves a header out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityHeaderSettings {
+    /// Every `{nonce}` is replaced with a new nonce for each response, which pages put on
+    /// their `<script>` tags, see [`CspNonce`].
+    pub content_security_policy: Option<String>,
+    /// Browsers only take `Strict-Transport-Security` into account over HTTPS, and then
+    /// refuse plain HTTP for the host, whatever the port, until it expires.
+    pub hsts_max_age: Option<Duration>,
+    pub frame_options: Option<String>,
+    pub referrer_policy: Option<String>,
+    /// Sends `X-Content-Type-Options: nosniff`.
+    pub content_type_options: bool,
+}
+
+impl Default for SecurityHeaderSettings {
+    fn default() -> Self {
+        Self {
+            content_security_policy: Some(DEFAULT_CONTENT_SECURITY_POLICY.to_owned()),
+            hsts_max_age: None,
+            frame_options: Some("DENY".to_owned()),
+            referrer_policy: Some("no-referrer".to_owned()),
+            content_type_options: true,
+        }
+    }
+}
+
+impl SecurityHeaderSettings {
+    /// Creates a new `SecurityHeaderSettings` instance.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the settings from the environment, falling back to the defaults for the
+    /// variables that are not set. `off` leaves a header out. `Strict-Transport-Security` is
+    /// sent for a year by default when the service is served over `https`.
+    pub fn from_env(https: bool) -> Self {
+        Self::from_vars(https, |name| std::env::var(name).ok())
+    }
+
+    fn from_vars(https: bool, var: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |name: &str| {
+            var(name)
+                .map(|value| value.trim().to_owned())
+                .filter(|value| !value.is_empty())
+        };
+        let text = |name: &str, default: Option<String>| match var(name) {
+            Some(value) if value.eq_ignore_ascii_case(DISABLED) => None,
+            Some(value) => Some(value),
+            None => default,
+        };
+        let defaults = Self::default();
+        let default_hsts_max_age = https.then_some(DEFAULT_HSTS_MAX_AGE);
+
+        Self {
+            content_security_policy: text(
+                env::CONTENT_SECURITY_POLICY_ENV_VAR,
+                defaults.content_security_policy,
+            ),
+            hsts_max_age: match var(env::HSTS_MAX_AGE_SECS_ENV_VAR) {
+                Some(value) if value.eq_ignore_ascii_case(DISABLED) => None,
+                Some(value) => value
+                    .parse()
+                    .map(Duration::from_secs)
+                    .ok()
+                    .or(default_hsts_max_age),
+                None => default_hsts_max_age,
+            },
+            frame_options: text(env::X_FRAME_OPTIONS_ENV_VAR, defaults.frame_options),
+            referrer_policy: text(env::REFERRER_POLICY_ENV_VAR, defaults.referrer_policy),
+            content_type_options: !var(env::X_CONTENT_TYPE_OPTIONS_ENV_VAR)
+                .is_some_and(|value| value.eq_ignore_ascii_case(DISABLED)),
+        }
+    }
+
+    #[must_use]
+    pub fn with_content_security_policy(mut self, policy: Option<String>) -> Self {
+        self.content_security_policy = policy;
+        self
+    }
+
+    #[must_use]
+    pub fn with_hsts_max_age(mut self, max_age: Option<Duration>) -> Self {
+        self.hsts_max_age = max_age;
+        self
+    }
+
+    #[must_use]
+    pub fn with_frame_options(mut self, frame_options: Option<String>) -> Self {
+        self.frame_options = frame_options;
+        self
+    }
+
+    #[must_use]
+    pub fn with_referrer_policy(mut self, referrer_policy: Option<String>) -> Self {
+        self.referrer_policy = referrer_policy;
+        self
+    }
+
+    #[must_use]
+    pub fn with_content_type_options(mut self, content_type_options: bool) -> Self {
+        self.content_type_options = content_type_options;
+        self
+    }
+}
+
+/// The nonce of the `Content-Security-Policy` of a response, added to the request's
+/// extensions for the handlers that render pages, e.g. `<script nonce="{{ nonce }}">`.
+///
+/// Only present if the policy has a `{nonce}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CspNonce(String);
+
+impl CspNonce {
+    fn generate() -> Self {
+        let mut bytes = [0u8; 16];
+        // Only fails if the operating system has no source of randomness at all.
+        getrandom::getrandom(&mut bytes).expect("Failed to generate a CSP nonce");
+        Self(STANDARD.encode(bytes))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for CspNonce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Adds the configured security headers to every response, unless the handler already set
+/// them, e.g. a stricter policy for a single route.
+#[derive(Debug, Clone)]
+pub struct SecurityHeadersLayer {
+    headers: Arc<Headers>,
+}
+
+#[derive(Debug)]
+struct Headers {
+    fixed: Vec<(HeaderName, HeaderValue)>,
+    // The `Content-Security-Policy` if it has a nonce, which makes it differ per response.
+    policy_with_nonce: Option<String>,
+}
+
+impl SecurityHeadersLayer {
+    /// Fails if a setting is not a valid header value.
+    pub fn new(settings: &SecurityHeaderSettings) -> Result<Self, InvalidHeaderValue> {
+        let mut fixed = Vec::new();
+        let mut policy_with_nonce = None;
+        if let Some(policy) = &settings.content_security_policy {
+            if policy.contains(NONCE_PLACEHOLDER) {
+                // The nonce itself is always valid in a header.
+                HeaderValue::try_from(policy.replace(NONCE_PLACEHOLDER, ""))?;
+                policy_with_nonce = Some(policy.clone());
+            } else {
+                fixed.push((CONTENT_SECURITY_POLICY, HeaderValue::try_from(policy)?));
+            }
+        }
+        if let Some(max_age) = settings.hsts_max_age {
+            let value = format!("max-age={}", max_age.as_secs());
+            fixed.push((STRICT_TRANSPORT_SECURITY, HeaderValue::try_from(value)?));
+        }
+        if let Some(frame_options) = &settings.frame_options {
+            fixed.push((X_FRAME_OPTIONS, HeaderValue::try_from(frame_options)?));
+        }
+        if let Some(referrer_policy) = &settings.referrer_policy {
+            fixed.push((REFERRER_POLICY, HeaderValue::try_from(referrer_policy)?));
+        }
+        if settings.content_type_options {
+            fixed.push((X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")));
+        }
+
+        Ok(Self {
+            headers: Arc::new(Headers {
+                fixed,
+                policy_with_nonce,
+            }),
+        })
+    }
+}
+
+impl<S> Layer<S> for SecurityHeadersLayer {
+    type Service = SecurityHeaders<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SecurityHeaders {
+            inner,
+            headers: self.headers.clone(),
+        }
+    }
+}
+
+/// The service added by [`SecurityHeadersLayer`].
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders<S> {
+    inner: S,
+    headers: Arc<Headers>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for SecurityHeaders<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        let policy = self.headers.policy_with_nonce.as_ref().map(|policy| {
+            let nonce = CspNonce::generate();
+            let policy = policy.replace(NONCE_PLACEHOLDER, nonce.as_str());
+            request.extensions_mut().insert(nonce);
+            // Checked in `SecurityHeadersLayer::new`.
+            HeaderValue::try_from(policy).expect("Invalid Content-Security-Policy")
+        });
+        ResponseFuture {
+            inner: self.inner.call(request),
+            headers: self.headers.clone(),
+            policy,
+        }
+    }
+}
+
+pin_project! {
+    /// The response future of [`SecurityHeaders`].
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        headers: Arc<Headers>,
+        policy: Option<HeaderValue>,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut response = ready!(this.inner.poll(cx))?;
+        let headers = response.headers_mut();
+        if let Some(policy) = this.policy.take() {
+            headers.entry(CONTENT_SECURITY_POLICY).or_insert(policy);
+        }
+        for (name, value) in &this.headers.fixed {
+            headers.entry(name).or_insert_with(|| value.clone());
+        }
+        Poll::Ready(Ok(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use std::sync::Mutex;
+    use tower::{service_fn, ServiceExt};
+
+    // Answers with `response`, returning it along with the nonce the handler was given.
+    async fn respond(
+        layer: &SecurityHeadersLayer,
+        response: Response<String>,
+    ) -> (Response<String>, Option<CspNonce>) {
+        let nonce = Arc::new(Mutex::new(None));
+        let seen = nonce.clone();
+        let mut response = Some(response);
+        let service = layer.layer(service_fn(move |request: Request<()>| {
+            *seen.lock().unwrap() = request.extensions().get::<CspNonce>().cloned();
+            let response = response.take().expect("Called twice");
+            async move { Ok::<_, Infallible>(response) }
+        }));
+        let response = service.oneshot(Request::new(())).await.unwrap();
+        let nonce = nonce.lock().unwrap().take();
+        (response, nonce)
+    }
+
+    #[tokio::test]
+    async fn test_adds_the_default_headers() {
+        let layer = SecurityHeadersLayer::new(&SecurityHeaderSettings::default()).unwrap();
+        let (response, nonce) = respond(&layer, Response::new(String::new())).await;
+        let nonce = nonce.expect("No nonce given to the handler");
+
+        let headers = response.headers();
+        assert_eq!(
+            headers[CONTENT_SECURITY_POLICY],
+            DEFAULT_CONTENT_SECURITY_POLICY.replace(NONCE_PLACEHOLDER, nonce.as_str())
+        );
+        assert_eq!(headers[X_FRAME_OPTIONS], "DENY");
+        assert_eq!(headers[REFERRER_POLICY], "no-referrer");
+        assert_eq!(headers[X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert!(!headers.contains_key(STRICT_TRANSPORT_SECURITY));
+
+        let (_, other) = respond(&layer, Response::new(String::new())).await;
+        assert_ne!(other, Some(nonce));
+    }
+
+    #[tokio::test]
+    async fn test_keeps_headers_set_by_the_handler() {
+        let layer = SecurityHeadersLayer::new(&SecurityHeaderSettings::default()).unwrap();
+        let response = Response::builder()
+            .header(X_FRAME_OPTIONS, "SAMEORIGIN")
+            .header(CONTENT_SECURITY_POLICY, "default-src 'none'")
+            .body(String::new())
+            .unwrap();
+        let (response, _) = respond(&layer, response).await;
+        assert_eq!(response.headers()[X_FRAME_OPTIONS], "SAMEORIGIN");
+        assert_eq!(
+            response.headers()[CONTENT_SECURITY_POLICY],
+            "default-src 'none'"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_leaves_out_disabled_headers() {
+        let settings = SecurityHeaderSettings::new()
+            .with_content_security_policy(Some("default-src 'self'".to_owned()))
+            .with_hsts_max_age(Some(Duration::from_secs(60)))
+            .with_frame_options(None)
+            .with_referrer_policy(None)
+            .with_content_type_options(false);
+        let layer = SecurityHeadersLayer::new(&settings).unwrap();
+        let (response, nonce) = respond(&layer, Response::new(String::new())).await;
+        assert_eq!(nonce, None);
+
+        let headers = response.headers();
+        assert_eq!(headers[CONTENT_SECURITY_POLICY], "default-src 'self'");
+        assert_eq!(headers[STRICT_TRANSPORT_SECURITY], "max-age=60");
+        assert_eq!(headers.len(), 2);
+    }
+
+    #[test]
+    fn test_rejects_invalid_header_values() {
+        let settings = SecurityHeaderSettings::new()
+            .with_content_security_policy(Some("script-src 'nonce-{nonce}'\n".to_owned()));
+        assert!(SecurityHeadersLayer::new(&settings).is_err());
+    }
+
+    #[test]
+    fn test_reads_settings_from_the_environment() {
+        let vars = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        assert_eq!(
+            SecurityHeaderSettings::from_vars(false, vars(&[])),
+            SecurityHeaderSettings::default()
+        );
+        assert_eq!(
+            SecurityHeaderSettings::from_vars(true, vars(&[])).hsts_max_age,
+            Some(DEFAULT_HSTS_MAX_AGE)
+        );
+
+        let settings = SecurityHeaderSettings::from_vars(
+            true,
+            vars(&[
+                (env::CONTENT_SECURITY_POLICY_ENV_VAR, "off"),
+                (env::HSTS_MAX_AGE_SECS_ENV_VAR, "60"),
+                (env::X_FRAME_OPTIONS_ENV_VAR, " SAMEORIGIN "),
+                (env::REFERRER_POLICY_ENV_VAR, ""),
+                (env::X_CONTENT_TYPE_OPTIONS_ENV_VAR, "OFF"),
+            ]),
+        );
+        assert_eq!(settings.content_security_policy, None);
+        assert_eq!(settings.hsts_max_age, Some(Duration::from_secs(60)));
+        assert_eq!(settings.frame_options.as_deref(), Some("SAMEORIGIN"));
+        assert_eq!(settings.referrer_policy.as_deref(), Some("no-referrer"));
+        assert!(!settings.content_type_options);
+    }
+}